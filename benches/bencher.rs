@@ -38,7 +38,7 @@ fn criterion_benchmark(c: &mut Criterion) {
 
     let first_dkim: &HeaderValue<'_> = parsed_message
         .header_values("DKIM-Signature")
-        .nth(0)
+        .next()
         .unwrap();
 
     c.bench_function("From<mail_parser::HeaderValue> for DkimSignature", |b| {
@@ -49,15 +49,14 @@ fn criterion_benchmark(c: &mut Criterion) {
 
     let first_auth_result: &HeaderValue<'_> = parsed_message
         .header_values("Authentication-Results")
-        .nth(0)
+        .next()
         .unwrap();
 
     c.bench_function(
         "From<mail_parser::HeaderValue> for AuthenticationResults",
         |b| {
             b.iter(|| {
-                let _res: AuthenticationResults<'_> =
-                    black_box(first_auth_result).try_into().unwrap();
+                let _res: AuthenticationResults<'_> = black_box(first_auth_result).into();
             })
         },
     );
@@ -22,7 +22,7 @@ fn main() {
     // In real world scenario you have many.
     let first_header = parsed_message
         .header_values("Authentication-Results")
-        .nth(0)
+        .next()
         .expect("No Authentication-Results header present at all?");
 
     // Since this represents multiple results we have errors embedded potentially in each result
@@ -23,7 +23,7 @@ fn main() {
     // In real world scenario you have many.
     let first_header = parsed_message
         .header_values("DKIM-Signature")
-        .nth(0)
+        .next()
         .expect("No Signature header present at all?");
 
     let res: Result<DkimSignature<'_>, DkimSignatureError> = first_header.try_into();
@@ -37,7 +37,10 @@ fn main() {
     // DKIM canonicalization - relaxed if you're lucky.
     assert_eq!(
         dkim_res.c,
-        Some(msg_auth_status::dkim::DkimCanonicalization::Relaxed)
+        msg_auth_status::dkim::DkimCanonicalization {
+            header: msg_auth_status::dkim::DkimCanon::Relaxed,
+            body: msg_auth_status::dkim::DkimCanon::Relaxed,
+        }
     );
     // See the other tags from the RFC 6376
     // We can also check the raw which was:
@@ -1,7 +1,9 @@
-//! Allocating variants of AuthenticationResults, DkimSignatures & ReturnPathVerifier
+//! Allocating variants of AuthenticationResults, DkimSignatures, ReturnPathVerifier & HeaderFromVerifier
 
 mod auth_results;
+mod dkim_correlation;
 mod dkim_signatures;
+mod header_from;
 mod verifier;
 
 //-----------------------------------
@@ -11,8 +13,14 @@ mod verifier;
 #[doc(inline)]
 pub use auth_results::*;
 
+#[doc(inline)]
+pub use dkim_correlation::*;
+
 #[doc(inline)]
 pub use dkim_signatures::*;
 
+#[doc(inline)]
+pub use header_from::*;
+
 #[doc(inline)]
 pub use verifier::*;
@@ -1,7 +1,8 @@
 //! Allocating DkimResultsHandler, SpfResultsHandler, AuthResultsHandler, IpRevResultsHandler
 
 use crate::auth::SmtpAuthResult;
-use crate::dkim::DkimResult;
+use crate::dkim::{DkimAtpsResult, DkimResult};
+use crate::dmarc::DmarcResult;
 use crate::iprev::IpRevResult;
 use crate::spf::SpfResult;
 
@@ -9,6 +10,9 @@ use crate::auth_results::*;
 
 use crate::error::AuthResultsError;
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
 /// Unknown / unsupported methods
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct UnknownResult<'hdr> {
@@ -27,8 +31,12 @@ pub struct AuthenticationResults<'hdr> {
     pub spf_result: Vec<SpfResult<'hdr>>,
     /// Parsed dkim = .. records
     pub dkim_result: Vec<DkimResult<'hdr>>,
+    /// Parsed dkim-atps = .. records
+    pub dkim_atps_result: Vec<DkimAtpsResult<'hdr>>,
     /// Parsed iprev = .. records
     pub iprev_result: Vec<IpRevResult<'hdr>>,
+    /// Parsed dmarc = .. records
+    pub dmarc_result: Vec<DmarcResult<'hdr>>,
     /// Unknown .. = .. records
     pub unknown_result: Vec<UnknownResult<'hdr>>,
     /// Whether none was encountered denoting no result
@@ -39,6 +47,15 @@ pub struct AuthenticationResults<'hdr> {
     pub errors: Vec<AuthResultsError<'hdr>>,
 }
 
+impl<'hdr> AuthenticationResults<'hdr> {
+    /// Whether added by one of the given trusted authserv-ids - never when the authserv-id did not parse
+    pub fn is_trusted_by(&self, authserv_ids: &[&str]) -> bool {
+        self.host
+            .as_ref()
+            .is_some_and(|host| host.is_trusted_by(authserv_ids))
+    }
+}
+
 /// Allocating type for parsed all Authentication-Results in email
 #[cfg(any(feature = "alloc", feature = "std"))]
 #[derive(Debug, Default)]
@@ -68,6 +85,29 @@ impl<'hdr> MessageAuthStatus<'hdr> {
 
         Ok(new_self)
     }
+    /// Only the Authentication-Results added by the given trusted authserv-ids - RFC 8601 s. 5
+    ///
+    /// See [`HostVersion::is_trusted_by`] for the matching rules. Results without
+    /// a parseable authserv-id are never trusted.
+    pub fn trusted(&self, authserv_ids: &[&str]) -> MessageAuthStatus<'hdr> {
+        MessageAuthStatus {
+            auth_results: self
+                .auth_results
+                .iter()
+                .filter(|res| res.is_trusted_by(authserv_ids))
+                .cloned()
+                .collect(),
+        }
+    }
+    /// Authentication-Results not added by the given trusted authserv-ids which should be ignored
+    pub fn untrusted<'a>(
+        &'a self,
+        authserv_ids: &'a [&'a str],
+    ) -> impl Iterator<Item = &'a AuthenticationResults<'hdr>> {
+        self.auth_results
+            .iter()
+            .filter(move |res| !res.is_trusted_by(authserv_ids))
+    }
 }
 
 #[cfg(test)]
@@ -99,4 +139,18 @@ mod test {
             }
         });
     }
+
+    #[rstest]
+    #[case(&["mx3.messagingengine.com"], 4, 0)]
+    #[case(&["*.messagingengine.com"], 4, 0)]
+    #[case(&["messagingengine.com"], 0, 4)]
+    #[case(&["mail.localhost.horse"], 0, 4)]
+    fn trusted_only(#[case] ids: &[&str], #[case] trusted: usize, #[case] untrusted: usize) {
+        let raw = load_test_data("test_data/to_in_fastmail.eml");
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(&raw).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        assert_eq!(status.trusted(ids).auth_results.len(), trusted);
+        assert_eq!(status.untrusted(ids).count(), untrusted);
+    }
 }
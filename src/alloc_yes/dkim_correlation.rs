@@ -0,0 +1,251 @@
+//! Pairing Authentication-Results dkim= results with their DKIM-Signatures
+//!
+//! RFC 6008 header.b carries a prefix of the signature b= which identifies the
+//! signature a result is about even when several share the same d= and s=.
+//! Results without header.b fall back to header.d / header.s / header.i / header.a.
+
+use crate::alloc_yes::{DkimSignatures, MessageAuthStatus};
+use crate::dkim::{DkimAlgorithm, DkimResult, DkimResultCode, DkimSignature};
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{string::String, vec, vec::Vec};
+
+/// How a result was paired with its signature
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DkimMatchedBy {
+    /// header.b is a prefix of the signature b= - RFC 6008
+    HeaderB,
+    /// header.b absent and header.d / header.s / header.i / header.a identify a single signature
+    Identity,
+}
+
+/// Result paired with the signature it is about
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkimResultMatch<'a, 'hdr> {
+    /// dkim= result in Authentication-Results
+    pub result: &'a DkimResult<'hdr>,
+    /// DKIM-Signature the result is about
+    pub signature: &'a DkimSignature<'hdr>,
+    /// How the two were paired
+    pub matched_by: DkimMatchedBy,
+}
+
+/// All dkim= results in a message paired with the DKIM-Signatures in it
+///
+/// Unmatched results often mean a forged or stale Authentication-Results and
+/// unmatched signatures that no host reported on them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DkimCorrelation<'a, 'hdr> {
+    /// Results paired with their signature
+    pub matched: Vec<DkimResultMatch<'a, 'hdr>>,
+    /// Results with no signature - or with several equally likely ones
+    pub unmatched_results: Vec<&'a DkimResult<'hdr>>,
+    /// Parsed signatures no result was paired with
+    pub unmatched_signatures: Vec<&'a DkimSignature<'hdr>>,
+}
+
+// header.b with any folding whitespace removed
+fn compact_header_b(header_b: &str) -> String {
+    header_b
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+        .collect()
+}
+
+// Every given header.* property agrees with the signature and at least header.d or header.i is given
+fn identity_matches(res: &DkimResult<'_>, sig: &DkimSignature<'_>) -> bool {
+    if res.header_d.is_none() && res.header_i.is_none() {
+        return false;
+    }
+    if res.header_d.is_some_and(|d| !d.eq_ignore_ascii_case(sig.d)) {
+        return false;
+    }
+    if res.header_s.is_some_and(|s| !s.eq_ignore_ascii_case(sig.s)) {
+        return false;
+    }
+    if let Some(header_i) = res.header_i {
+        // i= defaults to @d= - RFC 6376 s. 3.5
+        let matches = match sig.i {
+            Some(ref i) => header_i.eq_ignore_ascii_case(i.raw),
+            None => header_i
+                .strip_prefix('@')
+                .is_some_and(|domain| domain.eq_ignore_ascii_case(sig.d)),
+        };
+        if !matches {
+            return false;
+        }
+    }
+    match res.header_a {
+        Some(DkimAlgorithm::Unknown(_)) | None => true,
+        Some(ref a) => *a == sig.a,
+    }
+}
+
+// Index of the single candidate or None if there are none or several
+fn single<T>(mut candidates: impl Iterator<Item = (usize, T)>) -> Option<usize> {
+    match (candidates.next(), candidates.next()) {
+        (Some((idx, _)), None) => Some(idx),
+        _ => None,
+    }
+}
+
+impl<'a, 'hdr> DkimCorrelation<'a, 'hdr> {
+    /// Pair every dkim= result in all the Authentication-Results with the signatures
+    ///
+    /// A result with header.b is only paired by it - a header.b matching no
+    /// signature is reported unmatched rather than guessed by identity.
+    pub fn new(status: &'a MessageAuthStatus<'hdr>, signatures: &'a DkimSignatures<'hdr>) -> Self {
+        let mut new_self = Self::default();
+
+        let sigs_b: Vec<(&'a DkimSignature<'hdr>, String)> = signatures
+            .signatures()
+            .map(|sig| (sig, sig.b.to_base64()))
+            .collect();
+        let mut paired = vec![false; sigs_b.len()];
+
+        for res in status
+            .auth_results
+            .iter()
+            .flat_map(|ar| ar.dkim_result.iter())
+        {
+            let found = match res.header_b {
+                Some(header_b) => {
+                    let prefix = compact_header_b(header_b);
+                    let candidates = sigs_b
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (_, b))| !prefix.is_empty() && b.starts_with(&prefix));
+                    single(candidates).map(|idx| (idx, DkimMatchedBy::HeaderB))
+                }
+                None => {
+                    let candidates = sigs_b
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (sig, _))| identity_matches(res, sig));
+                    single(candidates).map(|idx| (idx, DkimMatchedBy::Identity))
+                }
+            };
+            match found {
+                Some((idx, matched_by)) => {
+                    paired[idx] = true;
+                    new_self.matched.push(DkimResultMatch {
+                        result: res,
+                        signature: sigs_b[idx].0,
+                        matched_by,
+                    });
+                }
+                None => new_self.unmatched_results.push(res),
+            }
+        }
+
+        new_self.unmatched_signatures = sigs_b
+            .iter()
+            .zip(paired)
+            .filter(|(_, paired)| !paired)
+            .map(|((sig, _), _)| *sig)
+            .collect();
+
+        new_self
+    }
+    /// Whether at least one result paired with a d= signature of the domain is a pass
+    ///
+    /// Only paired results count so a pass in a forged or stale Authentication-Results
+    /// about a signature no longer in the message is not enough.
+    pub fn dkim_pass_for_domain(&self, domain: &str) -> bool {
+        self.matched.iter().any(|m| {
+            m.result.code == DkimResultCode::Pass && m.signature.d.eq_ignore_ascii_case(domain)
+        })
+    }
+}
+
+impl<'a, 'hdr> crate::traits::ResultsVerifier for DkimCorrelation<'a, 'hdr> {
+    fn return_path_atleast_one_dkim_pass(&self, domain: &str) -> bool {
+        self.dkim_pass_for_domain(domain)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "mail_parser")]
+mod test {
+    use super::*;
+    use rstest::rstest;
+    use std::{fs::File, io::Read, path::PathBuf};
+
+    fn load_test_data(file: &str) -> Vec<u8> {
+        let mut file = File::open(PathBuf::from("test_data").join(file)).unwrap();
+        let mut data: Vec<u8> = vec![];
+        file.read_to_end(&mut data).unwrap();
+        data
+    }
+
+    // (by header.b, by identity, unmatched results, unmatched signatures)
+    fn counts(raw: &[u8]) -> (usize, usize, usize, usize) {
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(raw).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        let signatures = DkimSignatures::from_mail_parser(&parsed_message).unwrap();
+        let correlation = DkimCorrelation::new(&status, &signatures);
+        let by = |matched_by| {
+            correlation
+                .matched
+                .iter()
+                .filter(|m| m.matched_by == matched_by)
+                .count()
+        };
+        (
+            by(DkimMatchedBy::HeaderB),
+            by(DkimMatchedBy::Identity),
+            correlation.unmatched_results.len(),
+            correlation.unmatched_signatures.len(),
+        )
+    }
+
+    #[rstest]
+    #[case("to_in_fastmail.eml", (2, 0, 0, 0))]
+    #[case("from_arewe_at_to_gmail.eml", (1, 1, 0, 0))]
+    #[case("to_in_areweat.eml", (2, 0, 0, 0))]
+    #[case("rfc6541_atps.eml", (0, 1, 0, 0))]
+    #[case("rfc8601_b6_multi_tiered_auth.eml", (0, 0, 3, 0))]
+    fn test_data(#[case] file: &str, #[case] expected: (usize, usize, usize, usize)) {
+        assert_eq!(counts(&load_test_data(file)), expected);
+    }
+
+    #[rstest]
+    #[case("to_in_areweat.eml", "fastmail.com", true)]
+    #[case("to_in_areweat.eml", "FastMail.com", true)]
+    #[case("to_in_areweat.eml", "arewe.at", false)]
+    #[case("fail_to_in_areweat.eml", "fastmail.com", false)]
+    fn results_verifier(#[case] file: &str, #[case] domain: &str, #[case] expected: bool) {
+        use crate::traits::ResultsVerifier;
+
+        let raw = load_test_data(file);
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(&raw).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        let signatures = DkimSignatures::from_mail_parser(&parsed_message).unwrap();
+        let correlation = DkimCorrelation::new(&status, &signatures);
+        assert_eq!(
+            correlation.return_path_atleast_one_dkim_pass(domain),
+            expected
+        );
+    }
+
+    #[test]
+    fn stale_header_b() {
+        let raw = String::from_utf8(load_test_data("to_in_fastmail.eml")).unwrap();
+        let stale = raw.replace("header.b=Nh7CKMWS", "header.b=AAAAAAAA");
+        assert_eq!(counts(stale.as_bytes()), (1, 0, 1, 1));
+    }
+
+    #[test]
+    fn ambiguous_identity() {
+        let raw = String::from_utf8(load_test_data("to_in_fastmail.eml")).unwrap();
+        let ambiguous = raw
+            .replace("header.b=Nh7CKMWS header.a=rsa-sha256 header.s=202405r", "")
+            .replace(
+                "header.b=yBgMwq3y header.a=ed25519-sha256 header.s=202405e",
+                "",
+            );
+        assert_eq!(counts(ambiguous.as_bytes()), (0, 0, 2, 2));
+    }
+}
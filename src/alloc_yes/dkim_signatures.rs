@@ -1,8 +1,12 @@
 //! Allocating DkimSignaturesHandler
 
-use crate::dkim::DkimSignature;
+use crate::alloc_yes::{DkimCorrelation, MessageAuthStatus};
+use crate::dkim::{DkimAlgorithm, DkimSignature, DkimTimestamp};
 use crate::error::DkimSignatureError;
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
 /// No holding errors - currently all errors are inside results
 #[derive(Debug, PartialEq)]
 pub enum DkimSignaturesError {}
@@ -15,12 +19,6 @@ pub struct DkimSignatures<'hdr> {
     dkim_signatures: Vec<Result<DkimSignature<'hdr>, DkimSignatureError<'hdr>>>,
 }
 
-impl<'hdr> crate::traits::ResultsVerifier for DkimSignatures<'hdr> {
-    fn return_path_atleast_one_dkim_pass(&self, _selector: &str) -> bool {
-        false // TODO
-    }
-}
-
 impl<'hdr> DkimSignatures<'hdr> {
     /// Parse all DKIM Signatures into allocating Vec from mail_parser::Message
     #[cfg(feature = "mail_parser")]
@@ -38,6 +36,68 @@ impl<'hdr> DkimSignatures<'hdr> {
 
         Ok(new_self)
     }
+    /// All DKIM-Signatures in header order whether they parsed or not
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = &Result<DkimSignature<'hdr>, DkimSignatureError<'hdr>>> {
+        self.dkim_signatures.iter()
+    }
+    /// Number of DKIM-Signatures including the ones that did not parse
+    pub fn len(&self) -> usize {
+        self.dkim_signatures.len()
+    }
+    /// Whether there were no DKIM-Signatures at all
+    pub fn is_empty(&self) -> bool {
+        self.dkim_signatures.is_empty()
+    }
+    /// Successfully parsed signatures in header order
+    pub fn signatures(&self) -> impl Iterator<Item = &DkimSignature<'hdr>> {
+        self.dkim_signatures
+            .iter()
+            .filter_map(|sig| sig.as_ref().ok())
+    }
+    /// Errors of the signatures that did not parse in header order
+    pub fn errors(&self) -> impl Iterator<Item = &DkimSignatureError<'hdr>> {
+        self.dkim_signatures
+            .iter()
+            .filter_map(|sig| sig.as_ref().err())
+    }
+    /// Parsed signatures with the given d= compared case-insensitively
+    pub fn by_domain<'a>(&'a self, d: &'a str) -> impl Iterator<Item = &'a DkimSignature<'hdr>> {
+        self.signatures()
+            .filter(move |sig| sig.d.eq_ignore_ascii_case(d))
+    }
+    /// Parsed signatures with the given s= selector compared case-insensitively
+    pub fn by_selector<'a>(&'a self, s: &'a str) -> impl Iterator<Item = &'a DkimSignature<'hdr>> {
+        self.signatures()
+            .filter(move |sig| sig.s.eq_ignore_ascii_case(s))
+    }
+    /// Parsed signatures with the given a= algorithm
+    pub fn by_algorithm<'a>(
+        &'a self,
+        a: &'a DkimAlgorithm<'_>,
+    ) -> impl Iterator<Item = &'a DkimSignature<'hdr>> {
+        self.signatures().filter(move |sig| sig.a == *a)
+    }
+    /// Parsed signatures with x= before the given time
+    pub fn expired_at(&self, now: DkimTimestamp) -> impl Iterator<Item = &DkimSignature<'hdr>> {
+        self.signatures().filter(move |sig| sig.is_expired_at(now))
+    }
+    /// Parsed signatures without x= or with x= not before the given time
+    pub fn unexpired_at(&self, now: DkimTimestamp) -> impl Iterator<Item = &DkimSignature<'hdr>> {
+        self.signatures().filter(move |sig| !sig.is_expired_at(now))
+    }
+    /// Whether at least one dkim= pass in the Authentication-Results is about a
+    /// d= signature of the domain in these signatures
+    ///
+    /// Results are paired with the signatures as in [`DkimCorrelation`].
+    pub fn return_path_atleast_one_dkim_pass(
+        &self,
+        status: &MessageAuthStatus<'hdr>,
+        domain: &str,
+    ) -> bool {
+        DkimCorrelation::new(status, self).dkim_pass_for_domain(domain)
+    }
 }
 
 #[cfg(test)]
@@ -69,4 +129,69 @@ mod test {
             }
         });
     }
+
+    #[test]
+    fn query() {
+        let raw = load_test_data("test_data/from_arewe_at_to_gmail.eml");
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(&raw).unwrap();
+        let sigs = DkimSignatures::from_mail_parser(&parsed_message).unwrap();
+
+        assert_eq!(sigs.len(), 2);
+        assert_eq!(sigs.signatures().count(), 2);
+        assert_eq!(sigs.errors().count(), 0);
+        assert_eq!(sigs.by_domain("AREWE.at").count(), 2);
+        assert_eq!(sigs.by_domain("example.com").count(), 0);
+
+        let selectors: Vec<&str> = sigs.by_selector("202405e").map(|sig| sig.s).collect();
+        assert_eq!(selectors, vec!["202405e"]);
+        let ed25519 = DkimAlgorithm::Ed25519_Sha256;
+        let selectors: Vec<&str> = sigs.by_algorithm(&ed25519).map(|sig| sig.s).collect();
+        assert_eq!(selectors, vec!["202405e"]);
+    }
+
+    #[rstest]
+    #[case("to_in_areweat.eml", "fastmail.com", true)]
+    #[case("to_in_areweat.eml", "arewe.at", false)]
+    #[case("fail_to_in_areweat.eml", "fastmail.com", false)]
+    fn return_path_atleast_one_dkim_pass(
+        #[case] file: &str,
+        #[case] domain: &str,
+        #[case] expected: bool,
+    ) {
+        let raw = load_test_data(&format!("test_data/{}", file));
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(&raw).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        let sigs = DkimSignatures::from_mail_parser(&parsed_message).unwrap();
+        assert_eq!(
+            sigs.return_path_atleast_one_dkim_pass(&status, domain),
+            expected
+        );
+    }
+
+    #[test]
+    fn query_errors_and_expiry() {
+        let raw = load_test_data("test_data/rfc8601_b6_multi_tiered_auth.eml");
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(&raw).unwrap();
+        let sigs = DkimSignatures::from_mail_parser(&parsed_message).unwrap();
+        assert_eq!(sigs.len(), 2);
+        assert_eq!(sigs.signatures().count(), 0);
+        assert_eq!(sigs.errors().count(), 2);
+
+        let raw = b"DKIM-Signature: v=1; a=rsa-sha256; d=example.com; s=a; x=100; h=from; bh=AAAA; b=AAAA\r\nDKIM-Signature: v=1; a=rsa-sha256; d=example.com; s=b; h=from; bh=AAAA; b=AAAA\r\nFrom: a@example.com\r\n\r\nBody\r\n";
+        let parsed_message = parser.parse(&raw[..]).unwrap();
+        let sigs = DkimSignatures::from_mail_parser(&parsed_message).unwrap();
+        let expired: Vec<&str> = sigs
+            .expired_at(DkimTimestamp(101))
+            .map(|sig| sig.s)
+            .collect();
+        assert_eq!(expired, vec!["a"]);
+        let unexpired: Vec<&str> = sigs
+            .unexpired_at(DkimTimestamp(100))
+            .map(|sig| sig.s)
+            .collect();
+        assert_eq!(unexpired, vec!["a", "b"]);
+    }
 }
@@ -0,0 +1,301 @@
+//! Header From (RFC5322.From) Verifier API
+//!
+//! The From address is what recipients see so it is the identifier DMARC
+//! aligns DKIM and SPF against - RFC 7489 s. 3.1.
+
+use crate::alloc_yes::{AuthenticationResults, MessageAuthStatus};
+
+use crate::addr::AddrSpec;
+#[cfg(feature = "mail_parser")]
+use crate::parser::addr_spec::{parse_mailbox_list, AddrSpecToken};
+
+use crate::error::HeaderFromVerifierError;
+
+use super::verifier::{Decision, Outcome};
+use super::{AlignedEvidence, AlignedMechanism, Alignment};
+
+#[cfg(feature = "mail_parser")]
+use logos::Logos;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
+/// Verify that the `From` header is authenticated by an aligned DKIM or SPF pass
+#[derive(Debug)]
+pub struct HeaderFromVerifier<'hdr> {
+    auth_status: &'hdr MessageAuthStatus<'hdr>,
+    from: AddrSpec<'hdr>,
+    trusted_authserv_ids: Option<&'hdr [&'hdr str]>,
+    alignment: Alignment,
+}
+
+// Validate that From exists exactly only once with exactly one mailbox and return it - RFC 7489 s. 6.6.1
+#[cfg(feature = "mail_parser")]
+fn exact_once_from<'hdr>(
+    msg: &'hdr mail_parser::Message<'hdr>,
+) -> Result<AddrSpec<'hdr>, HeaderFromVerifierError<'hdr>> {
+    let mut items = msg
+        .headers_raw()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("From"))
+        .map(|(_, value)| value);
+
+    let candidate = match items.next() {
+        Some(candidate) => candidate,
+        None => return Err(HeaderFromVerifierError::NoHeader),
+    };
+
+    if items.next().is_some() {
+        return Err(HeaderFromVerifierError::MultipleNotAllowed);
+    }
+
+    let mut lex = AddrSpecToken::lexer(candidate);
+    let mut mailboxes = match parse_mailbox_list(&mut lex) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(HeaderFromVerifierError::InvalidHeader(e)),
+    };
+
+    match mailboxes.len() {
+        0 => Err(HeaderFromVerifierError::NoAddress),
+        1 => Ok(mailboxes.remove(0)),
+        count => Err(HeaderFromVerifierError::MultipleAddresses(count)),
+    }
+}
+
+/// Header From verifier Status
+#[derive(Debug, PartialEq)]
+pub enum HeaderFromVerifierStatus {
+    /// No DKIM or SPF results seen aligned with the From domain
+    Nothing,
+    /// Seen at least one "Pass" DKIM or SPF Result code aligned with the From domain
+    Pass(AlignedMechanism),
+    /// Seen no "Pass" but at least one "Fail" or SPF "SoftFail" aligned with the From domain
+    Fail,
+    /// Seen no "Pass" or "Fail" but at least one "TempError" aligned with the From domain
+    TempError,
+    /// Seen no "Pass", "Fail" or "TempError" but at least one "PermError" aligned with the From domain
+    PermError,
+}
+
+/// Header From verdict with the evidence for it
+#[derive(Debug, PartialEq)]
+pub struct HeaderFromVerdict<'hdr> {
+    /// Outcome
+    pub status: HeaderFromVerifierStatus,
+    /// The verified From mailbox
+    pub from: AddrSpec<'hdr>,
+    /// Results that decided the outcome e.g. all the passes when it is a pass
+    pub evidence: Vec<AlignedEvidence<'hdr>>,
+    /// Aligned results in Authentication-Results not added by a trusted authserv-id
+    pub ignored: Vec<AlignedEvidence<'hdr>>,
+}
+
+impl<'hdr> HeaderFromVerifier<'hdr> {
+    /// Construct Verifier from alloc_yes AuthenticationResults and mail_parser Headers containing From
+    #[cfg(all(any(feature = "alloc", feature = "std"), feature = "mail_parser"))]
+    pub fn from_alloc_yes(
+        auth_status: &'hdr MessageAuthStatus<'hdr>,
+        msg: &'hdr mail_parser::Message<'hdr>,
+    ) -> Result<Self, HeaderFromVerifierError<'hdr>> {
+        let from = exact_once_from(msg)?;
+        Ok(Self {
+            auth_status,
+            from,
+            trusted_authserv_ids: None,
+            alignment: Alignment::Relaxed,
+        })
+    }
+    /// The From mailbox being verified
+    pub fn from(&self) -> &AddrSpec<'hdr> {
+        &self.from
+    }
+    /// Only evaluate Authentication-Results added by the given authserv-ids - RFC 8601 s. 5
+    ///
+    /// Without this every Authentication-Results in the message is trusted
+    /// including any injected by the sender.
+    pub fn with_trusted_authserv_ids(mut self, authserv_ids: &'hdr [&'hdr str]) -> Self {
+        self.trusted_authserv_ids = Some(authserv_ids);
+        self
+    }
+    /// How header.d and smtp.mailfrom must match the From domain - relaxed by default as in DMARC
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+    /// Authentication-Results ignored by verify as not added by a trusted authserv-id
+    pub fn ignored(&self) -> Vec<&'hdr AuthenticationResults<'hdr>> {
+        match self.trusted_authserv_ids {
+            Some(ids) => self.auth_status.untrusted(ids).collect(),
+            None => vec![],
+        }
+    }
+    /// Verify that Auth-Results contain at least one DKIM pass for header.d or SPF pass for
+    /// smtp.mailfrom aligned with the From domain
+    pub fn verify(&self) -> Result<HeaderFromVerifierStatus, HeaderFromVerifierError<'hdr>> {
+        Ok(self.verdict().status)
+    }
+    /// Verdict with the results that decided it
+    ///
+    /// Host may have multiple signature methods - one of many passing is enough.
+    /// Without a pass a fail decides over a temperror over a permerror.
+    pub fn verdict(&self) -> HeaderFromVerdict<'hdr> {
+        let Decision {
+            outcome,
+            evidence,
+            ignored,
+        } = Decision::new(
+            self.auth_status,
+            self.trusted_authserv_ids,
+            self.from.domain,
+            self.alignment,
+        );
+
+        let status = match outcome {
+            Outcome::Pass => HeaderFromVerifierStatus::Pass(AlignedMechanism::of(&evidence)),
+            Outcome::Fail => HeaderFromVerifierStatus::Fail,
+            Outcome::TempError => HeaderFromVerifierStatus::TempError,
+            Outcome::PermError => HeaderFromVerifierStatus::PermError,
+            Outcome::Nothing => HeaderFromVerifierStatus::Nothing,
+        };
+
+        HeaderFromVerdict {
+            status,
+            from: self.from.clone(),
+            evidence,
+            ignored,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "mail_parser")]
+mod test {
+    use super::*;
+    use crate::error::AddrSpecError;
+    use rstest::rstest;
+    use std::{fs::File, io::Read, path::PathBuf};
+
+    fn load_test_data(file: &str) -> String {
+        let mut file = File::open(PathBuf::from("test_data").join(file)).unwrap();
+        let mut data: Vec<u8> = vec![];
+        file.read_to_end(&mut data).unwrap();
+        String::from_utf8(data).unwrap()
+    }
+
+    fn assert_verified(
+        raw: &str,
+        alignment: Alignment,
+        expected: Result<HeaderFromVerifierStatus, HeaderFromVerifierError<'_>>,
+    ) {
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(raw).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        let res = HeaderFromVerifier::from_alloc_yes(&status, &parsed_message)
+            .and_then(|verifier| verifier.with_alignment(alignment).verify());
+        assert_eq!(res, expected);
+    }
+
+    #[rstest]
+    #[case(
+        "to_in_fastmail.eml",
+        HeaderFromVerifierStatus::Pass(AlignedMechanism::Both)
+    )]
+    #[case(
+        "to_in_protonmail.eml",
+        HeaderFromVerifierStatus::Pass(AlignedMechanism::Both)
+    )]
+    #[case(
+        "to_in_areweat.eml",
+        HeaderFromVerifierStatus::Pass(AlignedMechanism::Both)
+    )]
+    #[case(
+        "from_gmail_to_arewe_at.eml",
+        HeaderFromVerifierStatus::Pass(AlignedMechanism::Dkim)
+    )]
+    #[case(
+        "rfc8601_b3_auth_done.eml",
+        HeaderFromVerifierStatus::Pass(AlignedMechanism::Spf)
+    )]
+    #[case("rfc6541_atps.eml", HeaderFromVerifierStatus::Nothing)]
+    fn from_mail_parser(#[case] file: &str, #[case] expected: HeaderFromVerifierStatus) {
+        let raw = load_test_data(file);
+        assert_verified(&raw, Alignment::Relaxed, Ok(expected));
+    }
+
+    #[rstest]
+    #[case(
+        "From: Past Job <past.job1295@bounces.fastmail.com>",
+        Alignment::Relaxed,
+        Ok(HeaderFromVerifierStatus::Pass(AlignedMechanism::Both))
+    )]
+    #[case(
+        "From: Past Job <past.job1295@bounces.fastmail.com>",
+        Alignment::Strict,
+        Ok(HeaderFromVerifierStatus::Nothing)
+    )]
+    #[case(
+        "From: a@fastmail.com, b@fastmail.com",
+        Alignment::Relaxed,
+        Err(HeaderFromVerifierError::MultipleAddresses(2))
+    )]
+    #[case(
+        "From: Team: a@fastmail.com;",
+        Alignment::Relaxed,
+        Ok(HeaderFromVerifierStatus::Pass(AlignedMechanism::Both))
+    )]
+    #[case(
+        "From: undisclosed-recipients:;",
+        Alignment::Relaxed,
+        Err(HeaderFromVerifierError::NoAddress)
+    )]
+    #[case(
+        "From: Past Job",
+        Alignment::Relaxed,
+        Err(HeaderFromVerifierError::InvalidHeader(AddrSpecError::NoAssociatedAddrSpec))
+    )]
+    #[case(
+        "From: a@fastmail.com\r\nFrom: b@fastmail.com",
+        Alignment::Relaxed,
+        Err(HeaderFromVerifierError::MultipleNotAllowed)
+    )]
+    #[case(
+        "X-No-From: a@fastmail.com",
+        Alignment::Relaxed,
+        Err(HeaderFromVerifierError::NoHeader)
+    )]
+    fn from_header(
+        #[case] from: &str,
+        #[case] alignment: Alignment,
+        #[case] expected: Result<HeaderFromVerifierStatus, HeaderFromVerifierError<'static>>,
+    ) {
+        let raw =
+            load_test_data("to_in_areweat.eml").replace("From: past.job1295@fastmail.com", from);
+        assert_verified(&raw, alignment, expected);
+    }
+
+    #[test]
+    fn verdict_evidence() {
+        let raw = load_test_data("to_in_fastmail.eml");
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(&raw).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        let verifier = HeaderFromVerifier::from_alloc_yes(&status, &parsed_message).unwrap();
+        assert_eq!(verifier.from().display_name, Some("Rust Sydney"));
+
+        let verdict = verifier.verdict();
+        assert_eq!(verdict.from.domain, "arewe.at");
+        assert_eq!(
+            verdict.status,
+            HeaderFromVerifierStatus::Pass(AlignedMechanism::Both)
+        );
+        assert_eq!(verdict.evidence.len(), 3);
+        assert_eq!(verdict.ignored, vec![]);
+
+        let verifier = HeaderFromVerifier::from_alloc_yes(&status, &parsed_message)
+            .unwrap()
+            .with_trusted_authserv_ids(&["mx.example.com"]);
+        let verdict = verifier.verdict();
+        assert_eq!(verdict.status, HeaderFromVerifierStatus::Nothing);
+        assert_eq!(verdict.ignored.len(), 3);
+        assert_eq!(verifier.ignored().len(), 4);
+    }
+}
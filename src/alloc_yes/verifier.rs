@@ -3,22 +3,78 @@
 //! This is a best effort implementation for now and may not work in all scenarios.
 
 #[cfg(any(feature = "alloc", feature = "std"))]
-use crate::alloc_yes::MessageAuthStatus;
+use crate::alloc_yes::{AuthenticationResults, MessageAuthStatus};
 
 use crate::addr::AddrSpec;
-use crate::dkim::DkimResultCode;
+use crate::dkim::{DkimResult, DkimResultCode};
+use crate::spf::{SpfResult, SpfResultCode};
 
 use crate::parser::addr_spec::{parse_addr_spec, AddrSpecToken};
 
 use crate::error::ReturnPathVerifierError;
+use crate::orgdomain::{normalize, PublicSuffixList};
+
+#[cfg(feature = "std")]
+use crate::orgdomain::bundled;
+#[cfg(not(feature = "std"))]
+use crate::orgdomain::BUNDLED;
 
 use logos::Logos;
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
 /// Verify that the `Return-Path` is authenticated
 #[derive(Debug)]
 pub struct ReturnPathVerifier<'hdr> {
     auth_status: &'hdr MessageAuthStatus<'hdr>,
     return_path: AddrSpec<'hdr>,
+    trusted_authserv_ids: Option<&'hdr [&'hdr str]>,
+    alignment: Alignment,
+}
+
+/// How a domain in a result must match the Return-Path domain
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Alignment {
+    /// Same domain ignoring case and a trailing dot
+    #[default]
+    Strict,
+    /// Same organizational domain by the Public Suffix List e.g. bounces.example.com and example.com
+    Relaxed,
+}
+
+impl Alignment {
+    /// Whether the two domains are aligned
+    ///
+    /// Relaxed alignment uses the bundled Public Suffix List. Without std the
+    /// list is parsed on every call - keep a parsed [`BUNDLED`](crate::orgdomain::BUNDLED) and use
+    /// [`Alignment::is_aligned_by`] instead when checking many.
+    pub fn is_aligned(&self, left: &str, right: &str) -> bool {
+        match self {
+            Self::Strict => normalize(left) == normalize(right),
+            Self::Relaxed => {
+                #[cfg(feature = "std")]
+                let list = bundled();
+                #[cfg(not(feature = "std"))]
+                let list = &PublicSuffixList::from(BUNDLED);
+                self.is_aligned_by(list, left, right)
+            }
+        }
+    }
+    /// Whether the two domains are aligned by the given Public Suffix List
+    pub fn is_aligned_by(&self, list: &PublicSuffixList, left: &str, right: &str) -> bool {
+        let (left, right) = (normalize(left), normalize(right));
+        if left == right {
+            return true;
+        }
+        match self {
+            Self::Strict => false,
+            Self::Relaxed => match list.org_domain(&left) {
+                Some(org) => list.org_domain(&right).is_some_and(|other| other == org),
+                None => false,
+            },
+        }
+    }
 }
 
 // Validate that Return-Path exists exactly only once and return it
@@ -50,46 +106,209 @@ fn exact_once_return_path<'hdr>(
     Ok(parsed_return_path)
 }
 
+/// Authentication mechanism that passed aligned with the verified domain
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlignedMechanism {
+    /// DKIM pass with header.d aligned with the domain
+    Dkim,
+    /// SPF pass with smtp.mailfrom aligned with the domain
+    Spf,
+    /// Both DKIM and SPF passed
+    Both,
+}
+
 /// Return-Path verifier Status
 #[derive(Debug, PartialEq)]
 pub enum ReturnPathVerifierStatus {
-    /// No DKIM results seen related to Return-path and header.d Authentication DKIM Result code
+    /// No DKIM or SPF results seen related to Return-path
     Nothing,
-    /// Seen at least one "Pass" DKIM Result code in Authentication-Results relevant to Return-Path
-    Pass,
-    /// Seen no "Pass" DKIM Result code in Authentication-Results relevant to Return-Path
+    /// Seen at least one "Pass" DKIM or SPF Result code in Authentication-Results relevant to Return-Path
+    Pass(AlignedMechanism),
+    /// Seen no "Pass" but at least one "Fail" or SPF "SoftFail" relevant to Return-Path
     Fail,
+    /// Seen no "Pass" or "Fail" but at least one "TempError" relevant to Return-Path
+    TempError,
+    /// Seen no "Pass", "Fail" or "TempError" but at least one "PermError" relevant to Return-Path
+    PermError,
 }
 
-// Check one AuthenticationResult header's DKIM Resutls against Domain
-// Since hosts may have spotty algorithm support - at least one DKIM pass is required for header.d
-fn check_dkim_res<'hdr>(
-    res: &'hdr crate::alloc_yes::AuthenticationResults<'hdr>,
+/// DKIM or SPF result relevant to the verified domain
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlignedResult<'hdr> {
+    /// dkim= with header.d aligned with the domain
+    Dkim(&'hdr DkimResult<'hdr>),
+    /// spf= with smtp.mailfrom aligned with the domain
+    Spf(&'hdr SpfResult<'hdr>),
+}
+
+/// Relevant result and the Authentication-Results header it was in
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlignedEvidence<'hdr> {
+    /// Index of the Authentication-Results header in the message counting from the top from 0
+    pub index: usize,
+    /// authserv-id of the Authentication-Results header if it was parsed
+    pub authserv_id: Option<&'hdr str>,
+    /// The result
+    pub result: AlignedResult<'hdr>,
+}
+
+/// Return-Path verdict with the evidence for it
+#[derive(Debug, PartialEq)]
+pub struct ReturnPathVerdict<'hdr> {
+    /// Outcome
+    pub status: ReturnPathVerifierStatus,
+    /// Results that decided the outcome e.g. all the passes when it is a pass
+    pub evidence: Vec<AlignedEvidence<'hdr>>,
+    /// Relevant results in Authentication-Results not added by a trusted authserv-id
+    pub ignored: Vec<AlignedEvidence<'hdr>>,
+}
+
+// Outcome of a single result in the order of precedence when deciding the status
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub(super) enum Outcome {
+    Nothing,
+    PermError,
+    TempError,
+    Fail,
+    Pass,
+}
+
+impl AlignedResult<'_> {
+    fn outcome(&self) -> Outcome {
+        match self {
+            Self::Dkim(dkim_res) => match dkim_res.code {
+                DkimResultCode::Pass => Outcome::Pass,
+                DkimResultCode::Fail => Outcome::Fail,
+                DkimResultCode::TempError => Outcome::TempError,
+                DkimResultCode::PermError => Outcome::PermError,
+                DkimResultCode::Neutral
+                | DkimResultCode::NoneDkim
+                | DkimResultCode::Unknown
+                | DkimResultCode::Policy => Outcome::Nothing,
+            },
+            Self::Spf(spf_res) => match spf_res.code {
+                SpfResultCode::Pass => Outcome::Pass,
+                SpfResultCode::Fail | SpfResultCode::SoftFail => Outcome::Fail,
+                SpfResultCode::TempError => Outcome::TempError,
+                SpfResultCode::PermError => Outcome::PermError,
+                SpfResultCode::Neutral
+                | SpfResultCode::NoneSpf
+                | SpfResultCode::Policy
+                | SpfResultCode::Unknown => Outcome::Nothing,
+            },
+        }
+    }
+}
+
+// Domain of smtp.mailfrom which may be a bare domain or an address in angle brackets or not
+fn mailfrom_domain(mailfrom: &str) -> &str {
+    let mailfrom = mailfrom.trim_start_matches('<').trim_end_matches('>');
+    match mailfrom.rsplit_once('@') {
+        Some((_, domain)) => domain,
+        None => mailfrom,
+    }
+}
+
+// DKIM results for header.d and SPF results for smtp.mailfrom aligned with the domain in one AuthenticationResults
+// SPF authenticates the MAIL FROM identity which is what Return-Path records
+fn relevant_results<'hdr>(
+    res: &'hdr AuthenticationResults<'hdr>,
     domain: &'hdr str,
-) -> ReturnPathVerifierStatus {
-    let mut ret = ReturnPathVerifierStatus::Nothing;
-    let dkim_res_iter = res.dkim_result.iter();
-    for dkim_res in dkim_res_iter {
-        if let Some(header_d) = dkim_res.header_d {
-            if header_d == domain {
-                let new_ret = match dkim_res.code {
-                    DkimResultCode::Pass => return ReturnPathVerifierStatus::Pass,
-                    DkimResultCode::Fail => Some(ReturnPathVerifierStatus::Fail),
-                    DkimResultCode::TempError => Some(ReturnPathVerifierStatus::Fail),
-                    DkimResultCode::PermError => Some(ReturnPathVerifierStatus::Fail),
-                    DkimResultCode::Neutral => None,
-                    DkimResultCode::NoneDkim => None,
-                    DkimResultCode::Unknown => None,
-                    DkimResultCode::Policy => None,
-                };
-                // One pass is enough for given header.d == domain
-                if let Some(new_ret) = new_ret {
-                    ret = new_ret;
-                }
+    alignment: Alignment,
+) -> impl Iterator<Item = AlignedResult<'hdr>> {
+    let dkim = res
+        .dkim_result
+        .iter()
+        .filter(move |dkim_res| {
+            dkim_res
+                .header_d
+                .is_some_and(|d| alignment.is_aligned(d, domain))
+        })
+        .map(AlignedResult::Dkim);
+    let spf = res
+        .spf_result
+        .iter()
+        .filter(move |spf_res| {
+            spf_res
+                .smtp_mailfrom
+                .is_some_and(|mailfrom| alignment.is_aligned(mailfrom_domain(mailfrom), domain))
+        })
+        .map(AlignedResult::Spf);
+    dkim.chain(spf)
+}
+
+// Outcome decided over the relevant results with the results that decided it
+pub(super) struct Decision<'hdr> {
+    pub(super) outcome: Outcome,
+    pub(super) evidence: Vec<AlignedEvidence<'hdr>>,
+    pub(super) ignored: Vec<AlignedEvidence<'hdr>>,
+}
+
+impl<'hdr> Decision<'hdr> {
+    // Host may have multiple signature methods - one of many passing is enough.
+    // Without a pass a fail decides over a temperror over a permerror.
+    pub(super) fn new(
+        auth_status: &'hdr MessageAuthStatus<'hdr>,
+        trusted_authserv_ids: Option<&[&str]>,
+        domain: &'hdr str,
+        alignment: Alignment,
+    ) -> Self {
+        let mut considered: Vec<AlignedEvidence<'hdr>> = vec![];
+        let mut ignored: Vec<AlignedEvidence<'hdr>> = vec![];
+
+        for (index, res) in auth_status.auth_results.iter().enumerate() {
+            // Option::is_none_or needs Rust 1.82
+            #[allow(clippy::unnecessary_map_or)]
+            let trusted = trusted_authserv_ids.map_or(true, |ids| res.is_trusted_by(ids));
+            let authserv_id = res.host.as_ref().map(|host| host.host);
+            let evidence = relevant_results(res, domain, alignment).map(|result| AlignedEvidence {
+                index,
+                authserv_id,
+                result,
+            });
+            match trusted {
+                true => considered.extend(evidence),
+                false => ignored.extend(evidence),
             }
         }
+
+        let outcome = considered
+            .iter()
+            .map(|evidence| evidence.result.outcome())
+            .fold(Outcome::Nothing, |decided, outcome| {
+                match outcome > decided {
+                    true => outcome,
+                    false => decided,
+                }
+            });
+        let evidence: Vec<AlignedEvidence<'hdr>> = considered
+            .into_iter()
+            .filter(|evidence| outcome != Outcome::Nothing && evidence.result.outcome() == outcome)
+            .collect();
+
+        Self {
+            outcome,
+            evidence,
+            ignored,
+        }
+    }
+}
+
+impl AlignedMechanism {
+    // Mechanisms of the passing evidence
+    pub(super) fn of(evidence: &[AlignedEvidence<'_>]) -> Self {
+        let dkim = evidence
+            .iter()
+            .any(|e| matches!(e.result, AlignedResult::Dkim(_)));
+        let spf = evidence
+            .iter()
+            .any(|e| matches!(e.result, AlignedResult::Spf(_)));
+        match (dkim, spf) {
+            (true, true) => Self::Both,
+            (true, false) => Self::Dkim,
+            _ => Self::Spf,
+        }
     }
-    ret
 }
 
 impl<'hdr> ReturnPathVerifier<'hdr> {
@@ -103,28 +322,63 @@ impl<'hdr> ReturnPathVerifier<'hdr> {
         Ok(Self {
             auth_status,
             return_path,
+            trusted_authserv_ids: None,
+            alignment: Alignment::default(),
         })
     }
-    /// Verify that Auth-Results contain at least one pass for DKIM header.d relevant to Return-Path header
-    pub fn verify(&self) -> Result<ReturnPathVerifierStatus, ReturnPathVerifierError<'hdr>> {
-        let mut dkim_pass_selector = false;
-        let res_iter = self.auth_status.auth_results.iter();
-
-        for res in res_iter {
-            // Host may have multiple signature methods - one of many must pass
-            match check_dkim_res(res, self.return_path.domain) {
-                ReturnPathVerifierStatus::Fail => {}
-                ReturnPathVerifierStatus::Pass => {
-                    dkim_pass_selector = true;
-                    break;
-                }
-                ReturnPathVerifierStatus::Nothing => {}
-            }
+    /// Only evaluate Authentication-Results added by the given authserv-ids - RFC 8601 s. 5
+    ///
+    /// Without this every Authentication-Results in the message is trusted
+    /// including any injected by the sender.
+    pub fn with_trusted_authserv_ids(mut self, authserv_ids: &'hdr [&'hdr str]) -> Self {
+        self.trusted_authserv_ids = Some(authserv_ids);
+        self
+    }
+    /// How header.d and smtp.mailfrom must match the Return-Path domain - strict by default
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+    /// Authentication-Results ignored by verify as not added by a trusted authserv-id
+    pub fn ignored(&self) -> Vec<&'hdr AuthenticationResults<'hdr>> {
+        match self.trusted_authserv_ids {
+            Some(ids) => self.auth_status.untrusted(ids).collect(),
+            None => vec![],
         }
+    }
+    /// Verify that Auth-Results contain at least one DKIM pass for header.d or SPF pass for
+    /// smtp.mailfrom relevant to Return-Path header
+    pub fn verify(&self) -> Result<ReturnPathVerifierStatus, ReturnPathVerifierError<'hdr>> {
+        Ok(self.verdict().status)
+    }
+    /// Verdict with the results that decided it
+    ///
+    /// Host may have multiple signature methods - one of many passing is enough.
+    /// Without a pass a fail decides over a temperror over a permerror.
+    pub fn verdict(&self) -> ReturnPathVerdict<'hdr> {
+        let Decision {
+            outcome,
+            evidence,
+            ignored,
+        } = Decision::new(
+            self.auth_status,
+            self.trusted_authserv_ids,
+            self.return_path.domain,
+            self.alignment,
+        );
 
-        match dkim_pass_selector {
-            true => Ok(ReturnPathVerifierStatus::Pass),
-            false => Ok(ReturnPathVerifierStatus::Fail),
+        let status = match outcome {
+            Outcome::Pass => ReturnPathVerifierStatus::Pass(AlignedMechanism::of(&evidence)),
+            Outcome::Fail => ReturnPathVerifierStatus::Fail,
+            Outcome::TempError => ReturnPathVerifierStatus::TempError,
+            Outcome::PermError => ReturnPathVerifierStatus::PermError,
+            Outcome::Nothing => ReturnPathVerifierStatus::Nothing,
+        };
+
+        ReturnPathVerdict {
+            status,
+            evidence,
+            ignored,
         }
     }
 }
@@ -146,10 +400,26 @@ mod test {
     }
 
     #[rstest]
-    #[case("to_in_protonmail.eml", Ok(ReturnPathVerifierStatus::Pass))]
-    #[case("to_in_fastmail.eml", Ok(ReturnPathVerifierStatus::Pass))]
-    #[case("to_in_areweat.eml", Ok(ReturnPathVerifierStatus::Pass))]
-    #[case("fail_to_in_areweat.eml", Ok(ReturnPathVerifierStatus::Fail))]
+    #[case(
+        "to_in_protonmail.eml",
+        Ok(ReturnPathVerifierStatus::Pass(AlignedMechanism::Both))
+    )]
+    #[case(
+        "to_in_fastmail.eml",
+        Ok(ReturnPathVerifierStatus::Pass(AlignedMechanism::Both))
+    )]
+    #[case(
+        "to_in_areweat.eml",
+        Ok(ReturnPathVerifierStatus::Pass(AlignedMechanism::Both))
+    )]
+    #[case(
+        "fail_to_in_areweat.eml",
+        Ok(ReturnPathVerifierStatus::Pass(AlignedMechanism::Spf))
+    )]
+    #[case(
+        "from_gmail_to_arewe_at.eml",
+        Ok(ReturnPathVerifierStatus::Pass(AlignedMechanism::Dkim))
+    )]
     fn from_mail_parser(
         #[case] file: &'static str,
         #[case] expected: Result<ReturnPathVerifierStatus, ReturnPathVerifierError<'static>>,
@@ -164,4 +434,211 @@ mod test {
 
         assert_eq!(verifier.verify(), expected);
     }
+
+    #[rstest]
+    #[case(&["mail.localhost.horse"], Ok(ReturnPathVerifierStatus::Pass(AlignedMechanism::Both)), 0)]
+    #[case(&["*.localhost.horse"], Ok(ReturnPathVerifierStatus::Pass(AlignedMechanism::Both)), 0)]
+    #[case(&["mx.example.com"], Ok(ReturnPathVerifierStatus::Nothing), 1)]
+    fn trusted_authserv_ids(
+        #[case] ids: &'static [&'static str],
+        #[case] expected: Result<ReturnPathVerifierStatus, ReturnPathVerifierError<'static>>,
+        #[case] ignored: usize,
+    ) {
+        let full_path = PathBuf::from("test_data").join("to_in_areweat.eml");
+        let raw = load_test_data(&full_path);
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(&raw).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        let verifier = ReturnPathVerifier::from_alloc_yes(&status, &parsed_message)
+            .unwrap()
+            .with_trusted_authserv_ids(ids);
+
+        assert_eq!(verifier.verify(), expected);
+        assert_eq!(verifier.ignored().len(), ignored);
+    }
+
+    #[test]
+    fn spf_softfail_without_dkim() {
+        let full_path = PathBuf::from("test_data").join("from_gmail_to_arewe_at.eml");
+        let raw = String::from_utf8(load_test_data(&full_path)).unwrap();
+        let no_dkim = raw.replace(
+            "dkim=pass header.d=gmail.com",
+            "dkim=fail header.d=gmail.com",
+        );
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(no_dkim.as_bytes()).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        let verifier = ReturnPathVerifier::from_alloc_yes(&status, &parsed_message).unwrap();
+
+        assert_eq!(verifier.verify(), Ok(ReturnPathVerifierStatus::Fail));
+    }
+
+    #[rstest]
+    #[case("example.com", "example.com")]
+    #[case("user@Example.com", "Example.com")]
+    #[case("<user@example.com>", "example.com")]
+    fn mailfrom_domains(#[case] mailfrom: &str, #[case] expected: &str) {
+        assert_eq!(mailfrom_domain(mailfrom), expected);
+    }
+
+    fn verdict_of<'hdr>(
+        status: &'hdr MessageAuthStatus<'hdr>,
+        parsed_message: &'hdr mail_parser::Message<'hdr>,
+        trusted: Option<&'hdr [&'hdr str]>,
+    ) -> ReturnPathVerdict<'hdr> {
+        let verifier = ReturnPathVerifier::from_alloc_yes(status, parsed_message).unwrap();
+        match trusted {
+            Some(ids) => verifier.with_trusted_authserv_ids(ids).verdict(),
+            None => verifier.verdict(),
+        }
+    }
+
+    #[test]
+    fn verdict_evidence() {
+        let full_path = PathBuf::from("test_data").join("to_in_areweat.eml");
+        let raw = load_test_data(&full_path);
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(&raw).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+
+        let verdict = verdict_of(&status, &parsed_message, None);
+        assert_eq!(
+            verdict.status,
+            ReturnPathVerifierStatus::Pass(AlignedMechanism::Both)
+        );
+        let evidence: Vec<(usize, Option<&str>, bool)> = verdict
+            .evidence
+            .iter()
+            .map(|e| {
+                (
+                    e.index,
+                    e.authserv_id,
+                    matches!(e.result, AlignedResult::Dkim(_)),
+                )
+            })
+            .collect();
+        assert_eq!(
+            evidence,
+            vec![
+                (0, Some("mail.localhost.horse"), true),
+                (0, Some("mail.localhost.horse"), false),
+            ]
+        );
+        match verdict.evidence[1].result {
+            AlignedResult::Spf(spf_res) => {
+                assert_eq!(spf_res.smtp_mailfrom, Some("past.job1295@fastmail.com"))
+            }
+            _ => panic!("expected SPF evidence"),
+        }
+        assert_eq!(verdict.ignored, vec![]);
+
+        let verdict = verdict_of(&status, &parsed_message, Some(&["mx.example.com"]));
+        assert_eq!(verdict.status, ReturnPathVerifierStatus::Nothing);
+        assert_eq!(verdict.evidence, vec![]);
+        assert_eq!(verdict.ignored.len(), 2);
+    }
+
+    #[rstest]
+    #[case("temperror", "temperror", ReturnPathVerifierStatus::TempError, 2)]
+    #[case("permerror", "temperror", ReturnPathVerifierStatus::TempError, 1)]
+    #[case("permerror", "permerror", ReturnPathVerifierStatus::PermError, 2)]
+    #[case("permerror", "fail", ReturnPathVerifierStatus::Fail, 1)]
+    #[case("neutral", "none", ReturnPathVerifierStatus::Nothing, 0)]
+    fn verdict_without_pass(
+        #[case] dkim: &str,
+        #[case] spf: &str,
+        #[case] expected: ReturnPathVerifierStatus,
+        #[case] evidence: usize,
+    ) {
+        let full_path = PathBuf::from("test_data").join("to_in_areweat.eml");
+        let raw = String::from_utf8(load_test_data(&full_path))
+            .unwrap()
+            .replace(
+                "dkim=pass header.d=fastmail.com",
+                &format!("dkim={} header.d=fastmail.com", dkim),
+            )
+            .replace(
+                "spf=pass (mail.localhost.horse: domain of past.job1295",
+                &format!("spf={} (mail.localhost.horse: domain of past.job1295", spf),
+            );
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(raw.as_bytes()).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+
+        let verdict = verdict_of(&status, &parsed_message, None);
+        assert_eq!(verdict.status, expected);
+        assert_eq!(verdict.evidence.len(), evidence);
+    }
+
+    #[rstest]
+    #[case(
+        "fastmail.com",
+        Alignment::Strict,
+        ReturnPathVerifierStatus::Pass(AlignedMechanism::Both)
+    )]
+    #[case(
+        "FastMail.COM.",
+        Alignment::Strict,
+        ReturnPathVerifierStatus::Pass(AlignedMechanism::Both)
+    )]
+    #[case(
+        "bounces.fastmail.com",
+        Alignment::Strict,
+        ReturnPathVerifierStatus::Nothing
+    )]
+    #[case(
+        "bounces.fastmail.com",
+        Alignment::Relaxed,
+        ReturnPathVerifierStatus::Pass(AlignedMechanism::Both)
+    )]
+    #[case(
+        "fastmail.co.uk",
+        Alignment::Relaxed,
+        ReturnPathVerifierStatus::Nothing
+    )]
+    fn return_path_alignment(
+        #[case] return_path_domain: &str,
+        #[case] alignment: Alignment,
+        #[case] expected: ReturnPathVerifierStatus,
+    ) {
+        let full_path = PathBuf::from("test_data").join("to_in_areweat.eml");
+        let raw = String::from_utf8(load_test_data(&full_path))
+            .unwrap()
+            .replace(
+                "Return-Path: <past.job1295@fastmail.com>",
+                &format!("Return-Path: <past.job1295@{}>", return_path_domain),
+            );
+        let parser = mail_parser::MessageParser::default();
+        let parsed_message = parser.parse(raw.as_bytes()).unwrap();
+        let status = MessageAuthStatus::from_mail_parser(&parsed_message).unwrap();
+        let verifier = ReturnPathVerifier::from_alloc_yes(&status, &parsed_message)
+            .unwrap()
+            .with_alignment(alignment);
+
+        assert_eq!(verifier.verify(), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("example.com", "EXAMPLE.com.", Alignment::Strict, true)]
+    #[case("bounces.example.com", "example.com", Alignment::Strict, false)]
+    #[case("bounces.example.com", "example.com", Alignment::Relaxed, true)]
+    #[case("a.example.co.uk", "b.example.co.uk", Alignment::Relaxed, true)]
+    #[case("example.co.uk", "other.co.uk", Alignment::Relaxed, false)]
+    #[case("co.uk", "example.co.uk", Alignment::Relaxed, false)]
+    fn is_aligned(
+        #[case] left: &str,
+        #[case] right: &str,
+        #[case] alignment: Alignment,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(alignment.is_aligned(left, right), expected);
+    }
+
+    #[test]
+    fn is_aligned_by() {
+        let list = PublicSuffixList::from("com\nexample.com\n");
+        assert!(!Alignment::Relaxed.is_aligned_by(&list, "a.example.com", "b.example.com"));
+        assert!(Alignment::Relaxed.is_aligned_by(&list, "x.a.example.com", "a.example.com"));
+        assert!(Alignment::Strict.is_aligned_by(&list, "Example.com", "example.com."));
+    }
 }
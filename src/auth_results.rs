@@ -1,7 +1,7 @@
 //! Parsed Authetication-Results
 
 use crate::auth::AuthProperty;
-use crate::dkim::DkimProperty;
+use crate::dkim::{domain_is_within, DkimProperty};
 use crate::iprev::IpRevProperty;
 use crate::spf::SpfProperty;
 
@@ -14,6 +14,26 @@ pub struct HostVersion<'hdr> {
     pub version: Option<u32>,
 }
 
+impl<'hdr> HostVersion<'hdr> {
+    /// Whether the authserv-id is one of the given trusted ones - RFC 8601 s. 5
+    ///
+    /// `example.com` matches only itself and `*.example.com` matches only its
+    /// subdomains, both case-insensitively and ignoring any trailing root dot.
+    pub fn is_trusted_by(&self, authserv_ids: &[&str]) -> bool {
+        authserv_ids
+            .iter()
+            .any(|pattern| match pattern.strip_prefix("*.") {
+                Some(parent) => {
+                    domain_is_within(self.host, parent) && !domain_is_within(parent, self.host)
+                }
+                None => self
+                    .host
+                    .trim_end_matches('.')
+                    .eq_ignore_ascii_case(pattern.trim_end_matches('.')),
+            })
+    }
+}
+
 /// Auth-Result properties
 #[derive(Clone, Debug, PartialEq)]
 pub enum Prop<'hdr> {
@@ -41,3 +61,28 @@ pub struct UnknownProperty<'hdr> {
     /// Unknown property value
     pval: &'hdr str,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("mx.example.com", &["mx.example.com"], true)]
+    #[case("MX.Example.com.", &["mx.example.com"], true)]
+    #[case("mx.example.com", &["example.com"], false)]
+    #[case("mx.example.com", &["*.example.com"], true)]
+    #[case("a.mx.example.com", &["*.example.com"], true)]
+    #[case("example.com", &["*.example.com"], false)]
+    #[case("mx.example.com.evil", &["*.example.com"], false)]
+    #[case("evilexample.com", &["*.example.com"], false)]
+    #[case("mx.example.org", &["*.example.com", "mx.example.org"], true)]
+    #[case("mx.example.com", &[], false)]
+    fn trusted(#[case] host: &str, #[case] ids: &[&str], #[case] expected: bool) {
+        let host_version = HostVersion {
+            host,
+            version: None,
+        };
+        assert_eq!(host_version.is_trusted_by(ids), expected);
+    }
+}
@@ -1,5 +1,8 @@
 //! Method dkim Result and associated types
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
 /// Parsed dkim Result - see RFC 6376 for the header tags
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct DkimResult<'hdr> {
@@ -40,7 +43,7 @@ impl<'hdr> DkimResult<'hdr> {
 }
 
 /// DKIM Result Codes - s.2.7.1
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Ord)]
 pub enum DkimResultCode {
     /// Result code not seen
     #[default]
@@ -83,6 +86,29 @@ pub enum DkimQueryMethod<'hdr> {
     Unknown(&'hdr str),
 }
 
+impl<'hdr> From<&'hdr str> for DkimQueryMethod<'hdr> {
+    fn from(method: &'hdr str) -> Self {
+        match method {
+            "dns/txt" => Self::DnsTxt,
+            _ => Self::Unknown(method),
+        }
+    }
+}
+
+// Parse q= colon separated query methods
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub(crate) fn parse_query_methods(
+    in_str: &str,
+) -> Result<Vec<DkimQueryMethod<'_>>, crate::error::DkimTagValueError> {
+    in_str
+        .split(':')
+        .map(|method| match method.trim() {
+            "" => Err(crate::error::DkimTagValueError::EmptyQueryMethod),
+            method => Ok(method.into()),
+        })
+        .collect()
+}
+
 pub mod ptypes;
 pub use ptypes::DkimProperty;
 
@@ -90,14 +116,63 @@ mod algorithm;
 pub use algorithm::DkimAlgorithm;
 
 mod canonicalization;
-pub use canonicalization::DkimCanonicalization;
+pub use canonicalization::{DkimCanon, DkimCanonicalization};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+mod canonicalize;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use canonicalize::BodyCanonicalizer;
 
+#[cfg(feature = "verifier")]
+mod body_hash;
+#[cfg(feature = "verifier")]
+pub use body_hash::{BodyHasher, DkimBodyHash, MultiBodyHasher};
+
+#[cfg(feature = "verifier")]
+mod verify;
+#[cfg(feature = "verifier")]
+pub use verify::{DkimResultReason, DkimVerification, DkimVerifier};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
 mod signature;
+#[cfg(any(feature = "alloc", feature = "std"))]
 pub use signature::DkimSignature;
 
 mod header;
 pub use header::DkimHeader;
 
+mod atps;
+pub use atps::{DkimAtpsHash, DkimAtpsResult, DkimAtpsResultCode};
+
+mod auid;
+pub(crate) use auid::domain_is_within;
+pub use auid::DkimAuid;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub(crate) mod copied_headers;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use copied_headers::DkimCopiedHeader;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+mod encoded;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use encoded::DkimBase64;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+mod key_record;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use key_record::DkimKeyRecord;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+mod lint;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use lint::{DkimLint, DkimLintFinding, DkimLintOptions, DkimLintSeverity};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+mod signed_headers;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use signed_headers::DkimSignedHeaders;
+
 mod timestamp;
 pub use timestamp::DkimTimestamp;
 
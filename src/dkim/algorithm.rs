@@ -16,6 +16,7 @@ pub enum DkimAlgorithm<'hdr> {
 
 use crate::error::DkimAlgorithmError;
 
+#[allow(clippy::infallible_try_from)]
 impl<'hdr> TryFrom<&'hdr str> for DkimAlgorithm<'hdr> {
     type Error = DkimAlgorithmError;
 
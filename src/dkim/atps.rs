@@ -0,0 +1,245 @@
+//! Authorized Third-Party Signatures (ATPS) - RFC 6541
+
+#[cfg(feature = "verifier")]
+use crate::error::ResolverError;
+#[cfg(feature = "verifier")]
+use crate::resolver::TxtResolver;
+
+use super::DkimHeader;
+#[cfg(feature = "verifier")]
+use super::DkimSignature;
+
+#[cfg(all(feature = "alloc", not(feature = "std"), feature = "verifier"))]
+use alloc::{format, string::String};
+
+/// The atpsh= hash algorithm used to form the ATPS query - RFC 6541 s. 4.2
+#[derive(Clone, Debug, PartialEq)]
+pub enum DkimAtpsHash<'hdr> {
+    /// The SDID is used as-is without hashing
+    None,
+    /// SHA-1
+    Sha1,
+    /// SHA-256
+    Sha256,
+    /// Unknown
+    Unknown(&'hdr str),
+}
+
+impl<'hdr> From<&'hdr str> for DkimAtpsHash<'hdr> {
+    fn from(hash: &'hdr str) -> Self {
+        match hash {
+            "none" => Self::None,
+            "sha1" => Self::Sha1,
+            "sha256" => Self::Sha256,
+            _ => Self::Unknown(hash),
+        }
+    }
+}
+
+/// Parsed dkim-atps Result - RFC 6541 s. 8.1
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DkimAtpsResult<'hdr> {
+    /// dkim-atps Result
+    pub code: DkimAtpsResultCode,
+    /// Reason if supplied
+    pub reason: Option<&'hdr str>,
+    /// header.from - the Author Domain the ATPS check was made against
+    pub header_from: Option<&'hdr str>,
+    /// Unparsed raw
+    pub raw: Option<&'hdr str>,
+}
+
+impl<'hdr> DkimAtpsResult<'hdr> {
+    pub(crate) fn set_header(&mut self, prop: &DkimHeader<'hdr>) -> bool {
+        match prop {
+            DkimHeader::Rfc5322From(val) => self.header_from = Some(val),
+            _ => {}
+        }
+        true
+    }
+}
+
+/// dkim-atps Result Codes - RFC 6541 s. 8.1
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum DkimAtpsResultCode {
+    /// Result code not seen
+    #[default]
+    Unknown,
+    /// No ATPS evaluation was done e.g. there was no atps= or the
+    /// signer is the Author Domain itself
+    NoneAtps,
+    /// The Author Domain publishes an ATPS record authorizing the signer
+    Pass,
+    /// The Author Domain does not authorize the signer
+    Fail,
+    /// The ATPS record could not be retrieved due to a transient error
+    TempError,
+    /// The signature ATPS tags could not be evaluated e.g. unsupported atpsh=
+    PermError,
+}
+
+#[cfg(feature = "verifier")]
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// RFC 4648 base32 without the trailing padding
+#[cfg(feature = "verifier")]
+fn base32_unpadded(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | u16::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[usize::from((buffer >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+    }
+    out
+}
+
+#[cfg(feature = "verifier")]
+impl<'hdr> DkimSignature<'hdr> {
+    /// ATPS query name for the given Author Domain - RFC 6541 s. 4.3
+    ///
+    /// The SDID is lowercased before hashing. None when atpsh= is
+    /// missing or names an unsupported algorithm.
+    pub fn atps_query_name(&self, author_domain: &str) -> Option<String> {
+        use sha1::Sha1;
+        use sha2::{Digest, Sha256};
+
+        let sdid = self.d.trim_end_matches('.').to_ascii_lowercase();
+        let label = match self.atpsh.as_ref()? {
+            DkimAtpsHash::None => sdid,
+            DkimAtpsHash::Sha1 => base32_unpadded(&Sha1::digest(sdid.as_bytes())),
+            DkimAtpsHash::Sha256 => base32_unpadded(&Sha256::digest(sdid.as_bytes())),
+            DkimAtpsHash::Unknown(_) => return None,
+        };
+        Some(format!(
+            "{}._atps.{}",
+            label,
+            author_domain.trim_end_matches('.')
+        ))
+    }
+
+    /// Check whether the Author Domain authorizes this signer - RFC 6541 s. 5
+    ///
+    /// Only meaningful for a signature that has already verified. The
+    /// resolver is queried only when atps= names the Author Domain and the
+    /// signer is a third party.
+    pub fn check_atps<R: TxtResolver + ?Sized>(
+        &self,
+        author_domain: &str,
+        resolver: &R,
+    ) -> DkimAtpsResultCode {
+        let author_domain = author_domain.trim_end_matches('.');
+        let atps = match self.atps {
+            Some(atps) => atps.trim_end_matches('.'),
+            None => return DkimAtpsResultCode::NoneAtps,
+        };
+        if self
+            .d
+            .trim_end_matches('.')
+            .eq_ignore_ascii_case(author_domain)
+        {
+            return DkimAtpsResultCode::NoneAtps;
+        }
+        if !atps.eq_ignore_ascii_case(author_domain) {
+            return DkimAtpsResultCode::Fail;
+        }
+        let query_name = match self.atps_query_name(author_domain) {
+            Some(query_name) => query_name,
+            None => return DkimAtpsResultCode::PermError,
+        };
+        match resolver.lookup_txt(&query_name) {
+            Ok(records) if records.iter().any(|r| is_atps_record(r)) => DkimAtpsResultCode::Pass,
+            Ok(_) | Err(ResolverError::NotFound) => DkimAtpsResultCode::Fail,
+            Err(ResolverError::Temporary) => DkimAtpsResultCode::TempError,
+            Err(ResolverError::Permanent) => DkimAtpsResultCode::PermError,
+        }
+    }
+}
+
+// RFC 6541 s. 4.4 the record must begin with v=ATPS1
+#[cfg(feature = "verifier")]
+fn is_atps_record(record: &str) -> bool {
+    let first_tag = record.split(';').next().unwrap_or_default();
+    match first_tag.split_once('=') {
+        Some((tag, value)) => tag.trim() == "v" && value.trim() == "ATPS1",
+        None => false,
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "verifier")]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    struct StaticResolver(&'static str, Result<Vec<String>, ResolverError>);
+
+    impl TxtResolver for StaticResolver {
+        fn lookup_txt(&self, name: &str) -> Result<Vec<String>, ResolverError> {
+            match name == self.0 {
+                true => self.1.clone(),
+                false => Err(ResolverError::NotFound),
+            }
+        }
+    }
+
+    fn signature(atps: &str, atpsh: &str) -> String {
+        format!(
+            "v=1; a=rsa-sha256; d=Third-Party.example; s=sel; h=from; bh=AAAA; b=AAAA; atps={}; atpsh={}",
+            atps, atpsh
+        )
+    }
+
+    #[rstest]
+    #[case("none", Some("third-party.example._atps.example.com"))]
+    #[case("sha1", Some("U3QGIK2NVRS3YPZ52Y6ZDLFBF774F3T7._atps.example.com"))]
+    #[case(
+        "sha256",
+        Some("O2WUCFSGAO66TPBG4SQF3KH4YE4RO45LQFAKFCUO3XLHBTZREBQA._atps.example.com")
+    )]
+    #[case("md5", None)]
+    fn query_name(#[case] atpsh: &str, #[case] expected: Option<&str>) {
+        let raw = signature("example.com", atpsh);
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        assert_eq!(sig.atps_query_name("example.com").as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case("example.com", Ok(vec!["v=ATPS1;".to_string()]), DkimAtpsResultCode::Pass)]
+    #[case("example.com", Ok(vec!["v=spf1 -all".to_string()]), DkimAtpsResultCode::Fail)]
+    #[case("example.com", Err(ResolverError::NotFound), DkimAtpsResultCode::Fail)]
+    #[case(
+        "example.com",
+        Err(ResolverError::Temporary),
+        DkimAtpsResultCode::TempError
+    )]
+    #[case("example.org", Ok(vec!["v=ATPS1".to_string()]), DkimAtpsResultCode::Fail)]
+    fn check(
+        #[case] atps: &str,
+        #[case] answer: Result<Vec<String>, ResolverError>,
+        #[case] expected: DkimAtpsResultCode,
+    ) {
+        let raw = signature(atps, "none");
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        let resolver = StaticResolver("third-party.example._atps.example.com", answer);
+        assert_eq!(sig.check_atps("example.com", &resolver), expected);
+    }
+
+    #[test]
+    fn first_party_is_none() {
+        let raw = signature("example.com", "none");
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        let resolver = StaticResolver("", Ok(vec![]));
+        assert_eq!(
+            sig.check_atps("third-party.example", &resolver),
+            DkimAtpsResultCode::NoneAtps
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! DKIM Agent or User Identifier (AUID) - i=
+
+use crate::error::DkimAuidError;
+
+/// The Agent or User Identifier (AUID) - RFC 6376 s. 3.5 i=
+///
+/// Syntax is `[ Local-part ] "@" domain-name` where the domain must be the same
+/// as or a subdomain of the SDID in d=
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkimAuid<'hdr> {
+    /// Local part if any - may be dkim-quoted-printable encoded
+    pub local_part: Option<&'hdr str>,
+    /// Domain
+    pub domain: &'hdr str,
+    /// Raw
+    pub raw: &'hdr str,
+}
+
+impl<'hdr> DkimAuid<'hdr> {
+    /// Whether the AUID domain is the same as or a subdomain of the given SDID
+    pub fn is_within(&self, sdid: &str) -> bool {
+        domain_is_within(self.domain, sdid)
+    }
+}
+
+// Case-insensitive same domain or subdomain check ignoring any trailing root dot
+pub(crate) fn domain_is_within(domain: &str, parent: &str) -> bool {
+    let domain = domain.trim_end_matches('.');
+    let parent = parent.trim_end_matches('.');
+
+    if domain.len() == parent.len() {
+        return domain.eq_ignore_ascii_case(parent);
+    }
+    if domain.len() < parent.len() + 1 {
+        return false;
+    }
+    let split = domain.len() - parent.len();
+    match (domain.get(..split), domain.get(split..)) {
+        (Some(prefix), Some(suffix)) => {
+            prefix.ends_with('.') && suffix.eq_ignore_ascii_case(parent)
+        }
+        _ => false,
+    }
+}
+
+impl<'hdr> TryFrom<&'hdr str> for DkimAuid<'hdr> {
+    type Error = DkimAuidError;
+
+    fn try_from(in_str: &'hdr str) -> Result<Self, Self::Error> {
+        let raw = in_str.trim();
+        let (local_part, domain) = match raw.rsplit_once('@') {
+            Some(parts) => parts,
+            None => return Err(DkimAuidError::NoAt),
+        };
+        if domain.is_empty() {
+            return Err(DkimAuidError::NoDomain);
+        }
+        let local_part = match local_part.is_empty() {
+            true => None,
+            false => Some(local_part),
+        };
+        Ok(Self {
+            local_part,
+            domain,
+            raw,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("example.com", "example.com", true)]
+    #[case("EXAMPLE.com.", "example.COM", true)]
+    #[case("football.example.com", "example.com", true)]
+    #[case("badexample.com", "example.com", false)]
+    #[case("example.com", "football.example.com", false)]
+    #[case("example.org", "example.com", false)]
+    fn within(#[case] domain: &str, #[case] parent: &str, #[case] expected: bool) {
+        assert_eq!(domain_is_within(domain, parent), expected);
+    }
+}
@@ -0,0 +1,351 @@
+//! Streaming DKIM body hashing - RFC 6376 s. 3.7
+//!
+//! The body is fed in arbitrary chunks e.g. as it arrives during the SMTP
+//! DATA phase and is never buffered whole.
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use super::{BodyCanonicalizer, DkimAlgorithm, DkimBase64, DkimCanon, DkimSignature};
+use crate::error::BodyHasherError;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HashKind {
+    Sha1,
+    Sha256,
+}
+
+impl TryFrom<&DkimAlgorithm<'_>> for HashKind {
+    type Error = BodyHasherError;
+
+    fn try_from(algorithm: &DkimAlgorithm<'_>) -> Result<Self, Self::Error> {
+        match algorithm {
+            DkimAlgorithm::Rsa_Sha1 => Ok(Self::Sha1),
+            DkimAlgorithm::Rsa_Sha256 | DkimAlgorithm::Ed25519_Sha256 => Ok(Self::Sha256),
+            DkimAlgorithm::Unknown(_) => Err(BodyHasherError::UnsupportedAlgorithm),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum HashState {
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl HashState {
+    fn new(kind: HashKind) -> Self {
+        match kind {
+            HashKind::Sha1 => Self::Sha1(Sha1::new()),
+            HashKind::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha1(hash) => hash.update(data),
+            Self::Sha256(hash) => hash.update(data),
+        }
+    }
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Self::Sha1(hash) => hash.finalize().to_vec(),
+            Self::Sha256(hash) => hash.finalize().to_vec(),
+        }
+    }
+}
+
+// Canonicalize + hash for one algorithm, canonicalization and l= combination
+#[derive(Clone, Debug)]
+struct BodyDigest {
+    canonicalizer: BodyCanonicalizer,
+    hash: HashState,
+    scratch: Vec<u8>,
+}
+
+impl BodyDigest {
+    fn new(sig: &DkimSignature<'_>) -> Result<Self, BodyHasherError> {
+        let hash = HashState::new(HashKind::try_from(&sig.a)?);
+        let canonicalizer = BodyCanonicalizer::new(&sig.c.body, sig.l)
+            .ok_or(BodyHasherError::UnsupportedCanonicalization)?;
+        Ok(Self {
+            canonicalizer,
+            hash,
+            scratch: vec![],
+        })
+    }
+    fn update(&mut self, chunk: &[u8]) {
+        self.canonicalizer.update(chunk, &mut self.scratch);
+        self.hash.update(&self.scratch);
+        self.scratch.clear();
+    }
+    // Hash with the hashed and the whole canonicalized body lengths
+    fn finish(mut self) -> (Vec<u8>, u64, u64) {
+        let written = self.canonicalizer.written();
+        let total = self.canonicalizer.finish(&mut self.scratch);
+        self.hash.update(&self.scratch);
+        (
+            self.hash.finish(),
+            written + self.scratch.len() as u64,
+            total,
+        )
+    }
+}
+
+/// Outcome of hashing the body for a signature
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkimBodyHash {
+    /// Hash computed over the canonicalized body
+    pub computed: DkimBase64,
+    /// Hash claimed in bh=
+    pub expected: DkimBase64,
+    /// Number of canonicalized body bytes hashed - less than l= when the body was shorter
+    pub length: u64,
+    /// Number of canonicalized body bytes ignoring l= - more than length when l= truncated the body
+    pub body_length: u64,
+}
+
+impl DkimBodyHash {
+    /// Whether the computed hash matches bh=
+    pub fn matches(&self) -> bool {
+        self.computed == self.expected
+    }
+    /// Whether l= left some of the body out of the hash
+    pub fn is_truncated(&self) -> bool {
+        self.body_length > self.length
+    }
+}
+
+/// Incremental body hasher for a single signature
+#[derive(Clone, Debug)]
+pub struct BodyHasher {
+    digest: BodyDigest,
+    expected: DkimBase64,
+}
+
+impl BodyHasher {
+    /// New hasher using the signature algorithm, body canonicalization and l=
+    pub fn new(sig: &DkimSignature<'_>) -> Result<Self, BodyHasherError> {
+        Ok(Self {
+            digest: BodyDigest::new(sig)?,
+            expected: sig.bh.clone(),
+        })
+    }
+    /// Feed the next chunk of the body - chunks may split lines anywhere
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.digest.update(chunk);
+    }
+    /// Finish hashing and compare against bh=
+    pub fn finish(self) -> DkimBodyHash {
+        let (computed, length, body_length) = self.digest.finish();
+        DkimBodyHash {
+            computed: DkimBase64(computed),
+            expected: self.expected,
+            length,
+            body_length,
+        }
+    }
+}
+
+// Signatures with the same key hash the body identically
+#[derive(Clone, Debug, PartialEq)]
+struct DigestKey<'hdr> {
+    hash: HashKind,
+    canon: DkimCanon<'hdr>,
+    limit: Option<u64>,
+}
+
+/// Hashes the body once for every signature on a message
+///
+/// Signatures sharing the same hash algorithm, body canonicalization and l=
+/// share a single digest so the body is only canonicalized and hashed once
+/// per distinct combination.
+#[derive(Clone, Debug)]
+pub struct MultiBodyHasher<'hdr> {
+    keys: Vec<DigestKey<'hdr>>,
+    digests: Vec<BodyDigest>,
+    slots: Vec<Result<(usize, DkimBase64), BodyHasherError>>,
+}
+
+impl<'hdr> MultiBodyHasher<'hdr> {
+    /// New hasher for all the given signatures
+    pub fn new<'a, I>(signatures: I) -> Self
+    where
+        I: IntoIterator<Item = &'a DkimSignature<'hdr>>,
+        'hdr: 'a,
+    {
+        let mut new_self = Self {
+            keys: vec![],
+            digests: vec![],
+            slots: vec![],
+        };
+        for sig in signatures {
+            let hash = match HashKind::try_from(&sig.a) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    new_self.slots.push(Err(e));
+                    continue;
+                }
+            };
+            let key = DigestKey {
+                hash,
+                canon: sig.c.body.clone(),
+                limit: sig.l,
+            };
+            let slot = match new_self.keys.iter().position(|k| *k == key) {
+                Some(idx) => Ok((idx, sig.bh.clone())),
+                None => BodyDigest::new(sig).map(|digest| {
+                    new_self.keys.push(key);
+                    new_self.digests.push(digest);
+                    (new_self.digests.len() - 1, sig.bh.clone())
+                }),
+            };
+            new_self.slots.push(slot);
+        }
+        new_self
+    }
+    /// Number of distinct digests computed per chunk
+    pub fn passes(&self) -> usize {
+        self.digests.len()
+    }
+    /// Feed the next chunk of the body to every digest
+    pub fn update(&mut self, chunk: &[u8]) {
+        for digest in self.digests.iter_mut() {
+            digest.update(chunk);
+        }
+    }
+    /// Finish hashing - results are in the same order as the signatures given
+    pub fn finish(self) -> Vec<Result<DkimBodyHash, BodyHasherError>> {
+        let finished: Vec<(Vec<u8>, u64, u64)> = self
+            .digests
+            .into_iter()
+            .map(|digest| digest.finish())
+            .collect();
+        self.slots
+            .into_iter()
+            .map(|slot| {
+                slot.map(|(idx, expected)| DkimBodyHash {
+                    computed: DkimBase64(finished[idx].0.clone()),
+                    expected,
+                    length: finished[idx].1,
+                    body_length: finished[idx].2,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    // RFC 6376 A.2 body as hashed for the published bh=
+    const BODY: &[u8] = b"Hi.\r\n\r\nWe lost the game. Are you hungry yet?\r\n\r\nJoe.\r\n";
+
+    fn signature(a: &str, c: &str, extra: &str) -> String {
+        format!(
+            "v=1; a={}; c={}; d=example.com; s=brisbane; h=from; bh=2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8=; b=AAAA{}",
+            a, c, extra
+        )
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    #[case(7)]
+    #[case(BODY.len())]
+    fn chunked(#[case] chunk_size: usize) {
+        let raw = signature("rsa-sha256", "simple/simple", "");
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        let mut hasher = BodyHasher::new(&sig).unwrap();
+        for chunk in BODY.chunks(chunk_size) {
+            hasher.update(chunk);
+        }
+        let res = hasher.finish();
+        assert!(res.matches());
+        assert_eq!(res.length, BODY.len() as u64);
+    }
+
+    #[test]
+    fn relaxed_whitespace_split_across_chunks() {
+        let raw = signature("rsa-sha256", "simple/relaxed", "");
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        let spaced =
+            b"Hi. \t\r\n\r\nWe  lost the game.\t Are you hungry yet?  \r\n\r\nJoe.\r\n\r\n";
+        let mut hasher = BodyHasher::new(&sig).unwrap();
+        for chunk in spaced.chunks(1) {
+            hasher.update(chunk);
+        }
+        let mut whole = BodyHasher::new(&sig).unwrap();
+        whole.update(b"Hi.\r\n\r\nWe lost the game. Are you hungry yet?\r\n\r\nJoe.\r\n");
+        assert_eq!(hasher.finish().computed, whole.finish().computed);
+    }
+
+    #[test]
+    fn appended_after_body_length() {
+        let limit = format!("; l={}", BODY.len());
+        let raw = signature("rsa-sha256", "simple/simple", &limit);
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        let mut hasher = BodyHasher::new(&sig).unwrap();
+        hasher.update(BODY);
+        hasher.update(b"Appended footer\r\n");
+        let res = hasher.finish();
+        assert!(res.matches());
+        assert_eq!(res.length, BODY.len() as u64);
+        assert!(res.is_truncated());
+    }
+
+    #[test]
+    fn mismatch() {
+        let raw = signature("rsa-sha256", "simple/simple", "");
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        let mut hasher = BodyHasher::new(&sig).unwrap();
+        hasher.update(b"Tampered\r\n");
+        assert!(!hasher.finish().matches());
+    }
+
+    #[test]
+    fn unsupported() {
+        let raw = signature("rsa-md5", "simple/simple", "");
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        assert_eq!(
+            BodyHasher::new(&sig).unwrap_err(),
+            BodyHasherError::UnsupportedAlgorithm
+        );
+        let raw = signature("rsa-sha256", "simple/fancy", "");
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        assert_eq!(
+            BodyHasher::new(&sig).unwrap_err(),
+            BodyHasherError::UnsupportedCanonicalization
+        );
+    }
+
+    #[test]
+    fn multi_shares_passes() {
+        let raws = [
+            signature("rsa-sha256", "simple/simple", ""),
+            signature("ed25519-sha256", "relaxed/simple", ""),
+            signature("rsa-sha256", "simple/relaxed", ""),
+            signature("rsa-md5", "simple/simple", ""),
+        ];
+        let sigs: Vec<DkimSignature<'_>> = raws
+            .iter()
+            .map(|raw| DkimSignature::try_from(raw.as_str()).unwrap())
+            .collect();
+        let mut hasher = MultiBodyHasher::new(sigs.iter());
+        assert_eq!(hasher.passes(), 2);
+        for chunk in BODY.chunks(5) {
+            hasher.update(chunk);
+        }
+        let results = hasher.finish();
+        assert_eq!(results.len(), 4);
+        assert!(results[0].as_ref().unwrap().matches());
+        assert!(results[1].as_ref().unwrap().matches());
+        assert!(results[2].as_ref().unwrap().matches());
+        assert_eq!(results[3], Err(BodyHasherError::UnsupportedAlgorithm));
+    }
+}
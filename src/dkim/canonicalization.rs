@@ -1,32 +1,103 @@
 //! DKIM Canonicalization behaviour
 
-/// DKIM Canonicalization (per RFC)
+/// DKIM Canonicalization algorithm for either the header or the body (per RFC)
 #[derive(Clone, Debug, Default, PartialEq)]
-pub enum DkimCanonicalization<'hdr> {
-    /// simple/simple & simple algorithm tolerates almost no modification    
+pub enum DkimCanon<'hdr> {
+    /// simple algorithm tolerates almost no modification
     #[default]
     Simple,
-    /// relaxed/simple & relaxed algorithm tolerates common modifications such
+    /// relaxed algorithm tolerates common modifications such
     /// as whitespace replacement and header field line rewrapping.
     Relaxed,
     /// Unknown RFC does not define
     Unknown(&'hdr str),
 }
 
+impl<'hdr> From<&'hdr str> for DkimCanon<'hdr> {
+    fn from(algo: &'hdr str) -> Self {
+        match algo {
+            "simple" => Self::Simple,
+            "relaxed" => Self::Relaxed,
+            _ => Self::Unknown(algo),
+        }
+    }
+}
+
+/// DKIM Canonicalization (per RFC) - see RFC 6376 s. 3.5 c= tag
+///
+/// The header and body are canonicalized separately. When c= is absent
+/// both default to simple and when only the header algorithm is given the
+/// body defaults to simple e.g. c=relaxed means relaxed/simple.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DkimCanonicalization<'hdr> {
+    /// Header canonicalization algorithm
+    pub header: DkimCanon<'hdr>,
+    /// Body canonicalization algorithm
+    pub body: DkimCanon<'hdr>,
+}
+
 use crate::error::DkimCanonicalizationError;
 
 impl<'hdr> TryFrom<&'hdr str> for DkimCanonicalization<'hdr> {
     type Error = DkimCanonicalizationError;
 
     fn try_from(hdr: &'hdr str) -> Result<Self, Self::Error> {
-        let ret = match hdr {
-            "simple" => Self::Simple,
-            "simple/simple" => Self::Simple,
-            "relaxed" => Self::Relaxed,
-            "relaxed/relaxed" => Self::Relaxed,
-            "relaxed/simple" => Self::Relaxed,
-            _ => Self::Unknown(hdr),
+        let (header, body) = match hdr.trim().split_once('/') {
+            Some((header, body)) => (header.trim(), Some(body.trim())),
+            None => (hdr.trim(), None),
         };
-        Ok(ret)
+
+        if header.is_empty() {
+            return Err(DkimCanonicalizationError::MissingHeaderAlgorithm);
+        }
+
+        let body = match body {
+            Some("") => return Err(DkimCanonicalizationError::MissingBodyAlgorithm),
+            Some(body) if body.contains('/') => {
+                return Err(DkimCanonicalizationError::TooManyAlgorithms)
+            }
+            Some(body) => body.into(),
+            None => DkimCanon::Simple,
+        };
+
+        Ok(Self {
+            header: header.into(),
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("simple", DkimCanon::Simple, DkimCanon::Simple)]
+    #[case("relaxed", DkimCanon::Relaxed, DkimCanon::Simple)]
+    #[case("simple/simple", DkimCanon::Simple, DkimCanon::Simple)]
+    #[case("simple/relaxed", DkimCanon::Simple, DkimCanon::Relaxed)]
+    #[case("relaxed/simple", DkimCanon::Relaxed, DkimCanon::Simple)]
+    #[case("relaxed/relaxed", DkimCanon::Relaxed, DkimCanon::Relaxed)]
+    #[case(" relaxed / relaxed ", DkimCanon::Relaxed, DkimCanon::Relaxed)]
+    #[case("nowsp/relaxed", DkimCanon::Unknown("nowsp"), DkimCanon::Relaxed)]
+    #[case("relaxed/nowsp", DkimCanon::Relaxed, DkimCanon::Unknown("nowsp"))]
+    #[case("nowsp", DkimCanon::Unknown("nowsp"), DkimCanon::Simple)]
+    fn canonicalization_ok(
+        #[case] in_str: &'static str,
+        #[case] header: DkimCanon<'static>,
+        #[case] body: DkimCanon<'static>,
+    ) {
+        let res = DkimCanonicalization::try_from(in_str);
+        assert_eq!(res, Ok(DkimCanonicalization { header, body }));
+    }
+
+    #[rstest]
+    #[case("", DkimCanonicalizationError::MissingHeaderAlgorithm)]
+    #[case("/relaxed", DkimCanonicalizationError::MissingHeaderAlgorithm)]
+    #[case("relaxed/", DkimCanonicalizationError::MissingBodyAlgorithm)]
+    #[case("relaxed/relaxed/simple", DkimCanonicalizationError::TooManyAlgorithms)]
+    fn canonicalization_err(#[case] in_str: &'static str, #[case] err: DkimCanonicalizationError) {
+        assert_eq!(DkimCanonicalization::try_from(in_str), Err(err));
     }
 }
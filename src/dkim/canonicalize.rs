@@ -0,0 +1,429 @@
+//! DKIM Canonicalization algorithms - RFC 6376 s. 3.4
+//!
+//! Bare LF line endings are treated as CRLF so messages stored with the
+//! local line endings canonicalize the same as they were on the wire.
+
+use super::{DkimCanon, DkimSignature};
+use crate::message::RawHeader;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
+fn is_wsp(b: u8) -> bool {
+    matches!(b, b' ' | b'\t')
+}
+
+// Unfold and normalise line endings for simple
+fn simple_header_value(value: &[u8], out: &mut Vec<u8>) {
+    let mut prev = 0;
+    for b in value {
+        if *b == b'\n' && prev != b'\r' {
+            out.push(b'\r');
+        }
+        out.push(*b);
+        prev = *b;
+    }
+}
+
+// RFC 6376 s. 3.4.2 unfold, reduce WSP to single SP and remove WSP around the value
+fn relaxed_header_value(value: &[u8], out: &mut Vec<u8>) {
+    let mut pending_wsp = false;
+    let mut started = false;
+    for b in value.iter().filter(|b| !matches!(b, b'\r' | b'\n')) {
+        if is_wsp(*b) {
+            pending_wsp = true;
+            continue;
+        }
+        if pending_wsp && started {
+            out.push(b' ');
+        }
+        pending_wsp = false;
+        started = true;
+        out.push(*b);
+    }
+}
+
+impl<'hdr> DkimCanon<'hdr> {
+    /// Canonicalize a single header field including the terminating CRLF
+    ///
+    /// The value is everything after the colon as it appears in the message.
+    /// None when the algorithm is unknown.
+    pub fn canonicalize_header(&self, name: &str, value: &[u8]) -> Option<Vec<u8>> {
+        let mut out = Vec::with_capacity(name.len() + value.len() + 3);
+        match self {
+            Self::Simple => {
+                out.extend_from_slice(name.as_bytes());
+                out.push(b':');
+                simple_header_value(value, &mut out);
+            }
+            Self::Relaxed => {
+                out.extend(name.trim_end().bytes().map(|b| b.to_ascii_lowercase()));
+                out.push(b':');
+                relaxed_header_value(value, &mut out);
+            }
+            Self::Unknown(_) => return None,
+        }
+        out.extend_from_slice(b"\r\n");
+        Some(out)
+    }
+
+    /// Canonicalize a whole body limited to the given l= length
+    ///
+    /// None when the algorithm is unknown.
+    pub fn canonicalize_body(&self, body: &[u8], limit: Option<u64>) -> Option<Vec<u8>> {
+        let mut canonicalizer = BodyCanonicalizer::new(self, limit)?;
+        let mut out = Vec::with_capacity(body.len() + 2);
+        canonicalizer.update(body, &mut out);
+        canonicalizer.finish(&mut out);
+        Some(out)
+    }
+}
+
+/// Streaming body canonicalization - RFC 6376 s. 3.4.3 & s. 3.4.4
+///
+/// Trailing empty lines are held back until more content arrives so the
+/// body may be fed in arbitrary chunks.
+#[derive(Clone, Debug)]
+pub struct BodyCanonicalizer {
+    relaxed: bool,
+    limit: Option<u64>,
+    written: u64,
+    total: u64,
+    // Empty lines held back in case they are at the end of the body
+    pending_empty_lines: usize,
+    // Relaxed WSP seen on the current line not written yet
+    pending_wsp: bool,
+    // CR seen that may be the start of CRLF
+    pending_cr: bool,
+    line_has_content: bool,
+    any_content: bool,
+}
+
+impl BodyCanonicalizer {
+    /// New canonicalizer for the given algorithm and l= limit - None if the algorithm is unknown
+    pub fn new(canon: &DkimCanon<'_>, limit: Option<u64>) -> Option<Self> {
+        let relaxed = match canon {
+            DkimCanon::Simple => false,
+            DkimCanon::Relaxed => true,
+            DkimCanon::Unknown(_) => return None,
+        };
+        Some(Self {
+            relaxed,
+            limit,
+            written: 0,
+            total: 0,
+            pending_empty_lines: 0,
+            pending_wsp: false,
+            pending_cr: false,
+            line_has_content: false,
+            any_content: false,
+        })
+    }
+
+    /// Number of canonicalized bytes written so far within the limit
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Number of canonicalized bytes so far ignoring the limit
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Canonicalize the next chunk appending the output
+    pub fn update(&mut self, chunk: &[u8], out: &mut Vec<u8>) {
+        for b in chunk {
+            match *b {
+                b'\r' => {
+                    if self.pending_cr {
+                        self.content(b'\r', out);
+                    }
+                    self.pending_cr = true;
+                }
+                b'\n' => {
+                    self.pending_cr = false;
+                    self.end_of_line(out);
+                }
+                b => {
+                    if self.pending_cr {
+                        self.pending_cr = false;
+                        self.content(b'\r', out);
+                    }
+                    self.content(b, out);
+                }
+            }
+        }
+    }
+
+    /// Finish the body appending any remaining output
+    ///
+    /// Returns the length of the whole canonicalized body ignoring l=
+    pub fn finish(mut self, out: &mut Vec<u8>) -> u64 {
+        if self.pending_cr {
+            self.pending_cr = false;
+            self.content(b'\r', out);
+        }
+        if self.line_has_content {
+            self.end_of_line(out);
+        }
+        // s. 3.4.3 an empty body is a single CRLF for simple
+        if !self.any_content && !self.relaxed {
+            self.emit(b"\r\n", out);
+        }
+        self.total
+    }
+
+    fn content(&mut self, b: u8, out: &mut Vec<u8>) {
+        if self.relaxed && is_wsp(b) {
+            self.pending_wsp = true;
+            return;
+        }
+        while self.pending_empty_lines > 0 {
+            self.pending_empty_lines -= 1;
+            self.emit(b"\r\n", out);
+        }
+        if self.pending_wsp {
+            self.pending_wsp = false;
+            self.emit(b" ", out);
+        }
+        self.emit(&[b], out);
+        self.line_has_content = true;
+        self.any_content = true;
+    }
+
+    fn end_of_line(&mut self, out: &mut Vec<u8>) {
+        match self.line_has_content {
+            true => self.emit(b"\r\n", out),
+            false => self.pending_empty_lines += 1,
+        }
+        // s. 3.4.4 relaxed ignores WSP at the end of lines
+        self.pending_wsp = false;
+        self.line_has_content = false;
+    }
+
+    fn emit(&mut self, bytes: &[u8], out: &mut Vec<u8>) {
+        let take = match self.limit {
+            Some(limit) => bytes
+                .len()
+                .min(usize::try_from(limit - self.written).unwrap_or(usize::MAX)),
+            None => bytes.len(),
+        };
+        out.extend_from_slice(&bytes[..take]);
+        self.written += take as u64;
+        self.total += bytes.len() as u64;
+    }
+}
+
+// RFC 6376 s. 3.5 b= value is removed including the surrounding whitespace
+pub(crate) fn strip_signature_value(value: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(value.len());
+    let mut rest = value;
+    while !rest.is_empty() {
+        let spec_len = match rest.iter().position(|b| *b == b';') {
+            Some(pos) => pos + 1,
+            None => rest.len(),
+        };
+        let spec = &rest[..spec_len];
+        let eq = spec.iter().position(|b| *b == b'=');
+        let is_b = eq.is_some_and(|eq| {
+            let tag: Vec<u8> = spec[..eq]
+                .iter()
+                .copied()
+                .filter(|b| !b.is_ascii_whitespace())
+                .collect();
+            tag == b"b"
+        });
+        match (is_b, eq) {
+            (true, Some(eq)) => {
+                out.extend_from_slice(&spec[..=eq]);
+                if spec.ends_with(b";") {
+                    out.push(b';');
+                }
+            }
+            _ => out.extend_from_slice(spec),
+        }
+        rest = &rest[spec_len..];
+    }
+    out
+}
+
+impl<'hdr> DkimSignature<'hdr> {
+    /// Canonicalized body as hashed for bh= including the l= limit
+    ///
+    /// None when the body canonicalization algorithm is unknown.
+    pub fn canonicalized_body(&self, body: &[u8]) -> Option<Vec<u8>> {
+        self.c.body.canonicalize_body(body, self.l)
+    }
+
+    /// Canonicalized header data as hashed for b= - RFC 6376 s. 3.7
+    ///
+    /// Header fields named in h= are picked from the bottom up and the
+    /// given DKIM-Signature field is appended with b= emptied and without
+    /// the final CRLF. None when the header canonicalization algorithm is unknown.
+    pub fn canonicalized_headers(
+        &self,
+        headers: &[RawHeader<'_>],
+        signature: &RawHeader<'_>,
+    ) -> Option<Vec<u8>> {
+        let mut out = vec![];
+        let mut used = vec![false; headers.len()];
+
+        for name in self.h.iter() {
+            let found = headers
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(idx, hdr)| !used[*idx] && hdr.is(name));
+            // s. 5.4.2 non-existent header fields do not contribute
+            if let Some((idx, hdr)) = found {
+                used[idx] = true;
+                out.extend(
+                    self.c
+                        .header
+                        .canonicalize_header(hdr.raw_name(), hdr.value)?,
+                );
+            }
+        }
+
+        let stripped = strip_signature_value(signature.value);
+        let sig = self
+            .c
+            .header
+            .canonicalize_header(signature.raw_name(), &stripped)?;
+        out.extend_from_slice(&sig[..sig.len() - 2]);
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::RawMessage;
+    use rstest::rstest;
+
+    fn load_rfc6376_a2() -> Vec<u8> {
+        std::fs::read("test_data/rfc6376_a2_signed.eml").unwrap()
+    }
+
+    // RFC 6376 s. 3.4.5
+    const EXAMPLE_BODY: &[u8] = b" C \r\nD \t E\r\n\r\n\r\n";
+
+    #[rstest]
+    #[case(DkimCanon::Simple, "A: X\r\n", "B : Y\t\r\n\tZ  \r\n")]
+    #[case(DkimCanon::Relaxed, "a:X\r\n", "b:Y Z\r\n")]
+    fn rfc6376_example_headers(
+        #[case] canon: DkimCanon<'_>,
+        #[case] expected_a: &str,
+        #[case] expected_b: &str,
+    ) {
+        let a = canon.canonicalize_header("A", b" X").unwrap();
+        let b = canon.canonicalize_header("B ", b" Y\t\r\n\tZ  ").unwrap();
+        assert_eq!(String::from_utf8(a).unwrap(), expected_a);
+        assert_eq!(String::from_utf8(b).unwrap(), expected_b);
+
+        // Same through the split message
+        let raw = b"A: X\r\nB : Y\t\r\n\tZ  \r\n\r\n";
+        let msg = RawMessage::try_from(&raw[..]).unwrap();
+        let b = &msg.headers[1];
+        let b = canon.canonicalize_header(b.raw_name(), b.value).unwrap();
+        assert_eq!(String::from_utf8(b).unwrap(), expected_b);
+    }
+
+    #[rstest]
+    #[case(DkimCanon::Simple, " C \r\nD \t E\r\n")]
+    #[case(DkimCanon::Relaxed, " C\r\nD E\r\n")]
+    fn rfc6376_example_body(#[case] canon: DkimCanon<'_>, #[case] expected: &str) {
+        let body = canon.canonicalize_body(EXAMPLE_BODY, None).unwrap();
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(DkimCanon::Simple, "", "\r\n")]
+    #[case(DkimCanon::Relaxed, "", "")]
+    #[case(DkimCanon::Simple, "\r\n\r\n", "\r\n")]
+    #[case(DkimCanon::Relaxed, " \r\n\t\r\n", "")]
+    #[case(DkimCanon::Simple, "no ending", "no ending\r\n")]
+    #[case(DkimCanon::Relaxed, "lf  only \n\nend\n\n", "lf only\r\n\r\nend\r\n")]
+    fn edge_bodies(#[case] canon: DkimCanon<'_>, #[case] body: &str, #[case] expected: &str) {
+        let out = canon.canonicalize_body(body.as_bytes(), None).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn streaming_matches_whole() {
+        for canon in [DkimCanon::Simple, DkimCanon::Relaxed] {
+            let whole = canon.canonicalize_body(EXAMPLE_BODY, None).unwrap();
+            let mut streamed = vec![];
+            let mut canonicalizer = BodyCanonicalizer::new(&canon, None).unwrap();
+            for chunk in EXAMPLE_BODY.chunks(1) {
+                canonicalizer.update(chunk, &mut streamed);
+            }
+            canonicalizer.finish(&mut streamed);
+            assert_eq!(streamed, whole);
+        }
+    }
+
+    #[test]
+    fn body_length_limit() {
+        let out = DkimCanon::Relaxed
+            .canonicalize_body(EXAMPLE_BODY, Some(4))
+            .unwrap();
+        assert_eq!(out, b" C\r\n");
+    }
+
+    #[test]
+    fn unknown_algorithm() {
+        assert_eq!(DkimCanon::Unknown("x").canonicalize_body(b"", None), None);
+    }
+
+    #[test]
+    fn rfc6376_a2_body() {
+        let raw = load_rfc6376_a2();
+        let msg = RawMessage::try_from(raw.as_slice()).unwrap();
+        let sig_hdr = &msg.headers[0];
+        let sig_value = core::str::from_utf8(sig_hdr.value).unwrap();
+        let sig = DkimSignature::try_from(sig_value).unwrap();
+
+        let body = sig.canonicalized_body(msg.body).unwrap();
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "Hi.\r\n\r\nWe lost the game.  Are you hungry yet?\r\n\r\nJoe.\r\n"
+        );
+    }
+
+    #[test]
+    fn rfc6376_a2_headers() {
+        let raw = load_rfc6376_a2();
+        let msg = RawMessage::try_from(raw.as_slice()).unwrap();
+        let sig_hdr = &msg.headers[0];
+        let sig_value = core::str::from_utf8(sig_hdr.value).unwrap();
+        let sig = DkimSignature::try_from(sig_value).unwrap();
+
+        let headers = sig.canonicalized_headers(&msg.headers, sig_hdr).unwrap();
+        insta::assert_snapshot!(String::from_utf8(headers).unwrap());
+    }
+
+    #[cfg(feature = "verifier")]
+    #[test]
+    fn rfc6376_a2_body_hash() {
+        use sha2::{Digest, Sha256};
+
+        let raw = load_rfc6376_a2();
+        let msg = RawMessage::try_from(raw.as_slice()).unwrap();
+        let sig_value = core::str::from_utf8(msg.headers[0].value).unwrap();
+        let sig = DkimSignature::try_from(sig_value).unwrap();
+
+        // The published bh= was computed over the body with a single space
+        // after "game." - see the RFC 6376 errata
+        let body = sig.canonicalized_body(msg.body).unwrap();
+        assert_ne!(Sha256::digest(&body).as_slice(), sig.bh.as_bytes());
+
+        let errata_body = String::from_utf8(body)
+            .unwrap()
+            .replace("game.  Are", "game. Are");
+        assert_eq!(
+            Sha256::digest(errata_body.as_bytes()).as_slice(),
+            sig.bh.as_bytes()
+        );
+    }
+}
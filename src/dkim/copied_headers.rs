@@ -0,0 +1,100 @@
+//! DKIM Copied header fields - z=
+
+use crate::error::DkimCopiedHeadersError;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{string::String, vec, vec::Vec};
+
+/// Copied header field - RFC 6376 s. 3.5 z=
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkimCopiedHeader<'hdr> {
+    /// Header field name
+    pub name: &'hdr str,
+    /// Header field value decoded from dkim-quoted-printable
+    pub value: String,
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        _ => None,
+    }
+}
+
+/// Decode dkim-quoted-printable (RFC 6376 s. 2.11) - whitespace is ignored
+pub(crate) fn decode_dkim_quoted_printable(
+    in_str: &str,
+) -> Result<Vec<u8>, DkimCopiedHeadersError> {
+    let mut out = Vec::with_capacity(in_str.len());
+    let mut bytes = in_str
+        .bytes()
+        .filter(|b| !matches!(b, b' ' | b'\t' | b'\r' | b'\n'));
+
+    while let Some(b) = bytes.next() {
+        if b != b'=' {
+            out.push(b);
+            continue;
+        }
+        let high = bytes.next().and_then(hex_value);
+        let low = bytes.next().and_then(hex_value);
+        match (high, low) {
+            (Some(high), Some(low)) => out.push(high << 4 | low),
+            _ => return Err(DkimCopiedHeadersError::InvalidQuotedPrintable),
+        }
+    }
+    Ok(out)
+}
+
+/// Parse z= copied header fields separated by '|'
+pub(crate) fn parse_copied_headers(
+    in_str: &str,
+) -> Result<Vec<DkimCopiedHeader<'_>>, DkimCopiedHeadersError> {
+    let mut copied = vec![];
+    for copy in in_str.split('|') {
+        let (name, value) = match copy.split_once(':') {
+            Some(parts) => parts,
+            None => return Err(DkimCopiedHeadersError::NoColon),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DkimCopiedHeadersError::EmptyName);
+        }
+        let decoded = decode_dkim_quoted_printable(value)?;
+        copied.push(DkimCopiedHeader {
+            name,
+            value: String::from_utf8_lossy(&decoded).into_owned(),
+        });
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rfc6376_example() {
+        let z = "From:foo@eng.example.net|To:joe@example.com|\n   Subject:demo=20run|Date:July=205,=202005=203:44:08=20PM=20-0700";
+        let copied = parse_copied_headers(z).unwrap();
+        let pairs: Vec<(&str, &str)> = copied.iter().map(|c| (c.name, c.value.as_str())).collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("From", "foo@eng.example.net"),
+                ("To", "joe@example.com"),
+                ("Subject", "demo run"),
+                ("Date", "July 5, 2005 3:44:08 PM -0700"),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_hex() {
+        assert_eq!(
+            parse_copied_headers("Subject:demo=2"),
+            Err(DkimCopiedHeadersError::InvalidQuotedPrintable)
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! DKIM base64 encoded tag values - b= and bh=
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::error::DkimBase64Error;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{string::String, vec::Vec};
+
+/// Decoded base64 tag value (RFC 6376 s. 2.11 base64string)
+///
+/// Folding whitespace is removed before decoding. Debug shows the value
+/// re-encoded as base64 without the whitespace.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct DkimBase64(pub Vec<u8>);
+
+impl DkimBase64 {
+    /// Decoded bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+    /// Re-encode as base64 without any folding whitespace
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.0)
+    }
+}
+
+impl core::fmt::Debug for DkimBase64 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("DkimBase64")
+            .field(&self.to_base64())
+            .finish()
+    }
+}
+
+impl<'hdr> TryFrom<&'hdr str> for DkimBase64 {
+    type Error = DkimBase64Error;
+
+    fn try_from(in_str: &'hdr str) -> Result<Self, Self::Error> {
+        let compact: String = in_str
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+            .collect();
+        STANDARD
+            .decode(compact)
+            .map(Self)
+            .map_err(|_| DkimBase64Error::Invalid)
+    }
+}
@@ -33,9 +33,9 @@ pub enum DkimHeader<'hdr> {
     /// Internationalized selector names MUST be encoded as A-labels, as described in Section 2.3 of RFC 5890.
     S(&'hdr str),
     /// Recommended - Signature Timestamp
-    T(DkimTimestamp<'hdr>),
+    T(DkimTimestamp),
     /// Recommended - Signature Expiration
-    X(DkimTimestamp<'hdr>),
+    X(DkimTimestamp),
     /// Optional - Copied header fields
     Z(&'hdr str),
     /// RFC 6541
@@ -0,0 +1,152 @@
+//! DKIM public key record published in DNS - RFC 6376 s. 3.6.1
+
+use super::DkimBase64;
+use crate::error::DkimKeyRecordError;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
+/// Parsed `<selector>._domainkey.<domain>` TXT record - RFC 6376 s. 3.6.1
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DkimKeyRecord<'rec> {
+    /// Acceptable hash algorithms - None means all are allowed
+    pub h: Option<Vec<&'rec str>>,
+    /// Key type - defaults to rsa
+    pub k: &'rec str,
+    /// Notes for humans
+    pub n: Option<&'rec str>,
+    /// Public key data decoded from base64 - empty when the key is revoked
+    pub p: DkimBase64,
+    /// Service types - defaults to "*"
+    pub s: Vec<&'rec str>,
+    /// Flags - "y" testing mode, "s" no subdomains in i=
+    pub t: Vec<&'rec str>,
+    /// Raw unparsed
+    pub raw: &'rec str,
+}
+
+impl<'rec> DkimKeyRecord<'rec> {
+    /// Whether the domain is testing DKIM - t=y
+    pub fn is_testing(&self) -> bool {
+        self.t.contains(&"y")
+    }
+    /// Whether the i= domain must be the same as d= - t=s
+    pub fn is_strict(&self) -> bool {
+        self.t.contains(&"s")
+    }
+    /// Whether the key has been revoked with an empty p=
+    pub fn is_revoked(&self) -> bool {
+        self.p.as_bytes().is_empty()
+    }
+    /// Whether the given hash algorithm e.g. "sha256" is acceptable
+    pub fn allows_hash(&self, hash: &str) -> bool {
+        match &self.h {
+            Some(hashes) => hashes.iter().any(|h| h.eq_ignore_ascii_case(hash)),
+            None => true,
+        }
+    }
+}
+
+fn colon_list(val: &str) -> Vec<&str> {
+    val.split(':')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+impl<'rec> TryFrom<&'rec str> for DkimKeyRecord<'rec> {
+    type Error = DkimKeyRecordError;
+
+    fn try_from(raw: &'rec str) -> Result<Self, Self::Error> {
+        let mut record = Self {
+            k: "rsa",
+            s: vec!["*"],
+            raw,
+            ..Default::default()
+        };
+        let mut seen: Vec<&str> = vec![];
+        let mut p: Option<&str> = None;
+
+        for (pos, spec) in raw
+            .split(';')
+            .map(|spec| spec.trim())
+            .filter(|spec| !spec.is_empty())
+            .enumerate()
+        {
+            let (tag, val) = match spec.split_once('=') {
+                Some((tag, val)) => (tag.trim(), val.trim()),
+                None => return Err(DkimKeyRecordError::NoEqual),
+            };
+            if seen.contains(&tag) {
+                return Err(DkimKeyRecordError::Duplicate);
+            }
+            seen.push(tag);
+            match tag {
+                // s. 3.6.1 v= MUST be the first tag if present
+                "v" if pos != 0 || val != "DKIM1" => {
+                    return Err(DkimKeyRecordError::InvalidVersion)
+                }
+                "v" => {}
+                "h" => record.h = Some(colon_list(val)),
+                "k" => record.k = val,
+                "n" => record.n = Some(val),
+                "p" => p = Some(val),
+                "s" => record.s = colon_list(val),
+                "t" => record.t = colon_list(val),
+                // Unrecognised tags MUST be ignored
+                _ => {}
+            }
+        }
+
+        record.p = match p {
+            Some(p) => p.try_into()?,
+            None => return Err(DkimKeyRecordError::MissingPublicKey),
+        };
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn rfc6376_example() {
+        let record = DkimKeyRecord::try_from(
+            "v=DKIM1; p=MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDwIRP/UC3SBsEmGqZ9ZJW3/DkMoGeLnQg1fWn7/zYtIxN2SnFCjxOCKG9v3b4jYfcTNh5ijSsq631uBItLa7od+v/RtdC2UzJ1lWT947qR+Rcac2gbto/NMqJ0fzfVjH4OuKhitdY9tf6mcwGjaNBcWToIMmPSPDdQPNUYckcQ2QIDAQAB",
+        )
+        .unwrap();
+        assert_eq!(record.k, "rsa");
+        assert_eq!(record.s, vec!["*"]);
+        assert_eq!(record.p.as_bytes().len(), 162);
+        assert!(!record.is_testing());
+        assert!(!record.is_revoked());
+    }
+
+    #[rstest]
+    #[case("k=ed25519; t=y:s; p=", true, true, true)]
+    #[case("p=AAAA; t=y", true, false, false)]
+    #[case("p=AAAA; t=s ", false, true, false)]
+    fn flags(
+        #[case] raw: &str,
+        #[case] testing: bool,
+        #[case] strict: bool,
+        #[case] revoked: bool,
+    ) {
+        let record = DkimKeyRecord::try_from(raw).unwrap();
+        assert_eq!(record.is_testing(), testing);
+        assert_eq!(record.is_strict(), strict);
+        assert_eq!(record.is_revoked(), revoked);
+    }
+
+    #[rstest]
+    #[case("k=rsa; v=DKIM1; p=AAAA", DkimKeyRecordError::InvalidVersion)]
+    #[case("v=DKIM2; p=AAAA", DkimKeyRecordError::InvalidVersion)]
+    #[case("v=DKIM1; k=rsa", DkimKeyRecordError::MissingPublicKey)]
+    #[case("p=AAAA; p=AAAA", DkimKeyRecordError::Duplicate)]
+    #[case("p", DkimKeyRecordError::NoEqual)]
+    fn invalid(#[case] raw: &str, #[case] expected: DkimKeyRecordError) {
+        assert_eq!(DkimKeyRecord::try_from(raw), Err(expected));
+    }
+}
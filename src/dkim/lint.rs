@@ -0,0 +1,177 @@
+//! DKIM-Signature security lints
+
+use super::*;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
+/// Header fields that should be oversigned to prevent additional instances
+/// being prepended after signing - RFC 6376 s. 8.15
+const OVERSIGN_HEADERS: [&str; 4] = ["From", "Subject", "To", "Date"];
+
+/// Severity of a lint finding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DkimLintSeverity {
+    /// Best practice not followed
+    Info,
+    /// Weakens the protection the signature gives
+    Warning,
+    /// Signature is unsafe or will not be honoured by verifiers
+    Critical,
+}
+
+/// What was found
+#[derive(Clone, Debug, PartialEq)]
+pub enum DkimLint<'hdr> {
+    /// rsa-sha1 MUST NOT be used for signing - RFC 8301 s. 3.1
+    RsaSha1,
+    /// l= allows content to be appended after signing - RFC 6376 s. 8.2
+    BodyLengthLimit(u64),
+    /// From is not in h= - RFC 6376 s. 5.4 says it MUST be signed
+    FromNotSigned,
+    /// Header field is signed only once and not oversigned - RFC 6376 s. 8.15
+    NotOversigned(&'static str),
+    /// No t= Signature Timestamp
+    MissingTimestamp,
+    /// No x= Signature Expiration
+    MissingExpiration,
+    /// x= is before the time given
+    Expired(DkimTimestamp),
+    /// i= domain is not the same as or a subdomain of d=
+    AuidOutsideSdid(&'hdr str),
+    /// The public key record has t=y set
+    TestingMode,
+}
+
+/// Lint finding with its severity
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkimLintFinding<'hdr> {
+    /// Severity
+    pub severity: DkimLintSeverity,
+    /// Finding
+    pub lint: DkimLint<'hdr>,
+}
+
+impl<'hdr> DkimLint<'hdr> {
+    /// Severity of this finding
+    pub fn severity(&self) -> DkimLintSeverity {
+        match self {
+            Self::RsaSha1 | Self::FromNotSigned | Self::AuidOutsideSdid(_) => {
+                DkimLintSeverity::Critical
+            }
+            Self::BodyLengthLimit(_) | Self::Expired(_) | Self::TestingMode => {
+                DkimLintSeverity::Warning
+            }
+            Self::NotOversigned(_) | Self::MissingTimestamp | Self::MissingExpiration => {
+                DkimLintSeverity::Info
+            }
+        }
+    }
+}
+
+/// Optional context for the lints not answerable from the signature alone
+#[derive(Clone, Debug, Default)]
+pub struct DkimLintOptions<'opt> {
+    /// Current time to check x= against
+    pub now: Option<DkimTimestamp>,
+    /// Public key record of the signer s=._domainkey.d=
+    pub key: Option<&'opt DkimKeyRecord<'opt>>,
+}
+
+impl<'hdr> DkimSignature<'hdr> {
+    /// Lint the signature using only the data in it
+    pub fn lint(&self) -> Vec<DkimLintFinding<'hdr>> {
+        self.lint_with(&DkimLintOptions::default())
+    }
+
+    /// Lint the signature with the expiration checked against the given time
+    /// and the testing mode against the given key record
+    pub fn lint_with(&self, opts: &DkimLintOptions<'_>) -> Vec<DkimLintFinding<'hdr>> {
+        let mut lints = vec![];
+
+        if self.a == DkimAlgorithm::Rsa_Sha1 {
+            lints.push(DkimLint::RsaSha1);
+        }
+        if let Some(l) = self.l {
+            lints.push(DkimLint::BodyLengthLimit(l));
+        }
+        if !self.h.contains("From") {
+            lints.push(DkimLint::FromNotSigned);
+        }
+        for name in OVERSIGN_HEADERS {
+            if self.h.count(name) == 1 {
+                lints.push(DkimLint::NotOversigned(name));
+            }
+        }
+        if self.t.is_none() {
+            lints.push(DkimLint::MissingTimestamp);
+        }
+        match (self.x, opts.now) {
+            (None, _) => lints.push(DkimLint::MissingExpiration),
+            (Some(x), Some(now)) if x < now => lints.push(DkimLint::Expired(x)),
+            _ => {}
+        }
+        if let Some(ref i) = self.i {
+            if !i.is_within(self.d) {
+                lints.push(DkimLint::AuidOutsideSdid(i.domain));
+            }
+        }
+        if opts.key.is_some_and(|key| key.is_testing()) {
+            lints.push(DkimLint::TestingMode);
+        }
+
+        lints
+            .into_iter()
+            .map(|lint| DkimLintFinding {
+                severity: lint.severity(),
+                lint,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn clean() {
+        let sig = DkimSignature::try_from(
+            "v=1; a=rsa-sha256; d=example.com; s=sel; t=1700000000; x=1800000000; h=from:from:to:to:subject:subject:date:date; bh=AAAA; b=AAAA",
+        )
+        .unwrap();
+        assert_eq!(sig.lint(), vec![]);
+    }
+
+    #[test]
+    fn everything() {
+        let sig = DkimSignature::try_from(
+            "v=1; a=rsa-sha1; d=example.com; s=sel; l=100; x=1700000000; h=to:subject:date; bh=AAAA; b=AAAA",
+        )
+        .unwrap();
+        let key = DkimKeyRecord::try_from("t=y; p=AAAA").unwrap();
+        let opts = DkimLintOptions {
+            now: Some(DkimTimestamp(1800000000)),
+            key: Some(&key),
+        };
+        assert_debug_snapshot!(sig.lint_with(&opts));
+    }
+
+    #[test]
+    fn auid_outside_sdid() {
+        let mut sig = DkimSignature::try_from(
+            "v=1; a=rsa-sha256; d=example.com; s=sel; t=1; x=2; h=from:from:to:to:subject:subject:date:date; bh=AAAA; b=AAAA",
+        )
+        .unwrap();
+        sig.i = Some("@example.org".try_into().unwrap());
+        let findings = sig.lint();
+        assert_eq!(
+            findings,
+            vec![DkimLintFinding {
+                severity: DkimLintSeverity::Critical,
+                lint: DkimLint::AuidOutsideSdid("example.org"),
+            }]
+        );
+    }
+}
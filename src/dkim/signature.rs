@@ -2,6 +2,9 @@
 
 use crate::dkim::*;
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
 /// RFC 6376 s. 3.5
 #[derive(Clone, Debug, PartialEq)]
 pub struct DkimSignature<'hdr> {
@@ -9,30 +12,50 @@ pub struct DkimSignature<'hdr> {
     pub v: DkimVersion<'hdr>,
     /// Algorithm
     pub a: DkimAlgorithm<'hdr>,
-    /// Signature data (base64)
-    pub b: &'hdr str,
-    /// Hash of canonicalized body part of the message as limited by the 'l='
-    pub bh: &'hdr str,
+    /// Signature data decoded from base64
+    pub b: DkimBase64,
+    /// Hash of canonicalized body part of the message as limited by the 'l=' decoded from base64
+    pub bh: DkimBase64,
     /// Message canonicalization informs the verifier of the type of canonicalization used to prepare the message for signing. See s.3.4
-    pub c: Option<DkimCanonicalization<'hdr>>,
+    /// Defaults to simple/simple when c= is absent
+    pub c: DkimCanonicalization<'hdr>,
     /// The SDID claiming responsibility for an introduction of a message into the mail stream
     pub d: &'hdr str,
     /// Signed header fields separated by colon ':' - see 'h='
-    pub h: &'hdr str,
+    pub h: DkimSignedHeaders<'hdr>,
     /// The Agent or User Identifier (AUID) on behalf of which the SDID is taking responsibility.
-    pub i: Option<&'hdr str>,
+    pub i: Option<DkimAuid<'hdr>>,
     /// Body length limit - see misuse on RFC 6376 s. 8.2
-    pub l: Option<&'hdr str>,
+    pub l: Option<u64>,
     /// Query methods - currently only DnsTxt
-    pub q: Option<&'hdr str>,
+    pub q: Option<Vec<DkimQueryMethod<'hdr>>>,
     /// The selector subdividing the namespace for the "d=" (domain) tag
     pub s: &'hdr str,
     /// Recommended - Signature Timestamp
-    pub t: Option<DkimTimestamp<'hdr>>,
+    pub t: Option<DkimTimestamp>,
     /// Recommended - Signature Expiration
-    pub x: Option<DkimTimestamp<'hdr>>,
+    pub x: Option<DkimTimestamp>,
     /// Copied header fields
-    pub z: Option<&'hdr str>,
+    pub z: Option<Vec<DkimCopiedHeader<'hdr>>>,
+    /// Authorized Third-Party Signer Author Domain - RFC 6541
+    pub atps: Option<&'hdr str>,
+    /// Authorized Third-Party Signer hash algorithm used for the ATPS query - RFC 6541
+    pub atpsh: Option<DkimAtpsHash<'hdr>>,
+    /// Reporting requested - RFC 6651 only defines "y"
+    pub r: Option<&'hdr str>,
+    /// Unrecognised tags as DkimHeader::Unknown(tag, value) - RFC 6376 s. 3.2 says to ignore these
+    pub unknown_tags: Vec<DkimHeader<'hdr>>,
     /// Raw unparsed
     pub raw: Option<&'hdr str>,
 }
+
+impl<'hdr> DkimSignature<'hdr> {
+    /// Whether the signer requested failure reports with r=y - RFC 6651
+    pub fn reports_requested(&self) -> bool {
+        self.r == Some("y")
+    }
+    /// Whether x= is before the given time - RFC 6376 s. 3.5
+    pub fn is_expired_at(&self, now: DkimTimestamp) -> bool {
+        self.x.is_some_and(|x| x < now)
+    }
+}
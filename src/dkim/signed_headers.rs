@@ -0,0 +1,64 @@
+//! DKIM Signed header fields - h=
+
+use crate::error::DkimSignedHeadersError;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
+/// Signed header field names in the order presented to the signing algorithm (RFC 6376 s. 3.5 h=)
+///
+/// Header field names are compared case-insensitively.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DkimSignedHeaders<'hdr> {
+    /// Header field names as they appear in h= without the surrounding whitespace
+    pub names: Vec<&'hdr str>,
+}
+
+impl<'hdr> DkimSignedHeaders<'hdr> {
+    /// Iterate the signed header field names in order
+    pub fn iter(&self) -> impl Iterator<Item = &'hdr str> + '_ {
+        self.names.iter().copied()
+    }
+    /// Number of signed header field instances
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+    /// Whether no header fields are signed
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+    /// Whether the given header field name is signed at least once
+    pub fn contains(&self, name: &str) -> bool {
+        self.count(name) > 0
+    }
+    /// How many times the given header field name is signed
+    pub fn count(&self, name: &str) -> usize {
+        self.names
+            .iter()
+            .filter(|signed| signed.eq_ignore_ascii_case(name))
+            .count()
+    }
+}
+
+// RFC 5322 s. 3.6.8 ftext = %d33-57 / %d59-126
+fn is_ftext(b: u8) -> bool {
+    matches!(b, 33..=57 | 59..=126)
+}
+
+impl<'hdr> TryFrom<&'hdr str> for DkimSignedHeaders<'hdr> {
+    type Error = DkimSignedHeadersError;
+
+    fn try_from(in_str: &'hdr str) -> Result<Self, Self::Error> {
+        let mut names = vec![];
+        for name in in_str.split(':').map(|name| name.trim()) {
+            if name.is_empty() {
+                return Err(DkimSignedHeadersError::EmptyName);
+            }
+            if !name.bytes().all(is_ftext) {
+                return Err(DkimSignedHeadersError::InvalidName);
+            }
+            names.push(name);
+        }
+        Ok(Self { names })
+    }
+}
@@ -1,21 +1,38 @@
 //! DKIM Timestamp
 
-/// DKIM Timestamp
-#[derive(Clone, Debug, Default, PartialEq)]
-pub enum DkimTimestamp<'hdr> {
-    /// Unknown timestamp
-    #[default]
-    Unknown,
-    /// Raw value - parsing delegated downstream
-    Raw(&'hdr str),
+/// DKIM Timestamp - seconds since 00:00:00 on January 1, 1970 in the UTC time zone
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DkimTimestamp(pub u64);
+
+impl DkimTimestamp {
+    /// Seconds since the UNIX epoch
+    pub fn as_secs(&self) -> u64 {
+        self.0
+    }
 }
 
 use crate::error::DkimTimestampError;
 
-impl<'hdr> TryFrom<&'hdr str> for DkimTimestamp<'hdr> {
+// RFC 6376 s. 3.5 sig-t-tag / sig-x-tag = 1*12DIGIT
+const MAX_TIMESTAMP_DIGITS: usize = 12;
+
+impl<'hdr> TryFrom<&'hdr str> for DkimTimestamp {
     type Error = DkimTimestampError;
 
     fn try_from(in_str: &'hdr str) -> Result<Self, Self::Error> {
-        Ok(Self::Raw(in_str))
+        let digits = in_str.trim();
+        if digits.is_empty() {
+            return Err(DkimTimestampError::Empty);
+        }
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(DkimTimestampError::InvalidDigits);
+        }
+        if digits.len() > MAX_TIMESTAMP_DIGITS {
+            return Err(DkimTimestampError::TooManyDigits);
+        }
+        digits
+            .parse::<u64>()
+            .map(Self)
+            .map_err(|_| DkimTimestampError::InvalidDigits)
     }
 }
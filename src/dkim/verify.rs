@@ -0,0 +1,692 @@
+//! DKIM-Signature verification with failure diagnosis - RFC 6376 s. 6
+
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Pkcs1v15Sign, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use super::{
+    BodyHasher, DkimAlgorithm, DkimBodyHash, DkimKeyRecord, DkimResultCode, DkimSignature,
+    DkimTimestamp,
+};
+use crate::error::ResolverError;
+use crate::message::{RawHeader, RawMessage};
+use crate::resolver::TxtResolver;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{format, string::String};
+
+/// Why a signature got the result it did - suitable for the reason= of Authentication-Results
+#[derive(Clone, Debug, PartialEq)]
+pub enum DkimResultReason<'hdr> {
+    /// a= is not supported
+    UnsupportedAlgorithm,
+    /// c= is not supported
+    UnsupportedCanonicalization,
+    /// x= is in the past
+    Expired,
+    /// h= does not include From - RFC 6376 s. 6.1.1
+    FromNotSigned,
+    /// The key record query returned no record
+    KeyNotFound,
+    /// The key record query failed temporarily
+    KeyUnavailable,
+    /// The key record or the key in p= could not be parsed
+    KeyInvalid,
+    /// The key record has an empty p=
+    KeyRevoked,
+    /// k= of the key record does not match a=
+    KeyTypeMismatch,
+    /// h= of the key record does not allow the hash in a=
+    HashNotAllowed,
+    /// The key record t=s requires i= to use the d= domain exactly
+    AuidNotAllowed,
+    /// Body hash did not verify
+    BodyHashMismatch {
+        /// The body with trailing lines removed verifies e.g. a mailing list footer was appended
+        footer_appended: bool,
+    },
+    /// Header signature did not verify
+    SignatureMismatch,
+    /// Header signature did not verify and a header field in h= is not in the message
+    SignedHeaderMissing(&'hdr str),
+    /// A header field in h= appears more times than signed - the extra instances are
+    /// not covered by the signature even when it passes
+    SignedHeaderDuplicated(&'hdr str),
+    /// Passed but l= leaves content after the signed length unsigned
+    BodyLengthLimited,
+}
+
+impl core::fmt::Display for DkimResultReason<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedAlgorithm => write!(f, "unsupported algorithm"),
+            Self::UnsupportedCanonicalization => write!(f, "unsupported canonicalization"),
+            Self::Expired => write!(f, "signature expired"),
+            Self::FromNotSigned => write!(f, "From not signed"),
+            Self::KeyNotFound => write!(f, "no key for signature"),
+            Self::KeyUnavailable => write!(f, "key unavailable"),
+            Self::KeyInvalid => write!(f, "key syntax error"),
+            Self::KeyRevoked => write!(f, "key revoked"),
+            Self::KeyTypeMismatch => write!(f, "inappropriate key algorithm"),
+            Self::HashNotAllowed => write!(f, "inappropriate hash algorithm"),
+            Self::AuidNotAllowed => write!(f, "domain mismatch"),
+            Self::BodyHashMismatch {
+                footer_appended: false,
+            } => write!(f, "body hash did not verify"),
+            Self::BodyHashMismatch {
+                footer_appended: true,
+            } => write!(f, "body hash did not verify - footer appended"),
+            Self::SignatureMismatch => write!(f, "signature did not verify"),
+            Self::SignedHeaderMissing(name) => {
+                write!(f, "signature did not verify - signed {} missing", name)
+            }
+            Self::SignedHeaderDuplicated(name) => {
+                write!(f, "{} appears more times than signed", name)
+            }
+            Self::BodyLengthLimited => write!(f, "body length limit in effect"),
+        }
+    }
+}
+
+/// Outcome of verifying a single DKIM-Signature
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkimVerification<'hdr> {
+    /// dkim Result
+    pub code: DkimResultCode,
+    /// Detailed reason if any
+    pub reason: Option<DkimResultReason<'hdr>>,
+    /// Body hash if the verification got that far
+    pub body_hash: Option<DkimBodyHash>,
+}
+
+impl<'hdr> DkimVerification<'hdr> {
+    fn new(code: DkimResultCode, reason: DkimResultReason<'hdr>) -> Self {
+        Self {
+            code,
+            reason: Some(reason),
+            body_hash: None,
+        }
+    }
+}
+
+/// Verifies DKIM-Signatures fetching the keys through the given resolver
+#[derive(Debug)]
+pub struct DkimVerifier<'res, R: TxtResolver + ?Sized> {
+    resolver: &'res R,
+    now: Option<DkimTimestamp>,
+}
+
+impl<'res, R: TxtResolver + ?Sized> DkimVerifier<'res, R> {
+    /// New verifier - expiration is not checked unless the time is given
+    pub fn new(resolver: &'res R) -> Self {
+        Self {
+            resolver,
+            now: None,
+        }
+    }
+
+    /// Check x= against the given time
+    pub fn with_time(mut self, now: DkimTimestamp) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Verify the signature against the whole message
+    ///
+    /// The signature header is the DKIM-Signature field the signature was
+    /// parsed from as it appears in the message.
+    pub fn verify<'hdr>(
+        &self,
+        sig: &DkimSignature<'hdr>,
+        signature_header: &RawHeader<'_>,
+        message: &RawMessage<'hdr>,
+    ) -> DkimVerification<'hdr> {
+        if let Err(res) = self.precheck(sig) {
+            return res;
+        }
+        let body_hash = match BodyHasher::new(sig) {
+            Ok(mut hasher) => {
+                hasher.update(message.body);
+                hasher.finish()
+            }
+            Err(_) => {
+                return DkimVerification::new(
+                    DkimResultCode::Neutral,
+                    DkimResultReason::UnsupportedAlgorithm,
+                )
+            }
+        };
+        let mut res = self.verify_prechecked(sig, signature_header, &message.headers, body_hash);
+        if let Some(DkimResultReason::BodyHashMismatch {
+            ref mut footer_appended,
+        }) = res.reason
+        {
+            *footer_appended = footer_appended_to(sig, message.body);
+        }
+        res
+    }
+
+    /// Verify the signature with a body hash computed separately e.g. by
+    /// [`super::MultiBodyHasher`] while the body was streamed
+    pub fn verify_headers<'hdr>(
+        &self,
+        sig: &DkimSignature<'hdr>,
+        signature_header: &RawHeader<'_>,
+        headers: &[RawHeader<'hdr>],
+        body_hash: DkimBodyHash,
+    ) -> DkimVerification<'hdr> {
+        if let Err(res) = self.precheck(sig) {
+            return res;
+        }
+        self.verify_prechecked(sig, signature_header, headers, body_hash)
+    }
+
+    // Verification after precheck() has passed
+    fn verify_prechecked<'hdr>(
+        &self,
+        sig: &DkimSignature<'hdr>,
+        signature_header: &RawHeader<'_>,
+        headers: &[RawHeader<'hdr>],
+        body_hash: DkimBodyHash,
+    ) -> DkimVerification<'hdr> {
+        let key_record = match self.fetch_key(sig) {
+            Ok(key_record) => key_record,
+            Err(res) => return res,
+        };
+        let key = match KeyCheck::new(sig, &key_record) {
+            Ok(key) => key,
+            Err(res) => return res,
+        };
+
+        if !body_hash.matches() {
+            let mut res = DkimVerification::new(
+                DkimResultCode::Fail,
+                DkimResultReason::BodyHashMismatch {
+                    footer_appended: false,
+                },
+            );
+            res.body_hash = Some(body_hash);
+            return res;
+        }
+
+        let header_data = match sig.canonicalized_headers(headers, signature_header) {
+            Some(header_data) => header_data,
+            None => {
+                return DkimVerification::new(
+                    DkimResultCode::Neutral,
+                    DkimResultReason::UnsupportedCanonicalization,
+                )
+            }
+        };
+
+        let duplicated = unsigned_duplicate(sig, headers);
+        let mut res = match key.verify(&sig.a, &header_data, sig.b.as_bytes()) {
+            true => DkimVerification {
+                code: DkimResultCode::Pass,
+                reason: match body_hash.is_truncated() {
+                    true => Some(DkimResultReason::BodyLengthLimited),
+                    false => duplicated,
+                },
+                body_hash: None,
+            },
+            false => DkimVerification::new(
+                DkimResultCode::Fail,
+                missing_signed(sig, headers)
+                    .or(duplicated)
+                    .unwrap_or(DkimResultReason::SignatureMismatch),
+            ),
+        };
+        res.body_hash = Some(body_hash);
+        res
+    }
+
+    // Checks not requiring the body or the key
+    fn precheck<'hdr>(&self, sig: &DkimSignature<'hdr>) -> Result<(), DkimVerification<'hdr>> {
+        if matches!(sig.a, DkimAlgorithm::Unknown(_)) {
+            return Err(DkimVerification::new(
+                DkimResultCode::Neutral,
+                DkimResultReason::UnsupportedAlgorithm,
+            ));
+        }
+        if matches!(sig.c.header, super::DkimCanon::Unknown(_))
+            || matches!(sig.c.body, super::DkimCanon::Unknown(_))
+        {
+            return Err(DkimVerification::new(
+                DkimResultCode::Neutral,
+                DkimResultReason::UnsupportedCanonicalization,
+            ));
+        }
+        // RFC 6376 s. 6.1.1 the From header field MUST be signed
+        if !sig.h.contains("From") {
+            return Err(DkimVerification::new(
+                DkimResultCode::PermError,
+                DkimResultReason::FromNotSigned,
+            ));
+        }
+        if let (Some(x), Some(now)) = (sig.x, self.now) {
+            if x < now {
+                return Err(DkimVerification::new(
+                    DkimResultCode::Fail,
+                    DkimResultReason::Expired,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // RFC 6376 s. 3.6.2.1 <selector>._domainkey.<domain>
+    fn fetch_key<'hdr>(&self, sig: &DkimSignature<'hdr>) -> Result<String, DkimVerification<'hdr>> {
+        let name = format!("{}._domainkey.{}", sig.s, sig.d.trim_end_matches('.'));
+        match self.resolver.lookup_txt(&name) {
+            Ok(records) => match records.into_iter().next() {
+                Some(record) => Ok(record),
+                None => Err(DkimVerification::new(
+                    DkimResultCode::PermError,
+                    DkimResultReason::KeyNotFound,
+                )),
+            },
+            Err(ResolverError::NotFound) => Err(DkimVerification::new(
+                DkimResultCode::PermError,
+                DkimResultReason::KeyNotFound,
+            )),
+            Err(ResolverError::Temporary) => Err(DkimVerification::new(
+                DkimResultCode::TempError,
+                DkimResultReason::KeyUnavailable,
+            )),
+            Err(ResolverError::Permanent) => Err(DkimVerification::new(
+                DkimResultCode::PermError,
+                DkimResultReason::KeyUnavailable,
+            )),
+        }
+    }
+}
+
+// Public key checked against the signature
+enum KeyCheck {
+    Rsa(RsaPublicKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+impl KeyCheck {
+    fn new<'hdr>(
+        sig: &DkimSignature<'hdr>,
+        key_record: &str,
+    ) -> Result<Self, DkimVerification<'hdr>> {
+        let perm = |reason| DkimVerification::new(DkimResultCode::PermError, reason);
+
+        let record =
+            DkimKeyRecord::try_from(key_record).map_err(|_| perm(DkimResultReason::KeyInvalid))?;
+        if record.is_revoked() {
+            return Err(perm(DkimResultReason::KeyRevoked));
+        }
+        let hash = match sig.a {
+            DkimAlgorithm::Rsa_Sha1 => "sha1",
+            _ => "sha256",
+        };
+        if !record.allows_hash(hash) {
+            return Err(perm(DkimResultReason::HashNotAllowed));
+        }
+        if record.is_strict() {
+            if let Some(ref i) = sig.i {
+                let i_domain = i.domain.trim_end_matches('.');
+                if !i_domain.eq_ignore_ascii_case(sig.d.trim_end_matches('.')) {
+                    return Err(perm(DkimResultReason::AuidNotAllowed));
+                }
+            }
+        }
+
+        let key = record.p.as_bytes();
+        match (&sig.a, record.k) {
+            (DkimAlgorithm::Rsa_Sha1 | DkimAlgorithm::Rsa_Sha256, "rsa") => {
+                RsaPublicKey::from_public_key_der(key)
+                    .or_else(|_| RsaPublicKey::from_pkcs1_der(key))
+                    .map(Self::Rsa)
+                    .map_err(|_| perm(DkimResultReason::KeyInvalid))
+            }
+            (DkimAlgorithm::Ed25519_Sha256, "ed25519") => <[u8; 32]>::try_from(key)
+                .ok()
+                .and_then(|key| ed25519_dalek::VerifyingKey::from_bytes(&key).ok())
+                .map(Self::Ed25519)
+                .ok_or_else(|| perm(DkimResultReason::KeyInvalid)),
+            _ => Err(perm(DkimResultReason::KeyTypeMismatch)),
+        }
+    }
+
+    fn verify(&self, algorithm: &DkimAlgorithm<'_>, data: &[u8], signature: &[u8]) -> bool {
+        match (self, algorithm) {
+            (Self::Rsa(key), DkimAlgorithm::Rsa_Sha1) => key
+                .verify(Pkcs1v15Sign::new::<Sha1>(), &Sha1::digest(data), signature)
+                .is_ok(),
+            (Self::Rsa(key), _) => key
+                .verify(
+                    Pkcs1v15Sign::new::<Sha256>(),
+                    &Sha256::digest(data),
+                    signature,
+                )
+                .is_ok(),
+            // RFC 8463 s. 3 ed25519 signs the SHA-256 hash of the header data
+            (Self::Ed25519(key), _) => match ed25519_dalek::Signature::from_slice(signature) {
+                Ok(signature) => key.verify_strict(&Sha256::digest(data), &signature).is_ok(),
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+// Signed header field not in the message that was there when signed - may have been removed in transit
+//
+// Names of absent fields may be in h= on purpose to prevent them being added - RFC 6376 s. 8.15.
+// A signer that lists every field in the message once more than it appears does this for all
+// fields so a name it lists only once was absent when signing and does not explain the failure.
+fn missing_signed<'hdr>(
+    sig: &DkimSignature<'hdr>,
+    headers: &[RawHeader<'_>],
+) -> Option<DkimResultReason<'hdr>> {
+    let present = |name: &str| headers.iter().filter(|hdr| hdr.is(name)).count();
+    let oversigning = sig
+        .h
+        .iter()
+        .filter(|name| present(name) > 0)
+        .all(|name| sig.h.count(name) > present(name));
+    let least_signed = match oversigning {
+        true => 2,
+        false => 1,
+    };
+    sig.h
+        .iter()
+        .find(|name| present(name) == 0 && sig.h.count(name) >= least_signed)
+        .map(DkimResultReason::SignedHeaderMissing)
+}
+
+// Signed header field with more instances than signed - RFC 6376 s. 8.15
+fn unsigned_duplicate<'hdr>(
+    sig: &DkimSignature<'hdr>,
+    headers: &[RawHeader<'_>],
+) -> Option<DkimResultReason<'hdr>> {
+    sig.h
+        .iter()
+        .find(|name| headers.iter().filter(|hdr| hdr.is(name)).count() > sig.h.count(name))
+        .map(DkimResultReason::SignedHeaderDuplicated)
+}
+
+// Whether the canonicalized body up to some earlier line ending hashes to bh=
+fn footer_appended_to(sig: &DkimSignature<'_>, body: &[u8]) -> bool {
+    let canonicalized = match sig.canonicalized_body(body) {
+        Some(canonicalized) => canonicalized,
+        None => return false,
+    };
+    let expected = sig.bh.as_bytes();
+    let mut sha1 = Sha1::new();
+    let mut sha256 = Sha256::new();
+    let mut line_start = 0;
+
+    for (pos, window) in canonicalized.windows(2).enumerate() {
+        if window != b"\r\n" {
+            continue;
+        }
+        let line = &canonicalized[line_start..pos + 2];
+        line_start = pos + 2;
+        if line_start == canonicalized.len() {
+            break;
+        }
+        let matched = match sig.a {
+            DkimAlgorithm::Rsa_Sha1 => {
+                sha1.update(line);
+                sha1.clone().finalize().as_slice() == expected
+            }
+            _ => {
+                sha256.update(line);
+                sha256.clone().finalize().as_slice() == expected
+            }
+        };
+        if matched {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+    use std::path::PathBuf;
+
+    // Serves the keys in test_data/domainkeys named <selector>.<domain>
+    struct DomainKeys;
+
+    impl TxtResolver for DomainKeys {
+        fn lookup_txt(&self, name: &str) -> Result<Vec<String>, ResolverError> {
+            let file = name.replace("._domainkey.", ".");
+            let path = PathBuf::from("test_data/domainkeys").join(file);
+            let key = std::fs::read_to_string(path).map_err(|_| ResolverError::NotFound)?;
+            let key = key.trim();
+            let k = match key.len() {
+                44 => "ed25519",
+                _ => "rsa",
+            };
+            Ok(vec![format!("v=DKIM1; k={}; p={}", k, key)])
+        }
+    }
+
+    struct StaticKey(&'static str);
+
+    impl TxtResolver for StaticKey {
+        fn lookup_txt(&self, _name: &str) -> Result<Vec<String>, ResolverError> {
+            Ok(vec![self.0.to_string()])
+        }
+    }
+
+    struct Unavailable;
+
+    impl TxtResolver for Unavailable {
+        fn lookup_txt(&self, _name: &str) -> Result<Vec<String>, ResolverError> {
+            Err(ResolverError::Temporary)
+        }
+    }
+
+    fn verify_nth<R: TxtResolver>(
+        raw: &[u8],
+        nth: usize,
+        verifier: &DkimVerifier<'_, R>,
+    ) -> (DkimResultCode, Option<String>) {
+        let msg = RawMessage::try_from(raw).unwrap();
+        let sig_hdr = msg.headers_named("DKIM-Signature").nth(nth).unwrap();
+        let sig_value = core::str::from_utf8(sig_hdr.value).unwrap();
+        let sig = DkimSignature::try_from(sig_value).unwrap();
+        let res = verifier.verify(&sig, sig_hdr, &msg);
+        (res.code, res.reason.map(|reason| reason.to_string()))
+    }
+
+    fn load(file: &str) -> Vec<u8> {
+        std::fs::read(PathBuf::from("test_data").join(file)).unwrap()
+    }
+
+    #[rstest]
+    #[case(0, "rsa")]
+    #[case(1, "ed25519")]
+    fn pass(#[case] nth: usize, #[case] _algorithm: &str) {
+        let raw = load("from_arewe_at_to_gmail.eml");
+        let verifier = DkimVerifier::new(&DomainKeys);
+        assert_eq!(
+            verify_nth(&raw, nth, &verifier),
+            (DkimResultCode::Pass, None)
+        );
+    }
+
+    #[test]
+    fn footer_appended() {
+        let mut raw = load("from_arewe_at_to_gmail.eml");
+        raw.extend_from_slice(b"--\r\nMailing list footer\r\n");
+        let verifier = DkimVerifier::new(&DomainKeys);
+        assert_eq!(
+            verify_nth(&raw, 0, &verifier),
+            (
+                DkimResultCode::Fail,
+                Some("body hash did not verify - footer appended".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn body_modified() {
+        let mut raw = load("from_arewe_at_to_gmail.eml");
+        let body_len = RawMessage::try_from(raw.as_slice()).unwrap().body.len();
+        let body_start = raw.len() - body_len;
+        raw[body_start] ^= 0x01;
+        let verifier = DkimVerifier::new(&DomainKeys);
+        assert_eq!(
+            verify_nth(&raw, 0, &verifier),
+            (
+                DkimResultCode::Fail,
+                Some("body hash did not verify".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn header_modified() {
+        let raw = String::from_utf8(load("from_arewe_at_to_gmail.eml")).unwrap();
+        let modified = raw.replacen("Subject: ", "Subject: Re: ", 1);
+        let verifier = DkimVerifier::new(&DomainKeys);
+        assert_eq!(
+            verify_nth(modified.as_bytes(), 0, &verifier),
+            (
+                DkimResultCode::Fail,
+                Some("signature did not verify".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn header_duplicated() {
+        let mut raw = b"Subject: Prepended\r\n".to_vec();
+        raw.extend(load("from_arewe_at_to_gmail.eml"));
+        let verifier = DkimVerifier::new(&DomainKeys);
+        assert_eq!(
+            verify_nth(&raw, 0, &verifier),
+            (
+                DkimResultCode::Pass,
+                Some("Subject appears more times than signed".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn header_missing() {
+        let raw = String::from_utf8(load("from_arewe_at_to_gmail.eml")).unwrap();
+        let modified = raw.replacen("Message-ID:", "X-Removed-Message-ID:", 1);
+        let verifier = DkimVerifier::new(&DomainKeys);
+        assert_eq!(
+            verify_nth(modified.as_bytes(), 0, &verifier),
+            (
+                DkimResultCode::Fail,
+                Some("signature did not verify - signed Message-ID missing".to_string())
+            )
+        );
+    }
+
+    #[rstest]
+    #[case("From:Subject:Message-ID", Some("Message-ID"))]
+    #[case("From:Subject:Reply-To", Some("Reply-To"))]
+    #[case("From:From:Subject:Subject:Reply-To", None)]
+    #[case(
+        "From:From:Subject:Subject:Message-ID:Message-ID:Reply-To",
+        Some("Message-ID")
+    )]
+    #[case("From:Subject", None)]
+    fn missing_signed_oversigning(#[case] h: &str, #[case] expected: Option<&str>) {
+        let raw = format!(
+            "v=1; a=rsa-sha256; d=example.com; s=sel; h={}; bh=AAAA; b=AAAA",
+            h
+        );
+        let sig = DkimSignature::try_from(raw.as_str()).unwrap();
+        let msg = RawMessage::try_from(&b"From: a@example.com\r\nSubject: x\r\n\r\n"[..]).unwrap();
+        assert_eq!(
+            missing_signed(&sig, &msg.headers),
+            expected.map(DkimResultReason::SignedHeaderMissing)
+        );
+    }
+
+    #[test]
+    fn from_not_signed() {
+        let raw = load("from_arewe_at_to_gmail.eml");
+        let msg = RawMessage::try_from(raw.as_slice()).unwrap();
+        let sig_hdr = msg.headers_named("DKIM-Signature").next().unwrap();
+        let value = core::str::from_utf8(sig_hdr.value)
+            .unwrap()
+            .replace("h=Subject:From:To", "h=Subject:To");
+        let sig = DkimSignature::try_from(value.as_str()).unwrap();
+        let res = DkimVerifier::new(&DomainKeys).verify(&sig, sig_hdr, &msg);
+        assert_eq!(res.code, DkimResultCode::PermError);
+        assert_eq!(res.reason, Some(DkimResultReason::FromNotSigned));
+    }
+
+    #[rstest]
+    #[case(StaticKey("v=DKIM1; p="), DkimResultCode::PermError, "key revoked")]
+    #[case(
+        StaticKey("v=DKIM1; k=ed25519; p=AAAA"),
+        DkimResultCode::PermError,
+        "inappropriate key algorithm"
+    )]
+    #[case(
+        StaticKey("v=DKIM1; h=sha1; p=AAAA"),
+        DkimResultCode::PermError,
+        "inappropriate hash algorithm"
+    )]
+    #[case(
+        StaticKey("v=DKIM1; p=AAAA"),
+        DkimResultCode::PermError,
+        "key syntax error"
+    )]
+    #[case(StaticKey("v=DKIM2"), DkimResultCode::PermError, "key syntax error")]
+    fn key_problems(
+        #[case] resolver: StaticKey,
+        #[case] code: DkimResultCode,
+        #[case] expected: &str,
+    ) {
+        let raw = load("from_arewe_at_to_gmail.eml");
+        let verifier = DkimVerifier::new(&resolver);
+        assert_eq!(
+            verify_nth(&raw, 0, &verifier),
+            (code, Some(expected.to_string()))
+        );
+    }
+
+    #[test]
+    fn key_not_found_and_unavailable() {
+        let raw = load("rfc6376_a2_signed.eml");
+        let verifier = DkimVerifier::new(&DomainKeys);
+        assert_eq!(
+            verify_nth(&raw, 0, &verifier),
+            (
+                DkimResultCode::PermError,
+                Some("no key for signature".to_string())
+            )
+        );
+        let verifier = DkimVerifier::new(&Unavailable);
+        assert_eq!(
+            verify_nth(&raw, 0, &verifier),
+            (
+                DkimResultCode::TempError,
+                Some("key unavailable".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let raw = load("from_arewe_at_to_gmail.eml");
+        let msg = RawMessage::try_from(raw.as_slice()).unwrap();
+        let sig_hdr = msg.headers_named("DKIM-Signature").next().unwrap();
+        let mut sig =
+            DkimSignature::try_from(core::str::from_utf8(sig_hdr.value).unwrap()).unwrap();
+        sig.x = Some(DkimTimestamp(1));
+        let verifier = DkimVerifier::new(&DomainKeys).with_time(DkimTimestamp(2));
+        let res = verifier.verify(&sig, sig_hdr, &msg);
+        assert_eq!(res.code, DkimResultCode::Fail);
+        assert_eq!(res.reason, Some(DkimResultReason::Expired));
+    }
+}
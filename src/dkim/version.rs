@@ -11,6 +11,7 @@ pub enum DkimVersion<'hdr> {
 
 use crate::error::DkimVersionError;
 
+#[allow(clippy::infallible_try_from)]
 impl<'hdr> TryFrom<&'hdr str> for DkimVersion<'hdr> {
     type Error = DkimVersionError;
 
@@ -0,0 +1,76 @@
+//! DMARC policy record and associated types - RFC 7489
+
+pub(crate) mod record;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use record::DmarcRecord;
+pub use record::{DmarcAlignment, DmarcFailureOption, DmarcPolicy, DmarcUri};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+mod evaluate;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use evaluate::{DmarcDiscovery, DmarcEvaluation, DmarcEvaluator};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod failure;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod report;
+
+/// DMARC Result - RFC 7489 s. 11.2
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DmarcResult<'hdr> {
+    /// Result
+    pub code: DmarcResultCode,
+    /// header.from - the RFC5322.From domain evaluated
+    pub header_from: Option<&'hdr str>,
+    /// policy.dmarc - the p= / sp= / np= policy that was in effect
+    pub policy_dmarc: Option<DmarcPolicy>,
+    /// What the Mail Receiver should do with the message after pct= and t=
+    pub disposition: Option<DmarcPolicy>,
+    /// unparsed
+    pub raw: Option<&'hdr str>,
+}
+
+impl<'hdr> DmarcResult<'hdr> {
+    pub(crate) fn set_header(&mut self, prop: &ptypes::DmarcHeader<'hdr>) -> bool {
+        match prop {
+            ptypes::DmarcHeader::From(val) => self.header_from = Some(val),
+            ptypes::DmarcHeader::Unknown(_key, _val) => {} // ignore RFC unknowns
+        }
+        true
+    }
+    pub(crate) fn set_policy(&mut self, prop: &ptypes::DmarcPolicyProperty<'hdr>) -> bool {
+        match prop {
+            ptypes::DmarcPolicyProperty::Dmarc(val) => {
+                self.policy_dmarc = DmarcPolicy::try_from(*val).ok()
+            }
+            ptypes::DmarcPolicyProperty::Unknown(_key, _val) => {} // ignore RFC unknowns
+        }
+        true
+    }
+}
+
+/// DMARC Result Codes - RFC 7489 s. 11.2
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum DmarcResultCode {
+    /// Result code not seen
+    #[default]
+    Unknown,
+    /// No DMARC policy record was published for the aligned identifier
+    /// or no aligned identifier could be extracted
+    NoneDmarc,
+    /// A DMARC policy record was published and at least one aligned
+    /// identifier passed
+    Pass,
+    /// A DMARC policy record was published but no aligned identifier passed
+    Fail,
+    /// A temporary error occurred during DMARC evaluation e.g. while
+    /// retrieving the policy record
+    TempError,
+    /// A permanent error occurred during DMARC evaluation e.g. a
+    /// syntactically incorrect policy record
+    PermError,
+}
+
+pub mod ptypes;
+pub use ptypes::DmarcProperty;
@@ -0,0 +1,696 @@
+//! DMARC evaluation of authenticated identifiers against the published policy - RFC 7489 s. 6.6
+
+use crate::alloc_yes::{Alignment, AuthenticationResults};
+use crate::dkim::DkimResultCode;
+use crate::error::{DmarcRecordError, ResolverError};
+use crate::orgdomain::{normalize, org_domain};
+use crate::resolver::TxtResolver;
+use crate::spf::SpfResultCode;
+
+use super::{DmarcAlignment, DmarcPolicy, DmarcRecord, DmarcResult, DmarcResultCode};
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// How the policy record is discovered when the From domain publishes none
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DmarcDiscovery {
+    /// Query the organizational domain by the Public Suffix List - RFC 7489 s. 6.6.3
+    #[default]
+    OrgDomain,
+    /// Query each parent domain removing a label at a time - DMARCbis
+    TreeWalk,
+}
+
+// DMARCbis tree walk queries at most this many names
+const TREE_WALK_MAX: usize = 8;
+
+impl From<DmarcAlignment> for Alignment {
+    fn from(alignment: DmarcAlignment) -> Self {
+        match alignment {
+            DmarcAlignment::Relaxed => Self::Relaxed,
+            DmarcAlignment::Strict => Self::Strict,
+        }
+    }
+}
+
+/// Evaluate DMARC for the From domain fetching the policy through the given resolver
+///
+/// The SPF and DKIM results can come from trusted Authentication-Results or
+/// from verifying the message with this crate.
+#[derive(Debug)]
+pub struct DmarcEvaluator<'res, 'hdr, R: TxtResolver + ?Sized> {
+    resolver: &'res R,
+    from_domain: &'hdr str,
+    spf: Vec<(SpfResultCode, &'hdr str)>,
+    dkim: Vec<(DkimResultCode, &'hdr str)>,
+    discovery: DmarcDiscovery,
+    non_existent: bool,
+    sample: u8,
+}
+
+/// DMARC result with what it was decided on
+#[derive(Clone, Debug, PartialEq)]
+pub struct DmarcEvaluation<'hdr> {
+    /// Result for Authentication-Results with the disposition
+    pub result: DmarcResult<'hdr>,
+    /// Domain the policy record was found at if any
+    pub record_domain: Option<String>,
+    /// The policy record as published
+    pub record: Option<String>,
+    /// Whether a DKIM pass was aligned with the From domain
+    pub dkim_aligned: bool,
+    /// Whether an SPF pass was aligned with the From domain
+    pub spf_aligned: bool,
+    /// Whether a failing message was exempted from the policy by pct= or t=y
+    pub sampled_out: bool,
+}
+
+impl DmarcEvaluation<'_> {
+    /// The parsed policy record - as if p=none for the RFC 7489 s. 6.6.3 fallback
+    pub fn record(&self) -> Option<DmarcRecord<'_>> {
+        DmarcRecord::parse_with_fallback(self.record.as_deref()?).ok()
+    }
+}
+
+// Domain of smtp.mailfrom which may be a bare domain or an address in angle brackets or not
+pub(crate) fn mailfrom_domain(mailfrom: &str) -> &str {
+    let mailfrom = mailfrom.trim_start_matches('<').trim_end_matches('>');
+    match mailfrom.rsplit_once('@') {
+        Some((_, domain)) => domain,
+        None => mailfrom,
+    }
+}
+
+// RFC 7489 s. 6.6.3 records not starting with v=DMARC1 are discarded
+fn is_dmarc_record(record: &str) -> bool {
+    let first_tag = record.split(';').next().unwrap_or_default();
+    match first_tag.split_once('=') {
+        Some((tag, value)) => tag.trim() == "v" && value.trim() == "DMARC1",
+        None => false,
+    }
+}
+
+// Names queried after the From domain itself in the order queried
+fn parent_domains(domain: &str, discovery: DmarcDiscovery) -> Vec<String> {
+    match discovery {
+        DmarcDiscovery::OrgDomain => match org_domain(domain) {
+            Some(org) if org != domain => vec![org],
+            _ => vec![],
+        },
+        // Long names skip to the last seven labels and the TLD is never queried
+        DmarcDiscovery::TreeWalk => {
+            let labels: Vec<&str> = domain.split('.').collect();
+            let first = labels.len().saturating_sub(TREE_WALK_MAX - 1).max(1);
+            (first..labels.len().saturating_sub(1))
+                .map(|skip| labels[skip..].join("."))
+                .collect()
+        }
+    }
+}
+
+// The name one label below the given parent of the domain
+fn one_label_below(domain: &str, parent: &str) -> String {
+    let labels: Vec<&str> = domain.split('.').collect();
+    let keep = (parent.split('.').count() + 1).min(labels.len());
+    labels[labels.len() - keep..].join(".")
+}
+
+// Reject becomes quarantine and quarantine becomes none - RFC 7489 s. 6.6.4
+fn downgrade(policy: DmarcPolicy) -> DmarcPolicy {
+    match policy {
+        DmarcPolicy::Reject => DmarcPolicy::Quarantine,
+        DmarcPolicy::Quarantine | DmarcPolicy::None => DmarcPolicy::None,
+    }
+}
+
+// Record fetched for one name
+#[derive(Clone)]
+enum Fetched {
+    Record(String),
+    NoRecord,
+    // Missing or invalid p= without a valid rua= - RFC 7489 s. 6.6.3
+    NoPolicy,
+    Invalid,
+    Error(ResolverError),
+}
+
+impl<'res, 'hdr, R: TxtResolver + ?Sized> DmarcEvaluator<'res, 'hdr, R> {
+    /// New evaluator for the RFC5322.From domain
+    pub fn new(resolver: &'res R, from_domain: &'hdr str) -> Self {
+        Self {
+            resolver,
+            from_domain,
+            spf: vec![],
+            dkim: vec![],
+            discovery: DmarcDiscovery::default(),
+            non_existent: false,
+            sample: 0,
+        }
+    }
+    /// SPF result for the domain of the MAIL FROM or HELO identity it authenticated
+    pub fn with_spf(mut self, code: SpfResultCode, smtp_mailfrom: &'hdr str) -> Self {
+        self.spf.push((code, smtp_mailfrom));
+        self
+    }
+    /// DKIM result for the signature with the given d=
+    pub fn with_dkim(mut self, code: DkimResultCode, header_d: &'hdr str) -> Self {
+        self.dkim.push((code, header_d));
+        self
+    }
+    /// All the SPF and DKIM results in the Authentication-Results
+    ///
+    /// Only pass Authentication-Results added by a trusted authserv-id.
+    /// SPF results without smtp.mailfrom use smtp.helo.
+    pub fn with_auth_results(mut self, auth_results: &AuthenticationResults<'hdr>) -> Self {
+        for spf_res in auth_results.spf_result.iter() {
+            if let Some(identity) = spf_res.smtp_mailfrom.or(spf_res.smtp_helo) {
+                self.spf.push((spf_res.code.clone(), identity));
+            }
+        }
+        for dkim_res in auth_results.dkim_result.iter() {
+            if let Some(header_d) = dkim_res.header_d {
+                self.dkim.push((dkim_res.code.clone(), header_d));
+            }
+        }
+        self
+    }
+    /// How the policy record is discovered - organizational domain by default
+    pub fn with_discovery(mut self, discovery: DmarcDiscovery) -> Self {
+        self.discovery = discovery;
+        self
+    }
+    /// The From domain does not exist in DNS so np= applies - DMARCbis
+    pub fn with_non_existent_domain(mut self) -> Self {
+        self.non_existent = true;
+        self
+    }
+    /// Number between 0 and 99 chosen at random per message for pct= sampling
+    ///
+    /// The policy applies to a failing message when the number is below pct=.
+    /// Defaults to 0 which applies the policy unless pct=0.
+    pub fn with_sample(mut self, sample: u8) -> Self {
+        self.sample = sample;
+        self
+    }
+
+    // Each name is looked up once per evaluation as the tree walk revisits them
+    fn fetch(&self, fetched: &mut Vec<(String, Fetched)>, domain: &str) -> Fetched {
+        if let Some((_, known)) = fetched.iter().find(|(name, _)| name == domain) {
+            return known.clone();
+        }
+        let answer = self.lookup(domain);
+        fetched.push((domain.to_string(), answer.clone()));
+        answer
+    }
+
+    fn lookup(&self, domain: &str) -> Fetched {
+        let records = match self.resolver.lookup_txt(&format!("_dmarc.{}", domain)) {
+            Ok(records) => records,
+            Err(ResolverError::NotFound) => return Fetched::NoRecord,
+            Err(e) => return Fetched::Error(e),
+        };
+        let mut dmarc = records.into_iter().filter(|r| is_dmarc_record(r));
+        match (dmarc.next(), dmarc.next()) {
+            (Some(record), None) => match DmarcRecord::parse_with_fallback(record.as_str()) {
+                Ok(_) => Fetched::Record(record),
+                Err(DmarcRecordError::MissingPolicy) => Fetched::NoPolicy,
+                Err(_) => Fetched::Invalid,
+            },
+            // RFC 7489 s. 6.6.3 more than one record is as good as none
+            _ => Fetched::NoRecord,
+        }
+    }
+
+    // Organizational Domain by the DMARCbis tree walk - the name with psd=n, the
+    // name below the one with psd=y or else the shortest name with a record
+    fn tree_walk_org_domain(&self, fetched: &mut Vec<(String, Fetched)>, domain: &str) -> String {
+        let mut names = vec![domain.to_string()];
+        names.extend(parent_domains(domain, DmarcDiscovery::TreeWalk));
+        let mut org = None;
+        for name in names.iter() {
+            let raw = match self.fetch(fetched, name) {
+                Fetched::Record(raw) => raw,
+                _ => continue,
+            };
+            let psd = DmarcRecord::parse_with_fallback(&raw)
+                .ok()
+                .and_then(|record| {
+                    record
+                        .unknown_tags
+                        .iter()
+                        .find(|(tag, _)| tag.eq_ignore_ascii_case("psd"))
+                        .map(|(_, value)| value.to_ascii_lowercase())
+                });
+            match psd.as_deref() {
+                Some("n") => return name.clone(),
+                // The walk may skip labels of long names so count them from the domain
+                Some("y") if name != domain => return one_label_below(domain, name),
+                _ => org = Some(name.clone()),
+            }
+        }
+        org.unwrap_or_else(|| domain.to_string())
+    }
+
+    // Relaxed alignment compares the tree walk Organizational Domains when given the From one
+    fn aligned(
+        &self,
+        fetched: &mut Vec<(String, Fetched)>,
+        alignment: Alignment,
+        domain: &str,
+        from_org: Option<&str>,
+    ) -> bool {
+        match (alignment, from_org) {
+            (Alignment::Relaxed, Some(from_org)) => {
+                let domain = normalize(domain);
+                // The Organizational Domain is always the name itself or a parent of it
+                let under_from_org = domain == from_org
+                    || domain
+                        .strip_suffix(from_org)
+                        .is_some_and(|sub| sub.ends_with('.'));
+                domain == normalize(self.from_domain)
+                    || (under_from_org && self.tree_walk_org_domain(fetched, &domain) == from_org)
+            }
+            _ => alignment.is_aligned(domain, self.from_domain),
+        }
+    }
+
+    /// Evaluate DMARC - RFC 7489 s. 6.6.2
+    ///
+    /// One aligned DKIM or SPF pass is enough. A failing message gets the
+    /// p= policy when the record is at the From domain and otherwise sp= or
+    /// np= when the From domain does not exist.
+    pub fn evaluate(&self) -> DmarcEvaluation<'hdr> {
+        let mut evaluation = DmarcEvaluation {
+            result: DmarcResult {
+                code: DmarcResultCode::NoneDmarc,
+                header_from: Some(self.from_domain),
+                policy_dmarc: None,
+                disposition: None,
+                raw: None,
+            },
+            record_domain: None,
+            record: None,
+            dkim_aligned: false,
+            spf_aligned: false,
+            sampled_out: false,
+        };
+
+        let domain = normalize(self.from_domain);
+        let mut fetched = vec![];
+        let mut candidates = vec![domain.clone()];
+        candidates.extend(parent_domains(&domain, self.discovery));
+
+        let (record_domain, raw) = match candidates.into_iter().find_map(|candidate| {
+            match self.fetch(&mut fetched, &candidate) {
+                Fetched::NoRecord => None,
+                fetched => Some((candidate, fetched)),
+            }
+        }) {
+            Some((candidate, Fetched::Record(raw))) => (candidate, raw),
+            Some((_, Fetched::Error(ResolverError::Temporary))) => {
+                evaluation.result.code = DmarcResultCode::TempError;
+                return evaluation;
+            }
+            Some((_, Fetched::NoPolicy)) => return evaluation,
+            Some(_) => {
+                evaluation.result.code = DmarcResultCode::PermError;
+                return evaluation;
+            }
+            None => return evaluation,
+        };
+        evaluation.record_domain = Some(record_domain.clone());
+        evaluation.record = Some(raw);
+        let record = match evaluation.record() {
+            Some(record) => record,
+            None => return evaluation,
+        };
+
+        let dkim_alignment = Alignment::from(record.adkim);
+        let spf_alignment = Alignment::from(record.aspf);
+        // DMARCbis replaces the Public Suffix List with the tree walk
+        let from_org = match self.discovery {
+            DmarcDiscovery::TreeWalk => Some(self.tree_walk_org_domain(&mut fetched, &domain)),
+            DmarcDiscovery::OrgDomain => None,
+        };
+        let from_org = from_org.as_deref();
+        let dkim_aligned = self.dkim.iter().any(|(code, header_d)| {
+            *code == DkimResultCode::Pass
+                && self.aligned(&mut fetched, dkim_alignment, header_d, from_org)
+        });
+        let spf_aligned = self.spf.iter().any(|(code, mailfrom)| {
+            *code == SpfResultCode::Pass
+                && self.aligned(
+                    &mut fetched,
+                    spf_alignment,
+                    mailfrom_domain(mailfrom),
+                    from_org,
+                )
+        });
+
+        let policy = match (record_domain == domain, self.non_existent) {
+            (true, _) => record.p,
+            (false, true) => record.non_existent_policy(),
+            (false, false) => record.subdomain_policy(),
+        };
+        let applied = !record.is_testing() && self.sample < record.pct;
+        let (code, disposition, sampled_out) = match dkim_aligned || spf_aligned {
+            true => (DmarcResultCode::Pass, DmarcPolicy::None, false),
+            false if applied => (DmarcResultCode::Fail, policy, false),
+            false => (DmarcResultCode::Fail, downgrade(policy), true),
+        };
+
+        evaluation.result.code = code;
+        evaluation.result.policy_dmarc = Some(policy);
+        evaluation.result.disposition = Some(disposition);
+        evaluation.dkim_aligned = dkim_aligned;
+        evaluation.spf_aligned = spf_aligned;
+        evaluation.sampled_out = sampled_out;
+        evaluation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    // Answers by name with the names queried
+    #[derive(Default)]
+    struct ZoneResolver(
+        HashMap<&'static str, Result<Vec<String>, ResolverError>>,
+        RefCell<Vec<String>>,
+    );
+
+    impl ZoneResolver {
+        fn with(mut self, name: &'static str, answer: Result<Vec<&str>, ResolverError>) -> Self {
+            let answer = answer.map(|records| records.iter().map(|r| r.to_string()).collect());
+            self.0.insert(name, answer);
+            self
+        }
+    }
+
+    impl TxtResolver for ZoneResolver {
+        fn lookup_txt(&self, name: &str) -> Result<Vec<String>, ResolverError> {
+            self.1.borrow_mut().push(name.to_string());
+            self.0
+                .get(name)
+                .cloned()
+                .unwrap_or(Err(ResolverError::NotFound))
+        }
+    }
+
+    fn zone(record: &'static str) -> ZoneResolver {
+        ZoneResolver::default().with("_dmarc.example.com", Ok(vec![record]))
+    }
+
+    #[rstest]
+    #[case(
+        "v=DMARC1; p=reject",
+        DkimResultCode::Pass,
+        "example.com",
+        DmarcResultCode::Pass,
+        DmarcPolicy::None
+    )]
+    #[case(
+        "v=DMARC1; p=reject",
+        DkimResultCode::Pass,
+        "mail.example.com",
+        DmarcResultCode::Pass,
+        DmarcPolicy::None
+    )]
+    #[case(
+        "v=DMARC1; p=reject; adkim=s",
+        DkimResultCode::Pass,
+        "mail.example.com",
+        DmarcResultCode::Fail,
+        DmarcPolicy::Reject
+    )]
+    #[case(
+        "v=DMARC1; p=reject",
+        DkimResultCode::Pass,
+        "example.org",
+        DmarcResultCode::Fail,
+        DmarcPolicy::Reject
+    )]
+    #[case(
+        "v=DMARC1; p=quarantine",
+        DkimResultCode::Fail,
+        "example.com",
+        DmarcResultCode::Fail,
+        DmarcPolicy::Quarantine
+    )]
+    #[case(
+        "v=DMARC1; p=none",
+        DkimResultCode::Fail,
+        "example.com",
+        DmarcResultCode::Fail,
+        DmarcPolicy::None
+    )]
+    fn dkim_alignment(
+        #[case] record: &'static str,
+        #[case] code: DkimResultCode,
+        #[case] header_d: &str,
+        #[case] expected: DmarcResultCode,
+        #[case] disposition: DmarcPolicy,
+    ) {
+        let resolver = zone(record);
+        let evaluation = DmarcEvaluator::new(&resolver, "example.com")
+            .with_dkim(code, header_d)
+            .evaluate();
+        assert_eq!(evaluation.result.code, expected);
+        assert_eq!(evaluation.result.disposition, Some(disposition));
+        assert_eq!(evaluation.record_domain.as_deref(), Some("example.com"));
+    }
+
+    #[rstest]
+    #[case(
+        "v=DMARC1; p=reject",
+        "bounces@bounces.example.com",
+        DmarcResultCode::Pass
+    )]
+    #[case("v=DMARC1; p=reject", "<bounces@example.com>", DmarcResultCode::Pass)]
+    #[case(
+        "v=DMARC1; p=reject; aspf=s",
+        "bounces.example.com",
+        DmarcResultCode::Fail
+    )]
+    #[case("v=DMARC1; p=reject", "example.org", DmarcResultCode::Fail)]
+    fn spf_alignment(
+        #[case] record: &'static str,
+        #[case] mailfrom: &str,
+        #[case] expected: DmarcResultCode,
+    ) {
+        let resolver = zone(record);
+        let evaluation = DmarcEvaluator::new(&resolver, "example.com")
+            .with_spf(SpfResultCode::Pass, mailfrom)
+            .with_spf(SpfResultCode::Fail, "example.com")
+            .evaluate();
+        assert_eq!(evaluation.result.code, expected);
+        assert_eq!(evaluation.spf_aligned, expected == DmarcResultCode::Pass);
+        assert!(!evaluation.dkim_aligned);
+    }
+
+    #[rstest]
+    #[case("v=DMARC1; p=reject; sp=quarantine", false, DmarcPolicy::Quarantine)]
+    #[case("v=DMARC1; p=reject", false, DmarcPolicy::Reject)]
+    #[case(
+        "v=DMARC1; p=reject; sp=quarantine; np=none",
+        false,
+        DmarcPolicy::Quarantine
+    )]
+    #[case("v=DMARC1; p=reject; sp=quarantine; np=none", true, DmarcPolicy::None)]
+    #[case("v=DMARC1; p=reject; sp=quarantine", true, DmarcPolicy::Quarantine)]
+    fn subdomain_policy(
+        #[case] record: &'static str,
+        #[case] non_existent: bool,
+        #[case] expected: DmarcPolicy,
+    ) {
+        let resolver = zone(record);
+        let mut evaluator = DmarcEvaluator::new(&resolver, "Mail.Example.com.");
+        if non_existent {
+            evaluator = evaluator.with_non_existent_domain();
+        }
+        let evaluation = evaluator.evaluate();
+        assert_eq!(evaluation.result.code, DmarcResultCode::Fail);
+        assert_eq!(evaluation.result.policy_dmarc, Some(expected));
+        assert_eq!(evaluation.result.header_from, Some("Mail.Example.com."));
+        assert_eq!(evaluation.record_domain.as_deref(), Some("example.com"));
+    }
+
+    #[rstest]
+    #[case("v=DMARC1; p=reject; pct=50", 49, DmarcPolicy::Reject, false)]
+    #[case("v=DMARC1; p=reject; pct=50", 50, DmarcPolicy::Quarantine, true)]
+    #[case("v=DMARC1; p=quarantine; pct=0", 0, DmarcPolicy::None, true)]
+    #[case("v=DMARC1; p=reject; t=y", 0, DmarcPolicy::Quarantine, true)]
+    #[case("v=DMARC1; p=reject", 99, DmarcPolicy::Reject, false)]
+    fn sampling(
+        #[case] record: &'static str,
+        #[case] sample: u8,
+        #[case] disposition: DmarcPolicy,
+        #[case] sampled_out: bool,
+    ) {
+        let resolver = zone(record);
+        let evaluation = DmarcEvaluator::new(&resolver, "example.com")
+            .with_sample(sample)
+            .evaluate();
+        assert_eq!(evaluation.result.code, DmarcResultCode::Fail);
+        assert_eq!(evaluation.result.disposition, Some(disposition));
+        assert_eq!(evaluation.sampled_out, sampled_out);
+    }
+
+    #[rstest]
+    #[case(Err(ResolverError::NotFound), DmarcResultCode::NoneDmarc)]
+    #[case(Err(ResolverError::Temporary), DmarcResultCode::TempError)]
+    #[case(Err(ResolverError::Permanent), DmarcResultCode::PermError)]
+    #[case(Ok(vec!["v=spf1 -all"]), DmarcResultCode::NoneDmarc)]
+    #[case(Ok(vec!["v=DMARC1; p=reject", "v=DMARC1; p=none"]), DmarcResultCode::NoneDmarc)]
+    #[case(Ok(vec!["v=DMARC1; p=bogus"]), DmarcResultCode::NoneDmarc)]
+    #[case(Ok(vec!["v=DMARC1; p=bogus; rua=mailto:a@example.com"]), DmarcResultCode::Fail)]
+    #[case(Ok(vec!["v=DMARC1; p=reject; sp=bogus"]), DmarcResultCode::NoneDmarc)]
+    #[case(Ok(vec!["v=DMARC2; p=reject"]), DmarcResultCode::NoneDmarc)]
+    #[case(Ok(vec!["v=DMARC1; p=reject; np=bogus"]), DmarcResultCode::PermError)]
+    #[case(Ok(vec!["v=spf1 -all", "v=DMARC1; p=none"]), DmarcResultCode::Fail)]
+    fn record_lookup(
+        #[case] answer: Result<Vec<&str>, ResolverError>,
+        #[case] expected: DmarcResultCode,
+    ) {
+        let resolver = ZoneResolver::default().with("_dmarc.example.com", answer);
+        let evaluation = DmarcEvaluator::new(&resolver, "example.com").evaluate();
+        assert_eq!(evaluation.result.code, expected);
+        match expected {
+            DmarcResultCode::Fail => {
+                assert_eq!(evaluation.result.policy_dmarc, Some(DmarcPolicy::None))
+            }
+            _ => {
+                assert_eq!(evaluation.result.policy_dmarc, None);
+                assert_eq!(evaluation.result.disposition, None);
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(DmarcDiscovery::OrgDomain, Some("example.com"), DmarcPolicy::Reject)]
+    #[case(
+        DmarcDiscovery::TreeWalk,
+        Some("b.c.example.com"),
+        DmarcPolicy::Quarantine
+    )]
+    fn record_discovery(
+        #[case] discovery: DmarcDiscovery,
+        #[case] record_domain: Option<&str>,
+        #[case] expected: DmarcPolicy,
+    ) {
+        let resolver = zone("v=DMARC1; p=reject").with(
+            "_dmarc.b.c.example.com",
+            Ok(vec!["v=DMARC1; p=none; sp=quarantine"]),
+        );
+        let evaluation = DmarcEvaluator::new(&resolver, "a.b.c.example.com")
+            .with_discovery(discovery)
+            .evaluate();
+        assert_eq!(evaluation.record_domain.as_deref(), record_domain);
+        assert_eq!(evaluation.result.policy_dmarc, Some(expected));
+    }
+
+    #[rstest]
+    #[case(DmarcDiscovery::OrgDomain, "c.example.com", DmarcResultCode::Pass)]
+    #[case(DmarcDiscovery::TreeWalk, "c.example.com", DmarcResultCode::Fail)]
+    #[case(DmarcDiscovery::TreeWalk, "x.b.example.com", DmarcResultCode::Pass)]
+    #[case(DmarcDiscovery::TreeWalk, "B.Example.com.", DmarcResultCode::Pass)]
+    fn tree_walk_psd_n_alignment(
+        #[case] discovery: DmarcDiscovery,
+        #[case] header_d: &str,
+        #[case] expected: DmarcResultCode,
+    ) {
+        let resolver = zone("v=DMARC1; p=none").with(
+            "_dmarc.b.example.com",
+            Ok(vec!["v=DMARC1; p=reject; psd=n"]),
+        );
+        let evaluation = DmarcEvaluator::new(&resolver, "a.b.example.com")
+            .with_dkim(DkimResultCode::Pass, header_d)
+            .with_discovery(discovery)
+            .evaluate();
+        assert_eq!(evaluation.result.code, expected);
+    }
+
+    #[rstest]
+    #[case(DmarcDiscovery::OrgDomain, DmarcResultCode::Pass)]
+    #[case(DmarcDiscovery::TreeWalk, DmarcResultCode::Fail)]
+    fn tree_walk_psd_y_alignment(
+        #[case] discovery: DmarcDiscovery,
+        #[case] expected: DmarcResultCode,
+    ) {
+        let resolver = zone("v=DMARC1; p=reject; psd=y");
+        let evaluation = DmarcEvaluator::new(&resolver, "a.example.com")
+            .with_spf(SpfResultCode::Pass, "b.example.com")
+            .with_discovery(discovery)
+            .evaluate();
+        assert_eq!(evaluation.result.code, expected);
+    }
+
+    #[rstest]
+    #[case("x.c.d.e.f.g.h.example.com", DmarcResultCode::Pass)]
+    #[case("x.d.e.f.g.h.example.com", DmarcResultCode::Fail)]
+    fn tree_walk_psd_y_long_name(#[case] header_d: &str, #[case] expected: DmarcResultCode) {
+        // The walk skips c.d.e.f.g.h.example.com for names of nine labels or more
+        let resolver = ZoneResolver::default().with(
+            "_dmarc.d.e.f.g.h.example.com",
+            Ok(vec!["v=DMARC1; p=reject; psd=y"]),
+        );
+        let evaluation = DmarcEvaluator::new(&resolver, "a.b.c.d.e.f.g.h.example.com")
+            .with_dkim(DkimResultCode::Pass, header_d)
+            .with_discovery(DmarcDiscovery::TreeWalk)
+            .evaluate();
+        assert_eq!(evaluation.result.code, expected);
+    }
+
+    #[test]
+    fn tree_walk_names_fetched_once() {
+        let resolver = zone("v=DMARC1; p=reject; psd=n");
+        let evaluation = DmarcEvaluator::new(&resolver, "a.b.example.com")
+            .with_dkim(DkimResultCode::Pass, "c.b.example.com")
+            .with_dkim(DkimResultCode::Pass, "d.b.example.com")
+            .with_spf(SpfResultCode::Pass, "e.b.example.com")
+            .with_discovery(DmarcDiscovery::TreeWalk)
+            .evaluate();
+        assert_eq!(evaluation.result.code, DmarcResultCode::Pass);
+        let mut queried = resolver.1.take();
+        let total = queried.len();
+        queried.sort();
+        queried.dedup();
+        assert_eq!(queried.len(), total);
+    }
+
+    #[rstest]
+    #[case("example.com", vec![])]
+    #[case("a.example.com", vec!["example.com"])]
+    #[case(
+        "a.b.c.d.e.f.g.h.i.example.com",
+        vec!["e.f.g.h.i.example.com", "f.g.h.i.example.com", "g.h.i.example.com", "h.i.example.com", "i.example.com", "example.com"]
+    )]
+    fn tree_walk(#[case] domain: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(parent_domains(domain, DmarcDiscovery::TreeWalk), expected);
+    }
+
+    #[test]
+    fn from_auth_results() {
+        let auth_results = AuthenticationResults::from(
+            " mx.example.net; spf=fail smtp.mailfrom=example.com; dkim=pass header.d=mail.example.com; dkim=fail header.d=example.org",
+        );
+        let resolver = zone("v=DMARC1; p=reject");
+        let evaluation = DmarcEvaluator::new(&resolver, "example.com")
+            .with_auth_results(&auth_results)
+            .evaluate();
+        assert_eq!(evaluation.result.code, DmarcResultCode::Pass);
+        assert!(evaluation.dkim_aligned);
+        assert!(!evaluation.spf_aligned);
+        assert_eq!(evaluation.record().map(|r| r.p), Some(DmarcPolicy::Reject));
+    }
+}
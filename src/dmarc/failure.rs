@@ -0,0 +1,713 @@
+//! DMARC failure reports - RFC 7489 s. 7.3 in the ARF format of RFC 6591
+//!
+//! Whether a message gets reported is decided by fo= with [`auth_failures`].
+//! RFC 6591 allows a single Auth-Failure per report so each failure decided
+//! on is reported with a [`FailureReport`] of its own.
+
+use core::fmt::Write;
+use core::net::IpAddr;
+
+use crate::alloc_yes::{AuthenticationResults, DkimSignatures};
+use crate::dkim::{DkimResultCode, DkimSignature};
+use crate::error::RawMessageError;
+use crate::message::RawMessage;
+use crate::spf::SpfResultCode;
+
+use super::report::fnv1a;
+use super::{DmarcEvaluation, DmarcFailureOption};
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{format, string::String, vec, vec::Vec};
+
+const CRLF: &str = "\r\n";
+
+/// User-Agent unless given - RFC 5965 s. 3.1
+const USER_AGENT: &str = concat!("msg-auth-status/", env!("CARGO_PKG_VERSION"));
+
+/// Replaces the local-parts of the addresses when redacting
+const REDACTED: &str = "redacted";
+
+// Header fields carrying addresses that get redacted
+const ADDRESS_FIELDS: &[&str] = &[
+    "From",
+    "Sender",
+    "Reply-To",
+    "To",
+    "Cc",
+    "Bcc",
+    "Return-Path",
+    "Delivered-To",
+    "Resent-From",
+    "Resent-Sender",
+    "Resent-To",
+    "Resent-Cc",
+];
+
+/// Auth-Failure - RFC 6591 s. 3.2.2 with dmarc from RFC 7489 s. 7.3
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum AuthFailure {
+    /// The message failed DMARC evaluation - fo=0 / fo=1
+    Dmarc,
+    /// A DKIM signature failed verification - fo=d
+    Dkim,
+    /// SPF evaluation failed - fo=s
+    Spf,
+}
+
+fn auth_failure(failure: &AuthFailure) -> &'static str {
+    match failure {
+        AuthFailure::Dmarc => "dmarc",
+        AuthFailure::Dkim => "dkim",
+        AuthFailure::Spf => "spf",
+    }
+}
+
+/// How much of the original message goes into the report - RFC 6590
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Redaction {
+    /// The whole message as message/rfc822
+    #[default]
+    None,
+    /// Only the header fields as text/rfc822-headers
+    Body,
+    /// Only the header fields with the local-parts of the addresses replaced
+    LocalParts,
+}
+
+// Verification failed whether aligned or not - a missing signature is not a failure
+fn dkim_failed(code: &DkimResultCode) -> bool {
+    !matches!(
+        code,
+        DkimResultCode::Unknown | DkimResultCode::NoneDkim | DkimResultCode::Pass
+    )
+}
+
+fn spf_failed(code: &SpfResultCode) -> bool {
+    matches!(
+        code,
+        SpfResultCode::Fail
+            | SpfResultCode::SoftFail
+            | SpfResultCode::TempError
+            | SpfResultCode::PermError
+    )
+}
+
+/// Failures the fo= options ask a report for - RFC 7489 s. 6.3
+///
+/// Nothing is reported without a policy record. The DKIM and SPF results
+/// are taken from the Authentication-Results regardless of alignment.
+pub fn auth_failures(
+    fo: &[DmarcFailureOption],
+    evaluation: &DmarcEvaluation<'_>,
+    auth_results: &AuthenticationResults<'_>,
+) -> Vec<AuthFailure> {
+    let mut failures = vec![];
+    if evaluation.record.is_none() {
+        return failures;
+    }
+    let dmarc = fo.iter().any(|option| match option {
+        DmarcFailureOption::All => !evaluation.dkim_aligned && !evaluation.spf_aligned,
+        DmarcFailureOption::Any => !evaluation.dkim_aligned || !evaluation.spf_aligned,
+        _ => false,
+    });
+    if dmarc {
+        failures.push(AuthFailure::Dmarc);
+    }
+    if fo.contains(&DmarcFailureOption::Dkim)
+        && auth_results
+            .dkim_result
+            .iter()
+            .any(|res| dkim_failed(&res.code))
+    {
+        failures.push(AuthFailure::Dkim);
+    }
+    if fo.contains(&DmarcFailureOption::Spf)
+        && auth_results
+            .spf_result
+            .iter()
+            .any(|res| spf_failed(&res.code))
+    {
+        failures.push(AuthFailure::Spf);
+    }
+    failures
+}
+
+// Local-part before each @ replaced - a quoted local-part as a whole
+fn redact_local_parts(value: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(value.len());
+    for byte in value {
+        if *byte == b'@' {
+            let start = match out.split_last() {
+                Some((b'"', rest)) => rest.iter().rposition(|b| *b == b'"'),
+                _ => out
+                    .iter()
+                    .rposition(|b| b" \t\r\n<>,:;()\"".contains(b))
+                    .map(|pos| pos + 1)
+                    .or(Some(0)),
+            };
+            if let Some(start) = start.filter(|start| *start < out.len()) {
+                out.truncate(start);
+                out.extend_from_slice(REDACTED.as_bytes());
+            }
+        }
+        out.push(*byte);
+    }
+    out
+}
+
+// DKIM-Domain, DKIM-Identity and DKIM-Selector
+struct DkimFields<'a> {
+    domain: &'a str,
+    identity: Option<&'a str>,
+    selector: Option<&'a str>,
+}
+
+impl<'a> From<&'a DkimSignature<'_>> for DkimFields<'a> {
+    fn from(sig: &'a DkimSignature<'_>) -> Self {
+        Self {
+            domain: sig.d,
+            identity: sig.i.as_ref().map(|i| i.raw),
+            selector: Some(sig.s),
+        }
+    }
+}
+
+/// Authentication failure report of a single message - RFC 6591
+pub struct FailureReport<'a, 'hdr> {
+    auth_failure: AuthFailure,
+    raw: &'a [u8],
+    message: RawMessage<'a>,
+    evaluation: &'a DmarcEvaluation<'hdr>,
+    auth_results: &'a AuthenticationResults<'hdr>,
+    dkim_signatures: &'a DkimSignatures<'hdr>,
+    dkim_signature: Option<&'a DkimSignature<'hdr>>,
+    reported_domain: Option<&'a str>,
+    source_ip: Option<IpAddr>,
+    original_mail_from: Option<&'a str>,
+    original_rcpt_to: Vec<&'a str>,
+    arrival_date: Option<&'a str>,
+    date: Option<&'a str>,
+    message_id: Option<&'a str>,
+    user_agent: &'a str,
+    redaction: Redaction,
+}
+
+impl<'a, 'hdr> FailureReport<'a, 'hdr> {
+    /// Report the failure of the raw message - the header fields alone will do
+    ///
+    /// The Reported-Domain is the From domain the message was evaluated for.
+    pub fn new(
+        auth_failure: AuthFailure,
+        raw: &'a [u8],
+        evaluation: &'a DmarcEvaluation<'hdr>,
+        auth_results: &'a AuthenticationResults<'hdr>,
+        dkim_signatures: &'a DkimSignatures<'hdr>,
+    ) -> Result<Self, RawMessageError> {
+        Ok(Self {
+            auth_failure,
+            raw,
+            message: RawMessage::try_from(raw)?,
+            evaluation,
+            auth_results,
+            dkim_signatures,
+            dkim_signature: None,
+            reported_domain: None,
+            source_ip: None,
+            original_mail_from: None,
+            original_rcpt_to: vec![],
+            arrival_date: None,
+            date: None,
+            message_id: None,
+            user_agent: USER_AGENT,
+            redaction: Redaction::None,
+        })
+    }
+    /// Signature for the DKIM- fields instead of the first one that failed
+    pub fn with_dkim_signature(mut self, sig: &'a DkimSignature<'hdr>) -> Self {
+        self.dkim_signature = Some(sig);
+        self
+    }
+    /// Reported-Domain instead of the evaluated From domain
+    pub fn with_reported_domain(mut self, domain: &'a str) -> Self {
+        self.reported_domain = Some(domain);
+        self
+    }
+    /// Source-IP the message was received from
+    pub fn with_source_ip(mut self, ip: IpAddr) -> Self {
+        self.source_ip = Some(ip);
+        self
+    }
+    /// Original-Mail-From - the RFC5321.MailFrom
+    pub fn with_original_mail_from(mut self, mailfrom: &'a str) -> Self {
+        self.original_mail_from = Some(mailfrom);
+        self
+    }
+    /// Original-Rcpt-To - may be given for each recipient
+    pub fn with_original_rcpt_to(mut self, rcpt_to: &'a str) -> Self {
+        self.original_rcpt_to.push(rcpt_to);
+        self
+    }
+    /// Arrival-Date as an RFC 5322 date-time
+    pub fn with_arrival_date(mut self, date: &'a str) -> Self {
+        self.arrival_date = Some(date);
+        self
+    }
+    /// Date of the report message itself as an RFC 5322 date-time
+    pub fn with_date(mut self, date: &'a str) -> Self {
+        self.date = Some(date);
+        self
+    }
+    /// Message-ID of the report message itself including the angle brackets
+    pub fn with_message_id(mut self, message_id: &'a str) -> Self {
+        self.message_id = Some(message_id);
+        self
+    }
+    /// User-Agent instead of this crate and version
+    pub fn with_user_agent(mut self, user_agent: &'a str) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+    /// How much of the original message to include
+    pub fn with_redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+    fn reported_domain(&self) -> Option<&str> {
+        self.reported_domain.or(self.evaluation.result.header_from)
+    }
+    // The given signature, the first failed one or for dmarc any signature
+    fn dkim_fields(&self) -> Option<DkimFields<'_>> {
+        if self.auth_failure == AuthFailure::Spf {
+            return None;
+        }
+        if let Some(sig) = self.dkim_signature {
+            return Some(sig.into());
+        }
+        let failed = self.auth_results.dkim_result.iter().find_map(|res| {
+            match (dkim_failed(&res.code), res.header_d) {
+                (true, Some(d)) => Some((res, d)),
+                _ => None,
+            }
+        });
+        match (failed, self.auth_failure) {
+            (Some((res, d)), _) => {
+                // Option::is_none_or needs Rust 1.82
+                #[allow(clippy::unnecessary_map_or)]
+                let sig = self
+                    .dkim_signatures
+                    .by_domain(d)
+                    .find(|sig| res.header_s.map_or(true, |s| sig.s.eq_ignore_ascii_case(s)));
+                Some(match sig {
+                    Some(sig) => sig.into(),
+                    None => DkimFields {
+                        domain: d,
+                        identity: res.header_i,
+                        selector: res.header_s,
+                    },
+                })
+            }
+            (None, AuthFailure::Dmarc) => self.dkim_signatures.signatures().next().map(Into::into),
+            _ => None,
+        }
+    }
+    fn redacted(&self, addr: &str) -> String {
+        match self.redaction {
+            Redaction::LocalParts => {
+                String::from_utf8_lossy(&redact_local_parts(addr.as_bytes())).into_owned()
+            }
+            _ => addr.into(),
+        }
+    }
+    // Human readable first part
+    fn description(&self) -> String {
+        let mut text = format!(
+            "This is an authentication failure report for an email message that failed {}",
+            match self.auth_failure {
+                AuthFailure::Dmarc => "DMARC",
+                AuthFailure::Dkim => "DKIM",
+                AuthFailure::Spf => "SPF",
+            }
+        );
+        if let Some(domain) = self.reported_domain() {
+            let _ = write!(text, " for {}", domain);
+        }
+        if let Some(ip) = self.source_ip {
+            let _ = write!(text, " received from {}", ip);
+        }
+        text.push('.');
+        text.push_str(CRLF);
+        text
+    }
+    // message/feedback-report fields
+    fn feedback_report(&self) -> String {
+        let mut fields = String::new();
+        let mut field = |name: &str, value: &str| {
+            let _ = write!(fields, "{}: {}{}", name, value, CRLF);
+        };
+        field("Feedback-Type", "auth-failure");
+        field("User-Agent", self.user_agent);
+        field("Version", "1");
+        if let Some(mailfrom) = self.original_mail_from {
+            field("Original-Mail-From", &self.redacted(mailfrom));
+        }
+        for rcpt_to in self.original_rcpt_to.iter() {
+            field("Original-Rcpt-To", &self.redacted(rcpt_to));
+        }
+        if let Some(date) = self.arrival_date {
+            field("Arrival-Date", date);
+        }
+        if let Some(ip) = self.source_ip {
+            field("Source-IP", &format!("{}", ip));
+        }
+        if let Some(domain) = self.reported_domain() {
+            field("Reported-Domain", domain);
+        }
+        field("Auth-Failure", auth_failure(&self.auth_failure));
+        if let Some(dkim) = self.dkim_fields() {
+            field("DKIM-Domain", dkim.domain);
+            if let Some(identity) = dkim.identity {
+                field("DKIM-Identity", &self.redacted(identity));
+            }
+            if let Some(selector) = dkim.selector {
+                field("DKIM-Selector", selector);
+            }
+        }
+        // Left out when there is no authserv-id to render
+        if let Ok(auth_results) = self.auth_results.to_header_field(CRLF) {
+            fields.push_str(&auth_results);
+        }
+        fields
+    }
+    // Original message as redacted with its own line endings
+    fn original(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.raw.len());
+        for header in self.message.headers.iter() {
+            let redact = self.redaction == Redaction::LocalParts
+                && ADDRESS_FIELDS.iter().any(|name| header.is(name));
+            match redact {
+                true => out.extend_from_slice(&redact_local_parts(header.raw)),
+                false => out.extend_from_slice(header.raw),
+            }
+        }
+        if self.redaction == Redaction::None && !self.message.body.is_empty() {
+            // The empty line in between as it was
+            let headers_len: usize = self.message.headers.iter().map(|h| h.raw.len()).sum();
+            let body_at = self.raw.len() - self.message.body.len();
+            out.extend_from_slice(&self.raw[headers_len..body_at]);
+            out.extend_from_slice(self.message.body);
+        }
+        if !out.ends_with(b"\n") {
+            out.extend_from_slice(CRLF.as_bytes());
+        }
+        out
+    }
+    /// The report as multipart/report from the reporter to the ruf= address
+    pub fn to_message(&self, from: &str, to: &str) -> Vec<u8> {
+        let failure = auth_failure(&self.auth_failure);
+        let boundary = format!(
+            "=_{:016x}",
+            fnv1a(&[self.raw, failure.as_bytes(), to.as_bytes()])
+        );
+        let original_type = match self.redaction {
+            Redaction::None => "message/rfc822",
+            _ => "text/rfc822-headers",
+        };
+        let subject = match self.reported_domain() {
+            Some(domain) => format!("Authentication failure report for {}", domain),
+            None => "Authentication failure report".into(),
+        };
+
+        let mut head = String::new();
+        let _ = write!(head, "From: {}{}", from, CRLF);
+        let _ = write!(
+            head,
+            "To: {}{}",
+            to.strip_prefix("mailto:").unwrap_or(to),
+            CRLF
+        );
+        let _ = write!(head, "Subject: {}{}", subject, CRLF);
+        if let Some(date) = self.date {
+            let _ = write!(head, "Date: {}{}", date, CRLF);
+        }
+        if let Some(message_id) = self.message_id {
+            let _ = write!(head, "Message-ID: {}{}", message_id, CRLF);
+        }
+        let _ = write!(head, "MIME-Version: 1.0{}", CRLF);
+        let _ = write!(
+            head,
+            "Content-Type: multipart/report; report-type=feedback-report;{}\tboundary=\"{}\"{}{}",
+            CRLF, boundary, CRLF, CRLF
+        );
+        let _ = write!(
+            head,
+            "--{b}{n}Content-Type: text/plain; charset=us-ascii{n}{n}{}{n}--{b}{n}\
+             Content-Type: message/feedback-report{n}{n}{}{n}--{b}{n}\
+             Content-Type: {}{n}{n}",
+            self.description(),
+            self.feedback_report(),
+            original_type,
+            b = boundary,
+            n = CRLF,
+        );
+
+        let mut out = head.into_bytes();
+        out.extend_from_slice(&self.original());
+        out.extend_from_slice(format!("--{}--{}", boundary, CRLF).as_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dmarc::{DmarcEvaluator, DmarcResult};
+    use crate::error::ResolverError;
+    use crate::resolver::TxtResolver;
+    use rstest::rstest;
+
+    const RAW: &[u8] = b"From: \"Joe Q\" <joe.q@example.com>\r\n\
+        To: a@example.net, \"b c\"@example.net\r\n\
+        Subject: hello\r\n\
+        \r\n\
+        Hi there\r\n";
+
+    const AUTH_RESULTS: &str = " mx.example.net; dkim=fail header.d=example.com header.s=sel header.i=@example.com; spf=softfail smtp.mailfrom=example.org";
+
+    fn evaluation(dkim_aligned: bool, spf_aligned: bool) -> DmarcEvaluation<'static> {
+        DmarcEvaluation {
+            result: DmarcResult::default(),
+            record_domain: Some("example.com".into()),
+            record: Some("v=DMARC1; p=reject".into()),
+            dkim_aligned,
+            spf_aligned,
+            sampled_out: false,
+        }
+    }
+
+    #[rstest]
+    #[case("0", (false, false), vec![AuthFailure::Dmarc])]
+    #[case("0", (true, false), vec![])]
+    #[case("1", (true, false), vec![AuthFailure::Dmarc])]
+    #[case("1", (true, true), vec![])]
+    #[case("d", (true, true), vec![AuthFailure::Dkim])]
+    #[case("s", (true, true), vec![AuthFailure::Spf])]
+    #[case("1:d:s", (false, false), vec![AuthFailure::Dmarc, AuthFailure::Dkim, AuthFailure::Spf])]
+    fn failure_options(
+        #[case] fo: &str,
+        #[case] aligned: (bool, bool),
+        #[case] expected: Vec<AuthFailure>,
+    ) {
+        let record = format!("v=DMARC1; p=reject; fo={}", fo);
+        let record = super::super::DmarcRecord::try_from(record.as_str()).unwrap();
+        let auth_results = AuthenticationResults::from(AUTH_RESULTS);
+        let evaluation = evaluation(aligned.0, aligned.1);
+        assert_eq!(
+            auth_failures(&record.fo, &evaluation, &auth_results),
+            expected
+        );
+    }
+
+    #[test]
+    fn no_record_no_failures() {
+        let auth_results = AuthenticationResults::from(AUTH_RESULTS);
+        let evaluation = DmarcEvaluation {
+            record: None,
+            ..evaluation(false, false)
+        };
+        assert_eq!(
+            auth_failures(&[DmarcFailureOption::Any], &evaluation, &auth_results),
+            vec![]
+        );
+    }
+
+    #[rstest]
+    #[case(b"\"Joe Q\" <joe.q@example.com>", b"\"Joe Q\" <redacted@example.com>")]
+    #[case(
+        b"a@example.net, \"b c\"@example.net",
+        b"redacted@example.net, redacted@example.net"
+    )]
+    #[case(b"<>", b"<>")]
+    #[case(b"@example.com", b"@example.com")]
+    fn redaction(#[case] value: &[u8], #[case] expected: &[u8]) {
+        assert_eq!(redact_local_parts(value), expected);
+    }
+
+    // Answers with a reject policy for example.com only
+    struct Resolver;
+
+    impl TxtResolver for Resolver {
+        fn lookup_txt(&self, name: &str) -> Result<Vec<String>, ResolverError> {
+            match name {
+                "_dmarc.example.com" => Ok(vec!["v=DMARC1; p=reject; fo=1".into()]),
+                _ => Err(ResolverError::NotFound),
+            }
+        }
+    }
+
+    #[test]
+    fn message() {
+        let auth_results = AuthenticationResults::from(AUTH_RESULTS);
+        let evaluation = DmarcEvaluator::new(&Resolver, "example.com")
+            .with_auth_results(&auth_results)
+            .evaluate();
+        let signatures = DkimSignatures::default();
+        let report = FailureReport::new(
+            AuthFailure::Dkim,
+            RAW,
+            &evaluation,
+            &auth_results,
+            &signatures,
+        )
+        .unwrap()
+        .with_source_ip("192.0.2.1".parse().unwrap())
+        .with_original_mail_from("bounce@example.org")
+        .with_original_rcpt_to("a@example.net")
+        .with_arrival_date("Mon, 4 Mar 2024 10:00:00 +0000")
+        .with_user_agent("test/1.0")
+        .with_date("Mon, 4 Mar 2024 11:00:00 +0000");
+        let message = String::from_utf8(
+            report.to_message("dmarc-reports@example.net", "mailto:ruf@example.com"),
+        )
+        .unwrap();
+        let boundary = format!(
+            "=_{:016x}",
+            fnv1a(&[RAW, b"dkim", b"mailto:ruf@example.com"])
+        );
+        let expected = format!(
+            "From: dmarc-reports@example.net\r\n\
+             To: ruf@example.com\r\n\
+             Subject: Authentication failure report for example.com\r\n\
+             Date: Mon, 4 Mar 2024 11:00:00 +0000\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: multipart/report; report-type=feedback-report;\r\n\
+             \tboundary=\"{b}\"\r\n\
+             \r\n\
+             --{b}\r\n\
+             Content-Type: text/plain; charset=us-ascii\r\n\
+             \r\n\
+             This is an authentication failure report for an email message that failed DKIM for example.com received from 192.0.2.1.\r\n\
+             \r\n\
+             --{b}\r\n\
+             Content-Type: message/feedback-report\r\n\
+             \r\n\
+             Feedback-Type: auth-failure\r\n\
+             User-Agent: test/1.0\r\n\
+             Version: 1\r\n\
+             Original-Mail-From: bounce@example.org\r\n\
+             Original-Rcpt-To: a@example.net\r\n\
+             Arrival-Date: Mon, 4 Mar 2024 10:00:00 +0000\r\n\
+             Source-IP: 192.0.2.1\r\n\
+             Reported-Domain: example.com\r\n\
+             Auth-Failure: dkim\r\n\
+             DKIM-Domain: example.com\r\n\
+             DKIM-Identity: @example.com\r\n\
+             DKIM-Selector: sel\r\n\
+             {ar}\
+             \r\n\
+             --{b}\r\n\
+             Content-Type: message/rfc822\r\n\
+             \r\n\
+             {raw}\
+             --{b}--\r\n",
+            b = boundary,
+            ar = auth_results.to_header_field("\r\n").unwrap(),
+            raw = core::str::from_utf8(RAW).unwrap(),
+        );
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn redacted() {
+        let evaluation = evaluation(false, false);
+        let auth_results = AuthenticationResults::from(AUTH_RESULTS);
+        let signatures = DkimSignatures::default();
+        let report = FailureReport::new(
+            AuthFailure::Spf,
+            RAW,
+            &evaluation,
+            &auth_results,
+            &signatures,
+        )
+        .unwrap()
+        .with_original_mail_from("bounce@example.org")
+        .with_redaction(Redaction::LocalParts);
+        let message = String::from_utf8(report.to_message("a@b", "c@d")).unwrap();
+        assert!(message.contains("Original-Mail-From: redacted@example.org\r\n"));
+        assert!(message.contains("Auth-Failure: spf\r\n"));
+        assert!(!message.contains("DKIM-Domain"));
+        assert!(message.contains(
+            "Content-Type: text/rfc822-headers\r\n\r\n\
+             From: \"Joe Q\" <redacted@example.com>\r\n\
+             To: redacted@example.net, redacted@example.net\r\n\
+             Subject: hello\r\n--"
+        ));
+        assert!(!message.contains("Hi there"));
+    }
+
+    #[test]
+    fn headers_only() {
+        let evaluation = evaluation(false, false);
+        let auth_results = AuthenticationResults::from(AUTH_RESULTS);
+        let signatures = DkimSignatures::default();
+        let report = FailureReport::new(
+            AuthFailure::Dmarc,
+            RAW,
+            &evaluation,
+            &auth_results,
+            &signatures,
+        )
+        .unwrap()
+        .with_redaction(Redaction::Body);
+        let message = String::from_utf8(report.to_message("a@b", "c@d")).unwrap();
+        assert!(message.contains("From: \"Joe Q\" <joe.q@example.com>\r\n"));
+        assert!(!message.contains("Hi there"));
+        // dmarc reports the failed signature too
+        assert!(message.contains(
+            "DKIM-Domain: example.com\r\nDKIM-Identity: @example.com\r\nDKIM-Selector: sel\r\n"
+        ));
+    }
+
+    #[cfg(feature = "mail_parser")]
+    #[test]
+    fn signature_identity() {
+        let evaluation = evaluation(false, false);
+        let raw = b"DKIM-Signature: v=1; a=rsa-sha256; d=example.com; s=sel;\r\n\
+            \ti=joe@example.com; h=from; bh=AAAA; b=AAAA\r\n\
+            From: joe@example.com\r\n\
+            \r\n\
+            body\r\n";
+        let parsed = mail_parser::MessageParser::default().parse(raw).unwrap();
+        let signatures = DkimSignatures::from_mail_parser(&parsed).unwrap();
+        let auth_results = AuthenticationResults::from(AUTH_RESULTS);
+        let report = FailureReport::new(
+            AuthFailure::Dkim,
+            raw,
+            &evaluation,
+            &auth_results,
+            &signatures,
+        )
+        .unwrap()
+        .with_redaction(Redaction::LocalParts);
+        let message = String::from_utf8(report.to_message("a@b", "c@d")).unwrap();
+        assert!(message.contains("DKIM-Identity: redacted@example.com\r\n"));
+        assert!(message.contains("From: redacted@example.com\r\n"));
+    }
+
+    #[test]
+    fn invalid_message() {
+        let evaluation = evaluation(false, false);
+        let auth_results = AuthenticationResults::default();
+        let signatures = DkimSignatures::default();
+        assert_eq!(
+            FailureReport::new(
+                AuthFailure::Dmarc,
+                b"no colon\r\n",
+                &evaluation,
+                &auth_results,
+                &signatures
+            )
+            .err(),
+            Some(RawMessageError::InvalidHeader(0))
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! dmarc ptype and it's properties
+
+/// DMARC ptypes
+#[derive(Clone, Debug, PartialEq)]
+pub enum DmarcProperty<'hdr> {
+    /// header.*
+    Header(DmarcHeader<'hdr>),
+    /// policy.*
+    Policy(DmarcPolicyProperty<'hdr>),
+}
+
+/// DMARC ptype header
+#[derive(Clone, Debug, PartialEq)]
+pub enum DmarcHeader<'hdr> {
+    /// header.from - RFC 7489 s. 11.2
+    From(&'hdr str),
+    /// header.*
+    Unknown(&'hdr str, &'hdr str),
+}
+
+/// DMARC ptype policy
+#[derive(Clone, Debug, PartialEq)]
+pub enum DmarcPolicyProperty<'hdr> {
+    /// policy.dmarc - RFC 7489 s. 11.2
+    Dmarc(&'hdr str),
+    /// policy.* e.g. policy.published-domain-policy
+    Unknown(&'hdr str, &'hdr str),
+}
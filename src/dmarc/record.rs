@@ -0,0 +1,206 @@
+//! DMARC policy record published in DNS - RFC 7489 s. 6.3
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+use crate::error::DmarcRecordError;
+use crate::error::DmarcTagValueError;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
+/// Requested Mail Receiver policy - p= / sp= / np=
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum DmarcPolicy {
+    /// No specific action
+    None,
+    /// Treat as suspicious e.g. deliver to spam
+    Quarantine,
+    /// Reject during the SMTP transaction
+    Reject,
+}
+
+impl TryFrom<&str> for DmarcPolicy {
+    type Error = DmarcTagValueError;
+
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            v if v.eq_ignore_ascii_case("none") => Ok(Self::None),
+            v if v.eq_ignore_ascii_case("quarantine") => Ok(Self::Quarantine),
+            v if v.eq_ignore_ascii_case("reject") => Ok(Self::Reject),
+            _ => Err(DmarcTagValueError::InvalidPolicy),
+        }
+    }
+}
+
+/// Identifier alignment mode - adkim= / aspf=
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, PartialOrd, Ord)]
+pub enum DmarcAlignment {
+    /// Same organizational domain - r
+    #[default]
+    Relaxed,
+    /// Exact same domain - s
+    Strict,
+}
+
+impl TryFrom<&str> for DmarcAlignment {
+    type Error = DmarcTagValueError;
+
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "r" | "R" => Ok(Self::Relaxed),
+            "s" | "S" => Ok(Self::Strict),
+            _ => Err(DmarcTagValueError::InvalidAlignment),
+        }
+    }
+}
+
+/// Reporting URI in rua= / ruf= with the optional size limit
+#[derive(Clone, Debug, PartialEq)]
+pub struct DmarcUri<'rec> {
+    /// The URI e.g. `mailto:dmarc@example.com`
+    pub uri: &'rec str,
+    /// Maximum report size in bytes from the `!` suffix e.g. `!10m`
+    pub max_size: Option<u64>,
+}
+
+// Size limit is digits with an optional k / m / g / t unit - RFC 7489 s. 6.4
+fn parse_size_limit(val: &str) -> Result<u64, DmarcTagValueError> {
+    let (digits, multiplier) = match val.as_bytes().last() {
+        Some(b'k' | b'K') => (&val[..val.len() - 1], 1u64 << 10),
+        Some(b'm' | b'M') => (&val[..val.len() - 1], 1u64 << 20),
+        Some(b'g' | b'G') => (&val[..val.len() - 1], 1u64 << 30),
+        Some(b't' | b'T') => (&val[..val.len() - 1], 1u64 << 40),
+        _ => (val, 1),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(DmarcTagValueError::InvalidSizeLimit);
+    }
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|size| size.checked_mul(multiplier))
+        .ok_or(DmarcTagValueError::InvalidSizeLimit)
+}
+
+impl<'rec> TryFrom<&'rec str> for DmarcUri<'rec> {
+    type Error = DmarcTagValueError;
+
+    fn try_from(val: &'rec str) -> Result<Self, Self::Error> {
+        let val = val.trim();
+        let (uri, max_size) = match val.rsplit_once('!') {
+            Some((uri, size)) => (uri, Some(parse_size_limit(size)?)),
+            None => (val, None),
+        };
+        // Absolute URI needs a scheme
+        match uri.split_once(':') {
+            Some((scheme, rest))
+                if !scheme.is_empty()
+                    && !rest.is_empty()
+                    && scheme
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b)) =>
+            {
+                Ok(Self { uri, max_size })
+            }
+            _ => Err(DmarcTagValueError::InvalidUri),
+        }
+    }
+}
+
+// Comma separated rua= / ruf=
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub(crate) fn parse_uri_list(val: &str) -> Result<Vec<DmarcUri<'_>>, DmarcTagValueError> {
+    val.split(',').map(DmarcUri::try_from).collect()
+}
+
+/// Failure reporting option - fo=
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum DmarcFailureOption {
+    /// All mechanisms failed to produce an aligned pass - 0
+    All,
+    /// Any mechanism failed to produce an aligned pass - 1
+    Any,
+    /// DKIM signature failed evaluation regardless of alignment - d
+    Dkim,
+    /// SPF failed evaluation regardless of alignment - s
+    Spf,
+}
+
+impl TryFrom<&str> for DmarcFailureOption {
+    type Error = DmarcTagValueError;
+
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "0" => Ok(Self::All),
+            "1" => Ok(Self::Any),
+            "d" | "D" => Ok(Self::Dkim),
+            "s" | "S" => Ok(Self::Spf),
+            _ => Err(DmarcTagValueError::InvalidFailureOption),
+        }
+    }
+}
+
+// Colon separated fo=
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub(crate) fn parse_failure_options(
+    val: &str,
+) -> Result<Vec<DmarcFailureOption>, DmarcTagValueError> {
+    val.split(':')
+        .map(|option| DmarcFailureOption::try_from(option.trim()))
+        .collect()
+}
+
+/// Parsed `_dmarc.<domain>` TXT record - RFC 7489 s. 6.3
+///
+/// Invalid values of tags other than v=, p=, sp= and np= are replaced by the
+/// defaults as RFC 7489 s. 6.3 asks and the errors kept in `ignored`. See
+/// [`DmarcRecord::parse_with_fallback`] for the p=none fallback of RFC 7489 s. 6.6.3.
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DmarcRecord<'rec> {
+    /// Policy for the domain itself
+    pub p: DmarcPolicy,
+    /// Policy for subdomains - p= when not given
+    pub sp: Option<DmarcPolicy>,
+    /// Policy for non-existent subdomains - DMARCbis
+    pub np: Option<DmarcPolicy>,
+    /// Percentage of failing messages the policy applies to - defaults to 100
+    pub pct: u8,
+    /// DKIM alignment mode - defaults to relaxed
+    pub adkim: DmarcAlignment,
+    /// SPF alignment mode - defaults to relaxed
+    pub aspf: DmarcAlignment,
+    /// Aggregate report URIs
+    pub rua: Vec<DmarcUri<'rec>>,
+    /// Failure report URIs
+    pub ruf: Vec<DmarcUri<'rec>>,
+    /// Failure reporting options - defaults to 0
+    pub fo: Vec<DmarcFailureOption>,
+    /// Failure report formats - defaults to afrf
+    pub rf: Vec<&'rec str>,
+    /// Aggregate report interval in seconds - defaults to 86400
+    pub ri: u32,
+    /// Testing mode t=y - DMARCbis
+    pub t: bool,
+    /// Unrecognised tags - ignored as the RFC requires
+    pub unknown_tags: Vec<(&'rec str, &'rec str)>,
+    /// Tags with invalid values replaced by the defaults
+    pub ignored: Vec<DmarcRecordError<'rec>>,
+    /// Raw unparsed
+    pub raw: &'rec str,
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<'rec> DmarcRecord<'rec> {
+    /// Policy for subdomains - sp= falling back to p=
+    pub fn subdomain_policy(&self) -> DmarcPolicy {
+        self.sp.unwrap_or(self.p)
+    }
+    /// Policy for non-existent subdomains - np= falling back to sp= and p=
+    pub fn non_existent_policy(&self) -> DmarcPolicy {
+        self.np.unwrap_or(self.subdomain_policy())
+    }
+    /// Whether the domain owner is testing the policy - t=y
+    pub fn is_testing(&self) -> bool {
+        self.t
+    }
+}
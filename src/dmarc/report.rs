@@ -0,0 +1,1107 @@
+//! DMARC aggregate reports - RFC 7489 s. 7.2 and Appendix C
+//!
+//! Rows are collected per message from the evaluation and the
+//! Authentication-Results and counted together per policy domain and
+//! reporting period. Each period renders into the Appendix C XML.
+
+use core::fmt::Write;
+use core::net::IpAddr;
+
+use crate::alloc_yes::AuthenticationResults;
+use crate::dkim::DkimResultCode;
+use crate::render::{dkim_code, dmarc_policy, spf_code};
+use crate::spf::SpfResultCode;
+
+use super::evaluate::mailfrom_domain;
+use super::{DmarcAlignment, DmarcEvaluation, DmarcFailureOption, DmarcPolicy, DmarcRecord};
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "report_parser")]
+mod parse;
+#[cfg(feature = "report_parser")]
+pub use parse::MAX_REPORT_SIZE;
+
+mod summary;
+pub use summary::{PassFail, ReportSummary};
+
+/// Default reporting interval - ri= defaults to a day
+const DEFAULT_INTERVAL: u64 = 86400;
+
+/// Who is reporting - report_metadata
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportMetadata {
+    /// Reporting organization e.g. `example.net`
+    pub org_name: String,
+    /// Contact address of the reporting organization
+    pub email: String,
+    /// Where to find more about the reporting organization if any
+    pub extra_contact_info: Option<String>,
+    /// Errors the reporting organization ran into while generating the report
+    pub errors: Vec<String>,
+}
+
+impl ReportMetadata {
+    /// Reporting organization with the contact address
+    pub fn new(org_name: &str, email: &str) -> Self {
+        Self {
+            org_name: org_name.to_string(),
+            email: email.to_string(),
+            extra_contact_info: None,
+            errors: vec![],
+        }
+    }
+}
+
+/// The policy record the messages were evaluated against - policy_published
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct PolicyPublished {
+    /// Domain the record was found at
+    pub domain: String,
+    /// DKIM alignment mode
+    pub adkim: DmarcAlignment,
+    /// SPF alignment mode
+    pub aspf: DmarcAlignment,
+    /// Policy for the domain
+    pub p: DmarcPolicy,
+    /// Policy for subdomains
+    pub sp: DmarcPolicy,
+    /// Percentage of failing messages the policy applies to
+    pub pct: u8,
+    /// Failure reporting options
+    pub fo: Vec<DmarcFailureOption>,
+}
+
+impl PolicyPublished {
+    /// Record found at the domain
+    pub fn new(domain: &str, record: &DmarcRecord<'_>) -> Self {
+        Self {
+            domain: domain.to_ascii_lowercase(),
+            adkim: record.adkim,
+            aspf: record.aspf,
+            p: record.p,
+            sp: record.subdomain_policy(),
+            pct: record.pct,
+            fo: record.fo.clone(),
+        }
+    }
+    /// Record the evaluation found - None when there was no record to report to
+    pub fn from_evaluation(evaluation: &DmarcEvaluation<'_>) -> Option<Self> {
+        let domain = evaluation.record_domain.as_deref()?;
+        Some(Self::new(domain, &evaluation.record()?))
+    }
+}
+
+/// Why the disposition differs from the published policy - PolicyOverrideType
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum PolicyOverride {
+    /// The message was relayed by a forwarder
+    Forwarded,
+    /// Left out of the policy by pct=
+    SampledOut,
+    /// Relayed by a forwarder known to be trustworthy
+    TrustedForwarder,
+    /// Relayed by a mailing list
+    MailingList,
+    /// Local policy of the receiver
+    LocalPolicy,
+    /// Any other reason
+    Other,
+}
+
+/// Policy override with an optional comment - PolicyOverrideReason
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct PolicyOverrideReason {
+    /// The override
+    pub kind: PolicyOverride,
+    /// Free text explaining it
+    pub comment: Option<String>,
+}
+
+/// DKIM result for a signature - DKIMAuthResultType
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct ReportDkim {
+    /// d= of the signature
+    pub domain: String,
+    /// s= of the signature
+    pub selector: Option<String>,
+    /// The result
+    pub result: DkimResultCode,
+}
+
+/// Identity SPF authenticated - SPFDomainScope
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum SpfScope {
+    /// HELO / EHLO
+    Helo,
+    /// MAIL FROM
+    MailFrom,
+}
+
+/// SPF result for an identity - SPFAuthResultType
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct ReportSpf {
+    /// Domain of the identity
+    pub domain: String,
+    /// Which identity
+    pub scope: SpfScope,
+    /// The result
+    pub result: SpfResultCode,
+}
+
+/// One message - messages with identical rows are counted together - RecordType
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct ReportRow {
+    /// Connecting IP address
+    pub source_ip: IpAddr,
+    /// What was done with the message
+    pub disposition: DmarcPolicy,
+    /// Whether DKIM produced an aligned pass
+    pub dkim_aligned: bool,
+    /// Whether SPF produced an aligned pass
+    pub spf_aligned: bool,
+    /// Why the disposition differs from the policy if it does
+    pub reasons: Vec<PolicyOverrideReason>,
+    /// RFC5321.RcptTo domain
+    pub envelope_to: Option<String>,
+    /// RFC5321.MailFrom domain - empty for the null reverse-path
+    pub envelope_from: Option<String>,
+    /// RFC5322.From domain
+    pub header_from: String,
+    /// DKIM results for all the signatures
+    pub dkim: Vec<ReportDkim>,
+    /// SPF results
+    pub spf: Vec<ReportSpf>,
+}
+
+impl ReportRow {
+    /// Row for the evaluated message received from the IP address
+    pub fn from_evaluation(source_ip: IpAddr, evaluation: &DmarcEvaluation<'_>) -> Self {
+        let reasons = match evaluation.sampled_out {
+            true => vec![PolicyOverrideReason {
+                kind: PolicyOverride::SampledOut,
+                comment: None,
+            }],
+            false => vec![],
+        };
+        Self {
+            source_ip,
+            disposition: evaluation.result.disposition.unwrap_or(DmarcPolicy::None),
+            dkim_aligned: evaluation.dkim_aligned,
+            spf_aligned: evaluation.spf_aligned,
+            reasons,
+            envelope_to: None,
+            envelope_from: None,
+            header_from: evaluation
+                .result
+                .header_from
+                .unwrap_or_default()
+                .trim_end_matches('.')
+                .to_ascii_lowercase(),
+            dkim: vec![],
+            spf: vec![],
+        }
+    }
+    /// The DKIM and SPF results in the Authentication-Results
+    ///
+    /// smtp.mailfrom of the first SPF result is the envelope_from unless set.
+    pub fn with_auth_results(mut self, auth_results: &AuthenticationResults<'_>) -> Self {
+        for dkim_res in auth_results.dkim_result.iter() {
+            if let Some(header_d) = dkim_res.header_d {
+                self = self.with_dkim(header_d, dkim_res.header_s, dkim_res.code.clone());
+            }
+        }
+        for spf_res in auth_results.spf_result.iter() {
+            match (spf_res.smtp_mailfrom, spf_res.smtp_helo) {
+                (Some(mailfrom), _) => {
+                    let domain = mailfrom_domain(mailfrom);
+                    if self.envelope_from.is_none() {
+                        self.envelope_from = Some(domain.to_ascii_lowercase());
+                    }
+                    self = self.with_spf(domain, SpfScope::MailFrom, spf_res.code.clone());
+                }
+                (None, Some(helo)) => {
+                    self = self.with_spf(helo, SpfScope::Helo, spf_res.code.clone());
+                }
+                (None, None) => {}
+            }
+        }
+        self
+    }
+    /// DKIM result for the signature - Unknown results are left out
+    pub fn with_dkim(
+        mut self,
+        domain: &str,
+        selector: Option<&str>,
+        result: DkimResultCode,
+    ) -> Self {
+        if result != DkimResultCode::Unknown {
+            self.dkim.push(ReportDkim {
+                domain: domain.to_ascii_lowercase(),
+                selector: selector.map(ToOwned::to_owned),
+                result,
+            });
+        }
+        self
+    }
+    /// SPF result for the identity - Unknown and Policy results are left out
+    pub fn with_spf(mut self, domain: &str, scope: SpfScope, result: SpfResultCode) -> Self {
+        if !matches!(result, SpfResultCode::Unknown | SpfResultCode::Policy) {
+            self.spf.push(ReportSpf {
+                domain: domain.to_ascii_lowercase(),
+                scope,
+                result,
+            });
+        }
+        self
+    }
+    /// RFC5321.MailFrom domain
+    pub fn with_envelope_from(mut self, domain: &str) -> Self {
+        self.envelope_from = Some(domain.to_ascii_lowercase());
+        self
+    }
+    /// RFC5321.RcptTo domain
+    pub fn with_envelope_to(mut self, domain: &str) -> Self {
+        self.envelope_to = Some(domain.to_ascii_lowercase());
+        self
+    }
+    /// Policy override e.g. for a trusted forwarder
+    pub fn with_reason(mut self, kind: PolicyOverride, comment: Option<&str>) -> Self {
+        self.reasons.push(PolicyOverrideReason {
+            kind,
+            comment: comment.map(ToOwned::to_owned),
+        });
+        self
+    }
+}
+
+/// Aggregate report for one policy domain and reporting period
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregateReport {
+    /// Who is reporting
+    pub metadata: ReportMetadata,
+    /// Unique for the reporting organization - derived from the policy domain and period when generated
+    pub report_id: String,
+    /// Policy record the first message of the period was evaluated against
+    pub policy_published: PolicyPublished,
+    /// Start of the period in seconds since the epoch
+    pub begin: u64,
+    /// End of the period in seconds since the epoch - inclusive
+    pub end: u64,
+    /// Rows with the count of messages for each
+    pub rows: BTreeMap<ReportRow, u64>,
+}
+
+// FNV-1a 64 - stable across runs and platforms unlike the std hashers
+pub(crate) fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.iter().chain(&[0]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+fn alignment(alignment: &DmarcAlignment) -> &'static str {
+    match alignment {
+        DmarcAlignment::Relaxed => "r",
+        DmarcAlignment::Strict => "s",
+    }
+}
+
+fn failure_option(option: &DmarcFailureOption) -> &'static str {
+    match option {
+        DmarcFailureOption::All => "0",
+        DmarcFailureOption::Any => "1",
+        DmarcFailureOption::Dkim => "d",
+        DmarcFailureOption::Spf => "s",
+    }
+}
+
+fn policy_override(kind: &PolicyOverride) -> &'static str {
+    match kind {
+        PolicyOverride::Forwarded => "forwarded",
+        PolicyOverride::SampledOut => "sampled_out",
+        PolicyOverride::TrustedForwarder => "trusted_forwarder",
+        PolicyOverride::MailingList => "mailing_list",
+        PolicyOverride::LocalPolicy => "local_policy",
+        PolicyOverride::Other => "other",
+    }
+}
+
+fn spf_scope(scope: &SpfScope) -> &'static str {
+    match scope {
+        SpfScope::Helo => "helo",
+        SpfScope::MailFrom => "mfrom",
+    }
+}
+
+fn pass_fail(aligned: bool) -> &'static str {
+    match aligned {
+        true => "pass",
+        false => "fail",
+    }
+}
+
+// Indented XML document built an element at a time
+struct Xml {
+    doc: String,
+    depth: usize,
+}
+
+impl Xml {
+    fn new() -> Self {
+        Self {
+            doc: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"),
+            depth: 0,
+        }
+    }
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.doc.push_str("  ");
+        }
+    }
+    fn open(&mut self, name: &str) {
+        self.indent();
+        let _ = writeln!(self.doc, "<{}>", name);
+        self.depth += 1;
+    }
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        let _ = writeln!(self.doc, "</{}>", name);
+    }
+    fn leaf(&mut self, name: &str, value: &str) {
+        self.indent();
+        let _ = write!(self.doc, "<{}>", name);
+        for c in value.chars() {
+            match c {
+                '&' => self.doc.push_str("&amp;"),
+                '<' => self.doc.push_str("&lt;"),
+                '>' => self.doc.push_str("&gt;"),
+                '"' => self.doc.push_str("&quot;"),
+                '\'' => self.doc.push_str("&apos;"),
+                c => self.doc.push(c),
+            }
+        }
+        let _ = writeln!(self.doc, "</{}>", name);
+    }
+}
+
+// The same report generated again gets the same report-id so the domain owner can tell it is a duplicate
+fn report_id(metadata: &ReportMetadata, domain: &str, begin: u64, end: u64) -> String {
+    let hash = fnv1a(&[
+        metadata.org_name.as_bytes(),
+        domain.as_bytes(),
+        &begin.to_be_bytes(),
+        &end.to_be_bytes(),
+    ]);
+    format!("{:016x}", hash)
+}
+
+impl AggregateReport {
+    /// Number of messages in the report
+    pub fn message_count(&self) -> u64 {
+        self.rows
+            .values()
+            .fold(0, |sum, count| sum.saturating_add(*count))
+    }
+    /// Report as RFC 7489 Appendix C XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = Xml::new();
+        xml.open("feedback");
+        xml.leaf("version", "1.0");
+
+        let metadata = &self.metadata;
+        xml.open("report_metadata");
+        xml.leaf("org_name", &metadata.org_name);
+        xml.leaf("email", &metadata.email);
+        if let Some(ref extra_contact_info) = metadata.extra_contact_info {
+            xml.leaf("extra_contact_info", extra_contact_info);
+        }
+        xml.leaf("report_id", &self.report_id);
+        xml.open("date_range");
+        xml.leaf("begin", &self.begin.to_string());
+        xml.leaf("end", &self.end.to_string());
+        xml.close("date_range");
+        for error in metadata.errors.iter() {
+            xml.leaf("error", error);
+        }
+        xml.close("report_metadata");
+
+        let policy = &self.policy_published;
+        let fo: Vec<&str> = policy.fo.iter().map(failure_option).collect();
+        xml.open("policy_published");
+        xml.leaf("domain", &policy.domain);
+        xml.leaf("adkim", alignment(&policy.adkim));
+        xml.leaf("aspf", alignment(&policy.aspf));
+        xml.leaf("p", dmarc_policy(&policy.p));
+        xml.leaf("sp", dmarc_policy(&policy.sp));
+        xml.leaf("pct", &policy.pct.to_string());
+        xml.leaf("fo", &fo.join(":"));
+        xml.close("policy_published");
+
+        for (row, count) in self.rows.iter() {
+            xml.open("record");
+            xml.open("row");
+            xml.leaf("source_ip", &row.source_ip.to_string());
+            xml.leaf("count", &count.to_string());
+            xml.open("policy_evaluated");
+            xml.leaf("disposition", dmarc_policy(&row.disposition));
+            xml.leaf("dkim", pass_fail(row.dkim_aligned));
+            xml.leaf("spf", pass_fail(row.spf_aligned));
+            for reason in row.reasons.iter() {
+                xml.open("reason");
+                xml.leaf("type", policy_override(&reason.kind));
+                if let Some(ref comment) = reason.comment {
+                    xml.leaf("comment", comment);
+                }
+                xml.close("reason");
+            }
+            xml.close("policy_evaluated");
+            xml.close("row");
+
+            let envelope_from = row.envelope_from.as_deref().unwrap_or_default();
+            xml.open("identifiers");
+            if let Some(ref envelope_to) = row.envelope_to {
+                xml.leaf("envelope_to", envelope_to);
+            }
+            xml.leaf("envelope_from", envelope_from);
+            xml.leaf("header_from", &row.header_from);
+            xml.close("identifiers");
+
+            xml.open("auth_results");
+            for dkim in row.dkim.iter() {
+                xml.open("dkim");
+                xml.leaf("domain", &dkim.domain);
+                if let Some(ref selector) = dkim.selector {
+                    xml.leaf("selector", selector);
+                }
+                xml.leaf("result", dkim_code(&dkim.result).unwrap_or("none"));
+                xml.close("dkim");
+            }
+            // At least one spf is required so a missing one is reported as none
+            let no_spf = [ReportSpf {
+                domain: match envelope_from.is_empty() {
+                    true => row.header_from.clone(),
+                    false => envelope_from.to_string(),
+                },
+                scope: SpfScope::MailFrom,
+                result: SpfResultCode::NoneSpf,
+            }];
+            let spfs = match row.spf.is_empty() {
+                true => &no_spf[..],
+                false => &row.spf[..],
+            };
+            for spf in spfs {
+                xml.open("spf");
+                xml.leaf("domain", &spf.domain);
+                xml.leaf("scope", spf_scope(&spf.scope));
+                xml.leaf("result", spf_code(&spf.result).unwrap_or("none"));
+                xml.close("spf");
+            }
+            xml.close("auth_results");
+            xml.close("record");
+        }
+
+        xml.close("feedback");
+        xml.doc
+    }
+    /// Report as gzip compressed XML - RFC 7489 s. 7.2.1.1
+    #[cfg(feature = "gzip")]
+    pub fn to_xml_gzip(&self) -> std::io::Result<Vec<u8>> {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write as _;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(self.to_xml().as_bytes())?;
+        encoder.finish()
+    }
+}
+
+/// Collects rows into aggregate reports per policy domain and reporting period
+#[derive(Debug)]
+pub struct AggregateReporter {
+    metadata: ReportMetadata,
+    interval: u64,
+    reports: BTreeMap<(String, u64), AggregateReport>,
+}
+
+impl AggregateReporter {
+    /// New reporter with daily reporting periods starting at midnight UTC
+    pub fn new(metadata: ReportMetadata) -> Self {
+        Self {
+            metadata,
+            interval: DEFAULT_INTERVAL,
+            reports: BTreeMap::new(),
+        }
+    }
+    /// Reporting period length in seconds - periods start at multiples of it since the epoch
+    pub fn with_interval(mut self, seconds: u64) -> Self {
+        self.interval = seconds.max(1);
+        self
+    }
+    /// Count the row of a message received at the timestamp in seconds since the epoch
+    pub fn add(&mut self, timestamp: u64, policy: &PolicyPublished, row: ReportRow) {
+        let begin = timestamp - timestamp % self.interval;
+        let end = begin + (self.interval - 1);
+        let report = self
+            .reports
+            .entry((policy.domain.clone(), begin))
+            .or_insert_with(|| AggregateReport {
+                metadata: self.metadata.clone(),
+                report_id: report_id(&self.metadata, &policy.domain, begin, end),
+                policy_published: policy.clone(),
+                begin,
+                end,
+                rows: BTreeMap::new(),
+            });
+        *report.rows.entry(row).or_insert(0) += 1;
+    }
+    /// Reports collected so far ordered by policy domain and period
+    pub fn reports(&self) -> impl Iterator<Item = &AggregateReport> {
+        self.reports.values()
+    }
+    /// Take the reports whose period ended before the timestamp
+    pub fn finished(&mut self, timestamp: u64) -> Vec<AggregateReport> {
+        let (finished, pending) = core::mem::take(&mut self.reports)
+            .into_iter()
+            .partition(|(_, report)| report.end < timestamp);
+        self.reports = pending;
+        finished.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dmarc::DmarcEvaluator;
+    use crate::error::ResolverError;
+    use crate::resolver::TxtResolver;
+    use rstest::rstest;
+
+    struct StaticResolver(&'static str, &'static str);
+
+    impl TxtResolver for StaticResolver {
+        fn lookup_txt(&self, name: &str) -> Result<Vec<String>, ResolverError> {
+            match name == self.0 {
+                true => Ok(vec![self.1.to_string()]),
+                false => Err(ResolverError::NotFound),
+            }
+        }
+    }
+
+    fn policy(domain: &str) -> PolicyPublished {
+        let record = DmarcRecord::try_from("v=DMARC1; p=reject; sp=quarantine; adkim=s").unwrap();
+        PolicyPublished::new(domain, &record)
+    }
+
+    fn row(ip: &str) -> ReportRow {
+        ReportRow {
+            source_ip: ip.parse().unwrap(),
+            disposition: DmarcPolicy::None,
+            dkim_aligned: true,
+            spf_aligned: false,
+            reasons: vec![],
+            envelope_to: None,
+            envelope_from: Some("bounces.example.com".to_string()),
+            header_from: "example.com".to_string(),
+            dkim: vec![],
+            spf: vec![],
+        }
+        .with_dkim("example.com", Some("sel"), DkimResultCode::Pass)
+        .with_spf(
+            "bounces.example.com",
+            SpfScope::MailFrom,
+            SpfResultCode::SoftFail,
+        )
+    }
+
+    fn example_net() -> AggregateReporter {
+        AggregateReporter::new(ReportMetadata::new("mx.example.net", "dmarc@example.net"))
+    }
+
+    const EXPECTED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feedback>
+  <version>1.0</version>
+  <report_metadata>
+    <org_name>mx.example.net</org_name>
+    <email>dmarc@example.net</email>
+    <report_id>REPORT_ID</report_id>
+    <date_range>
+      <begin>1700006400</begin>
+      <end>1700092799</end>
+    </date_range>
+  </report_metadata>
+  <policy_published>
+    <domain>example.com</domain>
+    <adkim>s</adkim>
+    <aspf>r</aspf>
+    <p>reject</p>
+    <sp>quarantine</sp>
+    <pct>100</pct>
+    <fo>0</fo>
+  </policy_published>
+  <record>
+    <row>
+      <source_ip>192.0.2.1</source_ip>
+      <count>2</count>
+      <policy_evaluated>
+        <disposition>none</disposition>
+        <dkim>pass</dkim>
+        <spf>fail</spf>
+      </policy_evaluated>
+    </row>
+    <identifiers>
+      <envelope_from>bounces.example.com</envelope_from>
+      <header_from>example.com</header_from>
+    </identifiers>
+    <auth_results>
+      <dkim>
+        <domain>example.com</domain>
+        <selector>sel</selector>
+        <result>pass</result>
+      </dkim>
+      <spf>
+        <domain>bounces.example.com</domain>
+        <scope>mfrom</scope>
+        <result>softfail</result>
+      </spf>
+    </auth_results>
+  </record>
+  <record>
+    <row>
+      <source_ip>2001:db8::1</source_ip>
+      <count>1</count>
+      <policy_evaluated>
+        <disposition>none</disposition>
+        <dkim>pass</dkim>
+        <spf>fail</spf>
+      </policy_evaluated>
+    </row>
+    <identifiers>
+      <envelope_from>bounces.example.com</envelope_from>
+      <header_from>example.com</header_from>
+    </identifiers>
+    <auth_results>
+      <dkim>
+        <domain>example.com</domain>
+        <selector>sel</selector>
+        <result>pass</result>
+      </dkim>
+      <spf>
+        <domain>bounces.example.com</domain>
+        <scope>mfrom</scope>
+        <result>softfail</result>
+      </spf>
+    </auth_results>
+  </record>
+</feedback>
+"#;
+
+    #[test]
+    fn xml() {
+        let mut reporter = example_net();
+        let policy = policy("example.com");
+        reporter.add(1700006400, &policy, row("2001:db8::1"));
+        reporter.add(1700050000, &policy, row("192.0.2.1"));
+        reporter.add(1700092799, &policy, row("192.0.2.1"));
+
+        let reports: Vec<_> = reporter.reports().collect();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].message_count(), 3);
+        let expected = EXPECTED.replace("REPORT_ID", &reports[0].report_id);
+        assert_eq!(reports[0].to_xml(), expected);
+    }
+
+    #[rstest]
+    #[case(86400, vec![("example.com", 1699920000), ("example.com", 1700006400), ("example.org", 1700006400)])]
+    #[case(3600, vec![("example.com", 1700002800), ("example.com", 1700006400), ("example.com", 1700010000), ("example.org", 1700006400)])]
+    fn periods(#[case] interval: u64, #[case] expected: Vec<(&str, u64)>) {
+        let mut reporter = example_net().with_interval(interval);
+        for timestamp in [1700006399, 1700006400, 1700010000] {
+            reporter.add(timestamp, &policy("example.com"), row("192.0.2.1"));
+        }
+        reporter.add(1700006400, &policy("example.org"), row("192.0.2.1"));
+
+        let periods: Vec<_> = reporter
+            .reports()
+            .map(|report| (report.policy_published.domain.as_str(), report.begin))
+            .collect();
+        assert_eq!(periods, expected);
+        assert!(reporter
+            .reports()
+            .all(|report| report.end == report.begin + interval - 1));
+    }
+
+    #[test]
+    fn report_id() {
+        let mut reporter = example_net();
+        reporter.add(1700006400, &policy("example.com"), row("192.0.2.1"));
+        reporter.add(1700006400, &policy("example.org"), row("192.0.2.1"));
+        reporter.add(1700092800, &policy("example.com"), row("192.0.2.1"));
+        let ids: Vec<String> = reporter.reports().map(|r| r.report_id.clone()).collect();
+        assert_eq!(ids[0], "3d78b7542d67cfdb");
+        assert_ne!(ids[0], ids[1]);
+        assert_ne!(ids[0], ids[2]);
+
+        // Same reporter, domain and period gives the same report-id regardless of the rows
+        let mut again = example_net();
+        again.add(1700050000, &policy("example.com"), row("198.51.100.1"));
+        assert_eq!(again.reports().next().unwrap().report_id, ids[0]);
+    }
+
+    #[test]
+    fn finished() {
+        let mut reporter = example_net();
+        reporter.add(1700006400, &policy("example.com"), row("192.0.2.1"));
+        reporter.add(1700092800, &policy("example.com"), row("192.0.2.1"));
+        assert_eq!(reporter.finished(1700092799), vec![]);
+
+        let finished = reporter.finished(1700092800);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].begin, 1700006400);
+        assert_eq!(reporter.reports().count(), 1);
+    }
+
+    #[test]
+    fn escaped() {
+        let mut metadata = ReportMetadata::new("Smith & <Sons>", "dmarc@example.net");
+        metadata.extra_contact_info = Some("https://example.net/?a=1&b='2'".to_string());
+        let mut reporter = AggregateReporter::new(metadata);
+        reporter.add(0, &policy("example.com"), row("192.0.2.1"));
+        let xml = reporter.reports().next().unwrap().to_xml();
+        assert!(xml.contains("<org_name>Smith &amp; &lt;Sons&gt;</org_name>"));
+        assert!(xml.contains(
+            "<extra_contact_info>https://example.net/?a=1&amp;b=&apos;2&apos;</extra_contact_info>"
+        ));
+    }
+
+    #[test]
+    fn from_evaluation() {
+        let resolver = StaticResolver("_dmarc.example.com", "v=DMARC1; p=reject; pct=50");
+        let auth_results = AuthenticationResults::from(
+            " mx.example.net; spf=fail smtp.mailfrom=<bounce@Bounces.Example.org>; spf=pass smtp.helo=mx.example.org; dkim=fail header.d=example.com header.s=sel",
+        );
+        let evaluation = DmarcEvaluator::new(&resolver, "Example.com")
+            .with_auth_results(&auth_results)
+            .with_sample(70)
+            .evaluate();
+
+        let policy = PolicyPublished::from_evaluation(&evaluation).unwrap();
+        assert_eq!(policy.domain, "example.com");
+        assert_eq!(policy.sp, DmarcPolicy::Reject);
+        assert_eq!(policy.pct, 50);
+
+        let row = ReportRow::from_evaluation("192.0.2.1".parse().unwrap(), &evaluation)
+            .with_auth_results(&auth_results);
+        assert_eq!(row.disposition, DmarcPolicy::Quarantine);
+        assert_eq!(row.header_from, "example.com");
+        assert_eq!(row.envelope_from.as_deref(), Some("bounces.example.org"));
+        assert_eq!(row.reasons[0].kind, PolicyOverride::SampledOut);
+        assert_eq!(
+            row.spf,
+            vec![
+                ReportSpf {
+                    domain: "bounces.example.org".to_string(),
+                    scope: SpfScope::MailFrom,
+                    result: SpfResultCode::Fail,
+                },
+                ReportSpf {
+                    domain: "mx.example.org".to_string(),
+                    scope: SpfScope::Helo,
+                    result: SpfResultCode::Pass,
+                },
+            ]
+        );
+        assert_eq!(row.dkim[0].selector.as_deref(), Some("sel"));
+
+        let none = DmarcEvaluator::new(&resolver, "example.org").evaluate();
+        assert_eq!(PolicyPublished::from_evaluation(&none), None);
+    }
+
+    // RFC 7489 Appendix C - children of each complex element in order with min and max occurrences
+    type Occurs = (&'static str, usize, usize);
+
+    const SCHEMA: &[(&str, &[Occurs])] = &[
+        (
+            "/feedback",
+            &[
+                ("version", 1, 1),
+                ("report_metadata", 1, 1),
+                ("policy_published", 1, 1),
+                ("record", 1, usize::MAX),
+            ],
+        ),
+        (
+            "feedback/report_metadata",
+            &[
+                ("org_name", 1, 1),
+                ("email", 1, 1),
+                ("extra_contact_info", 0, 1),
+                ("report_id", 1, 1),
+                ("date_range", 1, 1),
+                ("error", 0, usize::MAX),
+            ],
+        ),
+        (
+            "report_metadata/date_range",
+            &[("begin", 1, 1), ("end", 1, 1)],
+        ),
+        (
+            "feedback/policy_published",
+            &[
+                ("domain", 1, 1),
+                ("adkim", 0, 1),
+                ("aspf", 0, 1),
+                ("p", 1, 1),
+                ("sp", 1, 1),
+                ("pct", 1, 1),
+                ("fo", 1, 1),
+            ],
+        ),
+        (
+            "feedback/record",
+            &[("row", 1, 1), ("identifiers", 1, 1), ("auth_results", 1, 1)],
+        ),
+        (
+            "record/row",
+            &[
+                ("source_ip", 1, 1),
+                ("count", 1, 1),
+                ("policy_evaluated", 1, 1),
+            ],
+        ),
+        (
+            "row/policy_evaluated",
+            &[
+                ("disposition", 1, 1),
+                ("dkim", 1, 1),
+                ("spf", 1, 1),
+                ("reason", 0, usize::MAX),
+            ],
+        ),
+        (
+            "policy_evaluated/reason",
+            &[("type", 1, 1), ("comment", 0, 1)],
+        ),
+        (
+            "record/identifiers",
+            &[
+                ("envelope_to", 0, 1),
+                ("envelope_from", 1, 1),
+                ("header_from", 1, 1),
+            ],
+        ),
+        (
+            "record/auth_results",
+            &[("dkim", 0, usize::MAX), ("spf", 1, usize::MAX)],
+        ),
+        (
+            "auth_results/dkim",
+            &[
+                ("domain", 1, 1),
+                ("selector", 0, 1),
+                ("result", 1, 1),
+                ("human_result", 0, 1),
+            ],
+        ),
+        (
+            "auth_results/spf",
+            &[("domain", 1, 1), ("scope", 0, 1), ("result", 1, 1)],
+        ),
+    ];
+
+    // RFC 7489 Appendix C - enumerated simple types
+    const ENUMS: &[(&str, &[&str])] = &[
+        ("feedback/version", &["1.0"]),
+        ("policy_published/adkim", &["r", "s"]),
+        ("policy_published/aspf", &["r", "s"]),
+        ("policy_published/p", &["none", "quarantine", "reject"]),
+        ("policy_published/sp", &["none", "quarantine", "reject"]),
+        (
+            "policy_evaluated/disposition",
+            &["none", "quarantine", "reject"],
+        ),
+        ("policy_evaluated/dkim", &["pass", "fail"]),
+        ("policy_evaluated/spf", &["pass", "fail"]),
+        (
+            "reason/type",
+            &[
+                "forwarded",
+                "sampled_out",
+                "trusted_forwarder",
+                "mailing_list",
+                "local_policy",
+                "other",
+            ],
+        ),
+        (
+            "dkim/result",
+            &[
+                "none",
+                "pass",
+                "fail",
+                "policy",
+                "neutral",
+                "temperror",
+                "permerror",
+            ],
+        ),
+        (
+            "spf/result",
+            &[
+                "none",
+                "neutral",
+                "pass",
+                "fail",
+                "softfail",
+                "temperror",
+                "permerror",
+            ],
+        ),
+        ("spf/scope", &["helo", "mfrom"]),
+    ];
+
+    const INTEGERS: &[&str] = &[
+        "date_range/begin",
+        "date_range/end",
+        "row/count",
+        "policy_published/pct",
+    ];
+
+    #[derive(Debug, Default)]
+    struct Element {
+        name: String,
+        text: String,
+        children: Vec<Element>,
+    }
+
+    fn parse_xml(xml: &str) -> Element {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+        let mut stack = vec![Element::default()];
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(start) => stack.push(Element {
+                    name: String::from_utf8(start.name().as_ref().to_vec()).unwrap(),
+                    ..Default::default()
+                }),
+                Event::Text(text) => {
+                    stack.last_mut().unwrap().text = text.unescape().unwrap().to_string()
+                }
+                Event::End(_) => {
+                    let element = stack.pop().unwrap();
+                    stack.last_mut().unwrap().children.push(element);
+                }
+                Event::Empty(_) => panic!("empty elements are not emitted"),
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        stack.pop().unwrap()
+    }
+
+    fn conforms(parent: &str, element: &Element) {
+        let path = format!("{}/{}", parent, element.name);
+        let complex = SCHEMA.iter().find(|(p, _)| *p == path);
+        let Some((_, sequence)) = complex else {
+            assert!(element.children.is_empty(), "{} must be simple", path);
+            if let Some((_, values)) = ENUMS.iter().find(|(p, _)| *p == path) {
+                assert!(
+                    values.contains(&element.text.as_str()),
+                    "{} = {}",
+                    path,
+                    element.text
+                );
+            }
+            if INTEGERS.contains(&path.as_str()) {
+                element.text.parse::<u64>().unwrap();
+            }
+            if path == "row/source_ip" {
+                element.text.parse::<IpAddr>().unwrap();
+            }
+            return;
+        };
+        let mut children = element.children.iter().peekable();
+        for (name, min, max) in sequence.iter() {
+            let mut seen = 0;
+            while let Some(child) = children.next_if(|child| child.name == *name) {
+                conforms(&element.name, child);
+                seen += 1;
+            }
+            assert!(
+                seen >= *min && seen <= *max,
+                "{}/{} seen {}",
+                path,
+                name,
+                seen
+            );
+        }
+        assert!(
+            children.next().is_none(),
+            "{} has unexpected children",
+            path
+        );
+    }
+
+    #[test]
+    fn schema_conformance() {
+        let mut metadata = ReportMetadata::new("mx.example.net", "dmarc@example.net");
+        metadata.extra_contact_info = Some("https://example.net/dmarc".to_string());
+        metadata.errors = vec!["DNS timeout".to_string(), "partial".to_string()];
+        let mut reporter = AggregateReporter::new(metadata);
+        let policy = policy("example.com");
+        reporter.add(0, &policy, row("192.0.2.1"));
+        reporter.add(0, &policy, row("192.0.2.2").with_envelope_to("example.net"));
+        let no_results = ReportRow {
+            dkim: vec![],
+            spf: vec![],
+            envelope_from: None,
+            ..row("192.0.2.3")
+        };
+        reporter.add(
+            0,
+            &policy,
+            no_results.with_reason(PolicyOverride::Forwarded, Some("list <x>")),
+        );
+        let xml = reporter.reports().next().unwrap().to_xml();
+
+        let root = parse_xml(&xml);
+        assert_eq!(root.children.len(), 1);
+        conforms("", &root.children[0]);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        use std::io::Read;
+
+        let mut reporter = example_net();
+        reporter.add(0, &policy("example.com"), row("192.0.2.1"));
+        let report = reporter.reports().next().unwrap();
+        let gzip = report.to_xml_gzip().unwrap();
+        assert_eq!(&gzip[..2], &[0x1f, 0x8b]);
+        assert_eq!(report.to_xml_gzip().unwrap(), gzip);
+
+        let mut xml = String::new();
+        flate2::read::GzDecoder::new(&gzip[..])
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert_eq!(xml, report.to_xml());
+    }
+}
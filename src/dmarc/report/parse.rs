@@ -0,0 +1,630 @@
+//! Parsing received aggregate reports - RFC 7489 Appendix C
+//!
+//! Reporters differ in what they leave out so optional elements fall back to
+//! the policy record defaults. Unknown elements e.g. the DMARCbis additions
+//! and namespace prefixes are ignored.
+
+use core::net::IpAddr;
+use std::io::Read;
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::Crc;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::dkim::DkimResultCode;
+use crate::dmarc::record::parse_failure_options;
+use crate::dmarc::{DmarcAlignment, DmarcFailureOption, DmarcPolicy};
+use crate::error::DmarcReportError;
+use crate::spf::SpfResultCode;
+
+use super::{
+    AggregateReport, PolicyOverride, PolicyOverrideReason, PolicyPublished, ReportDkim,
+    ReportMetadata, ReportRow, ReportSpf, SpfScope,
+};
+
+use std::collections::BTreeMap;
+
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+const ZIP_LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+const ZIP_CENTRAL_HEADER: &[u8] = b"PK\x01\x02";
+const ZIP_END_OF_CENTRAL: &[u8] = b"PK\x05\x06";
+
+/// Default limit on the decompressed report size in bytes
+pub const MAX_REPORT_SIZE: u64 = 32 * 1024 * 1024;
+
+// Element tree with the text of each element
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn new(name: &[u8]) -> Self {
+        Self {
+            name: String::from_utf8_lossy(name).into_owned(),
+            ..Default::default()
+        }
+    }
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+    fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+    // Trimmed text of the child - None when missing or empty
+    fn text(&self, name: &str) -> Option<&str> {
+        self.child(name)
+            .map(|child| child.text.trim())
+            .filter(|text| !text.is_empty())
+    }
+    fn required(&self, name: &'static str) -> Result<&str, DmarcReportError> {
+        self.text(name)
+            .ok_or(DmarcReportError::MissingElement(name))
+    }
+    fn required_child(&self, name: &'static str) -> Result<&Element, DmarcReportError> {
+        self.child(name)
+            .ok_or(DmarcReportError::MissingElement(name))
+    }
+}
+
+fn parse_tree(xml: &str) -> Result<Element, DmarcReportError> {
+    let mut reader = Reader::from_str(xml);
+    let mut stack = vec![Element::default()];
+    loop {
+        let invalid =
+            |reader: &Reader<&[u8]>| DmarcReportError::InvalidXml(reader.error_position());
+        let event = reader.read_event().map_err(|_| invalid(&reader))?;
+        let current = stack.last_mut().ok_or_else(|| invalid(&reader))?;
+        match event {
+            Event::Start(start) => stack.push(Element::new(start.local_name().as_ref())),
+            Event::Empty(empty) => current
+                .children
+                .push(Element::new(empty.local_name().as_ref())),
+            Event::Text(text) => current
+                .text
+                .push_str(&text.unescape().map_err(|_| invalid(&reader))?),
+            Event::CData(cdata) => current.text.push_str(&String::from_utf8_lossy(&cdata)),
+            Event::End(_) => {
+                let element = stack.pop().ok_or_else(|| invalid(&reader))?;
+                let parent = stack.last_mut().ok_or_else(|| invalid(&reader))?;
+                parent.children.push(element);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    match (stack.pop(), stack.is_empty()) {
+        (Some(document), true) => Ok(document),
+        _ => Err(DmarcReportError::InvalidXml(reader.buffer_position())),
+    }
+}
+
+fn number<T: core::str::FromStr>(name: &'static str, val: &str) -> Result<T, DmarcReportError> {
+    val.parse()
+        .map_err(|_| DmarcReportError::InvalidValue(name, val.to_string()))
+}
+
+fn policy(name: &'static str, val: &str) -> Result<DmarcPolicy, DmarcReportError> {
+    DmarcPolicy::try_from(val).map_err(|_| DmarcReportError::InvalidValue(name, val.to_string()))
+}
+
+fn dkim_result(val: &str) -> DkimResultCode {
+    match val.to_ascii_lowercase().as_str() {
+        "none" => DkimResultCode::NoneDkim,
+        "pass" => DkimResultCode::Pass,
+        "fail" => DkimResultCode::Fail,
+        "policy" => DkimResultCode::Policy,
+        "neutral" => DkimResultCode::Neutral,
+        "temperror" => DkimResultCode::TempError,
+        "permerror" => DkimResultCode::PermError,
+        _ => DkimResultCode::Unknown,
+    }
+}
+
+fn spf_result(val: &str) -> SpfResultCode {
+    match val.to_ascii_lowercase().as_str() {
+        "none" => SpfResultCode::NoneSpf,
+        "neutral" => SpfResultCode::Neutral,
+        "pass" => SpfResultCode::Pass,
+        "fail" => SpfResultCode::Fail,
+        "softfail" => SpfResultCode::SoftFail,
+        "temperror" => SpfResultCode::TempError,
+        "permerror" => SpfResultCode::PermError,
+        _ => SpfResultCode::Unknown,
+    }
+}
+
+fn policy_override(val: &str) -> PolicyOverride {
+    match val {
+        "forwarded" => PolicyOverride::Forwarded,
+        "sampled_out" => PolicyOverride::SampledOut,
+        "trusted_forwarder" => PolicyOverride::TrustedForwarder,
+        "mailing_list" => PolicyOverride::MailingList,
+        "local_policy" => PolicyOverride::LocalPolicy,
+        _ => PolicyOverride::Other,
+    }
+}
+
+fn metadata(element: &Element) -> Result<(ReportMetadata, String, u64, u64), DmarcReportError> {
+    let date_range = element.required_child("date_range")?;
+    let metadata = ReportMetadata {
+        org_name: element.required("org_name")?.to_string(),
+        email: element.text("email").unwrap_or_default().to_string(),
+        extra_contact_info: element.text("extra_contact_info").map(ToOwned::to_owned),
+        errors: element
+            .all("error")
+            .map(|error| error.text.trim().to_string())
+            .collect(),
+    };
+    let report_id = element.required("report_id")?.to_string();
+    let begin = number("begin", date_range.required("begin")?)?;
+    let end = number("end", date_range.required("end")?)?;
+    Ok((metadata, report_id, begin, end))
+}
+
+fn policy_published(element: &Element) -> Result<PolicyPublished, DmarcReportError> {
+    let p = policy("p", element.required("p")?)?;
+    Ok(PolicyPublished {
+        domain: element.required("domain")?.to_ascii_lowercase(),
+        adkim: element
+            .text("adkim")
+            .and_then(|val| DmarcAlignment::try_from(val).ok())
+            .unwrap_or_default(),
+        aspf: element
+            .text("aspf")
+            .and_then(|val| DmarcAlignment::try_from(val).ok())
+            .unwrap_or_default(),
+        p,
+        sp: match element.text("sp") {
+            Some(val) => policy("sp", val)?,
+            None => p,
+        },
+        pct: element
+            .text("pct")
+            .and_then(|val| val.parse().ok())
+            .filter(|pct| *pct <= 100)
+            .unwrap_or(100),
+        fo: element
+            .text("fo")
+            .and_then(|val| parse_failure_options(val).ok())
+            .unwrap_or(vec![DmarcFailureOption::All]),
+    })
+}
+
+fn record(element: &Element) -> Result<(ReportRow, u64), DmarcReportError> {
+    let row = element.required_child("row")?;
+    let evaluated = row.required_child("policy_evaluated")?;
+    let identifiers = element.required_child("identifiers")?;
+    let source_ip: IpAddr = number("source_ip", row.required("source_ip")?)?;
+    let count = number("count", row.required("count")?)?;
+
+    let reasons = evaluated
+        .all("reason")
+        .filter_map(|reason| {
+            Some(PolicyOverrideReason {
+                kind: policy_override(reason.text("type")?),
+                comment: reason.text("comment").map(ToOwned::to_owned),
+            })
+        })
+        .collect();
+    let mut dkim = vec![];
+    let mut spf = vec![];
+    if let Some(auth_results) = element.child("auth_results") {
+        for result in auth_results.all("dkim") {
+            dkim.push(ReportDkim {
+                domain: result.required("domain")?.to_ascii_lowercase(),
+                selector: result.text("selector").map(ToOwned::to_owned),
+                result: dkim_result(result.text("result").unwrap_or_default()),
+            });
+        }
+        for result in auth_results.all("spf") {
+            spf.push(ReportSpf {
+                domain: result.required("domain")?.to_ascii_lowercase(),
+                scope: match result.text("scope") {
+                    Some("helo") => SpfScope::Helo,
+                    _ => SpfScope::MailFrom,
+                },
+                result: spf_result(result.text("result").unwrap_or_default()),
+            });
+        }
+    }
+
+    let report_row = ReportRow {
+        source_ip,
+        disposition: policy("disposition", evaluated.required("disposition")?)?,
+        dkim_aligned: evaluated.text("dkim") == Some("pass"),
+        spf_aligned: evaluated.text("spf") == Some("pass"),
+        reasons,
+        envelope_to: identifiers.text("envelope_to").map(str::to_ascii_lowercase),
+        envelope_from: identifiers
+            .text("envelope_from")
+            .map(str::to_ascii_lowercase),
+        header_from: identifiers.required("header_from")?.to_ascii_lowercase(),
+        dkim,
+        spf,
+    };
+    Ok((report_row, count))
+}
+
+// Size, crc and payload of the first file in the zip archive - APPNOTE.TXT s. 4.3
+fn zip_entry(bytes: &[u8]) -> Option<(usize, usize, u32, &[u8])> {
+    let le16 = |at: usize| -> Option<usize> {
+        let b = bytes.get(at..at + 2)?;
+        Some(usize::from(u16::from_le_bytes([b[0], b[1]])))
+    };
+    let le32 = |at: usize| -> Option<u32> {
+        let b = bytes.get(at..at + 4)?;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    // End of central directory is at the end unless followed by a comment
+    let end = (0..=bytes.len().checked_sub(22)?)
+        .rev()
+        .find(|at| bytes[*at..].starts_with(ZIP_END_OF_CENTRAL))?;
+    let central = le32(end + 16)? as usize;
+    if !bytes.get(central..)?.starts_with(ZIP_CENTRAL_HEADER) {
+        return None;
+    }
+    let method = le16(central + 10)?;
+    let crc = le32(central + 16)?;
+    let compressed = le32(central + 20)? as usize;
+    let local = le32(central + 42)? as usize;
+    if !bytes.get(local..)?.starts_with(ZIP_LOCAL_HEADER) {
+        return None;
+    }
+    let start = local + 30 + le16(local + 26)? + le16(local + 28)?;
+    Some((
+        method,
+        compressed,
+        crc,
+        bytes.get(start..start + compressed)?,
+    ))
+}
+
+// Whole decompressed stream unless it is over the limit
+fn decompress(reader: impl Read, limit: u64) -> Result<Vec<u8>, DmarcReportError> {
+    let mut out = vec![];
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut out)
+        .map_err(|_| DmarcReportError::Decompress)?;
+    match out.len() as u64 > limit {
+        true => Err(DmarcReportError::TooLarge(limit)),
+        false => Ok(out),
+    }
+}
+
+fn unzip(bytes: &[u8], limit: u64) -> Result<Vec<u8>, DmarcReportError> {
+    let (method, _, crc, data) = zip_entry(bytes).ok_or(DmarcReportError::Decompress)?;
+    let out = match method {
+        0 => decompress(data, limit)?,
+        8 => decompress(DeflateDecoder::new(data), limit)?,
+        method => return Err(DmarcReportError::UnsupportedCompression(method)),
+    };
+    let mut check = Crc::new();
+    check.update(&out);
+    match check.sum() == crc {
+        true => Ok(out),
+        false => Err(DmarcReportError::Decompress),
+    }
+}
+
+impl AggregateReport {
+    /// Parse the report XML
+    ///
+    /// Rows repeated in the report are counted together.
+    pub fn from_xml(xml: &str) -> Result<Self, DmarcReportError> {
+        let document = parse_tree(xml)?;
+        let feedback = match document.children.as_slice() {
+            [feedback] if feedback.name == "feedback" => feedback,
+            _ => return Err(DmarcReportError::NotFeedback),
+        };
+        let (metadata, report_id, begin, end) =
+            metadata(feedback.required_child("report_metadata")?)?;
+        let policy_published = policy_published(feedback.required_child("policy_published")?)?;
+
+        let mut rows = BTreeMap::new();
+        for element in feedback.all("record") {
+            let (row, count) = record(element)?;
+            let total = rows.entry(row).or_insert(0u64);
+            *total = total
+                .checked_add(count)
+                .ok_or_else(|| DmarcReportError::InvalidValue("count", count.to_string()))?;
+        }
+        Ok(Self {
+            metadata,
+            report_id,
+            policy_published,
+            begin,
+            end,
+            rows,
+        })
+    }
+    /// Parse the report attachment which may be gzip, zip or plain XML
+    ///
+    /// Decompressed reports over [`MAX_REPORT_SIZE`] are refused.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DmarcReportError> {
+        Self::from_bytes_limited(bytes, MAX_REPORT_SIZE)
+    }
+    /// Parse the report attachment refusing it when it decompresses to over limit bytes
+    pub fn from_bytes_limited(bytes: &[u8], limit: u64) -> Result<Self, DmarcReportError> {
+        let xml = match bytes {
+            b if b.starts_with(GZIP_MAGIC) => decompress(GzDecoder::new(b), limit)?,
+            b if b.starts_with(ZIP_LOCAL_HEADER) => unzip(b, limit)?,
+            b => b.to_vec(),
+        };
+        let xml = String::from_utf8(xml).map_err(|_| DmarcReportError::NotUtf8)?;
+        Self::from_xml(xml.trim_start_matches('\u{feff}'))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dmarc::report::AggregateReporter;
+    use rstest::rstest;
+    use std::io::Write;
+    use std::{fs::File, path::PathBuf};
+
+    fn load_test_data(file: &str) -> Vec<u8> {
+        let mut file = File::open(PathBuf::from("test_data").join(file)).unwrap();
+        let mut data: Vec<u8> = vec![];
+        file.read_to_end(&mut data).unwrap();
+        data
+    }
+
+    // Single file zip archive as the reporters attach
+    fn zip(name: &str, data: &[u8], method: u16) -> Vec<u8> {
+        let compressed = match method {
+            8 => {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            _ => data.to_vec(),
+        };
+        let mut crc = Crc::new();
+        crc.update(data);
+        let sizes = [
+            crc.sum().to_le_bytes(),
+            (compressed.len() as u32).to_le_bytes(),
+            (data.len() as u32).to_le_bytes(),
+        ]
+        .concat();
+        let name_len = (name.len() as u16).to_le_bytes();
+
+        let mut out = vec![];
+        out.extend_from_slice(ZIP_LOCAL_HEADER);
+        out.extend_from_slice(&[20, 0, 0, 0]);
+        out.extend_from_slice(&method.to_le_bytes());
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&sizes);
+        out.extend_from_slice(&name_len);
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&compressed);
+
+        let central = out.len() as u32;
+        out.extend_from_slice(ZIP_CENTRAL_HEADER);
+        out.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+        out.extend_from_slice(&method.to_le_bytes());
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&sizes);
+        out.extend_from_slice(&name_len);
+        out.extend_from_slice(&[0; 12]);
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        let central_len = out.len() as u32 - central;
+
+        out.extend_from_slice(ZIP_END_OF_CENTRAL);
+        out.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        out.extend_from_slice(&central_len.to_le_bytes());
+        out.extend_from_slice(&central.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out
+    }
+
+    #[test]
+    fn gmail() {
+        let xml = load_test_data("dmarc_report_gmail.xml");
+        let report = AggregateReport::from_bytes(&xml).unwrap();
+        assert_eq!(report.metadata.org_name, "google.com");
+        assert_eq!(report.report_id, "12838457207283950182");
+        assert_eq!((report.begin, report.end), (1709251200, 1709337599));
+        assert_eq!(report.policy_published.domain, "arewe.at");
+        assert_eq!(report.policy_published.sp, DmarcPolicy::Quarantine);
+        assert_eq!(report.policy_published.fo, vec![DmarcFailureOption::All]);
+        assert_eq!(report.message_count(), 14);
+
+        let (row, count) = report.rows.iter().next().unwrap();
+        assert_eq!(*count, 12);
+        assert_eq!(row.source_ip, "66.111.4.25".parse::<IpAddr>().unwrap());
+        assert!(row.dkim_aligned && row.spf_aligned);
+        assert_eq!(row.envelope_from.as_deref(), Some("arewe.at"));
+        assert_eq!(
+            row.dkim,
+            vec![
+                ReportDkim {
+                    domain: "arewe.at".to_string(),
+                    selector: Some("fm1".to_string()),
+                    result: DkimResultCode::Pass,
+                },
+                ReportDkim {
+                    domain: "messagingengine.com".to_string(),
+                    selector: Some("fm2".to_string()),
+                    result: DkimResultCode::Pass,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn outlook() {
+        let xml = load_test_data("dmarc_report_outlook.xml");
+        let report = AggregateReport::from_bytes(&xml).unwrap();
+        assert_eq!(report.metadata.org_name, "Outlook.com");
+        assert_eq!(report.metadata.errors, vec!["partial"]);
+        assert_eq!(report.policy_published.pct, 100);
+        assert_eq!(report.policy_published.adkim, DmarcAlignment::Relaxed);
+
+        let rows: Vec<_> = report.rows.iter().collect();
+        assert_eq!(rows.len(), 2);
+        let (row, count) = rows[1];
+        assert_eq!(*count, 3);
+        assert_eq!(row.disposition, DmarcPolicy::Quarantine);
+        assert_eq!(row.envelope_to.as_deref(), Some("hotmail.com"));
+        assert_eq!(row.envelope_from, None);
+        assert_eq!(
+            row.reasons,
+            vec![PolicyOverrideReason {
+                kind: PolicyOverride::Forwarded,
+                comment: Some("via list & relay".to_string()),
+            }]
+        );
+        assert_eq!(row.spf[0].scope, SpfScope::Helo);
+        assert_eq!(row.spf[0].result, SpfResultCode::SoftFail);
+        assert_eq!(row.dkim, vec![]);
+    }
+
+    #[test]
+    fn dmarcbis() {
+        let xml = load_test_data("dmarc_report_dmarcbis.xml");
+        let report = AggregateReport::from_bytes(&xml).unwrap();
+        assert_eq!(report.metadata.org_name, "Yahoo");
+        assert_eq!(report.policy_published.p, DmarcPolicy::Reject);
+        assert_eq!(report.policy_published.pct, 100);
+        let (row, count) = report.rows.iter().next().unwrap();
+        assert_eq!(*count, 1);
+        assert_eq!(row.source_ip, "2001:db8::25".parse::<IpAddr>().unwrap());
+        assert_eq!(row.dkim[0].result, DkimResultCode::Fail);
+    }
+
+    #[test]
+    fn repeated_rows_overflow() {
+        let xml = String::from_utf8(load_test_data("dmarc_report_dmarcbis.xml")).unwrap();
+        let (head, rest) = xml.split_once("  <record>").unwrap();
+        let (record, tail) = rest.split_once("</record>").unwrap();
+        let max = record.replace("<count>1</count>", &format!("<count>{}</count>", u64::MAX));
+        let xml = format!("{head}  <record>{max}</record><record>{record}</record>{tail}");
+        assert_eq!(
+            AggregateReport::from_xml(&xml),
+            Err(DmarcReportError::InvalidValue("count", "1".to_string()))
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let xml = String::from_utf8(load_test_data("dmarc_report_gmail.xml")).unwrap();
+        let report = AggregateReport::from_xml(&xml).unwrap();
+        let again = AggregateReport::from_xml(&report.to_xml()).unwrap();
+        assert_eq!(again, report);
+
+        let mut reporter = AggregateReporter::new(report.metadata.clone());
+        for (row, count) in report.rows.iter() {
+            for _ in 0..*count {
+                reporter.add(report.begin, &report.policy_published, row.clone());
+            }
+        }
+        let generated = reporter.reports().next().unwrap();
+        assert_eq!(
+            AggregateReport::from_xml(&generated.to_xml()).as_ref(),
+            Ok(generated)
+        );
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(8)]
+    fn compressed(#[case] method: u16) {
+        let xml = load_test_data("dmarc_report_gmail.xml");
+        let expected = AggregateReport::from_bytes(&xml).unwrap();
+
+        let zipped = zip(
+            "google.com!arewe.at!1709251200!1709337599.xml",
+            &xml,
+            method,
+        );
+        assert_eq!(AggregateReport::from_bytes(&zipped), Ok(expected.clone()));
+
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&xml).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        assert_eq!(AggregateReport::from_bytes(&gzipped), Ok(expected));
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(8)]
+    fn decompressed_over_limit(#[case] method: u16) {
+        let xml = load_test_data("dmarc_report_gmail.xml");
+        let limit = xml.len() as u64;
+        let bomb = vec![b' '; 1024 * 1024];
+
+        let zipped = zip("report.xml", &xml, method);
+        assert!(AggregateReport::from_bytes_limited(&zipped, limit).is_ok());
+        let zipped = zip("report.xml", &bomb, method);
+        assert_eq!(
+            AggregateReport::from_bytes_limited(&zipped, limit),
+            Err(DmarcReportError::TooLarge(limit))
+        );
+
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&bomb).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        assert_eq!(
+            AggregateReport::from_bytes_limited(&gzipped, limit),
+            Err(DmarcReportError::TooLarge(limit))
+        );
+    }
+
+    #[test]
+    fn corrupt_archives() {
+        let xml = load_test_data("dmarc_report_gmail.xml");
+        let mut zipped = zip("report.xml", &xml, 0);
+        zipped[40] ^= 0xff;
+        assert_eq!(
+            AggregateReport::from_bytes(&zipped),
+            Err(DmarcReportError::Decompress)
+        );
+        let bzip2 = zip("report.xml", &xml, 12);
+        assert_eq!(
+            AggregateReport::from_bytes(&bzip2),
+            Err(DmarcReportError::UnsupportedCompression(12))
+        );
+        assert_eq!(
+            AggregateReport::from_bytes(&[0x1f, 0x8b, 0, 1, 2]),
+            Err(DmarcReportError::Decompress)
+        );
+        assert_eq!(
+            AggregateReport::from_bytes(&[0xff, 0xfe]),
+            Err(DmarcReportError::NotUtf8)
+        );
+    }
+
+    #[rstest]
+    #[case("<feedback><report_metadata>", DmarcReportError::InvalidXml(27))]
+    #[case("<feedback></report>", DmarcReportError::InvalidXml(10))]
+    #[case("<rss></rss>", DmarcReportError::NotFeedback)]
+    #[case(
+        "<feedback></feedback>",
+        DmarcReportError::MissingElement("report_metadata")
+    )]
+    #[case(
+        "<feedback><report_metadata><org_name>x</org_name><report_id>1</report_id><date_range><begin>0</begin><end>-1</end></date_range></report_metadata></feedback>",
+        DmarcReportError::InvalidValue("end", "-1".to_string())
+    )]
+    #[case(
+        "<feedback><report_metadata><org_name>x</org_name><report_id>1</report_id><date_range><begin>0</begin><end>1</end></date_range></report_metadata><policy_published><domain>example.com</domain><p>block</p></policy_published></feedback>",
+        DmarcReportError::InvalidValue("p", "block".to_string())
+    )]
+    #[case(
+        "<feedback><report_metadata><org_name>x</org_name><report_id>1</report_id><date_range><begin>0</begin><end>1</end></date_range></report_metadata><policy_published><domain>example.com</domain><p>none</p></policy_published><record><row><source_ip>192.0.2.300</source_ip></row></record></feedback>",
+        DmarcReportError::MissingElement("policy_evaluated")
+    )]
+    fn invalid(#[case] xml: &str, #[case] expected: DmarcReportError) {
+        assert_eq!(AggregateReport::from_xml(xml), Err(expected));
+    }
+}
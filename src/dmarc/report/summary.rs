@@ -0,0 +1,151 @@
+//! Pass / fail volume over aggregate reports
+
+use core::net::IpAddr;
+
+use crate::dkim::DkimResultCode;
+
+use super::AggregateReport;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{collections::BTreeMap, string::String};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+/// Message counts by outcome
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PassFail {
+    /// Messages that passed
+    pub pass: u64,
+    /// Messages that did not pass
+    pub fail: u64,
+}
+
+impl PassFail {
+    /// All the messages
+    pub fn total(&self) -> u64 {
+        self.pass.saturating_add(self.fail)
+    }
+    fn add(&mut self, pass: bool, count: u64) {
+        match pass {
+            true => self.pass = self.pass.saturating_add(count),
+            false => self.fail = self.fail.saturating_add(count),
+        }
+    }
+}
+
+/// Volume per source IP and per DKIM selector over any number of reports
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReportSummary {
+    /// DMARC outcome - a pass when either DKIM or SPF produced an aligned pass
+    pub by_source_ip: BTreeMap<IpAddr, PassFail>,
+    /// DKIM result of the signatures by d= and s= regardless of alignment
+    pub by_dkim_selector: BTreeMap<(String, Option<String>), PassFail>,
+}
+
+impl ReportSummary {
+    /// Empty summary
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Count all the rows of the report
+    pub fn add(&mut self, report: &AggregateReport) {
+        for (row, count) in report.rows.iter() {
+            self.by_source_ip
+                .entry(row.source_ip)
+                .or_default()
+                .add(row.dkim_aligned || row.spf_aligned, *count);
+            for dkim in row.dkim.iter() {
+                self.by_dkim_selector
+                    .entry((dkim.domain.clone(), dkim.selector.clone()))
+                    .or_default()
+                    .add(dkim.result == DkimResultCode::Pass, *count);
+            }
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a AggregateReport> for ReportSummary {
+    fn from_iter<I: IntoIterator<Item = &'a AggregateReport>>(reports: I) -> Self {
+        let mut summary = Self::new();
+        for report in reports {
+            summary.add(report);
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dmarc::report::{
+        AggregateReporter, PolicyPublished, ReportMetadata, ReportRow, SpfScope,
+    };
+    use crate::dmarc::DmarcRecord;
+    use crate::spf::SpfResultCode;
+
+    fn row(ip: &str, aligned: bool, selector: &str, dkim: DkimResultCode) -> ReportRow {
+        ReportRow {
+            source_ip: ip.parse().unwrap(),
+            disposition: crate::dmarc::DmarcPolicy::None,
+            dkim_aligned: aligned,
+            spf_aligned: false,
+            reasons: vec![],
+            envelope_to: None,
+            envelope_from: None,
+            header_from: "example.com".to_string(),
+            dkim: vec![],
+            spf: vec![],
+        }
+        .with_dkim("example.com", Some(selector), dkim)
+        .with_spf("example.com", SpfScope::MailFrom, SpfResultCode::Fail)
+    }
+
+    #[test]
+    fn summary() {
+        let record = DmarcRecord::try_from("v=DMARC1; p=reject").unwrap();
+        let policy = PolicyPublished::new("example.com", &record);
+        let mut reporter =
+            AggregateReporter::new(ReportMetadata::new("mx.example.net", "dmarc@example.net"));
+        for _ in 0..3 {
+            reporter.add(
+                0,
+                &policy,
+                row("192.0.2.1", true, "s1", DkimResultCode::Pass),
+            );
+        }
+        reporter.add(
+            0,
+            &policy,
+            row("192.0.2.1", false, "s2", DkimResultCode::Fail),
+        );
+        reporter.add(
+            86400,
+            &policy,
+            row("192.0.2.2", false, "s1", DkimResultCode::PermError),
+        );
+
+        let summary: ReportSummary = reporter.reports().collect();
+        assert_eq!(
+            summary.by_source_ip.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("192.0.2.1".parse().unwrap(), PassFail { pass: 3, fail: 1 }),
+                ("192.0.2.2".parse().unwrap(), PassFail { pass: 0, fail: 1 }),
+            ]
+        );
+        let s1 = summary.by_dkim_selector[&("example.com".to_string(), Some("s1".to_string()))];
+        assert_eq!(s1, PassFail { pass: 3, fail: 1 });
+        assert_eq!(s1.total(), 4);
+        assert_eq!(summary.by_dkim_selector.len(), 2);
+    }
+
+    #[test]
+    fn saturating() {
+        let mut counts = PassFail {
+            pass: u64::MAX,
+            fail: 1,
+        };
+        counts.add(true, 1);
+        assert_eq!(counts.pass, u64::MAX);
+        assert_eq!(counts.total(), u64::MAX);
+    }
+}
@@ -1,5 +1,8 @@
 //! Public errors
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::String;
+
 /// Parsing Detail relating to an Error
 #[derive(Clone, Debug, PartialEq)]
 pub struct ParsingDetail<'hdr> {
@@ -46,6 +49,10 @@ pub enum AuthResultsError<'hdr> {
     InvalidSpfResult(String),
     /// Invalid iprev method Result Code
     InvalidIpRevResult(String),
+    /// Invalid dkim-atps method Result Code
+    InvalidDkimAtpsResult(String),
+    /// Invalid dmarc method Result Code
+    InvalidDmarcResult(String),
     /// Was not a valid ptype/property per IANA and strict validation was used
     InvalidProperty,
     /// Invalid auth method Result code
@@ -78,6 +85,10 @@ pub enum AuthResultsError<'hdr> {
     RunAwayDkimPropertyKey,
     /// Run-away dkim method property value
     RunAwayDkimPropertyValue,
+    /// Run-away dmarc method property key
+    RunAwayDmarcPropertyKey,
+    /// Run-away dmarc method property value
+    RunAwayDmarcPropertyValue,
     /// Run-away iprev method property key
     RunAwayIpRevPropertyKey,
     /// Run-away iprev method property value
@@ -93,7 +104,7 @@ pub enum AuthResultsError<'hdr> {
 }
 
 /// DKIM-Signature header parsing Errors
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum DkimSignatureError<'hdr> {
     /// Detailed with ParsingDetail
     ParsingDetailed(ParsingDetail<'hdr>),
@@ -121,17 +132,99 @@ pub enum DkimSignatureError<'hdr> {
     MissingSelector,
 }
 
+/// DKIM-Signature tag diagnostic with the location of the offending tag-spec
+#[derive(Debug, PartialEq)]
+pub struct DkimTagDiagnostic<'hdr> {
+    /// Tag name if known - for missing required tags the tag that was expected
+    pub tag: Option<&'hdr str>,
+    /// Byte span of the tag-spec within the header value - None for missing tags
+    pub span: Option<core::ops::Range<usize>>,
+    /// Error
+    pub error: DkimSignatureError<'hdr>,
+}
+
 /// Currently no error - may change in future
 #[derive(Debug, PartialEq)]
 pub enum DkimAlgorithmError {}
 
-/// Currently infallible may be changed in the future
+/// DKIM Canonicalization c= parsing errors
 #[derive(Clone, Debug, PartialEq)]
-pub enum DkimCanonicalizationError {}
+pub enum DkimCanonicalizationError {
+    /// Header canonicalization algorithm is empty
+    MissingHeaderAlgorithm,
+    /// Body canonicalization algorithm is empty after the '/' separator
+    MissingBodyAlgorithm,
+    /// More than header and body algorithm was given
+    TooManyAlgorithms,
+}
 
-/// Currently infallible, may change in the future
+/// DKIM Timestamp t= / x= parsing errors
+#[derive(Debug, PartialEq)]
+pub enum DkimTimestampError {
+    /// No digits given
+    Empty,
+    /// Timestamp must consist only of digits
+    InvalidDigits,
+    /// Timestamp is limited to 12 digits
+    TooManyDigits,
+}
+
+/// DKIM base64 b= / bh= decoding errors
+#[derive(Debug, PartialEq)]
+pub enum DkimBase64Error {
+    /// Not valid base64 after removing whitespace
+    Invalid,
+}
+
+/// DKIM Signed header fields h= parsing errors
+#[derive(Debug, PartialEq)]
+pub enum DkimSignedHeadersError {
+    /// Empty header field name in the colon separated list
+    EmptyName,
+    /// Header field name contains invalid characters
+    InvalidName,
+}
+
+/// DKIM AUID i= parsing errors
+#[derive(Debug, PartialEq)]
+pub enum DkimAuidError {
+    /// Missing required '@' separating local part and domain
+    NoAt,
+    /// Missing domain after '@'
+    NoDomain,
+}
+
+/// DKIM Copied header fields z= parsing errors
+#[derive(Debug, PartialEq)]
+pub enum DkimCopiedHeadersError {
+    /// Copied header field is missing the ':' separator
+    NoColon,
+    /// Empty header field name
+    EmptyName,
+    /// Invalid dkim-quoted-printable encoding
+    InvalidQuotedPrintable,
+}
+
+/// DKIM public key record parsing errors
 #[derive(Debug, PartialEq)]
-pub enum DkimTimestampError {}
+pub enum DkimKeyRecordError {
+    /// tag-spec is missing the '=' separator
+    NoEqual,
+    /// Tag appears more than once
+    Duplicate,
+    /// v= is not DKIM1 or is not the first tag
+    InvalidVersion,
+    /// Required p= is missing
+    MissingPublicKey,
+    /// p= is not valid base64
+    Base64(DkimBase64Error),
+}
+
+impl From<DkimBase64Error> for DkimKeyRecordError {
+    fn from(e: DkimBase64Error) -> Self {
+        Self::Base64(e)
+    }
+}
 
 /// Currently infallible, may change in the future
 #[derive(Debug, PartialEq)]
@@ -146,6 +239,8 @@ pub enum DkimVersionError {}
 pub enum DkimTagValueError {
     /// Tag value must appear only once per tag
     Duplicate,
+    /// Tag value is required to be non-empty
+    EmptyValue,
     /// DKIM Timestamp parsing error
     Timestamp(DkimTimestampError),
     /// DKIM Canonizalition parsing error
@@ -154,6 +249,22 @@ pub enum DkimTagValueError {
     Algorithm(DkimAlgorithmError),
     /// DKIM Version parsing error
     Version(DkimVersionError),
+    /// DKIM base64 b= / bh= decoding error
+    Base64(DkimBase64Error),
+    /// DKIM Signed header fields h= parsing error
+    SignedHeaders(DkimSignedHeadersError),
+    /// DKIM AUID i= parsing error
+    Auid(DkimAuidError),
+    /// DKIM Copied header fields z= parsing error
+    CopiedHeaders(DkimCopiedHeadersError),
+    /// Body length limit l= is not a valid number
+    InvalidBodyLength,
+    /// Empty query method in q=
+    EmptyQueryMethod,
+    /// Signature Expiration x= is before Signature Timestamp t=
+    ExpirationBeforeTimestamp,
+    /// AUID i= domain is not the same as or a subdomain of SDID d=
+    AuidOutsideSdid,
 }
 
 impl<'hdr> From<DkimTagValueError> for DkimSignatureError<'hdr> {
@@ -186,6 +297,74 @@ impl From<DkimVersionError> for DkimTagValueError {
     }
 }
 
+impl From<DkimBase64Error> for DkimTagValueError {
+    fn from(e: DkimBase64Error) -> Self {
+        Self::Base64(e)
+    }
+}
+
+impl From<DkimSignedHeadersError> for DkimTagValueError {
+    fn from(e: DkimSignedHeadersError) -> Self {
+        Self::SignedHeaders(e)
+    }
+}
+
+impl From<DkimAuidError> for DkimTagValueError {
+    fn from(e: DkimAuidError) -> Self {
+        Self::Auid(e)
+    }
+}
+
+impl From<DkimCopiedHeadersError> for DkimTagValueError {
+    fn from(e: DkimCopiedHeadersError) -> Self {
+        Self::CopiedHeaders(e)
+    }
+}
+
+/// DNS resolver errors returned by the resolver traits
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResolverError {
+    /// The name does not exist or has no records of the requested type
+    NotFound,
+    /// Transient failure e.g. a timeout or SERVFAIL - a later attempt may succeed
+    Temporary,
+    /// Any other permanent failure
+    Permanent,
+}
+
+/// DKIM body hashing errors
+#[derive(Clone, Debug, PartialEq)]
+pub enum BodyHasherError {
+    /// Signature algorithm a= is not supported
+    UnsupportedAlgorithm,
+    /// Body canonicalization algorithm in c= is not supported
+    UnsupportedCanonicalization,
+}
+
+/// Raw message splitting errors
+#[derive(Clone, Debug, PartialEq)]
+pub enum RawMessageError {
+    /// Header field at the given byte offset has no colon or an invalid name
+    InvalidHeader(usize),
+}
+
+/// Authentication-Results insertion errors
+#[derive(Clone, Debug, PartialEq)]
+pub enum InsertAuthResultsError {
+    /// The raw message could not be split into header fields
+    RawMessage(RawMessageError),
+    /// There are fewer Received header fields than the position given
+    ReceivedNotFound(usize),
+    /// The Authentication-Results have no authserv-id to render
+    MissingAuthservId,
+}
+
+impl From<RawMessageError> for InsertAuthResultsError {
+    fn from(e: RawMessageError) -> Self {
+        Self::RawMessage(e)
+    }
+}
+
 /// Comment errors
 #[derive(Clone, Debug, PartialEq)]
 pub enum CommentError<'hdr> {
@@ -221,6 +400,8 @@ pub enum AddrSpecError<'hdr> {
     ParseComment(CommentError<'hdr>),
     /// Parsing display name failed
     ParseDisplayName(QuotedError<'hdr>),
+    /// Group without the terminating semicolon
+    UnterminatedGroup,
 }
 
 /// Return-Path verifier errors
@@ -235,3 +416,120 @@ pub enum ReturnPathVerifierError<'hdr> {
     /// Invalid Return Path
     InvalidHeader(AddrSpecError<'hdr>),
 }
+
+/// DMARC record tag value errors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DmarcTagValueError {
+    /// Tag value must appear only once per tag
+    Duplicate,
+    /// v= is not DMARC1
+    InvalidVersion,
+    /// p= / sp= / np= is not none, quarantine or reject
+    InvalidPolicy,
+    /// pct= is not a number between 0 and 100
+    InvalidPercent,
+    /// adkim= / aspf= is not r or s
+    InvalidAlignment,
+    /// rua= / ruf= URI has no scheme
+    InvalidUri,
+    /// rua= / ruf= size limit is not a number with an optional k, m, g or t unit
+    InvalidSizeLimit,
+    /// fo= option is not 0, 1, d or s
+    InvalidFailureOption,
+    /// rf= has an empty report format
+    InvalidReportFormat,
+    /// ri= is not a number of seconds
+    InvalidInterval,
+    /// t= is not y or n
+    InvalidTesting,
+}
+
+/// DMARC record parsing errors
+#[derive(Clone, Debug, PartialEq)]
+pub enum DmarcRecordError<'rec> {
+    /// Detailed with ParsingDetail
+    ParsingDetailed(ParsingDetail<'rec>),
+    /// No tags found at all
+    NoTagFound,
+    /// Encountered unexpected Equal '=' character when a tag was expected
+    UnexpectedEqual,
+    /// v=DMARC1 is not the first tag - RFC 7489 s. 6.4
+    MissingVersion,
+    /// Required p= is missing
+    MissingPolicy,
+    /// Tag value is invalid - with the detail of the offending tag-spec
+    ParseValueInvalid(DmarcTagValueError, ParsingDetail<'rec>),
+}
+
+/// SPF record term errors - each one a permerror RFC 7208 s. 4.6
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpfTermError {
+    /// Mechanism name is not one of RFC 7208 s. 5
+    UnknownMechanism,
+    /// Mechanism takes no argument e.g. all
+    UnexpectedArgument,
+    /// include: / exists: / ip4: / ip6: / redirect= / exp= without the argument
+    MissingArgument,
+    /// Domain-spec does not end in a top label or a macro - s. 7.1
+    InvalidDomainSpec,
+    /// Macro is malformed or not allowed outside exp= explanations - s. 7.1
+    InvalidMacro,
+    /// ip4: is not an IPv4 address
+    InvalidIp4,
+    /// ip6: is not an IPv6 address
+    InvalidIp6,
+    /// CIDR prefix length is malformed or too long
+    InvalidCidr,
+    /// redirect= or exp= appears more than once - s. 6
+    DuplicateModifier,
+    /// Modifier name is not ALPHA *( ALPHA / DIGIT / "-" / "_" / "." )
+    InvalidModifier,
+}
+
+/// SPF record parsing errors
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpfRecordError<'rec> {
+    /// Detailed with ParsingDetail
+    ParsingDetailed(ParsingDetail<'rec>),
+    /// Does not begin with v=spf1 so it is not an SPF record at all - s. 4.5
+    NotSpf1,
+    /// Term is invalid - with the detail of the offending term
+    InvalidTerm(SpfTermError, ParsingDetail<'rec>),
+}
+
+/// DMARC aggregate report parsing errors
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum DmarcReportError {
+    /// gzip or zip attachment could not be decompressed
+    Decompress,
+    /// Decompressed report is over the size limit - with the limit
+    TooLarge(u64),
+    /// zip compression method other than stored or deflate - with the method
+    UnsupportedCompression(usize),
+    /// Report is not UTF-8
+    NotUtf8,
+    /// Malformed XML - with the byte offset
+    InvalidXml(u64),
+    /// Document element is not feedback
+    NotFeedback,
+    /// Required element is missing or empty
+    MissingElement(&'static str),
+    /// Element value is invalid - with the value
+    InvalidValue(&'static str, String),
+}
+
+/// Header From verifier errors
+#[derive(Debug, PartialEq)]
+pub enum HeaderFromVerifierError<'hdr> {
+    /// From header not present
+    NoHeader,
+    /// Encountered multiple From headers
+    MultipleNotAllowed,
+    /// From header has no mailbox e.g. only an empty group
+    NoAddress,
+    /// From header has more than one mailbox - with the count
+    MultipleAddresses(usize),
+    /// Invalid From
+    InvalidHeader(AddrSpecError<'hdr>),
+}
@@ -35,6 +35,13 @@ pub mod traits;
 
 pub mod error;
 
+//---------------------------------------------------------
+// Resolver traits used by the checks needing DNS
+//---------------------------------------------------------
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod resolver;
+
 //---------------------------------------------------------
 // Authentication-Results & DKIM-Signature etc. pub types
 //---------------------------------------------------------
@@ -43,7 +50,14 @@ pub mod addr;
 pub mod auth;
 pub mod auth_results;
 pub mod dkim;
+pub mod dmarc;
 pub mod iprev;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod message;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod render;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod sanitize;
 pub mod spf;
 
 //--------------------------------------------------------
@@ -65,3 +79,10 @@ pub mod alloc_yes;
 
 #[cfg(feature = "static")]
 pub mod alloc_no;
+
+//--------------------------------------------------------
+// Organizational domain by the Public Suffix List
+//--------------------------------------------------------
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod orgdomain;
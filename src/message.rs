@@ -0,0 +1,51 @@
+//! Raw message split into header fields and body
+//!
+//! Canonicalization and verification need the header fields exactly as they
+//! were received which the usual mail parsers do not retain.
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
+/// Header field as it appears in the raw message
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawHeader<'msg> {
+    /// Field name without the colon and any obsolete WSP before it
+    pub name: &'msg str,
+    /// Everything after the colon up to the terminating line ending - folding retained
+    pub value: &'msg [u8],
+    /// The whole field including the terminating line ending
+    pub raw: &'msg [u8],
+}
+
+impl<'msg> RawHeader<'msg> {
+    /// Whether the field name matches case-insensitively
+    pub fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+    /// Field name as it appears including any WSP before the colon - RFC 5322 s. 4.5
+    pub fn raw_name(&self) -> &'msg str {
+        match self.raw.iter().position(|b| *b == b':') {
+            Some(colon) => core::str::from_utf8(&self.raw[..colon]).unwrap_or(self.name),
+            None => self.name,
+        }
+    }
+}
+
+/// Raw message split at the first empty line
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RawMessage<'msg> {
+    /// Header fields in the order they appear
+    pub headers: Vec<RawHeader<'msg>>,
+    /// Body after the empty line separating it from the header fields
+    pub body: &'msg [u8],
+}
+
+impl<'msg> RawMessage<'msg> {
+    /// Iterate the header fields with the given name in the order they appear
+    pub fn headers_named<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl DoubleEndedIterator<Item = &'a RawHeader<'msg>> + 'a {
+        self.headers.iter().filter(move |hdr| hdr.is(name))
+    }
+}
@@ -0,0 +1,352 @@
+//! Organizational domain lookup with the Public Suffix List
+//!
+//! The organizational domain is the public suffix plus one label e.g.
+//! `bbc.co.uk` for `mail.bbc.co.uk` - see https://publicsuffix.org/list/
+//!
+//! Rules and looked up domains are compared as lowercase IDNA A-labels so
+//! `xn--55qx5d.cn` and `公司.cn` are the same suffix. Returned domains are in
+//! A-label form.
+//!
+//! ```
+//! use msg_auth_status::orgdomain::{org_domain, PublicSuffixList};
+//!
+//! assert_eq!(org_domain("mail.bbc.co.uk").as_deref(), Some("bbc.co.uk"));
+//!
+//! let list = PublicSuffixList::from("uk\nco.uk\n*.sch.uk\n");
+//! assert_eq!(list.org_domain("a.b.school.sch.uk").as_deref(), Some("b.school.sch.uk"));
+//! ```
+
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{collections::BTreeSet, string::String, vec::Vec};
+
+/// Bundled snapshot of https://publicsuffix.org/list/public_suffix_list.dat
+///
+/// The list is subject to the Mozilla Public License, v. 2.0.
+pub const BUNDLED: &str = include_str!("orgdomain/public_suffix_list.dat");
+
+/// Parsed Public Suffix List
+///
+/// Lines that are empty or comments are skipped and a rule is the first
+/// whitespace delimited word on a line. Wildcard `*.` and exception `!` rules
+/// are supported, both the ICANN and the private sections are used.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PublicSuffixList {
+    // e.g. co.uk
+    rules: BTreeSet<String>,
+    // *.ck stored as ck
+    wildcards: BTreeSet<String>,
+    // !www.ck stored as www.ck
+    exceptions: BTreeSet<String>,
+}
+
+// Punycode parameters - RFC 3492 s. 5
+const BASE: u32 = 36;
+const TMIN: u32 = 1;
+const TMAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+// Bias adaptation - RFC 3492 s. 6.1
+fn adapt(delta: u32, numpoints: u32, first: bool) -> u32 {
+    let mut delta = match first {
+        true => delta / DAMP,
+        false => delta / 2,
+    };
+    delta += delta / numpoints;
+    let mut k = 0;
+    while delta > ((BASE - TMIN) * TMAX) / 2 {
+        delta /= BASE - TMIN;
+        k += BASE;
+    }
+    k + (BASE - TMIN + 1) * delta / (delta + SKEW)
+}
+
+fn encode_digit(digit: u32) -> char {
+    match digit {
+        0..=25 => (b'a' + digit as u8) as char,
+        _ => (b'0' + (digit - 26) as u8) as char,
+    }
+}
+
+// Punycode encoding of a label - RFC 3492 s. 6.3 - None on overflow
+fn punycode(label: &str) -> Option<String> {
+    let input: Vec<u32> = label.chars().map(u32::from).collect();
+    let mut output: String = label.chars().filter(char::is_ascii).collect();
+    let basic = output.len() as u32;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let (mut n, mut delta, mut bias, mut handled) = (INITIAL_N, 0u32, INITIAL_BIAS, basic);
+    while (handled as usize) < input.len() {
+        let m = input.iter().copied().filter(|&c| c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+        for &c in input.iter() {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = match k {
+                        k if k <= bias => TMIN,
+                        k if k >= bias + TMAX => TMAX,
+                        k => k - bias,
+                    };
+                    if q < t {
+                        break;
+                    }
+                    output.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta = delta.checked_add(1)?;
+        n += 1;
+    }
+    Some(output)
+}
+
+// Lowercase A-label of a single label - non-ASCII labels that overflow are kept as is
+fn a_label(label: &str) -> String {
+    if label.is_ascii() {
+        return label.to_ascii_lowercase();
+    }
+    let lower: String = label.chars().flat_map(char::to_lowercase).collect();
+    match punycode(&lower) {
+        Some(encoded) => ["xn--", &encoded].concat(),
+        None => lower,
+    }
+}
+
+// Lowercase A-labels without the trailing dot of a fully qualified name
+//
+// The IDNA full stops U+3002, U+FF0E and U+FF61 separate labels like '.' does.
+pub(crate) fn normalize(domain: &str) -> String {
+    let domain = domain.replace(['\u{3002}', '\u{ff0e}', '\u{ff61}'], ".");
+    let domain = domain.strip_suffix('.').unwrap_or(&domain);
+    domain.split('.').map(a_label).collect::<Vec<_>>().join(".")
+}
+
+impl From<&str> for PublicSuffixList {
+    fn from(list: &str) -> Self {
+        let mut new_self = Self::default();
+        for line in list.lines() {
+            let rule = match line.split_whitespace().next() {
+                Some(rule) if !rule.starts_with("//") => rule,
+                _ => continue,
+            };
+            if let Some(exception) = rule.strip_prefix('!') {
+                new_self.exceptions.insert(normalize(exception));
+            } else if let Some(parent) = rule.strip_prefix("*.") {
+                new_self.wildcards.insert(normalize(parent));
+            } else {
+                new_self.rules.insert(normalize(rule));
+            }
+        }
+        new_self
+    }
+}
+
+impl PublicSuffixList {
+    /// Load the list from a file e.g. a fresher copy than the bundled snapshot
+    #[cfg(feature = "std")]
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        let list = std::fs::read_to_string(path)?;
+        Ok(Self::from(list.as_str()))
+    }
+    /// Number of rules including wildcard and exception rules
+    pub fn len(&self) -> usize {
+        self.rules.len() + self.wildcards.len() + self.exceptions.len()
+    }
+    /// Whether the list has no rules - every TLD is then a public suffix
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    // Number of labels in the public suffix of the normalized domain
+    fn public_suffix_labels(&self, domain: &str) -> usize {
+        let labels = domain.split('.').count();
+        // Unlisted TLDs are public suffixes by the implicit * rule
+        let mut found = 1;
+        let mut suffix = domain;
+        for idx in 0..labels {
+            let remaining = labels - idx;
+            if self.exceptions.contains(suffix) {
+                return remaining - 1;
+            }
+            if self.rules.contains(suffix) {
+                found = found.max(remaining);
+            }
+            if idx > 0 && self.wildcards.contains(suffix) {
+                found = found.max(remaining + 1);
+            }
+            suffix = match suffix.split_once('.') {
+                Some((_, parent)) => parent,
+                None => break,
+            };
+        }
+        found
+    }
+    // Normalized domain with its label count - None when empty or with an empty label
+    fn labels(domain: &str) -> Option<(String, usize)> {
+        let domain = normalize(domain);
+        match domain.split('.').any(str::is_empty) {
+            true => None,
+            false => {
+                let count = domain.split('.').count();
+                Some((domain, count))
+            }
+        }
+    }
+    // Last count labels of the normalized domain
+    fn last_labels(domain: &str, count: usize) -> String {
+        let labels: Vec<&str> = domain.split('.').collect();
+        labels[labels.len() - count..].join(".")
+    }
+    /// Public suffix of the domain e.g. `co.uk` for `mail.bbc.co.uk`
+    pub fn public_suffix(&self, domain: &str) -> Option<String> {
+        let (domain, count) = Self::labels(domain)?;
+        let suffix = self.public_suffix_labels(&domain).min(count);
+        Some(Self::last_labels(&domain, suffix))
+    }
+    /// Organizational domain e.g. `bbc.co.uk` for `mail.bbc.co.uk`
+    ///
+    /// None when the domain itself is a public suffix, empty or has an empty label.
+    pub fn org_domain(&self, domain: &str) -> Option<String> {
+        let (domain, count) = Self::labels(domain)?;
+        let keep = self.public_suffix_labels(&domain) + 1;
+        match count < keep {
+            true => None,
+            false => Some(Self::last_labels(&domain, keep)),
+        }
+    }
+}
+
+/// The bundled list parsed once
+#[cfg(feature = "std")]
+pub fn bundled() -> &'static PublicSuffixList {
+    static BUNDLED_LIST: std::sync::OnceLock<PublicSuffixList> = std::sync::OnceLock::new();
+    BUNDLED_LIST.get_or_init(|| PublicSuffixList::from(BUNDLED))
+}
+
+/// Organizational domain by the bundled list - see [`PublicSuffixList::org_domain`]
+#[cfg(feature = "std")]
+pub fn org_domain(domain: &str) -> Option<String> {
+    bundled().org_domain(domain)
+}
+
+/// Organizational domain by the bundled list - see [`PublicSuffixList::org_domain`]
+///
+/// Without std the list is parsed again on every call - parse [`BUNDLED`]
+/// once with [`PublicSuffixList::from`] and keep it instead when looking up many.
+#[cfg(not(feature = "std"))]
+pub fn org_domain(domain: &str) -> Option<String> {
+    PublicSuffixList::from(BUNDLED).org_domain(domain)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("mail.bbc.co.uk", Some("bbc.co.uk"))]
+    #[case("bbc.co.uk", Some("bbc.co.uk"))]
+    #[case("Bounces.Example.COM.", Some("example.com"))]
+    #[case("example.com", Some("example.com"))]
+    #[case("a.b.example.unlisted", Some("example.unlisted"))]
+    #[case("co.uk", None)]
+    #[case("com", None)]
+    #[case("foo.bar.ck", Some("foo.bar.ck"))]
+    #[case("bar.ck", None)]
+    #[case("www.ck", Some("www.ck"))]
+    #[case("a.www.ck", Some("www.ck"))]
+    #[case("www.例子.公司.cn", Some("xn--fsqu00a.xn--55qx5d.cn"))]
+    #[case("www.xn--fsqu00a.xn--55qx5d.cn", Some("xn--fsqu00a.xn--55qx5d.cn"))]
+    #[case("www。例子。公司。cn", Some("xn--fsqu00a.xn--55qx5d.cn"))]
+    #[case("", None)]
+    #[case("a..example.com", None)]
+    fn bundled_list(#[case] domain: &str, #[case] expected: Option<&str>) {
+        assert_eq!(org_domain(domain).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case("mail.bbc.co.uk", Some("co.uk"))]
+    #[case("co.uk", Some("co.uk"))]
+    #[case("a.b.bar.ck", Some("bar.ck"))]
+    #[case("a.www.ck", Some("ck"))]
+    #[case("example.unlisted", Some("unlisted"))]
+    #[case("", None)]
+    fn public_suffix(#[case] domain: &str, #[case] expected: Option<&str>) {
+        assert_eq!(bundled().public_suffix(domain).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case("bücher", "xn--bcher-kva")]
+    #[case("münchen", "xn--mnchen-3ya")]
+    #[case("MÜNCHEN", "xn--mnchen-3ya")]
+    #[case("例子", "xn--fsqu00a")]
+    #[case("公司", "xn--55qx5d")]
+    #[case("EXAMPLE", "example")]
+    fn a_labels(#[case] label: &str, #[case] expected: &str) {
+        assert_eq!(a_label(label), expected);
+    }
+
+    #[test]
+    fn user_supplied() {
+        let list = PublicSuffixList::from(
+            "// comment\n\nuk\nco.uk   trailing words\n*.sch.uk\n!special.sch.uk\nMÜNCHEN\n",
+        );
+        assert_eq!(list.len(), 5);
+        assert_eq!(
+            list.org_domain("mail.bbc.co.uk").as_deref(),
+            Some("bbc.co.uk")
+        );
+        assert_eq!(
+            list.org_domain("a.b.school.sch.uk").as_deref(),
+            Some("b.school.sch.uk")
+        );
+        assert_eq!(list.org_domain("school.sch.uk"), None);
+        assert_eq!(
+            list.org_domain("a.special.sch.uk").as_deref(),
+            Some("special.sch.uk")
+        );
+        assert_eq!(
+            list.org_domain("stadt.xn--mnchen-3ya").as_deref(),
+            Some("stadt.xn--mnchen-3ya")
+        );
+        assert_eq!(
+            list.org_domain("example.com").as_deref(),
+            Some("example.com")
+        );
+
+        let empty = PublicSuffixList::from("");
+        assert!(empty.is_empty());
+        assert_eq!(empty.org_domain("mail.bbc.co.uk").as_deref(), Some("co.uk"));
+    }
+
+    #[test]
+    fn from_file() {
+        let path = std::env::temp_dir().join("msg_auth_status_orgdomain_test.dat");
+        std::fs::write(&path, "com\n*.example.com\n").unwrap();
+        let list = PublicSuffixList::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            list.org_domain("a.b.c.example.com").as_deref(),
+            Some("b.c.example.com")
+        );
+        assert!(PublicSuffixList::from_file(&path).is_err());
+    }
+}
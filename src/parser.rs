@@ -4,4 +4,10 @@ pub mod addr_spec;
 pub mod auth_results;
 pub mod comment;
 pub mod dkim_signature;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod dmarc_record;
 pub mod quoted;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod raw_message;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod spf_record;
@@ -8,6 +8,9 @@ use crate::addr::AddrSpec;
 use crate::error::AddrSpecError;
 use logos::{Lexer, Logos};
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
 #[derive(Debug, Logos)]
 pub enum AddrSpecToken<'hdr> {
     #[token("(", priority = 100)]
@@ -25,10 +28,20 @@ pub enum AddrSpecToken<'hdr> {
     #[token(r##"""##, priority = 100)]
     DoubleQuoteStart,
 
+    #[token(",", priority = 100)]
+    Comma,
+
+    #[token(":", priority = 100)]
+    Colon,
+
+    #[token(";", priority = 100)]
+    Semicolon,
+
     #[regex(r##"[\r\n\t ]+"##, |lex| lex.slice(), priority = 50)]
+    #[allow(dead_code)]
     Fws(&'hdr str),
 
-    #[regex(r##"[^""><(@\r\n\t ]+"##, |lex| lex.slice(), priority = 2)]
+    #[regex(r##"[^""><(@,:;\r\n\t ]+"##, |lex| lex.slice(), priority = 2)]
     MaybeValue(&'hdr str),
 }
 
@@ -164,6 +177,189 @@ pub fn parse_addr_spec<'hdr>(
     progress.try_into()
 }
 
+#[derive(Debug, PartialEq)]
+enum WhereAtList {
+    WantPhrase,
+    WantLocalPart,
+    WantAt,
+    WantDomain,
+    WantGt,
+    GotMailbox,
+}
+
+impl From<WhereAtList> for &'static str {
+    fn from(w: WhereAtList) -> &'static str {
+        match w {
+            WhereAtList::WantPhrase => "WantPhrase",
+            WhereAtList::WantLocalPart => "WantLocalPart",
+            WhereAtList::WantAt => "WantAt",
+            WhereAtList::WantDomain => "WantDomain",
+            WhereAtList::WantGt => "WantGt",
+            WhereAtList::GotMailbox => "GotMailbox",
+        }
+    }
+}
+
+// Words before an angle-addr, an addr-spec or a group
+#[derive(Debug, Default)]
+struct Phrase<'hdr> {
+    start: Option<usize>,
+    end: usize,
+    words: usize,
+    quoted: Option<&'hdr str>,
+    last: Option<&'hdr str>,
+}
+
+impl<'hdr> Phrase<'hdr> {
+    fn push(&mut self, start: usize, end: usize, word: &'hdr str, quoted: bool) {
+        self.start.get_or_insert(start);
+        self.end = end;
+        self.words += 1;
+        self.last = Some(word);
+        self.quoted = match quoted && self.words == 1 {
+            true => Some(word),
+            false => None,
+        };
+    }
+    // A single quoted word is the display name without the quotes
+    fn display_name(&self, source: &'hdr str) -> Option<&'hdr str> {
+        match (self.quoted, self.start) {
+            (Some(quoted), _) => Some(quoted),
+            (None, Some(start)) => Some(&source[start..self.end]),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Parse RFC 5322 address-list - mailboxes and groups separated by commas
+///
+/// Mailboxes inside groups are returned in place and the group names are dropped.
+/// Empty groups e.g. `undisclosed-recipients:;` add no mailbox.
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub fn parse_mailbox_list<'hdr>(
+    lexer: &mut Lexer<'hdr, AddrSpecToken<'hdr>>,
+) -> Result<Vec<AddrSpec<'hdr>>, AddrSpecError<'hdr>> {
+    let mut mailboxes = vec![];
+    let mut stage = WhereAtList::WantPhrase;
+    let mut in_group = false;
+    let mut phrase = Phrase::default();
+    let mut progress = ParsingProgress::default();
+    let mut start = 0;
+    let mut angle = false;
+
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(AddrSpecToken::CommentStart) => {
+                let mut comment_lexer = CommentToken::lexer(lexer.remainder());
+                match parse_comment(&mut comment_lexer) {
+                    Ok(_comment) => {}
+                    Err(e) => return Err(AddrSpecError::ParseComment(e)),
+                }
+                lexer.bump(comment_lexer.span().end);
+            }
+            Ok(AddrSpecToken::Fws(_)) => {
+                // cont.
+            }
+            Ok(AddrSpecToken::DoubleQuoteStart) if stage == WhereAtList::WantPhrase => {
+                let quote_start = lexer.span().start;
+                let mut quoted_lexer = QuotedToken::lexer(lexer.remainder());
+                let quoted = match parse_quoted(&mut quoted_lexer) {
+                    Ok(quoted) => quoted,
+                    Err(e) => return Err(AddrSpecError::ParseDisplayName(e)),
+                };
+                lexer.bump(quoted_lexer.span().end);
+                phrase.push(quote_start, lexer.span().end, quoted, true);
+            }
+            Ok(AddrSpecToken::MaybeValue(val)) if stage == WhereAtList::WantPhrase => {
+                phrase.push(lexer.span().start, lexer.span().end, val, false);
+            }
+            Ok(AddrSpecToken::At) if stage == WhereAtList::WantPhrase && phrase.words == 1 => {
+                // Bare addr-spec - the single word was the local part
+                start = phrase.start.unwrap_or_default();
+                progress.local_part = phrase.last;
+                angle = false;
+                stage = WhereAtList::WantDomain;
+            }
+            Ok(AddrSpecToken::Lt) if stage == WhereAtList::WantPhrase => {
+                start = phrase.start.unwrap_or(lexer.span().start);
+                progress.display_name = phrase.display_name(lexer.source());
+                angle = true;
+                stage = WhereAtList::WantLocalPart;
+            }
+            Ok(AddrSpecToken::Colon) if stage == WhereAtList::WantPhrase && !in_group => {
+                // Group display name is not kept
+                in_group = true;
+                phrase = Phrase::default();
+            }
+            Ok(AddrSpecToken::MaybeValue(val)) if stage == WhereAtList::WantLocalPart => {
+                progress.local_part = Some(val);
+                stage = WhereAtList::WantAt;
+            }
+            Ok(AddrSpecToken::At) if stage == WhereAtList::WantAt => {
+                stage = WhereAtList::WantDomain;
+            }
+            Ok(AddrSpecToken::MaybeValue(val)) if stage == WhereAtList::WantDomain => {
+                progress.domain = Some(val);
+                stage = match angle {
+                    true => WhereAtList::WantGt,
+                    false => {
+                        progress.raw = Some(&lexer.source()[start..lexer.span().end]);
+                        mailboxes.push(core::mem::take(&mut progress).try_into()?);
+                        phrase = Phrase::default();
+                        WhereAtList::GotMailbox
+                    }
+                };
+            }
+            Ok(AddrSpecToken::Gt) if stage == WhereAtList::WantGt => {
+                progress.raw = Some(&lexer.source()[start..lexer.span().end]);
+                mailboxes.push(core::mem::take(&mut progress).try_into()?);
+                phrase = Phrase::default();
+                stage = WhereAtList::GotMailbox;
+            }
+            Ok(AddrSpecToken::Comma)
+                if stage == WhereAtList::GotMailbox
+                    || (stage == WhereAtList::WantPhrase && phrase.words == 0) =>
+            {
+                stage = WhereAtList::WantPhrase;
+            }
+            Ok(AddrSpecToken::Semicolon)
+                if in_group
+                    && (stage == WhereAtList::GotMailbox
+                        || (stage == WhereAtList::WantPhrase && phrase.words == 0)) =>
+            {
+                in_group = false;
+                stage = WhereAtList::GotMailbox;
+            }
+            _ => {
+                let cut_slice = &lexer.source()[lexer.span().start..];
+                let cut_span = &lexer.source()[lexer.span().start..lexer.span().end];
+
+                let detail = crate::error::ParsingDetail {
+                    component: stage.into(),
+                    span_start: lexer.span().start,
+                    span_end: lexer.span().end,
+                    source: lexer.source(),
+                    clipped_span: cut_span,
+                    clipped_remaining: cut_slice,
+                };
+                return Err(AddrSpecError::ParsingDetailed(detail));
+            }
+        }
+    }
+
+    match stage {
+        _ if in_group => Err(AddrSpecError::UnterminatedGroup),
+        WhereAtList::GotMailbox => Ok(mailboxes),
+        WhereAtList::WantPhrase if phrase.words == 0 => Ok(mailboxes),
+        WhereAtList::WantPhrase => Err(AddrSpecError::NoAssociatedAddrSpec),
+        WhereAtList::WantLocalPart | WhereAtList::WantAt => {
+            Err(AddrSpecError::NoAssociatedLocalPart)
+        }
+        WhereAtList::WantDomain => Err(AddrSpecError::NoAssociatedDomain),
+        WhereAtList::WantGt => Err(AddrSpecError::NoAssociatedAddrSpec),
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -179,4 +375,54 @@ mod test {
         let spec = parse_addr_spec(&mut lexer, false);
         assert_eq!(spec, Ok(expected))
     }
+
+    fn mailbox(
+        display_name: Option<&'static str>,
+        local_part: &'static str,
+        domain: &'static str,
+        raw: &'static str,
+    ) -> AddrSpec<'static> {
+        AddrSpec {
+            display_name,
+            local_part,
+            domain,
+            raw,
+        }
+    }
+
+    #[rstest]
+    #[case(" foo@bar.com\r\n", vec![mailbox(None, "foo", "bar.com", "foo@bar.com")])]
+    #[case("<foo@bar.com>", vec![mailbox(None, "foo", "bar.com", "<foo@bar.com>")])]
+    #[case(r#""meow" <foo@bar.com>"#, vec![mailbox(Some("meow"), "foo", "bar.com", r#""meow" <foo@bar.com>"#)])]
+    #[case("Foo Bar <foo@bar.com> (work)", vec![mailbox(Some("Foo Bar"), "foo", "bar.com", "Foo Bar <foo@bar.com>")])]
+    #[case("a@b.com, \"B\" <b@c.com>", vec![mailbox(None, "a", "b.com", "a@b.com"), mailbox(Some("B"), "b", "c.com", r#""B" <b@c.com>"#)])]
+    #[case("Team: a@b.com, <b@c.com>;, d@e.com", vec![mailbox(None, "a", "b.com", "a@b.com"), mailbox(None, "b", "c.com", "<b@c.com>"), mailbox(None, "d", "e.com", "d@e.com")])]
+    #[case("undisclosed-recipients:;", vec![])]
+    #[case("", vec![])]
+    fn mailbox_list(#[case] in_hdr: &'static str, #[case] expected: Vec<AddrSpec<'static>>) {
+        let mut lexer = AddrSpecToken::lexer(in_hdr);
+        assert_eq!(parse_mailbox_list(&mut lexer), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("Team: a@b.com", AddrSpecError::UnterminatedGroup)]
+    #[case("Foo Bar", AddrSpecError::NoAssociatedAddrSpec)]
+    #[case("foo@", AddrSpecError::NoAssociatedDomain)]
+    #[case("<foo@bar.com", AddrSpecError::NoAssociatedAddrSpec)]
+    fn mailbox_list_errors(#[case] in_hdr: &'static str, #[case] expected: AddrSpecError<'static>) {
+        let mut lexer = AddrSpecToken::lexer(in_hdr);
+        assert_eq!(parse_mailbox_list(&mut lexer), Err(expected));
+    }
+
+    #[test]
+    fn mailbox_list_detailed_error() {
+        let mut lexer = AddrSpecToken::lexer("a@b.com c@d.com");
+        match parse_mailbox_list(&mut lexer) {
+            Err(AddrSpecError::ParsingDetailed(detail)) => {
+                assert_eq!(detail.component, "GotMailbox");
+                assert_eq!(detail.clipped_span, "c");
+            }
+            other => panic!("expected ParsingDetailed, got {:?}", other),
+        }
+    }
 }
@@ -4,13 +4,17 @@ use logos::{Lexer, Logos};
 
 use crate::alloc_yes::AuthenticationResults;
 use crate::auth::{SmtpAuthResult, SmtpAuthResultCode};
-use crate::dkim::{DkimResult, DkimResultCode};
+use crate::dkim::{DkimAtpsResult, DkimAtpsResultCode, DkimResult, DkimResultCode};
+use crate::dmarc::{DmarcResult, DmarcResultCode};
 use crate::error::AuthResultsError;
 use crate::iprev::{IpRevResult, IpRevResultCode};
 use crate::spf::{SpfResult, SpfResultCode};
 
 use crate::alloc_yes::UnknownResult;
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::ToString;
+
 mod host_version;
 mod policy;
 mod ptypes;
@@ -63,6 +67,22 @@ impl<'hdr> TryFrom<AuthResultToken<'hdr>> for DkimResultCode {
     }
 }
 
+impl<'hdr> TryFrom<AuthResultToken<'hdr>> for DkimAtpsResultCode {
+    type Error = AuthResultsError<'hdr>;
+
+    fn try_from(token: AuthResultToken<'hdr>) -> Result<Self, Self::Error> {
+        let res = match token {
+            AuthResultToken::NoneNone => Self::NoneAtps,
+            AuthResultToken::Pass => Self::Pass,
+            AuthResultToken::Fail => Self::Fail,
+            AuthResultToken::TempError => Self::TempError,
+            AuthResultToken::PermError => Self::PermError,
+            _ => return Err(AuthResultsError::InvalidDkimAtpsResult("".to_string())),
+        };
+        Ok(res)
+    }
+}
+
 impl<'hdr> TryFrom<AuthResultToken<'hdr>> for SpfResultCode {
     type Error = AuthResultsError<'hdr>;
 
@@ -97,6 +117,22 @@ impl<'hdr> TryFrom<AuthResultToken<'hdr>> for IpRevResultCode {
     }
 }
 
+impl<'hdr> TryFrom<AuthResultToken<'hdr>> for DmarcResultCode {
+    type Error = AuthResultsError<'hdr>;
+
+    fn try_from(token: AuthResultToken<'_>) -> Result<Self, Self::Error> {
+        let res = match token {
+            AuthResultToken::NoneNone => Self::NoneDmarc,
+            AuthResultToken::Pass => Self::Pass,
+            AuthResultToken::Fail => Self::Fail,
+            AuthResultToken::TempError => Self::TempError,
+            AuthResultToken::PermError => Self::PermError,
+            _ => return Err(AuthResultsError::InvalidDmarcResult("".to_string())),
+        };
+        Ok(res)
+    }
+}
+
 #[derive(Debug, Logos)]
 #[logos(skip r"[ \t\r\n]+")]
 pub enum AuthResultToken<'hdr> {
@@ -104,10 +140,14 @@ pub enum AuthResultToken<'hdr> {
     Auth,
     #[token("dkim", priority = 200)]
     Dkim,
+    #[token("dkim-atps", priority = 200)]
+    DkimAtps,
     #[token("spf", priority = 200)]
     Spf,
     #[token("iprev", priority = 200)]
     IpRev,
+    #[token("dmarc", priority = 200)]
+    Dmarc,
 
     #[token("/", priority = 200)]
     ForwardSlash,
@@ -163,9 +203,17 @@ enum Stage {
     WantDkimEqual,
     WantDkimResult,
 
+    // dkim-atps = ...
+    WantDkimAtpsEqual,
+    WantDkimAtpsResult,
+
     // iprev = ...
     WantIpRevEqual,
     WantIpRevResult,
+
+    // dmarc = ...
+    WantDmarcEqual,
+    WantDmarcResult,
 }
 
 // State machine helpers
@@ -177,14 +225,21 @@ impl Stage {
             Self::WantAuthResult
                 | Self::WantSpfResult
                 | Self::WantDkimResult
+                | Self::WantDkimAtpsResult
                 | Self::WantIpRevResult
+                | Self::WantDmarcResult
         )
     }
     // Is the current stage expecting '=' equal for a result set
     fn is_cur_expect_resultset_equal(&self) -> bool {
         matches!(
             self,
-            Self::WantAuthEqual | Self::WantSpfEqual | Self::WantDkimEqual | Self::WantIpRevEqual
+            Self::WantAuthEqual
+                | Self::WantSpfEqual
+                | Self::WantDkimEqual
+                | Self::WantDkimAtpsEqual
+                | Self::WantIpRevEqual
+                | Self::WantDmarcEqual
         )
     }
     // Reflect the relevant Result for given WantEqual
@@ -193,7 +248,9 @@ impl Stage {
             Stage::WantAuthEqual => Stage::WantAuthResult,
             Stage::WantSpfEqual => Stage::WantSpfResult,
             Stage::WantDkimEqual => Stage::WantDkimResult,
+            Stage::WantDkimAtpsEqual => Stage::WantDkimAtpsResult,
             Stage::WantIpRevEqual => Stage::WantIpRevResult,
+            Stage::WantDmarcEqual => Stage::WantDmarcResult,
             _ => return false,
         };
         *self = new_stage;
@@ -206,13 +263,17 @@ enum ParseCurrentResultChoice<'hdr> {
     SmtpAuth(SmtpAuthResult<'hdr>),
     Spf(SpfResult<'hdr>),
     Dkim(DkimResult<'hdr>),
+    DkimAtps(DkimAtpsResult<'hdr>),
     IpRev(IpRevResult<'hdr>),
+    Dmarc(DmarcResult<'hdr>),
 }
 
 impl<'hdr> ParseCurrentResultChoice<'hdr> {
     fn set_reason(&mut self, reason: &'hdr str) {
-        if let ParseCurrentResultChoice::Dkim(ref mut dkim_res) = self {
-            dkim_res.reason = Some(reason);
+        match self {
+            ParseCurrentResultChoice::Dkim(ref mut dkim_res) => dkim_res.reason = Some(reason),
+            ParseCurrentResultChoice::DkimAtps(ref mut atps_res) => atps_res.reason = Some(reason),
+            _ => {}
         }
     }
 }
@@ -260,6 +321,16 @@ fn assign_result_code<'hdr>(
             *cur_res = new_res;
             Ok(())
         }
+        Stage::WantDkimAtpsResult => {
+            let code = DkimAtpsResultCode::try_from(token)?;
+            let atps_result = DkimAtpsResult {
+                code,
+                ..Default::default()
+            };
+            new_res.result = Some(ParseCurrentResultChoice::DkimAtps(atps_result));
+            *cur_res = new_res;
+            Ok(())
+        }
         Stage::WantIpRevResult => {
             let code = IpRevResultCode::try_from(token)?;
             let iprev_result = IpRevResult {
@@ -270,25 +341,40 @@ fn assign_result_code<'hdr>(
             *cur_res = new_res;
             Ok(())
         }
+        Stage::WantDmarcResult => {
+            let code = DmarcResultCode::try_from(token)?;
+            let dmarc_result = DmarcResult {
+                code,
+                ..Default::default()
+            };
+            new_res.result = Some(ParseCurrentResultChoice::Dmarc(dmarc_result));
+            *cur_res = new_res;
+            Ok(())
+        }
         _ => Err(AuthResultsError::InvalidResultStage),
     }
 }
 
+#[cfg(feature = "mail_parser")]
 impl<'hdr> From<&'hdr HeaderValue<'hdr>> for AuthenticationResults<'hdr> {
     fn from(hval: &'hdr HeaderValue<'hdr>) -> Self {
+        match hval.as_text() {
+            Some(text) => Self::from(text),
+            None => Self {
+                errors: vec![AuthResultsError::NoHeader],
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl<'hdr> From<&'hdr str> for AuthenticationResults<'hdr> {
+    fn from(text: &'hdr str) -> Self {
         let mut res = Self {
-            raw: hval.as_text(),
+            raw: Some(text),
             ..Default::default()
         };
 
-        let text = match hval.as_text() {
-            None => {
-                res.errors.push(AuthResultsError::NoHeader);
-                return res;
-            }
-            Some(text) => text,
-        };
-
         let mut host_lexer = HostVersionToken::lexer(text);
 
         let host = match parse_host_version(&mut host_lexer) {
@@ -324,10 +410,18 @@ impl<'hdr> From<&'hdr HeaderValue<'hdr>> for AuthenticationResults<'hdr> {
                     stage = Stage::WantDkimEqual;
                     raw_part_start = lexer.span().start;
                 }
+                Ok(AuthResultToken::DkimAtps) if stage == Stage::WantIdentifier => {
+                    stage = Stage::WantDkimAtpsEqual;
+                    raw_part_start = lexer.span().start;
+                }
                 Ok(AuthResultToken::IpRev) if stage == Stage::WantIdentifier => {
                     stage = Stage::WantIpRevEqual;
                     raw_part_start = lexer.span().start;
                 }
+                Ok(AuthResultToken::Dmarc) if stage == Stage::WantIdentifier => {
+                    stage = Stage::WantDmarcEqual;
+                    raw_part_start = lexer.span().start;
+                }
                 Ok(AuthResultToken::Equal) if stage.is_cur_expect_resultset_equal() => {
                     stage.equal_to_result();
                 }
@@ -382,11 +476,21 @@ impl<'hdr> From<&'hdr HeaderValue<'hdr>> for AuthenticationResults<'hdr> {
                                 Some(&lexer.source()[raw_part_start..lexer_end + raw_part_end]);
                             res.dkim_result.push(dkim_res)
                         }
+                        Some(ParseCurrentResultChoice::DkimAtps(mut atps_res)) => {
+                            atps_res.raw =
+                                Some(&lexer.source()[raw_part_start..lexer_end + raw_part_end]);
+                            res.dkim_atps_result.push(atps_res)
+                        }
                         Some(ParseCurrentResultChoice::IpRev(mut iprev_res)) => {
                             iprev_res.raw =
                                 Some(&lexer.source()[raw_part_start..lexer_end + raw_part_end]);
                             res.iprev_result.push(iprev_res)
                         }
+                        Some(ParseCurrentResultChoice::Dmarc(mut dmarc_res)) => {
+                            dmarc_res.raw =
+                                Some(&lexer.source()[raw_part_start..lexer_end + raw_part_end]);
+                            res.dmarc_result.push(dmarc_res)
+                        }
                         Some(ParseCurrentResultChoice::Spf(mut spf_res)) => {
                             spf_res.raw =
                                 Some(&lexer.source()[raw_part_start..lexer_end + raw_part_end]);
@@ -8,8 +8,11 @@ use crate::error::AuthResultsError;
 
 use logos::{Lexer, Logos};
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::ToString;
+
 #[derive(Debug, Logos)]
-#[logos(skip r"[ \r\n]+")]
+#[logos(skip r"[ \t\r\n]+")]
 pub enum HostVersionToken<'hdr> {
     #[token(";", priority = 1)]
     FieldSeparator,
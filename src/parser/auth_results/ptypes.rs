@@ -7,8 +7,6 @@ use logos::{Lexer, Logos};
 
 use crate::error::AuthResultsError;
 
-// mod dmarc
-
 //------------------------------------------------------------------------
 // SMTP Auth ptypes
 //------------------------------------------------------------------------
@@ -31,6 +29,20 @@ use dkim::dkim_property_key::{
 use dkim::dkim_property_value::{parse_dkim_header_property_value, DkimHeaderPropertyValueToken};
 use dkim::dkim_property_value::{parse_dkim_policy_property_value, DkimPolicyPropertyValueToken};
 
+//------------------------------------------------------------------------
+// DMARC ptypes
+//------------------------------------------------------------------------
+
+mod dmarc;
+use dmarc::dmarc_property_key::{
+    parse_dmarc_header_property_key, parse_dmarc_policy_property_key, DmarcHeaderPropertyKey,
+    DmarcHeaderPropertyKeyToken, DmarcPolicyPropertyKey, DmarcPolicyPropertyKeyToken,
+};
+use dmarc::dmarc_property_value::{
+    parse_dmarc_header_property_value, parse_dmarc_policy_property_value,
+    DmarcHeaderPropertyValueToken, DmarcPolicyPropertyValueToken,
+};
+
 //------------------------------------------------------------------------
 // IpRev ptypes
 //------------------------------------------------------------------------
@@ -69,7 +81,8 @@ pub enum PropTypeKey<'hdr> {
     SpfSmtp(SpfSmtpPropertyKey),
     IpRevPolicy(IpRevPolicyPropertyKey<'hdr>),
     IpRevSmtp(IpRevSmtpPropertyKey<'hdr>),
-    //Dmarc(DmarcPtype),
+    DmarcHeader(DmarcHeaderPropertyKey<'hdr>),
+    DmarcPolicy(DmarcPolicyPropertyKey<'hdr>),
 }
 
 #[derive(Debug, Default, PartialEq)]
@@ -82,6 +95,8 @@ pub enum PtypeChoice {
     IpRevPolicy,
     IpRevSmtp, // fastmail breaks RFC
     SpfSmtp,
+    DmarcHeader,
+    DmarcPolicy,
 }
 
 impl PtypeChoice {
@@ -95,6 +110,10 @@ impl PtypeChoice {
                 PtypeToken::PtypePolicy => Self::DkimPolicy,
                 _ => Self::Nothing,
             },
+            Some(ParseCurrentResultChoice::DkimAtps(_)) => match token {
+                PtypeToken::PtypeHeader => Self::DkimHeader,
+                _ => Self::Nothing,
+            },
             Some(ParseCurrentResultChoice::Spf(_)) => match token {
                 PtypeToken::PtypeSmtp => Self::SpfSmtp,
                 _ => Self::Nothing,
@@ -108,6 +127,11 @@ impl PtypeChoice {
                 PtypeToken::PtypeSmtp => Self::IpRevSmtp,
                 _ => Self::Nothing,
             },
+            Some(ParseCurrentResultChoice::Dmarc(_)) => match token {
+                PtypeToken::PtypeHeader => Self::DmarcHeader,
+                PtypeToken::PtypePolicy => Self::DmarcPolicy,
+                _ => Self::Nothing,
+            },
             _ => Self::Nothing,
         }
     }
@@ -227,22 +251,14 @@ pub fn parse_ptype_properties<'hdr>(
                     PtypeChoice::DkimHeader => {
                         let mut property_key_lexer =
                             DkimHeaderPropertyKeyToken::lexer(lexer.remainder());
-                        let property_key =
-                            match parse_dkim_header_property_key(&mut property_key_lexer) {
-                                Err(e) => return Err(e),
-                                Ok(property_key) => property_key,
-                            };
+                        let property_key = parse_dkim_header_property_key(&mut property_key_lexer)?;
                         lexer.bump(property_key_lexer.span().end);
                         PropTypeKey::DkimHeader(property_key)
                     }
                     PtypeChoice::DkimPolicy => {
                         let mut property_key_lexer =
                             DkimPolicyPropertyKeyToken::lexer(lexer.remainder());
-                        let property_key =
-                            match parse_dkim_policy_property_key(&mut property_key_lexer) {
-                                Err(e) => return Err(e),
-                                Ok(property_key) => property_key,
-                            };
+                        let property_key = parse_dkim_policy_property_key(&mut property_key_lexer)?;
                         lexer.bump(property_key_lexer.span().end);
                         PropTypeKey::DkimPolicy(property_key)
                     }
@@ -250,46 +266,47 @@ pub fn parse_ptype_properties<'hdr>(
                         let mut property_key_lexer =
                             IpRevPolicyPropertyKeyToken::lexer(lexer.remainder());
                         let property_key =
-                            match parse_iprev_policy_property_key(&mut property_key_lexer) {
-                                Err(e) => return Err(e),
-                                Ok(property_key) => property_key,
-                            };
+                            parse_iprev_policy_property_key(&mut property_key_lexer)?;
                         lexer.bump(property_key_lexer.span().end);
                         PropTypeKey::IpRevPolicy(property_key)
                     }
                     PtypeChoice::IpRevSmtp => {
                         let mut property_key_lexer =
                             IpRevSmtpPropertyKeyToken::lexer(lexer.remainder());
-                        let property_key =
-                            match parse_iprev_smtp_property_key(&mut property_key_lexer) {
-                                Err(e) => return Err(e),
-                                Ok(property_key) => property_key,
-                            };
+                        let property_key = parse_iprev_smtp_property_key(&mut property_key_lexer)?;
                         lexer.bump(property_key_lexer.span().end);
                         PropTypeKey::IpRevSmtp(property_key)
                     }
                     PtypeChoice::SpfSmtp => {
                         let mut property_key_lexer =
                             SpfSmtpPropertyKeyToken::lexer(lexer.remainder());
-                        let property_key =
-                            match parse_spf_smtp_property_key(&mut property_key_lexer) {
-                                Err(e) => return Err(e),
-                                Ok(property_key) => property_key,
-                            };
+                        let property_key = parse_spf_smtp_property_key(&mut property_key_lexer)?;
                         lexer.bump(property_key_lexer.span().end);
                         PropTypeKey::SpfSmtp(property_key)
                     }
                     PtypeChoice::AuthSmtp => {
                         let mut property_key_lexer =
                             AuthSmtpPropertyKeyToken::lexer(lexer.remainder());
-                        let property_key =
-                            match parse_auth_smtp_property_key(&mut property_key_lexer) {
-                                Err(e) => return Err(e),
-                                Ok(property_key) => property_key,
-                            };
+                        let property_key = parse_auth_smtp_property_key(&mut property_key_lexer)?;
                         lexer.bump(property_key_lexer.span().end);
                         PropTypeKey::AuthSmtp(property_key)
                     }
+                    PtypeChoice::DmarcHeader => {
+                        let mut property_key_lexer =
+                            DmarcHeaderPropertyKeyToken::lexer(lexer.remainder());
+                        let property_key =
+                            parse_dmarc_header_property_key(&mut property_key_lexer)?;
+                        lexer.bump(property_key_lexer.span().end);
+                        PropTypeKey::DmarcHeader(property_key)
+                    }
+                    PtypeChoice::DmarcPolicy => {
+                        let mut property_key_lexer =
+                            DmarcPolicyPropertyKeyToken::lexer(lexer.remainder());
+                        let property_key =
+                            parse_dmarc_policy_property_key(&mut property_key_lexer)?;
+                        lexer.bump(property_key_lexer.span().end);
+                        PropTypeKey::DmarcPolicy(property_key)
+                    }
                     _ => return Err(AuthResultsError::PropertiesNotImplemented),
                 };
                 stage = WantStage::Eq;
@@ -310,10 +327,7 @@ pub fn parse_ptype_properties<'hdr>(
             }
             Ok(PtypeToken::Equal) if stage == WantStage::ReasonEq => {
                 let mut reason_lexer = ReasonToken::lexer(lexer.remainder());
-                let reason_res = match parse_reason(&mut reason_lexer) {
-                    Err(e) => return Err(e),
-                    Ok(reason) => reason,
-                };
+                let reason_res = parse_reason(&mut reason_lexer)?;
                 if let Some(ref mut ref_choice) = cur_res {
                     ref_choice.set_reason(reason_res);
                 }
@@ -327,32 +341,25 @@ pub fn parse_ptype_properties<'hdr>(
                     PropTypeKey::DkimHeader(ref property) => {
                         let mut property_value_lexer =
                             DkimHeaderPropertyValueToken::lexer(lexer.remainder());
-                        let property_value = match parse_dkim_header_property_value(
-                            &mut property_value_lexer,
-                            property,
-                        ) {
-                            Err(e) => return Err(e),
-                            Ok(property_value) => property_value,
-                        };
+                        let property_value =
+                            parse_dkim_header_property_value(&mut property_value_lexer, property)?;
                         lexer.bump(property_value_lexer.span().end);
 
                         match cur_res {
                             Some(ParseCurrentResultChoice::Dkim(ref mut dkim_res)) => {
                                 dkim_res.set_header(&property_value);
                             }
+                            Some(ParseCurrentResultChoice::DkimAtps(ref mut atps_res)) => {
+                                atps_res.set_header(&property_value);
+                            }
                             _ => {}
                         }
                     }
                     PropTypeKey::DkimPolicy(ref property) => {
                         let mut property_value_lexer =
                             DkimPolicyPropertyValueToken::lexer(lexer.remainder());
-                        let property_value = match parse_dkim_policy_property_value(
-                            &mut property_value_lexer,
-                            property,
-                        ) {
-                            Err(e) => return Err(e),
-                            Ok(property_value) => property_value,
-                        };
+                        let property_value =
+                            parse_dkim_policy_property_value(&mut property_value_lexer, property)?;
                         lexer.bump(property_value_lexer.span().end);
 
                         match cur_res {
@@ -365,13 +372,8 @@ pub fn parse_ptype_properties<'hdr>(
                     PropTypeKey::IpRevPolicy(ref property) => {
                         let mut property_value_lexer =
                             IpRevPolicyPropertyValueToken::lexer(lexer.remainder());
-                        let property_value = match parse_iprev_policy_property_value(
-                            &mut property_value_lexer,
-                            property,
-                        ) {
-                            Err(e) => return Err(e),
-                            Ok(property_value) => property_value,
-                        };
+                        let property_value =
+                            parse_iprev_policy_property_value(&mut property_value_lexer, property)?;
                         lexer.bump(property_value_lexer.span().end);
 
                         match cur_res {
@@ -384,13 +386,8 @@ pub fn parse_ptype_properties<'hdr>(
                     PropTypeKey::IpRevSmtp(ref property) => {
                         let mut property_value_lexer =
                             IpRevSmtpPropertyValueToken::lexer(lexer.remainder());
-                        let property_value = match parse_iprev_smtp_property_value(
-                            &mut property_value_lexer,
-                            property,
-                        ) {
-                            Err(e) => return Err(e),
-                            Ok(property_value) => property_value,
-                        };
+                        let property_value =
+                            parse_iprev_smtp_property_value(&mut property_value_lexer, property)?;
                         lexer.bump(property_value_lexer.span().end);
 
                         match cur_res {
@@ -403,13 +400,8 @@ pub fn parse_ptype_properties<'hdr>(
                     PropTypeKey::SpfSmtp(ref property) => {
                         let mut property_value_lexer =
                             SpfSmtpPropertyValueToken::lexer(lexer.remainder());
-                        let property_value = match parse_spf_smtp_property_value(
-                            &mut property_value_lexer,
-                            property,
-                        ) {
-                            Err(e) => return Err(e),
-                            Ok(property_value) => property_value,
-                        };
+                        let property_value =
+                            parse_spf_smtp_property_value(&mut property_value_lexer, property)?;
                         lexer.bump(property_value_lexer.span().end);
 
                         match cur_res {
@@ -422,13 +414,8 @@ pub fn parse_ptype_properties<'hdr>(
                     PropTypeKey::AuthSmtp(ref property) => {
                         let mut property_value_lexer =
                             AuthSmtpPropertyValueToken::lexer(lexer.remainder());
-                        let property_value = match parse_auth_smtp_property_value(
-                            &mut property_value_lexer,
-                            property,
-                        ) {
-                            Err(e) => return Err(e),
-                            Ok(property_value) => property_value,
-                        };
+                        let property_value =
+                            parse_auth_smtp_property_value(&mut property_value_lexer, property)?;
                         lexer.bump(property_value_lexer.span().end);
 
                         match cur_res {
@@ -438,6 +425,34 @@ pub fn parse_ptype_properties<'hdr>(
                             _ => {}
                         }
                     }
+                    PropTypeKey::DmarcHeader(ref property) => {
+                        let mut property_value_lexer =
+                            DmarcHeaderPropertyValueToken::lexer(lexer.remainder());
+                        let property_value =
+                            parse_dmarc_header_property_value(&mut property_value_lexer, property)?;
+                        lexer.bump(property_value_lexer.span().end);
+
+                        match cur_res {
+                            Some(ParseCurrentResultChoice::Dmarc(ref mut dmarc_res)) => {
+                                dmarc_res.set_header(&property_value);
+                            }
+                            _ => {}
+                        }
+                    }
+                    PropTypeKey::DmarcPolicy(ref property) => {
+                        let mut property_value_lexer =
+                            DmarcPolicyPropertyValueToken::lexer(lexer.remainder());
+                        let property_value =
+                            parse_dmarc_policy_property_value(&mut property_value_lexer, property)?;
+                        lexer.bump(property_value_lexer.span().end);
+
+                        match cur_res {
+                            Some(ParseCurrentResultChoice::Dmarc(ref mut dmarc_res)) => {
+                                dmarc_res.set_policy(&property_value);
+                            }
+                            _ => {}
+                        }
+                    }
                     _ => {
                         return Err(AuthResultsError::PropertyValuesNotImplemented);
                     }
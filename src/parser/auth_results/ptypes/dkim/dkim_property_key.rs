@@ -151,6 +151,7 @@ pub enum DkimPolicyPropertyKeyToken<'hdr> {
     Unknown(&'hdr str),
 
     #[regex(r"\s+", |lex| lex.slice(), priority = 3)]
+    #[allow(dead_code)]
     WhiteSpaces(&'hdr str),
 }
 
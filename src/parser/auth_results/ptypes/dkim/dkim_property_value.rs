@@ -106,9 +106,11 @@ pub enum DkimPolicyPropertyValueToken<'hdr> {
     CommentStart,
 
     #[regex(r"\s+", |lex| lex.slice(), priority = 2)]
+    #[allow(dead_code)]
     WhiteSpaces(&'hdr str),
 
     #[regex(r"\n+", |lex| lex.slice(), priority = 3)]
+    #[allow(dead_code)]
     LineFeeds(&'hdr str),
 
     #[regex(r"[A-Za-z0-9]+", |lex| lex.slice(), priority = 4)]
@@ -1,19 +1,6 @@
-//! dmarc ptype and it's properties
+//! Parsing dmarc property types & values
 
-#[derive(Debug)]
-pub enum DmarcProperty<'hdr> {
-    /// RFC 7489
-    HeaderFrom(&'hdr str),
-    /// RFC 7489
-    PolicyDmarc(&'hdr str),
-}
+use super::*;
 
-/*
-#[derive(Debug)]
-pub enum DmarcPtype {
-    Header,
-    HeaderDotFrom,
-    Policy,
-    PolicyDotDmarc,
-}
-*/
+pub mod dmarc_property_key;
+pub mod dmarc_property_value;
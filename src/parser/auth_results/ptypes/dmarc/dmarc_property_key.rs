@@ -0,0 +1,171 @@
+//! Parsing dmarc property types & values
+
+use super::{parse_comment, CommentToken};
+use crate::error::AuthResultsError;
+use logos::{Lexer, Logos};
+
+//------------------------------
+// dmarc header.* RFC 7489 s. 11.2
+//------------------------------
+
+#[derive(Debug, PartialEq)]
+pub enum DmarcHeaderPropertyKey<'hdr> {
+    From,
+    Unknown(&'hdr str),
+}
+
+impl<'hdr> TryFrom<DmarcHeaderPropertyKeyToken<'hdr>> for DmarcHeaderPropertyKey<'hdr> {
+    type Error = AuthResultsError<'hdr>;
+    fn try_from(token: DmarcHeaderPropertyKeyToken<'hdr>) -> Result<Self, Self::Error> {
+        let okk = match token {
+            DmarcHeaderPropertyKeyToken::From => Self::From,
+            DmarcHeaderPropertyKeyToken::Unknown(key) => Self::Unknown(key),
+            _ => return Err(AuthResultsError::ParsePtypeBugInvalidProperty),
+        };
+        Ok(okk)
+    }
+}
+
+#[derive(Debug, Logos)]
+pub enum DmarcHeaderPropertyKeyToken<'hdr> {
+    #[token("from", priority = 100)]
+    From,
+
+    #[token("(", priority = 2)]
+    CommentStart,
+
+    #[regex(r"[a-zA-Z_-]+", priority = 1)]
+    Unknown(&'hdr str),
+
+    #[regex(r"\s+", |lex| lex.slice(), priority = 6)]
+    #[allow(dead_code)]
+    WhiteSpaces(&'hdr str),
+}
+
+pub fn parse_dmarc_header_property_key<'hdr>(
+    lexer: &mut Lexer<'hdr, DmarcHeaderPropertyKeyToken<'hdr>>,
+) -> Result<DmarcHeaderPropertyKey<'hdr>, AuthResultsError<'hdr>> {
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(DmarcHeaderPropertyKeyToken::From | DmarcHeaderPropertyKeyToken::Unknown(_)) => {
+                let property = token.map_err(|_| AuthResultsError::ParsePtypeBugPropertyGating)?;
+                let mapped_property_res: Result<
+                    DmarcHeaderPropertyKey<'hdr>,
+                    AuthResultsError<'hdr>,
+                > = property.try_into();
+                let mapped_property = mapped_property_res
+                    .map_err(|_| AuthResultsError::ParsePtypeBugInvalidProperty)?;
+                return Ok(mapped_property);
+            }
+            Ok(DmarcHeaderPropertyKeyToken::WhiteSpaces(_)) => {
+                // cont
+            }
+            Ok(DmarcHeaderPropertyKeyToken::CommentStart) => {
+                let mut comment_lexer = CommentToken::lexer(lexer.remainder());
+                match parse_comment(&mut comment_lexer) {
+                    Ok(_comment) => {}
+                    Err(e) => return Err(AuthResultsError::ParseComment(e)),
+                }
+                lexer.bump(comment_lexer.span().end);
+            }
+            _ => {
+                let cut_slice = &lexer.source()[lexer.span().start..];
+                let cut_span = &lexer.source()[lexer.span().start..lexer.span().end];
+
+                let detail = crate::error::ParsingDetail {
+                    component: "dmarc_property_key",
+                    span_start: lexer.span().start,
+                    span_end: lexer.span().end,
+                    source: lexer.source(),
+                    clipped_span: cut_span,
+                    clipped_remaining: cut_slice,
+                };
+                return Err(AuthResultsError::ParsingDetailed(detail));
+            }
+        }
+    }
+    Err(AuthResultsError::RunAwayDmarcPropertyKey)
+}
+
+//------------------------------
+// dmarc policy.* RFC 7489 s. 11.2
+//------------------------------
+
+#[derive(Debug, PartialEq)]
+pub enum DmarcPolicyPropertyKey<'hdr> {
+    Dmarc,
+    Unknown(&'hdr str),
+}
+
+impl<'hdr> TryFrom<DmarcPolicyPropertyKeyToken<'hdr>> for DmarcPolicyPropertyKey<'hdr> {
+    type Error = AuthResultsError<'hdr>;
+    fn try_from(token: DmarcPolicyPropertyKeyToken<'hdr>) -> Result<Self, Self::Error> {
+        let okk = match token {
+            DmarcPolicyPropertyKeyToken::Dmarc => Self::Dmarc,
+            DmarcPolicyPropertyKeyToken::Unknown(key) => Self::Unknown(key),
+            _ => return Err(AuthResultsError::ParsePtypeBugInvalidProperty),
+        };
+        Ok(okk)
+    }
+}
+
+#[derive(Debug, Logos)]
+pub enum DmarcPolicyPropertyKeyToken<'hdr> {
+    #[token("dmarc", priority = 100)]
+    Dmarc,
+
+    #[token("(", priority = 2)]
+    CommentStart,
+
+    #[regex(r"[a-zA-Z_-]+", priority = 1)]
+    Unknown(&'hdr str),
+
+    #[regex(r"\s+", |lex| lex.slice(), priority = 6)]
+    #[allow(dead_code)]
+    WhiteSpaces(&'hdr str),
+}
+
+pub fn parse_dmarc_policy_property_key<'hdr>(
+    lexer: &mut Lexer<'hdr, DmarcPolicyPropertyKeyToken<'hdr>>,
+) -> Result<DmarcPolicyPropertyKey<'hdr>, AuthResultsError<'hdr>> {
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(DmarcPolicyPropertyKeyToken::Dmarc | DmarcPolicyPropertyKeyToken::Unknown(_)) => {
+                let property = token.map_err(|_| AuthResultsError::ParsePtypeBugPropertyGating)?;
+                let mapped_property_res: Result<
+                    DmarcPolicyPropertyKey<'hdr>,
+                    AuthResultsError<'hdr>,
+                > = property.try_into();
+                let mapped_property = mapped_property_res
+                    .map_err(|_| AuthResultsError::ParsePtypeBugInvalidProperty)?;
+                return Ok(mapped_property);
+            }
+            Ok(DmarcPolicyPropertyKeyToken::WhiteSpaces(_)) => {
+                // cont
+            }
+            Ok(DmarcPolicyPropertyKeyToken::CommentStart) => {
+                let mut comment_lexer = CommentToken::lexer(lexer.remainder());
+                match parse_comment(&mut comment_lexer) {
+                    Ok(_comment) => {}
+                    Err(e) => return Err(AuthResultsError::ParseComment(e)),
+                }
+                lexer.bump(comment_lexer.span().end);
+            }
+            _ => {
+                let cut_slice = &lexer.source()[lexer.span().start..];
+                let cut_span = &lexer.source()[lexer.span().start..lexer.span().end];
+
+                let detail = crate::error::ParsingDetail {
+                    component: "dmarc_property_key",
+                    span_start: lexer.span().start,
+                    span_end: lexer.span().end,
+                    source: lexer.source(),
+                    clipped_span: cut_span,
+                    clipped_remaining: cut_slice,
+                };
+                return Err(AuthResultsError::ParsingDetailed(detail));
+            }
+        }
+    }
+    Err(AuthResultsError::RunAwayDmarcPropertyKey)
+}
@@ -0,0 +1,171 @@
+//! Parsing dmarc property values
+
+use super::{parse_comment, CommentToken};
+use crate::error::AuthResultsError;
+use logos::{Lexer, Logos};
+
+//-----------------------------
+// dmarc header.*
+//-----------------------------
+
+use super::DmarcHeaderPropertyKey;
+use crate::dmarc::ptypes::DmarcHeader;
+
+#[derive(Debug, Logos)]
+pub enum DmarcHeaderPropertyValueToken<'hdr> {
+    #[token("(", priority = 1)]
+    CommentStart,
+
+    #[regex(r#"[^(\s\r\n\t;]+"#, |lex| lex.slice(), priority = 2)]
+    MaybeValue(&'hdr str),
+
+    #[regex(r"[\s\r\n\t]+", |lex| lex.slice(), priority = 3)]
+    #[allow(dead_code)]
+    Whs(&'hdr str),
+}
+
+impl<'hdr> DmarcHeader<'hdr> {
+    fn from_parsed(pkey: &DmarcHeaderPropertyKey<'hdr>, val: &'hdr str) -> Self {
+        match pkey {
+            DmarcHeaderPropertyKey::From => DmarcHeader::From(val),
+            DmarcHeaderPropertyKey::Unknown(key) => DmarcHeader::Unknown(key, val),
+        }
+    }
+}
+
+pub fn parse_dmarc_header_property_value<'hdr>(
+    lexer: &mut Lexer<'hdr, DmarcHeaderPropertyValueToken<'hdr>>,
+    property_key: &DmarcHeaderPropertyKey<'hdr>,
+) -> Result<DmarcHeader<'hdr>, AuthResultsError<'hdr>> {
+    let mut cur_res: Option<DmarcHeader<'hdr>> = None;
+
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(DmarcHeaderPropertyValueToken::MaybeValue(val)) => {
+                cur_res = Some(DmarcHeader::from_parsed(property_key, val));
+                break;
+            }
+            Ok(DmarcHeaderPropertyValueToken::CommentStart) => {
+                let mut comment_lexer = CommentToken::lexer(lexer.remainder());
+                match parse_comment(&mut comment_lexer) {
+                    Ok(_comment) => {}
+                    Err(e) => return Err(AuthResultsError::ParseComment(e)),
+                }
+                lexer.bump(comment_lexer.span().end);
+            }
+            Ok(DmarcHeaderPropertyValueToken::Whs(_)) => {
+                // cont
+            }
+            _ => {
+                let cut_slice = &lexer.source()[lexer.span().start..];
+                let cut_span = &lexer.source()[lexer.span().start..lexer.span().end];
+
+                let detail = crate::error::ParsingDetail {
+                    component: "dmarc_property_value",
+                    span_start: lexer.span().start,
+                    span_end: lexer.span().end,
+                    source: lexer.source(),
+                    clipped_span: cut_span,
+                    clipped_remaining: cut_slice,
+                };
+                return Err(AuthResultsError::ParsingDetailed(detail));
+            }
+        }
+    }
+
+    if let Some(value) = cur_res {
+        return Ok(value);
+    }
+
+    Err(AuthResultsError::RunAwayDmarcPropertyValue)
+}
+
+//-----------------------------
+// dmarc policy.*
+//-----------------------------
+
+use super::DmarcPolicyPropertyKey;
+use crate::dmarc::ptypes::DmarcPolicyProperty;
+
+#[derive(Debug, Logos)]
+pub enum DmarcPolicyPropertyValueToken<'hdr> {
+    #[token("(", priority = 1)]
+    CommentStart,
+
+    #[regex(r#"[^(\s\r\n\t;]+"#, |lex| lex.slice(), priority = 2)]
+    MaybeValue(&'hdr str),
+
+    #[regex(r"[\s\r\n\t]+", |lex| lex.slice(), priority = 3)]
+    #[allow(dead_code)]
+    Whs(&'hdr str),
+}
+
+impl<'hdr> DmarcPolicyProperty<'hdr> {
+    fn from_parsed(pkey: &DmarcPolicyPropertyKey<'hdr>, val: &'hdr str) -> Self {
+        match pkey {
+            DmarcPolicyPropertyKey::Dmarc => DmarcPolicyProperty::Dmarc(val),
+            DmarcPolicyPropertyKey::Unknown(key) => DmarcPolicyProperty::Unknown(key, val),
+        }
+    }
+}
+
+pub fn parse_dmarc_policy_property_value<'hdr>(
+    lexer: &mut Lexer<'hdr, DmarcPolicyPropertyValueToken<'hdr>>,
+    property_key: &DmarcPolicyPropertyKey<'hdr>,
+) -> Result<DmarcPolicyProperty<'hdr>, AuthResultsError<'hdr>> {
+    let mut cur_res: Option<DmarcPolicyProperty<'hdr>> = None;
+
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(DmarcPolicyPropertyValueToken::MaybeValue(val)) => {
+                cur_res = Some(DmarcPolicyProperty::from_parsed(property_key, val));
+                break;
+            }
+            Ok(DmarcPolicyPropertyValueToken::CommentStart) => {
+                let mut comment_lexer = CommentToken::lexer(lexer.remainder());
+                match parse_comment(&mut comment_lexer) {
+                    Ok(_comment) => {}
+                    Err(e) => return Err(AuthResultsError::ParseComment(e)),
+                }
+                lexer.bump(comment_lexer.span().end);
+            }
+            Ok(DmarcPolicyPropertyValueToken::Whs(_)) => {
+                // cont
+            }
+            _ => {
+                let cut_slice = &lexer.source()[lexer.span().start..];
+                let cut_span = &lexer.source()[lexer.span().start..lexer.span().end];
+
+                let detail = crate::error::ParsingDetail {
+                    component: "dmarc_property_value",
+                    span_start: lexer.span().start,
+                    span_end: lexer.span().end,
+                    source: lexer.source(),
+                    clipped_span: cut_span,
+                    clipped_remaining: cut_slice,
+                };
+                return Err(AuthResultsError::ParsingDetailed(detail));
+            }
+        }
+    }
+
+    if let Some(value) = cur_res {
+        return Ok(value);
+    }
+
+    Err(AuthResultsError::RunAwayDmarcPropertyValue)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_comment() {
+        let mut lexer = DmarcHeaderPropertyValueToken::lexer("(foobar) example.com");
+        let res = parse_dmarc_header_property_value(&mut lexer, &DmarcHeaderPropertyKey::From);
+
+        assert_eq!(res, Ok(DmarcHeader::From("example.com")));
+    }
+}
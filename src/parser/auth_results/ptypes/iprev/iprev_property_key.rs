@@ -38,6 +38,7 @@ pub enum IpRevPolicyPropertyKeyToken<'hdr> {
     Unknown(&'hdr str),
 
     #[regex(r"\s+", |lex| lex.slice(), priority = 6)]
+    #[allow(dead_code)]
     WhiteSpaces(&'hdr str),
 }
 
@@ -115,6 +116,7 @@ pub enum IpRevSmtpPropertyKeyToken<'hdr> {
     CommentStart,
 
     #[regex(r"\s+", |lex| lex.slice(), priority = 6)]
+    #[allow(dead_code)]
     WhiteSpaces(&'hdr str),
 }
 
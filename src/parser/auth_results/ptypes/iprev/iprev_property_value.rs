@@ -20,6 +20,7 @@ pub enum IpRevPolicyPropertyValueToken<'hdr> {
     MaybeValue(&'hdr str),
 
     #[regex(r"[\s\r\n\t]+", |lex| lex.slice(), priority = 3)]
+    #[allow(dead_code)]
     Whs(&'hdr str),
 }
 
@@ -95,6 +96,7 @@ pub enum IpRevSmtpPropertyValueToken<'hdr> {
     MaybeValue(&'hdr str),
 
     #[regex(r"[\s\r\n\t]+", |lex| lex.slice(), priority = 3)]
+    #[allow(dead_code)]
     Whs(&'hdr str),
 }
 
@@ -41,6 +41,7 @@ pub enum SpfSmtpPropertyKeyToken<'hdr> {
     CommentStart,
 
     #[regex(r"\s+", |lex| lex.slice(), priority = 6)]
+    #[allow(dead_code)]
     WhiteSpaces(&'hdr str),
 }
 
@@ -17,6 +17,7 @@ pub enum SpfSmtpPropertyValueToken<'hdr> {
     MaybeValue(&'hdr str),
 
     #[regex(r"[\s\r\n\t]+", |lex| lex.slice(), priority = 3)]
+    #[allow(dead_code)]
     Whs(&'hdr str),
 }
 
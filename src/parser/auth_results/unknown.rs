@@ -17,6 +17,7 @@ pub enum UnknownToken<'hdr> {
     FieldSep,
 
     #[regex(r"[^(;]+", |lex| lex.slice())]
+    #[allow(dead_code)]
     EverythingElse(&'hdr str),
 }
 
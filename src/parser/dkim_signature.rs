@@ -2,13 +2,20 @@
 
 use logos::{Lexer, Logos};
 
+use crate::dkim::copied_headers::parse_copied_headers;
+use crate::dkim::parse_query_methods;
 use crate::dkim::*;
 
-use crate::error::{DkimSignatureError, DkimTagValueError};
+use crate::error::{DkimSignatureError, DkimTagDiagnostic, DkimTagValueError};
+
+use core::ops::Range;
 
 #[cfg(feature = "mail_parser")]
 use mail_parser::HeaderValue;
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
 #[derive(Debug, Logos)]
 pub enum DkimFieldValueToken<'hdr> {
     #[regex(r"[^;]+", |lex| lex.slice(), priority = 1)]
@@ -71,6 +78,18 @@ pub enum DkimFieldKeyToken<'hdr> {
     #[token("b", priority = 2)]
     TagB,
 
+    // RFC 6541 - must win over unknown tags
+    #[token("atps", priority = 4)]
+    TagAtps,
+
+    // RFC 6541 - must win over unknown tags
+    #[token("atpsh", priority = 4)]
+    TagAtpsh,
+
+    // RFC 6651 - must win over unknown tags
+    #[token("r", priority = 4)]
+    TagR,
+
     // Allows everything else alpha than above as unknown tags
     #[regex(r"([dvacdhilqtxvz][a-z]+|b[a-gi-z]|[efgjkmnopryu][a-z]*)", |lex| lex.slice(), priority = 3)]
     MaybeTag(&'hdr str),
@@ -92,6 +111,9 @@ pub enum DkimTagChoice<'hdr> {
     T,
     X,
     Z,
+    Atps,
+    Atpsh,
+    R,
     // RFC 6376 s. 3.2 Unrecognised tags MUST be ignored
     Unknown(&'hdr str),
 }
@@ -113,6 +135,9 @@ impl<'hdr> DkimTagChoice<'hdr> {
             DkimFieldKeyToken::TagT => Self::T,
             DkimFieldKeyToken::TagX => Self::X,
             DkimFieldKeyToken::TagZ => Self::Z,
+            DkimFieldKeyToken::TagAtps => Self::Atps,
+            DkimFieldKeyToken::TagAtpsh => Self::Atpsh,
+            DkimFieldKeyToken::TagR => Self::R,
             DkimFieldKeyToken::MaybeTag(tag) => Self::Unknown(tag),
             _ => return None,
         };
@@ -134,32 +159,54 @@ struct ParsedDkimSignature<'hdr> {
     pub v: Option<DkimVersion<'hdr>>,
     /// Algorithm
     pub a: Option<DkimAlgorithm<'hdr>>,
-    /// Signature data (base64)
-    pub b: Option<&'hdr str>,
-    /// Hash of canonicalized body part of the message as limited by the 'l='
-    pub bh: Option<&'hdr str>,
+    /// Signature data decoded from base64
+    pub b: Option<DkimBase64>,
+    /// Hash of canonicalized body part of the message as limited by the 'l=' decoded from base64
+    pub bh: Option<DkimBase64>,
     /// Message canonicalization informs the verifier of the type of canonicalization used to prepare the message for signing. See s.3.4
     pub c: Option<DkimCanonicalization<'hdr>>,
     /// The SDID claiming responsibility for an introduction of a message into the mail stream
     pub d: Option<&'hdr str>,
     /// Signed header fields separated by colon ':' - see 'h='
-    pub h: Option<&'hdr str>,
+    pub h: Option<DkimSignedHeaders<'hdr>>,
     /// The Agent or User Identifier (AUID) on behalf of which the SDID is taking responsibility.
-    pub i: Option<&'hdr str>,
+    pub i: Option<DkimAuid<'hdr>>,
     /// Body length limit - see misuse on RFC 6376 s. 8.2
-    pub l: Option<&'hdr str>,
+    pub l: Option<u64>,
     /// Query methods - currently only DnsTxt
-    pub q: Option<&'hdr str>,
+    pub q: Option<Vec<DkimQueryMethod<'hdr>>>,
     /// The selector subdividing the namespace for the "d=" (domain) tag
     pub s: Option<&'hdr str>,
     /// Recommended - Signature Timestamp
-    pub t: Option<DkimTimestamp<'hdr>>,
+    pub t: Option<DkimTimestamp>,
     /// Recommended - Signature Expiration
-    pub x: Option<DkimTimestamp<'hdr>>,
+    pub x: Option<DkimTimestamp>,
     /// Copied header fields
-    pub z: Option<&'hdr str>,
+    pub z: Option<Vec<DkimCopiedHeader<'hdr>>>,
+    /// Authorized Third-Party Signer Author Domain - RFC 6541
+    pub atps: Option<&'hdr str>,
+    /// Authorized Third-Party Signer hash algorithm - RFC 6541
+    pub atpsh: Option<DkimAtpsHash<'hdr>>,
+    /// Reporting requested - RFC 6651
+    pub r: Option<&'hdr str>,
+    /// Unknown tags
+    pub unknown_tags: Vec<DkimHeader<'hdr>>,
     /// Raw unparsed
     pub raw: Option<&'hdr str>,
+    /// Spans of the successfully parsed tag-specs
+    pub spans: Vec<(&'hdr str, Range<usize>)>,
+    /// Tags seen so far including those whose value failed to parse
+    pub seen: Vec<DkimTagChoice<'hdr>>,
+}
+
+// RFC 6376 s. 3.5 sig-l-tag = 1*76DIGIT - anything beyond u64 is not supported
+fn parse_body_length(val: &str) -> Result<u64, DkimTagValueError> {
+    match val.bytes().all(|b| b.is_ascii_digit()) {
+        true => val
+            .parse::<u64>()
+            .map_err(|_| DkimTagValueError::InvalidBodyLength),
+        false => Err(DkimTagValueError::InvalidBodyLength),
+    }
 }
 
 impl<'hdr> ParsedDkimSignature<'hdr> {
@@ -168,157 +215,449 @@ impl<'hdr> ParsedDkimSignature<'hdr> {
         tag: DkimTagChoice<'hdr>,
         val: &'hdr str,
     ) -> Result<(), DkimTagValueError> {
+        // RFC 6376 s. 3.2 Tags with duplicate names MUST NOT occur within a single tag-list
+        // Seen before parsing the value so a duplicate of an invalid tag is still caught
+        if self.seen.contains(&tag) {
+            return Err(DkimTagValueError::Duplicate);
+        }
+        self.seen.push(tag.clone());
+        // RFC 6376 s. 3.2 tag-spec allows FWS around the value
+        let val = val.trim();
+        if val.is_empty() && !matches!(tag, DkimTagChoice::Unknown(_)) {
+            return Err(DkimTagValueError::EmptyValue);
+        }
         match tag {
             DkimTagChoice::V => self.v = Some(val.try_into()?),
             DkimTagChoice::A => self.a = Some(val.try_into()?),
-            DkimTagChoice::B => self.b = Some(val),
-            DkimTagChoice::Bh => self.bh = Some(val),
+            DkimTagChoice::B => self.b = Some(val.try_into()?),
+            DkimTagChoice::Bh => self.bh = Some(val.try_into()?),
             DkimTagChoice::C => self.c = Some(val.try_into()?),
             DkimTagChoice::D => self.d = Some(val),
-            DkimTagChoice::H => self.h = Some(val),
-            DkimTagChoice::I => self.i = Some(val),
-            DkimTagChoice::L => self.l = Some(val),
-            DkimTagChoice::Q => self.q = Some(val),
+            DkimTagChoice::H => self.h = Some(val.try_into()?),
+            DkimTagChoice::I => self.i = Some(val.try_into()?),
+            DkimTagChoice::L => self.l = Some(parse_body_length(val)?),
+            DkimTagChoice::Q => self.q = Some(parse_query_methods(val)?),
             DkimTagChoice::S => self.s = Some(val),
             DkimTagChoice::T => self.t = Some(val.try_into()?),
             DkimTagChoice::X => self.x = Some(val.try_into()?),
-            DkimTagChoice::Z => self.z = Some(val),
-            // RFC 6376 s. 3.2 Unrecognised tags MUST be ignored
-            DkimTagChoice::Unknown(_) => {}
+            DkimTagChoice::Z => self.z = Some(parse_copied_headers(val)?),
+            DkimTagChoice::Atps => self.atps = Some(val),
+            DkimTagChoice::Atpsh => self.atpsh = Some(val.into()),
+            DkimTagChoice::R => self.r = Some(val),
+            // RFC 6376 s. 3.2 Unrecognised tags MUST be ignored - kept for the consumer
+            DkimTagChoice::Unknown(name) => self.unknown_tags.push(DkimHeader::Unknown(name, val)),
         }
         Ok(())
     }
+    fn span_of(&self, name: &str) -> Option<Range<usize>> {
+        self.spans
+            .iter()
+            .find(|(tag, _)| *tag == name)
+            .map(|(_, span)| span.clone())
+    }
+    // Validate required and cross-tag constraints into the final DkimSignature
+    fn finalize(self, diagnostics: &mut Diagnostics<'hdr>) -> Option<DkimSignature<'hdr>> {
+        // Required fields must be present
+        let required_missing = [
+            ("v", self.v.is_none(), DkimSignatureError::MissingVersion),
+            ("a", self.a.is_none(), DkimSignatureError::MissingAlgorithm),
+            ("b", self.b.is_none(), DkimSignatureError::MissingSignature),
+            ("bh", self.bh.is_none(), DkimSignatureError::MissingBodyHash),
+            (
+                "d",
+                self.d.is_none(),
+                DkimSignatureError::MissingResponsibleSdid,
+            ),
+            (
+                "h",
+                self.h.is_none(),
+                DkimSignatureError::MissingSignedHeaderFields,
+            ),
+            ("s", self.s.is_none(), DkimSignatureError::MissingSelector),
+        ];
+        for (tag, missing, error) in required_missing {
+            if missing && !diagnostics.push(Some(tag), None, error) {
+                return None;
+            }
+        }
+        // RFC 6376 s. 3.5 x= MUST be greater than t= when both are present
+        if let (Some(t), Some(x)) = (self.t, self.x) {
+            if x < t
+                && !diagnostics.push(
+                    Some("x"),
+                    self.span_of("x"),
+                    DkimTagValueError::ExpirationBeforeTimestamp.into(),
+                )
+            {
+                return None;
+            }
+        }
+        // RFC 6376 s. 3.5 i= domain MUST be the same as or a subdomain of d=
+        if let (Some(auid), Some(sdid)) = (&self.i, self.d) {
+            if !auid.is_within(sdid)
+                && !diagnostics.push(
+                    Some("i"),
+                    self.span_of("i"),
+                    DkimTagValueError::AuidOutsideSdid.into(),
+                )
+            {
+                return None;
+            }
+        }
+        if !diagnostics.errors.is_empty() {
+            return None;
+        }
+        // All required are present when no errors were seen
+        Some(DkimSignature {
+            v: self.v?,
+            a: self.a?,
+            b: self.b?,
+            bh: self.bh?,
+            d: self.d?,
+            h: self.h?,
+            s: self.s?,
+            // Optional c, i, l, q, t, x, z, atps, atpsh, r
+            c: self.c.unwrap_or_default(),
+            i: self.i,
+            l: self.l,
+            q: self.q,
+            t: self.t,
+            x: self.x,
+            z: self.z,
+            atps: self.atps,
+            atpsh: self.atpsh,
+            r: self.r,
+            unknown_tags: self.unknown_tags,
+            raw: self.raw,
+        })
+    }
 }
 
-// TODO: It would be helpful to highlight all errors
-impl<'hdr> TryFrom<ParsedDkimSignature<'hdr>> for DkimSignature<'hdr> {
-    type Error = DkimSignatureError<'hdr>;
+// Collects the tag errors - when not collecting all the first error stops parsing
+struct Diagnostics<'hdr> {
+    all: bool,
+    errors: Vec<DkimTagDiagnostic<'hdr>>,
+}
 
-    fn try_from(p: ParsedDkimSignature<'hdr>) -> Result<Self, Self::Error> {
-        // Required fields must be present
-        let version = match p.v {
-            Some(val) => val,
-            None => return Err(DkimSignatureError::MissingVersion),
-        };
-        let algorithm = match p.a {
-            Some(val) => val,
-            None => return Err(DkimSignatureError::MissingAlgorithm),
-        };
-        let signature = match p.b {
-            Some(val) => val,
-            None => return Err(DkimSignatureError::MissingSignature),
-        };
-        let body_hash = match p.bh {
-            Some(val) => val,
-            None => return Err(DkimSignatureError::MissingBodyHash),
-        };
-        let responsible_sdid = match p.d {
-            Some(val) => val,
-            None => return Err(DkimSignatureError::MissingResponsibleSdid),
-        };
-        let signed_header_fields = match p.h {
-            Some(val) => val,
-            None => return Err(DkimSignatureError::MissingSignedHeaderFields),
-        };
-        let selector = match p.s {
-            Some(val) => val,
-            None => return Err(DkimSignatureError::MissingSelector),
-        };
-        // Optional c, i, l, q, s, t, x, z,
-        let c = p.c;
-        let i = p.i;
-        let l = p.l;
-        let q = p.q;
-        let t = p.t;
-        let x = p.x;
-        let z = p.z;
-        let raw = p.raw;
-        Ok(Self {
-            v: version,
-            a: algorithm,
-            b: signature,
-            bh: body_hash,
-            d: responsible_sdid,
-            h: signed_header_fields,
-            s: selector,
-            c,
-            i,
-            l,
-            q,
-            t,
-            x,
-            z,
-            raw,
-        })
+impl<'hdr> Diagnostics<'hdr> {
+    // Returns whether parsing should continue
+    fn push(
+        &mut self,
+        tag: Option<&'hdr str>,
+        span: Option<Range<usize>>,
+        error: DkimSignatureError<'hdr>,
+    ) -> bool {
+        self.errors.push(DkimTagDiagnostic { tag, span, error });
+        self.all
     }
 }
 
-impl<'hdr> TryFrom<&'hdr HeaderValue<'hdr>> for DkimSignature<'hdr> {
-    type Error = DkimSignatureError<'hdr>;
+fn parsing_detail<'hdr>(lexer: &Lexer<'hdr, DkimFieldKeyToken<'hdr>>) -> DkimSignatureError<'hdr> {
+    let cut_slice = &lexer.source()[lexer.span().start..];
+    let cut_span = &lexer.source()[lexer.span().start..lexer.span().end];
 
-    fn try_from(hval: &'hdr HeaderValue<'hdr>) -> Result<Self, Self::Error> {
-        let text = match hval.as_text() {
-            None => return Err(DkimSignatureError::NoTagFound),
-            Some(text) => text,
-        };
+    let detail = crate::error::ParsingDetail {
+        component: "parse_dkim_signature",
+        span_start: lexer.span().start,
+        span_end: lexer.span().end,
+        source: lexer.source(),
+        clipped_span: cut_span,
+        clipped_remaining: cut_slice,
+    };
 
-        let mut tag_lexer = DkimFieldKeyToken::lexer(text);
-        let mut stage = Stage::WantTag;
-        let mut res = ParsedDkimSignature {
-            raw: Some(text),
-            ..Default::default()
-        };
+    DkimSignatureError::ParsingDetailed(detail)
+}
 
-        while let Some(token) = tag_lexer.next() {
-            match token {
-                Ok(DkimFieldKeyToken::Equal) if stage != Stage::WantTag => {
-                    stage = match stage {
-                        Stage::WantEq(ref key_tag) => {
-                            let mut value_lexer: Lexer<'hdr, DkimFieldValueToken<'hdr>> =
-                                tag_lexer.morph();
-
-                            for value_token in value_lexer.by_ref() {
-                                match value_token {
-                                    Ok(DkimFieldValueToken::MaybeValue(value)) => {
-                                        res.add_tag_value(key_tag.clone(), value)?;
-                                    }
-                                    Ok(DkimFieldValueToken::FieldSep) => {
-                                        break;
+fn parse_dkim_signature<'hdr>(
+    text: &'hdr str,
+    diagnostics: &mut Diagnostics<'hdr>,
+) -> Option<DkimSignature<'hdr>> {
+    let mut tag_lexer = DkimFieldKeyToken::lexer(text);
+    let mut stage = Stage::WantTag;
+    let mut res = ParsedDkimSignature {
+        raw: Some(text),
+        ..Default::default()
+    };
+
+    let mut tag_name: &'hdr str = "";
+    let mut tag_start = 0;
+
+    while let Some(token) = tag_lexer.next() {
+        match token {
+            Ok(DkimFieldKeyToken::Equal) if stage != Stage::WantTag => {
+                stage = match stage {
+                    Stage::WantEq(key_tag) => {
+                        let mut value_lexer: Lexer<'hdr, DkimFieldValueToken<'hdr>> =
+                            tag_lexer.morph();
+
+                        let mut tag_value: &'hdr str = "";
+                        let mut tag_end = value_lexer.span().end;
+
+                        while let Some(value_token) = value_lexer.next() {
+                            match value_token {
+                                Ok(DkimFieldValueToken::MaybeValue(value)) => {
+                                    tag_value = value;
+                                    tag_end = value_lexer.span().end;
+                                }
+                                Ok(DkimFieldValueToken::FieldSep) => {
+                                    break;
+                                }
+                                Err(_) => {
+                                    if !diagnostics.push(
+                                        Some(tag_name),
+                                        Some(tag_start..value_lexer.span().end),
+                                        DkimSignatureError::ParseValueUnmatch,
+                                    ) {
+                                        return None;
                                     }
-                                    Err(_) => return Err(DkimSignatureError::ParseValueUnmatch),
                                 }
                             }
-                            tag_lexer = value_lexer.morph();
-                            Stage::WantTag
                         }
-                        _ => return Err(DkimSignatureError::UnexpectedEqual),
-                    };
-                }
-                Ok(maybe_tag_token) if stage == Stage::WantTag => {
-                    let current_tag = DkimTagChoice::from_token(maybe_tag_token);
-                    stage = match current_tag {
-                        None => return Err(DkimSignatureError::NoTagFound),
-                        Some(tag) => Stage::WantEq(tag),
-                    };
+                        let span = tag_start..tag_end;
+                        match res.add_tag_value(key_tag, tag_value) {
+                            Ok(()) => res.spans.push((tag_name, span)),
+                            Err(e) => {
+                                if !diagnostics.push(Some(tag_name), Some(span), e.into()) {
+                                    return None;
+                                }
+                            }
+                        }
+                        tag_lexer = value_lexer.morph();
+                        Stage::WantTag
+                    }
+                    _ => {
+                        diagnostics.push(None, None, DkimSignatureError::UnexpectedEqual);
+                        return None;
+                    }
+                };
+            }
+            Ok(maybe_tag_token) if stage == Stage::WantTag => {
+                let current_tag = DkimTagChoice::from_token(maybe_tag_token);
+                stage = match current_tag {
+                    None => {
+                        if !diagnostics.push(
+                            None,
+                            Some(tag_lexer.span()),
+                            DkimSignatureError::NoTagFound,
+                        ) {
+                            return None;
+                        }
+                        Stage::WantTag
+                    }
+                    Some(tag) => {
+                        tag_name = tag_lexer.slice();
+                        tag_start = tag_lexer.span().start;
+                        Stage::WantEq(tag)
+                    }
+                };
+            }
+            _ => {
+                let span = tag_lexer.span();
+                if !diagnostics.push(None, Some(span), parsing_detail(&tag_lexer)) {
+                    return None;
                 }
-                _ => {
-                    let cut_slice = &tag_lexer.source()[tag_lexer.span().start..];
-                    let cut_span =
-                        &tag_lexer.source()[tag_lexer.span().start..tag_lexer.span().end];
-
-                    let detail = crate::error::ParsingDetail {
-                        component: "parse_dkim_signature",
-                        span_start: tag_lexer.span().start,
-                        span_end: tag_lexer.span().end,
-                        source: tag_lexer.source(),
-                        clipped_span: cut_span,
-                        clipped_remaining: cut_slice,
-                    };
-
-                    return Err(DkimSignatureError::ParsingDetailed(detail));
+                // Recover by skipping the rest of the broken tag-spec until the next ';'
+                let mut value_lexer: Lexer<'hdr, DkimFieldValueToken<'hdr>> = tag_lexer.morph();
+                for value_token in value_lexer.by_ref() {
+                    if let Ok(DkimFieldValueToken::FieldSep) = value_token {
+                        break;
+                    }
                 }
+                tag_lexer = value_lexer.morph();
+                stage = Stage::WantTag;
             }
         }
-        res.try_into()
+    }
+    res.finalize(diagnostics)
+}
+
+#[cfg(feature = "mail_parser")]
+impl<'hdr> TryFrom<&'hdr HeaderValue<'hdr>> for DkimSignature<'hdr> {
+    type Error = DkimSignatureError<'hdr>;
+
+    fn try_from(hval: &'hdr HeaderValue<'hdr>) -> Result<Self, Self::Error> {
+        match hval.as_text() {
+            None => Err(DkimSignatureError::NoTagFound),
+            Some(text) => text.try_into(),
+        }
+    }
+}
+
+/// Parse the DKIM-Signature header field value stopping at the first error
+impl<'hdr> TryFrom<&'hdr str> for DkimSignature<'hdr> {
+    type Error = DkimSignatureError<'hdr>;
+
+    fn try_from(text: &'hdr str) -> Result<Self, Self::Error> {
+        let mut diagnostics = Diagnostics {
+            all: false,
+            errors: vec![],
+        };
+        match parse_dkim_signature(text, &mut diagnostics) {
+            Some(signature) => Ok(signature),
+            None => match diagnostics.errors.into_iter().next() {
+                Some(diagnostic) => Err(diagnostic.error),
+                None => Err(DkimSignatureError::NoTagFound),
+            },
+        }
+    }
+}
+
+impl<'hdr> DkimSignature<'hdr> {
+    /// Parse the DKIM-Signature header field value in diagnostics mode
+    ///
+    /// Instead of stopping at the first error every missing, duplicate and
+    /// malformed tag is reported together with the span of the tag-spec.
+    pub fn diagnose(text: &'hdr str) -> Result<Self, Vec<DkimTagDiagnostic<'hdr>>> {
+        let mut diagnostics = Diagnostics {
+            all: true,
+            errors: vec![],
+        };
+        match parse_dkim_signature(text, &mut diagnostics) {
+            Some(signature) => Ok(signature),
+            None => Err(diagnostics.errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    const REQUIRED: &str =
+        "v=1; a=rsa-sha256; d=example.com; s=sel; h=From : To; bh=AAAA; b=AA\n AA;";
+
+    #[test]
+    fn typed_values() {
+        let text = "v=1; a=rsa-sha256; c=relaxed; d=example.com; s=brisbane;\n t=1117574938; x=1118006938; l=42; q=dns/txt;\n i=joe@football.example.com; h=From : To:SUBJECT;\n z=Subject:demo=20run|To:joe@example.com;\n bh=MTIzNDU2Nzg5MDEyMzQ1Njc4OTAxMjM0NTY3ODkwMTI=;\n b=dzdVyOfAKCdLXdJOc9G2q8LoXSlEniSbav+yuU4zGeeruD00lszZ\n VoG4ZHRNiYzR";
+        let sig = DkimSignature::try_from(text).unwrap();
+
+        assert_eq!(sig.t, Some(DkimTimestamp(1117574938)));
+        assert_eq!(sig.x, Some(DkimTimestamp(1118006938)));
+        assert_eq!(sig.l, Some(42));
+        assert_eq!(sig.q, Some(vec![DkimQueryMethod::DnsTxt]));
+        assert_eq!(sig.h.names, vec!["From", "To", "SUBJECT"]);
+        assert!(sig.h.contains("subject"));
+        assert_eq!(sig.bh.as_bytes(), b"12345678901234567890123456789012");
+        assert_eq!(
+            sig.b.to_base64(),
+            "dzdVyOfAKCdLXdJOc9G2q8LoXSlEniSbav+yuU4zGeeruD00lszZVoG4ZHRNiYzR"
+        );
+        let auid = sig.i.unwrap();
+        assert_eq!(auid.local_part, Some("joe"));
+        assert_eq!(auid.domain, "football.example.com");
+        let z = sig.z.unwrap();
+        assert_eq!((z[0].name, z[0].value.as_str()), ("Subject", "demo run"));
+        assert_eq!((z[1].name, z[1].value.as_str()), ("To", "joe@example.com"));
+    }
+
+    #[rstest]
+    #[case(
+        "t=12a;",
+        DkimTagValueError::Timestamp(crate::error::DkimTimestampError::InvalidDigits)
+    )]
+    #[case(
+        "t=1234567890123;",
+        DkimTagValueError::Timestamp(crate::error::DkimTimestampError::TooManyDigits)
+    )]
+    #[case("t=200; x=100;", DkimTagValueError::ExpirationBeforeTimestamp)]
+    #[case("l=-1;", DkimTagValueError::InvalidBodyLength)]
+    #[case("q=dns/txt:;", DkimTagValueError::EmptyQueryMethod)]
+    #[case("i=joe@example.org;", DkimTagValueError::AuidOutsideSdid)]
+    #[case("i=joe;", DkimTagValueError::Auid(crate::error::DkimAuidError::NoAt))]
+    fn invalid_values(#[case] extra: &str, #[case] expected: DkimTagValueError) {
+        let text = format!("{REQUIRED} {extra}");
+        match DkimSignature::try_from(text.as_str()) {
+            Err(DkimSignatureError::ParseValueInvalid(e)) => assert_eq!(e, expected),
+            other => panic!("Expected {:?} got {:?}", expected, other),
+        }
+    }
+
+    #[test]
+    fn invalid_base64() {
+        let text = "v=1; a=rsa-sha256; d=example.com; s=sel; h=From; bh=AAAA; b=A!AA;";
+        assert!(matches!(
+            DkimSignature::try_from(text),
+            Err(DkimSignatureError::ParseValueInvalid(
+                DkimTagValueError::Base64(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn duplicate_tag() {
+        let text =
+            "v=1; a=rsa-sha256; d=example.com; s=sel; h=From; bh=AAAA; b=AAAA; d=example.org;";
+        assert_eq!(
+            DkimSignature::try_from(text),
+            Err(DkimSignatureError::ParseValueInvalid(
+                DkimTagValueError::Duplicate
+            ))
+        );
+    }
+
+    #[test]
+    fn duplicate_of_invalid_tag() {
+        let text = "v=1; a=rsa-sha256; t=1x; d=example.com; s=sel; h=From; bh=AAAA; b=AAAA; t=1;";
+        let errors = DkimSignature::diagnose(text).unwrap_err();
+        let found: Vec<(Option<&str>, &DkimSignatureError<'_>)> =
+            errors.iter().map(|e| (e.tag, &e.error)).collect();
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            found[1],
+            (
+                Some("t"),
+                &DkimSignatureError::ParseValueInvalid(DkimTagValueError::Duplicate)
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_tags_kept() {
+        let text = "v=1; a=rsa-sha256; d=example.com; s=sel; darn=arewe.at; h=From; bh=AAAA; b=AAAA; foo=;";
+        let sig = DkimSignature::try_from(text).unwrap();
+        assert_eq!(
+            sig.unknown_tags,
+            vec![
+                DkimHeader::Unknown("darn", "arewe.at"),
+                DkimHeader::Unknown("foo", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn all_errors_with_spans() {
+        let text = "v=1; a=rsa-sha256; t=1x; d=example.com; d=example.org; bh=AAAA;";
+        let errors = DkimSignature::diagnose(text).unwrap_err();
+        let found: Vec<(Option<&str>, Option<&str>)> = errors
+            .iter()
+            .map(|e| (e.tag, e.span.clone().map(|span| &text[span])))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Some("t"), Some("t=1x")),
+                (Some("d"), Some("d=example.org")),
+                (Some("b"), None),
+                (Some("h"), None),
+                (Some("s"), None),
+            ]
+        );
+        assert_eq!(
+            errors[1].error,
+            DkimSignatureError::ParseValueInvalid(DkimTagValueError::Duplicate)
+        );
+        assert_eq!(errors[2].error, DkimSignatureError::MissingSignature);
+    }
+
+    #[test]
+    fn recovers_after_lexing_error() {
+        let text = "v=1; a=rsa-sha256; 9x=y; d=example.com; s=sel; h=From; bh=AAAA; b=AAAA;";
+        let errors = DkimSignature::diagnose(text).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].error,
+            DkimSignatureError::ParsingDetailed(_)
+        ));
     }
 }
@@ -0,0 +1,568 @@
+//! Parsing for DMARC policy record using Logos
+
+use logos::{Lexer, Logos};
+
+use crate::dmarc::record::{parse_failure_options, parse_uri_list};
+use crate::dmarc::*;
+
+use crate::error::{DmarcRecordError, DmarcTagValueError, ParsingDetail};
+
+use core::ops::Range;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
+#[derive(Debug, Logos)]
+pub enum DmarcFieldValueToken<'rec> {
+    #[regex(r"[^;]+", |lex| lex.slice(), priority = 1)]
+    MaybeValue(&'rec str),
+
+    #[token(";", priority = 2)]
+    FieldSep,
+}
+
+/// See RFC 7489 s. 6.3 - DMARC Tags
+#[derive(Debug, Logos)]
+#[logos(skip r"[ \t\r\n]+")]
+pub enum DmarcFieldKeyToken<'rec> {
+    #[token("v", priority = 3, ignore(ascii_case))]
+    TagV,
+
+    #[token("p", priority = 3, ignore(ascii_case))]
+    TagP,
+
+    #[token("sp", priority = 3, ignore(ascii_case))]
+    TagSp,
+
+    // DMARCbis
+    #[token("np", priority = 3, ignore(ascii_case))]
+    TagNp,
+
+    #[token("pct", priority = 3, ignore(ascii_case))]
+    TagPct,
+
+    #[token("adkim", priority = 3, ignore(ascii_case))]
+    TagAdkim,
+
+    #[token("aspf", priority = 3, ignore(ascii_case))]
+    TagAspf,
+
+    #[token("rua", priority = 3, ignore(ascii_case))]
+    TagRua,
+
+    #[token("ruf", priority = 3, ignore(ascii_case))]
+    TagRuf,
+
+    #[token("fo", priority = 3, ignore(ascii_case))]
+    TagFo,
+
+    #[token("rf", priority = 3, ignore(ascii_case))]
+    TagRf,
+
+    #[token("ri", priority = 3, ignore(ascii_case))]
+    TagRi,
+
+    // DMARCbis
+    #[token("t", priority = 3, ignore(ascii_case))]
+    TagT,
+
+    #[token(";", priority = 3)]
+    FieldSep,
+
+    #[token("=", priority = 3)]
+    Equal,
+
+    // Allows everything else as unknown tags
+    #[regex(r"[a-zA-Z][a-zA-Z0-9_]*", |lex| lex.slice(), priority = 1)]
+    MaybeTag(&'rec str),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DmarcTagChoice<'rec> {
+    V,
+    P,
+    Sp,
+    Np,
+    Pct,
+    Adkim,
+    Aspf,
+    Rua,
+    Ruf,
+    Fo,
+    Rf,
+    Ri,
+    T,
+    // RFC 7489 s. 6.3 Unknown tags MUST be ignored
+    Unknown(&'rec str),
+}
+
+impl<'rec> DmarcTagChoice<'rec> {
+    fn from_token(token: DmarcFieldKeyToken<'rec>) -> Option<Self> {
+        let ret = match token {
+            DmarcFieldKeyToken::TagV => Self::V,
+            DmarcFieldKeyToken::TagP => Self::P,
+            DmarcFieldKeyToken::TagSp => Self::Sp,
+            DmarcFieldKeyToken::TagNp => Self::Np,
+            DmarcFieldKeyToken::TagPct => Self::Pct,
+            DmarcFieldKeyToken::TagAdkim => Self::Adkim,
+            DmarcFieldKeyToken::TagAspf => Self::Aspf,
+            DmarcFieldKeyToken::TagRua => Self::Rua,
+            DmarcFieldKeyToken::TagRuf => Self::Ruf,
+            DmarcFieldKeyToken::TagFo => Self::Fo,
+            DmarcFieldKeyToken::TagRf => Self::Rf,
+            DmarcFieldKeyToken::TagRi => Self::Ri,
+            DmarcFieldKeyToken::TagT => Self::T,
+            DmarcFieldKeyToken::MaybeTag(tag) => Self::Unknown(tag),
+            _ => return None,
+        };
+        Some(ret)
+    }
+    // Invalid values of these make the whole record invalid - RFC 7489 s. 6.3
+    //
+    // With the RFC 7489 s. 6.6.3 fallback invalid p= and sp= are kept instead
+    fn is_required_valid(&self, fallback: bool) -> bool {
+        match self {
+            Self::V | Self::Np => true,
+            Self::P | Self::Sp => !fallback,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Stage<'rec> {
+    WantTag,
+    WantEq(DmarcTagChoice<'rec>),
+}
+
+// Intermediary parsed structure to the final DmarcRecord
+#[derive(Debug, Default)]
+struct ParsedDmarcRecord<'rec> {
+    v: bool,
+    p: Option<DmarcPolicy>,
+    sp: Option<DmarcPolicy>,
+    np: Option<DmarcPolicy>,
+    pct: Option<u8>,
+    adkim: Option<DmarcAlignment>,
+    aspf: Option<DmarcAlignment>,
+    rua: Option<Vec<DmarcUri<'rec>>>,
+    ruf: Option<Vec<DmarcUri<'rec>>>,
+    fo: Option<Vec<DmarcFailureOption>>,
+    rf: Option<Vec<&'rec str>>,
+    ri: Option<u32>,
+    t: Option<bool>,
+    unknown_tags: Vec<(&'rec str, &'rec str)>,
+    ignored: Vec<DmarcRecordError<'rec>>,
+    // Tags seen including the ones with invalid values
+    seen: Vec<DmarcTagChoice<'rec>>,
+}
+
+fn parse_percent(val: &str) -> Result<u8, DmarcTagValueError> {
+    match val.bytes().all(|b| b.is_ascii_digit()) {
+        true => match val.parse::<u8>() {
+            Ok(pct) if pct <= 100 => Ok(pct),
+            _ => Err(DmarcTagValueError::InvalidPercent),
+        },
+        false => Err(DmarcTagValueError::InvalidPercent),
+    }
+}
+
+fn parse_interval(val: &str) -> Result<u32, DmarcTagValueError> {
+    match val.bytes().all(|b| b.is_ascii_digit()) {
+        true => val
+            .parse::<u32>()
+            .map_err(|_| DmarcTagValueError::InvalidInterval),
+        false => Err(DmarcTagValueError::InvalidInterval),
+    }
+}
+
+fn parse_report_formats(val: &str) -> Result<Vec<&str>, DmarcTagValueError> {
+    val.split(':')
+        .map(|format| match format.trim() {
+            "" => Err(DmarcTagValueError::InvalidReportFormat),
+            format => Ok(format),
+        })
+        .collect()
+}
+
+fn parse_testing(val: &str) -> Result<bool, DmarcTagValueError> {
+    match val {
+        "y" | "Y" => Ok(true),
+        "n" | "N" => Ok(false),
+        _ => Err(DmarcTagValueError::InvalidTesting),
+    }
+}
+
+impl<'rec> ParsedDmarcRecord<'rec> {
+    fn add_tag_value(
+        &mut self,
+        tag: DmarcTagChoice<'rec>,
+        val: &'rec str,
+    ) -> Result<(), DmarcTagValueError> {
+        if self.seen.contains(&tag) {
+            return Err(DmarcTagValueError::Duplicate);
+        }
+        self.seen.push(tag.clone());
+        // Tag values may have whitespace around them
+        let val = val.trim();
+        match tag {
+            DmarcTagChoice::V => match val {
+                "DMARC1" => self.v = true,
+                _ => return Err(DmarcTagValueError::InvalidVersion),
+            },
+            DmarcTagChoice::P => self.p = Some(val.try_into()?),
+            DmarcTagChoice::Sp => self.sp = Some(val.try_into()?),
+            DmarcTagChoice::Np => self.np = Some(val.try_into()?),
+            DmarcTagChoice::Pct => self.pct = Some(parse_percent(val)?),
+            DmarcTagChoice::Adkim => self.adkim = Some(val.try_into()?),
+            DmarcTagChoice::Aspf => self.aspf = Some(val.try_into()?),
+            DmarcTagChoice::Rua => self.rua = Some(parse_uri_list(val)?),
+            DmarcTagChoice::Ruf => self.ruf = Some(parse_uri_list(val)?),
+            DmarcTagChoice::Fo => self.fo = Some(parse_failure_options(val)?),
+            DmarcTagChoice::Rf => self.rf = Some(parse_report_formats(val)?),
+            DmarcTagChoice::Ri => self.ri = Some(parse_interval(val)?),
+            DmarcTagChoice::T => self.t = Some(parse_testing(val)?),
+            DmarcTagChoice::Unknown(name) => self.unknown_tags.push((name, val)),
+        }
+        Ok(())
+    }
+    fn finalize(
+        self,
+        raw: &'rec str,
+        fallback: bool,
+    ) -> Result<DmarcRecord<'rec>, DmarcRecordError<'rec>> {
+        let invalid_sp = self.sp.is_none() && self.seen.contains(&DmarcTagChoice::Sp);
+        let has_rua = self.rua.as_ref().is_some_and(|rua| !rua.is_empty());
+        let p = match (self.p, invalid_sp) {
+            (Some(p), false) => p,
+            // RFC 7489 s. 6.6.3 act as if p=none given a valid rua=
+            _ if fallback && has_rua => DmarcPolicy::None,
+            _ => return Err(DmarcRecordError::MissingPolicy),
+        };
+        Ok(DmarcRecord {
+            p,
+            sp: self.sp,
+            np: self.np,
+            pct: self.pct.unwrap_or(100),
+            adkim: self.adkim.unwrap_or_default(),
+            aspf: self.aspf.unwrap_or_default(),
+            rua: self.rua.unwrap_or_default(),
+            ruf: self.ruf.unwrap_or_default(),
+            fo: self.fo.unwrap_or_else(|| vec![DmarcFailureOption::All]),
+            rf: self.rf.unwrap_or_else(|| vec!["afrf"]),
+            ri: self.ri.unwrap_or(86400),
+            t: self.t.unwrap_or(false),
+            unknown_tags: self.unknown_tags,
+            ignored: self.ignored,
+            raw,
+        })
+    }
+}
+
+fn detail<'rec>(source: &'rec str, span: Range<usize>) -> ParsingDetail<'rec> {
+    ParsingDetail {
+        component: "parse_dmarc_record",
+        span_start: span.start,
+        span_end: span.end,
+        source,
+        clipped_span: &source[span.clone()],
+        clipped_remaining: &source[span.start..],
+    }
+}
+
+fn parse_dmarc_record<'rec>(
+    text: &'rec str,
+    fallback: bool,
+) -> Result<DmarcRecord<'rec>, DmarcRecordError<'rec>> {
+    let mut tag_lexer = DmarcFieldKeyToken::lexer(text);
+    let mut stage = Stage::WantTag;
+    let mut res = ParsedDmarcRecord::default();
+
+    let mut tag_start = 0;
+
+    while let Some(token) = tag_lexer.next() {
+        match token {
+            Ok(DmarcFieldKeyToken::Equal) => {
+                let key_tag = match stage {
+                    Stage::WantEq(key_tag) => key_tag,
+                    Stage::WantTag => return Err(DmarcRecordError::UnexpectedEqual),
+                };
+                let mut value_lexer: Lexer<'rec, DmarcFieldValueToken<'rec>> = tag_lexer.morph();
+
+                let mut tag_value: &'rec str = "";
+                let mut tag_end = value_lexer.span().end;
+
+                while let Some(value_token) = value_lexer.next() {
+                    match value_token {
+                        Ok(DmarcFieldValueToken::MaybeValue(value)) => {
+                            tag_value = value;
+                            tag_end = value_lexer.span().end;
+                        }
+                        Ok(DmarcFieldValueToken::FieldSep) => break,
+                        Err(_) => {
+                            return Err(DmarcRecordError::ParsingDetailed(detail(
+                                text,
+                                value_lexer.span(),
+                            )))
+                        }
+                    }
+                }
+                // RFC 7489 s. 6.4 v=DMARC1 MUST be the first tag
+                if !res.v && key_tag != DmarcTagChoice::V {
+                    return Err(DmarcRecordError::MissingVersion);
+                }
+                let required_valid = key_tag.is_required_valid(fallback);
+                if let Err(e) = res.add_tag_value(key_tag, tag_value) {
+                    let error =
+                        DmarcRecordError::ParseValueInvalid(e, detail(text, tag_start..tag_end));
+                    match required_valid || e == DmarcTagValueError::Duplicate {
+                        true => return Err(error),
+                        // RFC 7489 s. 6.3 other syntax errors are discarded for the defaults
+                        false => res.ignored.push(error),
+                    }
+                }
+                tag_lexer = value_lexer.morph();
+                stage = Stage::WantTag;
+            }
+            Ok(DmarcFieldKeyToken::FieldSep) if stage == Stage::WantTag => {
+                // Empty tag-spec e.g. trailing ';'
+            }
+            Ok(maybe_tag_token) if stage == Stage::WantTag => {
+                stage = match DmarcTagChoice::from_token(maybe_tag_token) {
+                    Some(tag) => {
+                        tag_start = tag_lexer.span().start;
+                        Stage::WantEq(tag)
+                    }
+                    None => return Err(DmarcRecordError::NoTagFound),
+                };
+            }
+            _ => {
+                return Err(DmarcRecordError::ParsingDetailed(detail(
+                    text,
+                    tag_lexer.span(),
+                )))
+            }
+        }
+    }
+
+    match res.seen.is_empty() {
+        true => Err(DmarcRecordError::NoTagFound),
+        false => res.finalize(text, fallback),
+    }
+}
+
+impl<'rec> TryFrom<&'rec str> for DmarcRecord<'rec> {
+    type Error = DmarcRecordError<'rec>;
+
+    fn try_from(text: &'rec str) -> Result<Self, Self::Error> {
+        parse_dmarc_record(text, false)
+    }
+}
+
+impl<'rec> DmarcRecord<'rec> {
+    /// Parse the record acting as if p=none when p= is missing or invalid or sp=
+    /// is invalid but rua= has a valid URI - RFC 7489 s. 6.6.3
+    ///
+    /// Without a valid rua= such a record is MissingPolicy and no DMARC
+    /// processing applies to the message.
+    pub fn parse_with_fallback(text: &'rec str) -> Result<Self, DmarcRecordError<'rec>> {
+        parse_dmarc_record(text, true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn all_tags() {
+        let text = "v=DMARC1; p=quarantine; sp=reject; np=reject; pct=50; adkim=s; aspf=r;\n rua=mailto:agg@example.com!10m, https://report.example.com/dmarc;\n ruf=mailto:fail@example.com; fo=1:d; rf=afrf; ri=3600; t=y";
+        let record = DmarcRecord::try_from(text).unwrap();
+        assert_eq!(record.p, DmarcPolicy::Quarantine);
+        assert_eq!(record.sp, Some(DmarcPolicy::Reject));
+        assert_eq!(record.np, Some(DmarcPolicy::Reject));
+        assert_eq!(record.pct, 50);
+        assert_eq!(record.adkim, DmarcAlignment::Strict);
+        assert_eq!(record.aspf, DmarcAlignment::Relaxed);
+        assert_eq!(
+            record.rua,
+            vec![
+                DmarcUri {
+                    uri: "mailto:agg@example.com",
+                    max_size: Some(10 * 1024 * 1024)
+                },
+                DmarcUri {
+                    uri: "https://report.example.com/dmarc",
+                    max_size: None
+                },
+            ]
+        );
+        assert_eq!(record.ruf[0].uri, "mailto:fail@example.com");
+        assert_eq!(
+            record.fo,
+            vec![DmarcFailureOption::Any, DmarcFailureOption::Dkim]
+        );
+        assert_eq!(record.rf, vec!["afrf"]);
+        assert_eq!(record.ri, 3600);
+        assert!(record.is_testing());
+        assert_eq!(record.ignored, vec![]);
+    }
+
+    #[test]
+    fn defaults() {
+        let record = DmarcRecord::try_from("v=DMARC1; p=none").unwrap();
+        assert_eq!(record.pct, 100);
+        assert_eq!(record.adkim, DmarcAlignment::Relaxed);
+        assert_eq!(record.aspf, DmarcAlignment::Relaxed);
+        assert_eq!(record.fo, vec![DmarcFailureOption::All]);
+        assert_eq!(record.rf, vec!["afrf"]);
+        assert_eq!(record.ri, 86400);
+        assert!(!record.is_testing());
+        assert_eq!(record.subdomain_policy(), DmarcPolicy::None);
+        assert_eq!(record.non_existent_policy(), DmarcPolicy::None);
+    }
+
+    #[rstest]
+    #[case("v=DMARC1; p=reject", DmarcPolicy::Reject, DmarcPolicy::Reject)]
+    #[case("v=DMARC1; p=reject; sp=none", DmarcPolicy::None, DmarcPolicy::None)]
+    #[case(
+        "v=DMARC1; p=reject; sp=none; np=quarantine",
+        DmarcPolicy::None,
+        DmarcPolicy::Quarantine
+    )]
+    fn inherited_policies(
+        #[case] text: &str,
+        #[case] subdomain: DmarcPolicy,
+        #[case] non_existent: DmarcPolicy,
+    ) {
+        let record = DmarcRecord::try_from(text).unwrap();
+        assert_eq!(record.subdomain_policy(), subdomain);
+        assert_eq!(record.non_existent_policy(), non_existent);
+    }
+
+    #[test]
+    fn unknown_tags_ignored() {
+        let record =
+            DmarcRecord::try_from("v=DMARC1; P=Reject; psd=y; future_tag = some value ;").unwrap();
+        assert_eq!(record.p, DmarcPolicy::Reject);
+        assert_eq!(
+            record.unknown_tags,
+            vec![("psd", "y"), ("future_tag", "some value")]
+        );
+    }
+
+    #[rstest]
+    #[case("", DmarcRecordError::NoTagFound)]
+    #[case("v=DMARC1", DmarcRecordError::MissingPolicy)]
+    #[case(
+        "v=DMARC1; sp=none; rua=mailto:a@example.com",
+        DmarcRecordError::MissingPolicy
+    )]
+    #[case("p=none; v=DMARC1", DmarcRecordError::MissingVersion)]
+    #[case("v=DMARC1; = none", DmarcRecordError::UnexpectedEqual)]
+    fn invalid(#[case] text: &str, #[case] expected: DmarcRecordError<'_>) {
+        assert_eq!(DmarcRecord::try_from(text), Err(expected));
+    }
+
+    #[rstest]
+    #[case("v=DMARC2; p=none", DmarcTagValueError::InvalidVersion, "v=DMARC2")]
+    #[case("v=DMARC1; p=block", DmarcTagValueError::InvalidPolicy, "p=block")]
+    #[case(
+        "v=DMARC1; p=none; sp=maybe",
+        DmarcTagValueError::InvalidPolicy,
+        "sp=maybe"
+    )]
+    #[case("v=DMARC1; p=none; np=", DmarcTagValueError::InvalidPolicy, "np=")]
+    #[case(
+        "v=DMARC1; p=none; p=reject",
+        DmarcTagValueError::Duplicate,
+        "p=reject"
+    )]
+    #[case(
+        "v=DMARC1; p=none; pct=1; pct=2",
+        DmarcTagValueError::Duplicate,
+        "pct=2"
+    )]
+    fn invalid_value(
+        #[case] text: &str,
+        #[case] expected: DmarcTagValueError,
+        #[case] clipped: &str,
+    ) {
+        match DmarcRecord::try_from(text) {
+            Err(DmarcRecordError::ParseValueInvalid(e, detail)) => {
+                assert_eq!(e, expected);
+                assert_eq!(detail.clipped_span, clipped);
+            }
+            other => panic!("Expected {:?} got {:?}", expected, other),
+        }
+    }
+
+    #[rstest]
+    #[case("pct=101", DmarcTagValueError::InvalidPercent)]
+    #[case("pct=-1", DmarcTagValueError::InvalidPercent)]
+    #[case("adkim=x", DmarcTagValueError::InvalidAlignment)]
+    #[case("rua=example.com", DmarcTagValueError::InvalidUri)]
+    #[case("rua=mailto:a@example.com!10x", DmarcTagValueError::InvalidSizeLimit)]
+    #[case("ruf=mailto:a@example.com!", DmarcTagValueError::InvalidSizeLimit)]
+    #[case("fo=0:x", DmarcTagValueError::InvalidFailureOption)]
+    #[case("rf=afrf:", DmarcTagValueError::InvalidReportFormat)]
+    #[case("ri=1d", DmarcTagValueError::InvalidInterval)]
+    #[case("t=yes", DmarcTagValueError::InvalidTesting)]
+    fn invalid_value_defaulted(#[case] extra: &str, #[case] expected: DmarcTagValueError) {
+        let text = format!("v=DMARC1; p=reject; {extra}");
+        let record = DmarcRecord::try_from(text.as_str()).unwrap();
+        assert_eq!(record.p, DmarcPolicy::Reject);
+        assert_eq!(record.pct, 100);
+        assert_eq!(record.rua, vec![]);
+        match &record.ignored[..] {
+            [DmarcRecordError::ParseValueInvalid(e, detail)] => {
+                assert_eq!(*e, expected);
+                assert_eq!(detail.clipped_span, extra);
+            }
+            other => panic!("Expected {:?} got {:?}", expected, other),
+        }
+    }
+
+    #[rstest]
+    #[case("v=DMARC1", None)]
+    #[case("v=DMARC1; sp=none; rua=mailto:a@example.com", Some(vec![]))]
+    #[case("v=DMARC1; p=block", None)]
+    #[case("v=DMARC1; p=block; rua=mailto:a@example.com", Some(vec!["p=block"]))]
+    #[case("v=DMARC1; p=reject; sp=maybe", None)]
+    #[case(
+        "v=DMARC1; p=reject; sp=maybe; rua=mailto:a@example.com",
+        Some(vec!["sp=maybe"])
+    )]
+    #[case("v=DMARC1; p=block; rua=example.com", None)]
+    fn policy_fallback(#[case] text: &str, #[case] expected: Option<Vec<&str>>) {
+        let record = match (DmarcRecord::parse_with_fallback(text), expected) {
+            (Ok(record), Some(clipped)) => {
+                let ignored: Vec<&str> = record
+                    .ignored
+                    .iter()
+                    .filter_map(|e| match e {
+                        DmarcRecordError::ParseValueInvalid(_, detail) => Some(detail.clipped_span),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(ignored, clipped);
+                record
+            }
+            (Err(DmarcRecordError::MissingPolicy), None) => return,
+            (other, expected) => panic!("Expected {:?} got {:?}", expected, other),
+        };
+        assert_eq!(record.p, DmarcPolicy::None);
+        assert_eq!(record.subdomain_policy(), DmarcPolicy::None);
+    }
+
+    #[rstest]
+    #[case("mailto:a@example.com!512", Some(512))]
+    #[case("mailto:a@example.com!1k", Some(1024))]
+    #[case("mailto:a@example.com!2G", Some(2 << 30))]
+    #[case("mailto:a@example.com!1t", Some(1 << 40))]
+    #[case(" mailto:a@example.com ", None)]
+    fn size_limits(#[case] uri: &str, #[case] expected: Option<u64>) {
+        assert_eq!(DmarcUri::try_from(uri).unwrap().max_size, expected);
+    }
+}
@@ -5,6 +5,7 @@ use logos::{Lexer, Logos};
 #[derive(Debug, Logos)]
 pub enum QuotedToken<'hdr> {
     #[regex(r#"""#, |lex| lex.slice(), priority = 25)]
+    #[allow(dead_code)]
     QuotedEnd(&'hdr str),
 
     #[regex(r#"(\\"|[^\\"])+"#, |lex| lex.slice(), priority = 50)]
@@ -0,0 +1,134 @@
+//! Splitting a raw message into header fields and body
+
+use crate::error::RawMessageError;
+use crate::message::{RawHeader, RawMessage};
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec;
+
+// RFC 5322 s. 3.6.8 ftext = %d33-57 / %d59-126
+fn is_ftext(b: u8) -> bool {
+    matches!(b, 33..=57 | 59..=126)
+}
+
+// Length of the line including the terminating LF if any
+fn line_len(raw: &[u8]) -> usize {
+    match raw.iter().position(|b| *b == b'\n') {
+        Some(pos) => pos + 1,
+        None => raw.len(),
+    }
+}
+
+// Line ending CRLF or bare LF length at the end of the line
+fn line_ending_len(line: &[u8]) -> usize {
+    match line {
+        [.., b'\r', b'\n'] => 2,
+        [.., b'\n'] => 1,
+        _ => 0,
+    }
+}
+
+impl<'msg> TryFrom<&'msg [u8]> for RawMessage<'msg> {
+    type Error = RawMessageError;
+
+    fn try_from(raw: &'msg [u8]) -> Result<Self, Self::Error> {
+        let mut headers = vec![];
+        let mut pos = 0;
+
+        while pos < raw.len() {
+            let first_len = line_len(&raw[pos..]);
+            let first = &raw[pos..pos + first_len];
+
+            // Empty line separates the body
+            if first_len == line_ending_len(first) {
+                return Ok(Self {
+                    headers,
+                    body: &raw[pos + first_len..],
+                });
+            }
+
+            // Continuation lines begin with WSP
+            let mut end = pos + first_len;
+            while end < raw.len() && matches!(raw[end], b' ' | b'\t') {
+                end += line_len(&raw[end..]);
+            }
+
+            let field = &raw[pos..end];
+            let colon = match field.iter().position(|b| *b == b':') {
+                Some(colon) => colon,
+                None => return Err(RawMessageError::InvalidHeader(pos)),
+            };
+            // RFC 5322 s. 4.5 obs-optional allows WSP between the name and the colon
+            let name_len = field[..colon]
+                .iter()
+                .rposition(|b| !matches!(b, b' ' | b'\t'))
+                .map_or(0, |last| last + 1);
+            if name_len == 0 || !field[..name_len].iter().all(|b| is_ftext(*b)) {
+                return Err(RawMessageError::InvalidHeader(pos));
+            }
+            let name = core::str::from_utf8(&field[..name_len])
+                .map_err(|_| RawMessageError::InvalidHeader(pos))?;
+
+            headers.push(RawHeader {
+                name,
+                value: &field[colon + 1..field.len() - line_ending_len(field)],
+                raw: field,
+            });
+            pos = end;
+        }
+
+        // No body
+        Ok(Self {
+            headers,
+            body: &raw[raw.len()..],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn folded_and_body() {
+        let raw = b"Subject: one\r\n two\r\nTo: a@b\n\nBody\r\n";
+        let msg = RawMessage::try_from(&raw[..]).unwrap();
+        assert_eq!(msg.headers.len(), 2);
+        assert_eq!(msg.headers[0].name, "Subject");
+        assert_eq!(msg.headers[0].value, b" one\r\n two");
+        assert_eq!(msg.headers[0].raw, b"Subject: one\r\n two\r\n");
+        assert_eq!(msg.headers[1].value, b" a@b");
+        assert_eq!(msg.body, b"Body\r\n");
+    }
+
+    #[test]
+    fn no_body() {
+        let msg = RawMessage::try_from(&b"To: a@b"[..]).unwrap();
+        assert_eq!(msg.headers[0].value, b" a@b");
+        assert_eq!(msg.body, b"");
+    }
+
+    #[test]
+    fn obsolete_wsp_before_colon() {
+        let raw = b"Subject : x\r\nB \t: y\r\n\r\n";
+        let msg = RawMessage::try_from(&raw[..]).unwrap();
+        assert_eq!(msg.headers[0].name, "Subject");
+        assert_eq!(msg.headers[0].raw_name(), "Subject ");
+        assert_eq!(msg.headers[0].value, b" x");
+        assert_eq!(msg.headers[1].name, "B");
+        assert_eq!(msg.headers[1].raw_name(), "B \t");
+        assert!(msg.headers[1].is("b"));
+    }
+
+    #[rstest]
+    #[case(b"To: a@b\r\nnot a header\r\n\r\n", 9)]
+    #[case(b"To: a@b\r\nnot a: header\r\n\r\n", 9)]
+    #[case(b" : x\r\n\r\n", 0)]
+    fn invalid(#[case] raw: &[u8], #[case] offset: usize) {
+        assert_eq!(
+            RawMessage::try_from(raw),
+            Err(RawMessageError::InvalidHeader(offset))
+        );
+    }
+}
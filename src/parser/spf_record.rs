@@ -0,0 +1,462 @@
+//! Parsing for SPF record using Logos
+
+use logos::Logos;
+
+use crate::spf::macros::{check_domain_spec, check_macro_string};
+use crate::spf::*;
+
+use crate::error::{ParsingDetail, SpfRecordError, SpfTermError};
+
+use core::net::{Ipv4Addr, Ipv6Addr};
+use core::ops::Range;
+
+/// See RFC 7208 s. 4.5 - terms are separated by spaces
+#[derive(Debug, Logos)]
+#[logos(skip r"[ ]+")]
+pub enum SpfTermToken<'rec> {
+    #[token("v=spf1", priority = 3, ignore(ascii_case))]
+    Version,
+
+    #[regex(r"[^ ]+", |lex| lex.slice(), priority = 1)]
+    Term(&'rec str),
+}
+
+// ip4-cidr-length / ip6-cidr-length = "/" ( "0" / %x31-39 0*2DIGIT )
+fn parse_cidr(val: &str, max: u8) -> Result<u8, SpfTermError> {
+    let valid = !val.is_empty()
+        && val.len() <= 3
+        && val.bytes().all(|b| b.is_ascii_digit())
+        && (val == "0" || !val.starts_with('0'));
+    match valid {
+        true => match val.parse::<u8>() {
+            Ok(cidr) if cidr <= max => Ok(cidr),
+            _ => Err(SpfTermError::InvalidCidr),
+        },
+        false => Err(SpfTermError::InvalidCidr),
+    }
+}
+
+// IPv4 and IPv6 prefix lengths
+type DualCidr = (Option<u8>, Option<u8>);
+
+// dual-cidr-length = [ ip4-cidr-length ] [ "/" ip6-cidr-length ]
+fn parse_dual_cidr(val: &str) -> Result<DualCidr, SpfTermError> {
+    let (cidr4, cidr6) = match val.split_once("//") {
+        Some((cidr4, cidr6)) => (cidr4, Some(cidr6)),
+        None => (val, None),
+    };
+    let cidr4 = match cidr4 {
+        "" => None,
+        cidr4 => match cidr4.strip_prefix('/') {
+            Some(cidr4) => Some(parse_cidr(cidr4, 32)?),
+            None => return Err(SpfTermError::InvalidCidr),
+        },
+    };
+    let cidr6 = cidr6.map(|cidr6| parse_cidr(cidr6, 128)).transpose()?;
+    Ok((cidr4, cidr6))
+}
+
+// The optional ":domain" and dual CIDR following a / mx
+fn parse_domain_cidr(arg: &str) -> Result<(Option<&str>, DualCidr), SpfTermError> {
+    let (domain, cidr) = match arg.find('/') {
+        Some(slash) => arg.split_at(slash),
+        None => (arg, ""),
+    };
+    let domain = match domain {
+        "" => None,
+        domain => match domain.strip_prefix(':') {
+            Some(domain) => {
+                check_domain_spec(domain)?;
+                Some(domain)
+            }
+            None => return Err(SpfTermError::UnknownMechanism),
+        },
+    };
+    Ok((domain, parse_dual_cidr(cidr)?))
+}
+
+// The required ":domain" following include / exists
+fn parse_required_domain(arg: &str) -> Result<&str, SpfTermError> {
+    match arg.strip_prefix(':') {
+        Some(domain) => {
+            check_domain_spec(domain)?;
+            Ok(domain)
+        }
+        None if arg.is_empty() => Err(SpfTermError::MissingArgument),
+        None => Err(SpfTermError::UnknownMechanism),
+    }
+}
+
+// The network and the optional CIDR following ip4 / ip6
+fn parse_network(arg: &str, max: u8) -> Result<(&str, Option<u8>), SpfTermError> {
+    let arg = match arg.strip_prefix(':') {
+        Some(arg) if !arg.is_empty() => arg,
+        Some(_) => return Err(SpfTermError::MissingArgument),
+        None if arg.is_empty() => return Err(SpfTermError::MissingArgument),
+        None => return Err(SpfTermError::UnknownMechanism),
+    };
+    match arg.split_once('/') {
+        Some((addr, cidr)) => Ok((addr, Some(parse_cidr(cidr, max)?))),
+        None => Ok((arg, None)),
+    }
+}
+
+fn parse_mechanism<'rec>(name: &str, arg: &'rec str) -> Result<SpfMechanism<'rec>, SpfTermError> {
+    let mechanism = match name.to_ascii_lowercase().as_str() {
+        "all" => match arg.is_empty() {
+            true => SpfMechanism::All,
+            false => return Err(SpfTermError::UnexpectedArgument),
+        },
+        "include" => SpfMechanism::Include(parse_required_domain(arg)?),
+        "a" => {
+            let (domain, (cidr4, cidr6)) = parse_domain_cidr(arg)?;
+            SpfMechanism::A {
+                domain,
+                cidr4,
+                cidr6,
+            }
+        }
+        "mx" => {
+            let (domain, (cidr4, cidr6)) = parse_domain_cidr(arg)?;
+            SpfMechanism::Mx {
+                domain,
+                cidr4,
+                cidr6,
+            }
+        }
+        "ptr" => match arg {
+            "" => SpfMechanism::Ptr(None),
+            arg => SpfMechanism::Ptr(Some(parse_required_domain(arg)?)),
+        },
+        "ip4" => {
+            let (addr, cidr) = parse_network(arg, 32)?;
+            SpfMechanism::Ip4 {
+                addr: addr
+                    .parse::<Ipv4Addr>()
+                    .map_err(|_| SpfTermError::InvalidIp4)?,
+                cidr,
+            }
+        }
+        "ip6" => {
+            let (addr, cidr) = parse_network(arg, 128)?;
+            SpfMechanism::Ip6 {
+                addr: addr
+                    .parse::<Ipv6Addr>()
+                    .map_err(|_| SpfTermError::InvalidIp6)?,
+                cidr,
+            }
+        }
+        "exists" => SpfMechanism::Exists(parse_required_domain(arg)?),
+        _ => return Err(SpfTermError::UnknownMechanism),
+    };
+    Ok(mechanism)
+}
+
+// name = ALPHA *( ALPHA / DIGIT / "-" / "_" / "." )
+fn is_modifier_name(name: &str) -> bool {
+    let mut bytes = name.bytes();
+    bytes.next().is_some_and(|b| b.is_ascii_alphabetic())
+        && bytes.all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+impl<'rec> SpfRecord<'rec> {
+    fn add_term(&mut self, term: &'rec str) -> Result<(), SpfTermError> {
+        // Mechanism names end at ':' or '/' where modifier names end at '='
+        let name_end = term.find([':', '/', '=']).unwrap_or(term.len());
+        if term[name_end..].starts_with('=') {
+            let (name, val) = (&term[..name_end], &term[name_end + 1..]);
+            if !is_modifier_name(name) {
+                return Err(SpfTermError::InvalidModifier);
+            }
+            let modifier = match name {
+                n if n.eq_ignore_ascii_case("redirect") => &mut self.redirect,
+                n if n.eq_ignore_ascii_case("exp") => &mut self.exp,
+                _ => {
+                    check_macro_string(val, true)?;
+                    self.unknown_modifiers.push((name, val));
+                    return Ok(());
+                }
+            };
+            if modifier.is_some() {
+                return Err(SpfTermError::DuplicateModifier);
+            }
+            check_domain_spec(val)?;
+            *modifier = Some(val);
+            return Ok(());
+        }
+
+        let (qualifier, name_start) = match term.as_bytes().first() {
+            Some(b'+') => (SpfQualifier::Pass, 1),
+            Some(b'-') => (SpfQualifier::Fail, 1),
+            Some(b'~') => (SpfQualifier::SoftFail, 1),
+            Some(b'?') => (SpfQualifier::Neutral, 1),
+            _ => (SpfQualifier::Pass, 0),
+        };
+        let mechanism = parse_mechanism(&term[name_start..name_end], &term[name_end..])?;
+        self.directives.push(SpfDirective {
+            qualifier,
+            mechanism,
+            raw: term,
+        });
+        Ok(())
+    }
+}
+
+fn detail<'rec>(source: &'rec str, span: Range<usize>) -> ParsingDetail<'rec> {
+    ParsingDetail {
+        component: "parse_spf_record",
+        span_start: span.start,
+        span_end: span.end,
+        source,
+        clipped_span: &source[span.clone()],
+        clipped_remaining: &source[span.start..],
+    }
+}
+
+impl<'rec> TryFrom<&'rec str> for SpfRecord<'rec> {
+    type Error = SpfRecordError<'rec>;
+
+    fn try_from(text: &'rec str) -> Result<Self, Self::Error> {
+        let mut lexer = SpfTermToken::lexer(text);
+
+        // RFC 7208 s. 4.5 the version must come first followed by a space or the end
+        match lexer.next() {
+            Some(Ok(SpfTermToken::Version)) if lexer.span().start == 0 => {}
+            _ => return Err(SpfRecordError::NotSpf1),
+        }
+
+        let mut res = SpfRecord {
+            raw: text,
+            ..Default::default()
+        };
+
+        while let Some(token) = lexer.next() {
+            match token {
+                Ok(token) => {
+                    let term = match token {
+                        SpfTermToken::Term(term) => term,
+                        // A later v=spf1 is a modifier like any other
+                        SpfTermToken::Version => lexer.slice(),
+                    };
+                    res.add_term(term)
+                        .map_err(|e| SpfRecordError::InvalidTerm(e, detail(text, lexer.span())))?
+                }
+                Err(_) => return Err(SpfRecordError::ParsingDetailed(detail(text, lexer.span()))),
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn mechanisms(text: &str) -> Vec<(SpfQualifier, SpfMechanism<'_>)> {
+        SpfRecord::try_from(text)
+            .unwrap()
+            .directives
+            .into_iter()
+            .map(|directive| (directive.qualifier, directive.mechanism))
+            .collect()
+    }
+
+    #[test]
+    fn all_mechanisms() {
+        let text = "v=spf1 +a -mx:mail.example.com/24 ~ptr ?ip4:192.0.2.0/24 ip6:2001:db8::/32 include:_spf.example.net exists:%{i}._ip.%{d} PTR:example.org -ALL";
+        assert_eq!(
+            mechanisms(text),
+            vec![
+                (
+                    SpfQualifier::Pass,
+                    SpfMechanism::A {
+                        domain: None,
+                        cidr4: None,
+                        cidr6: None
+                    }
+                ),
+                (
+                    SpfQualifier::Fail,
+                    SpfMechanism::Mx {
+                        domain: Some("mail.example.com"),
+                        cidr4: Some(24),
+                        cidr6: None
+                    }
+                ),
+                (SpfQualifier::SoftFail, SpfMechanism::Ptr(None)),
+                (
+                    SpfQualifier::Neutral,
+                    SpfMechanism::Ip4 {
+                        addr: Ipv4Addr::new(192, 0, 2, 0),
+                        cidr: Some(24)
+                    }
+                ),
+                (
+                    SpfQualifier::Pass,
+                    SpfMechanism::Ip6 {
+                        addr: "2001:db8::".parse().unwrap(),
+                        cidr: Some(32)
+                    }
+                ),
+                (
+                    SpfQualifier::Pass,
+                    SpfMechanism::Include("_spf.example.net")
+                ),
+                (SpfQualifier::Pass, SpfMechanism::Exists("%{i}._ip.%{d}")),
+                (SpfQualifier::Pass, SpfMechanism::Ptr(Some("example.org"))),
+                (SpfQualifier::Fail, SpfMechanism::All),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("a", (None, None, None))]
+    #[case("a/24", (None, Some(24), None))]
+    #[case("a//64", (None, None, Some(64)))]
+    #[case("a/0//0", (None, Some(0), Some(0)))]
+    #[case("a:example.com/32//128", (Some("example.com"), Some(32), Some(128)))]
+    #[case("mx:example.com//48", (Some("example.com"), None, Some(48)))]
+    fn dual_cidr(#[case] term: &str, #[case] expected: (Option<&str>, Option<u8>, Option<u8>)) {
+        let text = format!("v=spf1 {}", term);
+        let got = match &mechanisms(&text)[..] {
+            [(
+                _,
+                SpfMechanism::A {
+                    domain,
+                    cidr4,
+                    cidr6,
+                }
+                | SpfMechanism::Mx {
+                    domain,
+                    cidr4,
+                    cidr6,
+                },
+            )] => (domain.map(|d| d.to_string()), *cidr4, *cidr6),
+            other => panic!("Expected a or mx got {:?}", other),
+        };
+        assert_eq!(
+            got,
+            (expected.0.map(|d| d.to_string()), expected.1, expected.2)
+        );
+    }
+
+    #[test]
+    fn modifiers() {
+        let record = SpfRecord::try_from(
+            "V=SPF1  redirect=_spf.example.com  exp=explain._spf.%{d} x-future=%{l}.1 v=spf1 ",
+        )
+        .unwrap();
+        assert_eq!(record.directives, vec![]);
+        assert_eq!(record.redirect, Some("_spf.example.com"));
+        assert_eq!(record.exp, Some("explain._spf.%{d}"));
+        assert_eq!(
+            record.unknown_modifiers,
+            vec![("x-future", "%{l}.1"), ("v", "spf1")]
+        );
+    }
+
+    #[test]
+    fn lookup_mechanisms() {
+        let record = SpfRecord::try_from(
+            "v=spf1 a mx ptr include:example.com exists:example.com ip4:192.0.2.1 ip6:::1 all",
+        )
+        .unwrap();
+        let lookups: Vec<bool> = record
+            .directives
+            .iter()
+            .map(|directive| directive.mechanism.is_dns_lookup())
+            .collect();
+        assert_eq!(
+            lookups,
+            vec![true, true, true, true, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn qualifier_results() {
+        assert_eq!(SpfQualifier::Pass.result_code(), SpfResultCode::Pass);
+        assert_eq!(SpfQualifier::Fail.result_code(), SpfResultCode::Fail);
+        assert_eq!(
+            SpfQualifier::SoftFail.result_code(),
+            SpfResultCode::SoftFail
+        );
+        assert_eq!(SpfQualifier::Neutral.result_code(), SpfResultCode::Neutral);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("v=spf10 -all")]
+    #[case(" v=spf1 -all")]
+    #[case("v=DMARC1; p=none")]
+    #[case("spf1 -all")]
+    fn not_spf1(#[case] text: &str) {
+        assert_eq!(SpfRecord::try_from(text), Err(SpfRecordError::NotSpf1));
+    }
+
+    #[rstest]
+    #[case("v=spf1 foo", SpfTermError::UnknownMechanism, "foo")]
+    #[case("v=spf1 a mx include", SpfTermError::MissingArgument, "include")]
+    #[case("v=spf1 exists:", SpfTermError::MissingArgument, "exists:")]
+    #[case(
+        "v=spf1 all:example.com",
+        SpfTermError::UnexpectedArgument,
+        "all:example.com"
+    )]
+    #[case("v=spf1 all/24", SpfTermError::UnexpectedArgument, "all/24")]
+    #[case(
+        "v=spf1 ip4:192.0.2.0/33",
+        SpfTermError::InvalidCidr,
+        "ip4:192.0.2.0/33"
+    )]
+    #[case(
+        "v=spf1 ip4:192.0.2.0/024",
+        SpfTermError::InvalidCidr,
+        "ip4:192.0.2.0/024"
+    )]
+    #[case("v=spf1 ip6:::1/129", SpfTermError::InvalidCidr, "ip6:::1/129")]
+    #[case("v=spf1 a/", SpfTermError::InvalidCidr, "a/")]
+    #[case("v=spf1 mx//129", SpfTermError::InvalidCidr, "mx//129")]
+    #[case("v=spf1 ip4:192.0.2", SpfTermError::InvalidIp4, "ip4:192.0.2")]
+    #[case("v=spf1 ip4:2001:db8::1", SpfTermError::InvalidIp4, "ip4:2001:db8::1")]
+    #[case("v=spf1 ip6:192.0.2.1", SpfTermError::InvalidIp6, "ip6:192.0.2.1")]
+    #[case("v=spf1 ip6", SpfTermError::MissingArgument, "ip6")]
+    #[case(
+        "v=spf1 include:localhost",
+        SpfTermError::InvalidDomainSpec,
+        "include:localhost"
+    )]
+    #[case(
+        "v=spf1 a:%{x}.example.com",
+        SpfTermError::InvalidMacro,
+        "a:%{x}.example.com"
+    )]
+    #[case(
+        "v=spf1 exists:%{c}.example.com",
+        SpfTermError::InvalidMacro,
+        "exists:%{c}.example.com"
+    )]
+    #[case(
+        "v=spf1 redirect=a.example.com redirect=b.example.com",
+        SpfTermError::DuplicateModifier,
+        "redirect=b.example.com"
+    )]
+    #[case(
+        "v=spf1 exp=a.example.com EXP=b.example.com",
+        SpfTermError::DuplicateModifier,
+        "EXP=b.example.com"
+    )]
+    #[case("v=spf1 redirect=", SpfTermError::MissingArgument, "redirect=")]
+    #[case("v=spf1 1x=y", SpfTermError::InvalidModifier, "1x=y")]
+    #[case("v=spf1 -all x=%", SpfTermError::InvalidMacro, "x=%")]
+    fn invalid_term(#[case] text: &str, #[case] expected: SpfTermError, #[case] clipped: &str) {
+        match SpfRecord::try_from(text) {
+            Err(SpfRecordError::InvalidTerm(e, detail)) => {
+                assert_eq!(e, expected);
+                assert_eq!(detail.clipped_span, clipped);
+                assert_eq!(detail.component, "parse_spf_record");
+            }
+            other => panic!("Expected {:?} got {:?}", expected, other),
+        }
+    }
+}
@@ -0,0 +1,657 @@
+//! Rendering Authentication-Results and inserting it into a raw message
+//!
+//! The header field value is the reverse of the parse path - RFC 8601 s. 2.2.
+//! Results with an Unknown result code are not valid resinfo and are left out.
+
+use core::fmt::{self, Display, Write};
+
+use crate::alloc_yes::AuthenticationResults;
+use crate::auth::{SmtpAuthResult, SmtpAuthResultCode};
+use crate::dkim::{DkimAlgorithm, DkimAtpsResult, DkimAtpsResultCode, DkimResult, DkimResultCode};
+use crate::dmarc::{DmarcPolicy, DmarcResult, DmarcResultCode};
+use crate::error::InsertAuthResultsError;
+use crate::iprev::{IpRevResult, IpRevResultCode};
+use crate::message::RawMessage;
+use crate::spf::{SpfResult, SpfResultCode};
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// Authentication-Results header field name
+const AUTH_RESULTS: &str = "Authentication-Results";
+
+/// Column header field lines are folded at - RFC 5322 s. 2.1.1
+const FOLD_AT: usize = 78;
+
+/// Where the Authentication-Results header field is inserted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InsertPosition {
+    /// Above every other header field
+    Top,
+    /// Right below the nth Received header field counting from the top from 0
+    AfterReceived(usize),
+}
+
+fn auth_code(code: &SmtpAuthResultCode) -> Option<&'static str> {
+    match code {
+        SmtpAuthResultCode::Unknown => None,
+        SmtpAuthResultCode::NoneSmtp => Some("none"),
+        SmtpAuthResultCode::Pass => Some("pass"),
+        SmtpAuthResultCode::Fail => Some("fail"),
+        SmtpAuthResultCode::TempError => Some("temperror"),
+        SmtpAuthResultCode::PermError => Some("permerror"),
+    }
+}
+
+pub(crate) fn spf_code(code: &SpfResultCode) -> Option<&'static str> {
+    match code {
+        SpfResultCode::Unknown => None,
+        SpfResultCode::NoneSpf => Some("none"),
+        SpfResultCode::Pass => Some("pass"),
+        SpfResultCode::Fail => Some("fail"),
+        SpfResultCode::SoftFail => Some("softfail"),
+        SpfResultCode::Policy => Some("policy"),
+        SpfResultCode::Neutral => Some("neutral"),
+        SpfResultCode::TempError => Some("temperror"),
+        SpfResultCode::PermError => Some("permerror"),
+    }
+}
+
+pub(crate) fn dkim_code(code: &DkimResultCode) -> Option<&'static str> {
+    match code {
+        DkimResultCode::Unknown => None,
+        DkimResultCode::NoneDkim => Some("none"),
+        DkimResultCode::Pass => Some("pass"),
+        DkimResultCode::Fail => Some("fail"),
+        DkimResultCode::Policy => Some("policy"),
+        DkimResultCode::Neutral => Some("neutral"),
+        DkimResultCode::TempError => Some("temperror"),
+        DkimResultCode::PermError => Some("permerror"),
+    }
+}
+
+fn dkim_atps_code(code: &DkimAtpsResultCode) -> Option<&'static str> {
+    match code {
+        DkimAtpsResultCode::Unknown => None,
+        DkimAtpsResultCode::NoneAtps => Some("none"),
+        DkimAtpsResultCode::Pass => Some("pass"),
+        DkimAtpsResultCode::Fail => Some("fail"),
+        DkimAtpsResultCode::TempError => Some("temperror"),
+        DkimAtpsResultCode::PermError => Some("permerror"),
+    }
+}
+
+fn iprev_code(code: &IpRevResultCode) -> Option<&'static str> {
+    match code {
+        IpRevResultCode::Unknown => None,
+        IpRevResultCode::Pass => Some("pass"),
+        IpRevResultCode::Fail => Some("fail"),
+        IpRevResultCode::TempError => Some("temperror"),
+        IpRevResultCode::PermError => Some("permerror"),
+    }
+}
+
+fn dmarc_code(code: &DmarcResultCode) -> Option<&'static str> {
+    match code {
+        DmarcResultCode::Unknown => None,
+        DmarcResultCode::NoneDmarc => Some("none"),
+        DmarcResultCode::Pass => Some("pass"),
+        DmarcResultCode::Fail => Some("fail"),
+        DmarcResultCode::TempError => Some("temperror"),
+        DmarcResultCode::PermError => Some("permerror"),
+    }
+}
+
+pub(crate) fn dmarc_policy(policy: &DmarcPolicy) -> &'static str {
+    match policy {
+        DmarcPolicy::None => "none",
+        DmarcPolicy::Quarantine => "quarantine",
+        DmarcPolicy::Reject => "reject",
+    }
+}
+
+fn dkim_algorithm<'a>(a: &DkimAlgorithm<'a>) -> &'a str {
+    match a {
+        DkimAlgorithm::Rsa_Sha1 => "rsa-sha1",
+        DkimAlgorithm::Rsa_Sha256 => "rsa-sha256",
+        DkimAlgorithm::Ed25519_Sha256 => "ed25519-sha256",
+        DkimAlgorithm::Unknown(a) => a,
+    }
+}
+
+// RFC 2045 token character - printable US-ASCII except tspecials
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_graphic() && !"()<>@,;:\\\"/[]?=".contains(c)
+}
+
+// RFC 5322 atext or the dot separating atoms
+fn is_dot_atom_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c)
+}
+
+// pvalue that can go without quotes - a token or [ [ local-part ] "@" ] domain-name - RFC 8601 s. 2.2
+fn is_bare_pvalue(value: &str) -> bool {
+    if !value.is_empty() && value.chars().all(is_token_char) {
+        return true;
+    }
+    match value.rsplit_once('@') {
+        Some((local, domain)) => {
+            local.chars().all(is_dot_atom_char)
+                && !domain.is_empty()
+                && domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+// One resinfo built up from the method, result and its properties
+struct ResInfo(String);
+
+impl ResInfo {
+    fn new(method: &str, code: &str) -> Self {
+        Self(format!("{}={}", method, code))
+    }
+    // quoted-string with the quotes and backslashes escaped
+    fn push_quoted(&mut self, value: &str) {
+        self.0.push('"');
+        for c in value.chars() {
+            if matches!(c, '"' | '\\') {
+                self.0.push('\\');
+            }
+            self.0.push(c);
+        }
+        self.0.push('"');
+    }
+    fn reason(mut self, reason: Option<&str>) -> Self {
+        if let Some(reason) = reason {
+            self.0.push_str(" reason=");
+            self.push_quoted(reason);
+        }
+        self
+    }
+    fn prop(mut self, ptype_property: &str, value: Option<&str>) -> Self {
+        if let Some(value) = value {
+            let _ = write!(self.0, " {}=", ptype_property);
+            match is_bare_pvalue(value) {
+                true => self.0.push_str(value),
+                false => self.push_quoted(value),
+            }
+        }
+        self
+    }
+}
+
+// Words of a resinfo split on the spaces outside quoted-strings
+fn words(resinfo: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, c) in resinfo.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ' ' if !quoted => {
+                words.push(&resinfo[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    words.push(&resinfo[start..]);
+    words
+}
+
+impl<'hdr> AuthenticationResults<'hdr> {
+    // Every resinfo with a known result code in method order
+    fn resinfos(&self) -> Vec<String> {
+        let auth = self
+            .smtp_auth_result
+            .iter()
+            .filter_map(|res: &SmtpAuthResult<'_>| {
+                auth_code(&res.code).map(|code| {
+                    ResInfo::new("auth", code)
+                        .prop("smtp.auth", res.smtp_auth)
+                        .prop("smtp.mailfrom", res.smtp_mailfrom)
+                })
+            });
+        let spf = self.spf_result.iter().filter_map(|res: &SpfResult<'_>| {
+            spf_code(&res.code).map(|code| {
+                ResInfo::new("spf", code)
+                    .reason(res.reason)
+                    .prop("smtp.mailfrom", res.smtp_mailfrom)
+                    .prop("smtp.helo", res.smtp_helo)
+            })
+        });
+        let dkim = self.dkim_result.iter().filter_map(|res: &DkimResult<'_>| {
+            dkim_code(&res.code).map(|code| {
+                ResInfo::new("dkim", code)
+                    .reason(res.reason)
+                    .prop("header.d", res.header_d)
+                    .prop("header.i", res.header_i)
+                    .prop("header.s", res.header_s)
+                    .prop("header.a", res.header_a.as_ref().map(dkim_algorithm))
+                    .prop("header.b", res.header_b)
+            })
+        });
+        let dkim_atps = self
+            .dkim_atps_result
+            .iter()
+            .filter_map(|res: &DkimAtpsResult<'_>| {
+                dkim_atps_code(&res.code).map(|code| {
+                    ResInfo::new("dkim-atps", code)
+                        .reason(res.reason)
+                        .prop("header.from", res.header_from)
+                })
+            });
+        let iprev = self
+            .iprev_result
+            .iter()
+            .filter_map(|res: &IpRevResult<'_>| {
+                iprev_code(&res.code).map(|code| {
+                    ResInfo::new("iprev", code)
+                        .reason(res.reason)
+                        .prop("policy.iprev", res.policy_iprev)
+                })
+            });
+        let dmarc = self
+            .dmarc_result
+            .iter()
+            .filter_map(|res: &DmarcResult<'_>| {
+                dmarc_code(&res.code).map(|code| {
+                    ResInfo::new("dmarc", code)
+                        .prop("header.from", res.header_from)
+                        .prop("policy.dmarc", res.policy_dmarc.as_ref().map(dmarc_policy))
+                })
+            });
+        let unknown = self
+            .unknown_result
+            .iter()
+            .map(|res| ResInfo(res.raw.trim().to_string()));
+
+        auth.chain(spf)
+            .chain(dkim)
+            .chain(dkim_atps)
+            .chain(iprev)
+            .chain(dmarc)
+            .chain(unknown)
+            .map(|resinfo| resinfo.0)
+            .collect()
+    }
+    // authserv-id with the version if any
+    fn authserv_id(&self) -> String {
+        match self.host {
+            Some(ref host) => match host.version {
+                Some(version) => format!("{} {}", host.host, version),
+                None => host.host.to_string(),
+            },
+            None => String::new(),
+        }
+    }
+    /// Whole header field folded at 78 columns on resinfo boundaries
+    ///
+    /// A resinfo too long for a line on its own is folded between its
+    /// properties - never inside a quoted-string. The line ending is used for folding and ends the field.
+    /// Fails without an authserv-id as one is required - RFC 8601 s. 2.2.
+    pub fn to_header_field(&self, line_ending: &str) -> Result<String, InsertAuthResultsError> {
+        if self.host.is_none() {
+            return Err(InsertAuthResultsError::MissingAuthservId);
+        }
+        let resinfos = self.resinfos();
+        let field = format!("{}: {};", AUTH_RESULTS, self.authserv_id());
+        let mut folder = Folder {
+            column: field.len(),
+            field,
+            line_ending,
+        };
+
+        if resinfos.is_empty() {
+            folder.push("none");
+        }
+        for (idx, resinfo) in resinfos.iter().enumerate() {
+            let separator = if idx + 1 < resinfos.len() { ";" } else { "" };
+            let whole = format!("{}{}", resinfo, separator);
+            if whole.len() < FOLD_AT {
+                folder.push(&whole);
+                continue;
+            }
+            // Too long for any line so fold between the properties from a new line
+            folder.fold();
+            let parts = words(resinfo);
+            for (part_idx, part) in parts.iter().enumerate() {
+                match part_idx + 1 == parts.len() {
+                    true => folder.push(&format!("{}{}", part, separator)),
+                    false => folder.push(part),
+                }
+            }
+        }
+        folder.field.push_str(line_ending);
+        Ok(folder.field)
+    }
+}
+
+// Header field folded greedily between the words pushed
+struct Folder<'a> {
+    field: String,
+    column: usize,
+    line_ending: &'a str,
+}
+
+impl Folder<'_> {
+    // Continue on a new line unless already at the start of one
+    fn fold(&mut self) {
+        if self.column > 0 {
+            self.field.push_str(self.line_ending);
+            self.column = 0;
+        }
+    }
+    fn push(&mut self, word: &str) {
+        // An overlong word gets a line of its own
+        if self.column + 1 + word.len() > FOLD_AT {
+            self.fold();
+        }
+        match self.column {
+            0 => self.field.push('\t'),
+            _ => self.field.push(' '),
+        }
+        self.field.push_str(word);
+        self.column += 1 + word.len();
+    }
+}
+
+impl Display for AuthenticationResults<'_> {
+    /// Unfolded header field value without the field name
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resinfos = self.resinfos();
+        write!(f, "{};", self.authserv_id())?;
+        match resinfos.is_empty() {
+            true => write!(f, " none"),
+            false => write!(f, " {}", resinfos.join("; ")),
+        }
+    }
+}
+
+/// Insert the rendered Authentication-Results into the raw message
+///
+/// The line ending is CRLF unless the first line of the message ends with a bare LF.
+/// Every other byte of the message is kept as it was.
+pub fn insert_auth_results(
+    raw: &[u8],
+    auth_results: &AuthenticationResults<'_>,
+    position: InsertPosition,
+) -> Result<Vec<u8>, InsertAuthResultsError> {
+    let msg = RawMessage::try_from(raw)?;
+
+    let line_ending = match raw.iter().position(|b| *b == b'\n') {
+        Some(pos) if pos == 0 || raw[pos - 1] != b'\r' => "\n",
+        _ => "\r\n",
+    };
+
+    let offset = match position {
+        InsertPosition::Top => 0,
+        InsertPosition::AfterReceived(nth) => {
+            let mut offset = 0;
+            let mut seen = 0;
+            let mut found = None;
+            for header in msg.headers.iter() {
+                offset += header.raw.len();
+                if header.is("Received") {
+                    if seen == nth {
+                        found = Some(offset);
+                        break;
+                    }
+                    seen += 1;
+                }
+            }
+            found.ok_or(InsertAuthResultsError::ReceivedNotFound(nth))?
+        }
+    };
+
+    let field = auth_results.to_header_field(line_ending)?;
+    let mut out = Vec::with_capacity(raw.len() + field.len());
+    out.extend_from_slice(&raw[..offset]);
+    out.extend_from_slice(field.as_bytes());
+    out.extend_from_slice(&raw[offset..]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::auth_results::HostVersion;
+
+    const FIELD: &str = " mx.example.com 1; auth=pass smtp.auth=sender@example.com;\r\n\tspf=fail smtp.mailfrom=example.org;\r\n dkim=pass reason=\"good signature\" header.d=example.org header.s=sel header.a=rsa-sha256 header.b=AbCd1234;\r\n iprev=pass policy.iprev=192.0.2.1; x-custom=foo bar=baz";
+
+    #[test]
+    fn display_round_trip() {
+        let parsed = AuthenticationResults::from(FIELD);
+        assert_eq!(parsed.errors, vec![]);
+        let rendered = parsed.to_string();
+        assert_eq!(
+            rendered,
+            "mx.example.com 1; auth=pass smtp.auth=sender@example.com; spf=fail smtp.mailfrom=example.org; dkim=pass reason=\"good signature\" header.d=example.org header.s=sel header.a=rsa-sha256 header.b=AbCd1234; iprev=pass policy.iprev=192.0.2.1; x-custom=foo bar=baz"
+        );
+    }
+
+    #[test]
+    fn unknown_kept() {
+        let parsed =
+            AuthenticationResults::from(" mx.example.com; x-custom=foo  (a  comment) bar=baz ");
+        assert_eq!(
+            parsed.to_string(),
+            "mx.example.com; x-custom=foo  (a  comment) bar=baz"
+        );
+    }
+
+    #[test]
+    fn missing_authserv_id() {
+        let res = AuthenticationResults::default();
+        assert_eq!(
+            res.to_header_field("\r\n"),
+            Err(InsertAuthResultsError::MissingAuthservId)
+        );
+        assert_eq!(
+            insert_auth_results(b"From: a@example.com\r\n\r\n", &res, InsertPosition::Top),
+            Err(InsertAuthResultsError::MissingAuthservId)
+        );
+    }
+
+    #[test]
+    fn reason_escaped() {
+        let res = AuthenticationResults {
+            host: Some(HostVersion {
+                host: "mx.example.com",
+                version: None,
+            }),
+            dkim_result: vec![DkimResult {
+                code: DkimResultCode::Fail,
+                reason: Some(r#"key "k1" \ bad"#),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            res.to_string(),
+            r#"mx.example.com; dkim=fail reason="key \"k1\" \\ bad""#
+        );
+    }
+
+    #[test]
+    fn property_quoted() {
+        let res = AuthenticationResults {
+            host: Some(HostVersion {
+                host: "mx.example.com",
+                version: None,
+            }),
+            dkim_result: vec![DkimResult {
+                code: DkimResultCode::Pass,
+                header_i: Some("first.last@example.com"),
+                header_s: Some("sel; x=1"),
+                header_b: Some("Ab/Cd+12="),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            res.to_string(),
+            r#"mx.example.com; dkim=pass header.i=first.last@example.com header.s="sel; x=1" header.b="Ab/Cd+12=""#
+        );
+    }
+
+    #[test]
+    fn quoted_never_folded() {
+        let reason =
+            "a reason well over seventy eight columns long that must still stay on a single line";
+        let res = AuthenticationResults {
+            host: Some(HostVersion {
+                host: "mx.example.com",
+                version: None,
+            }),
+            dkim_result: vec![DkimResult {
+                code: DkimResultCode::Fail,
+                reason: Some(reason),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            res.to_header_field("\r\n").unwrap(),
+            format!(
+                "Authentication-Results: mx.example.com;\r\n\tdkim=fail\r\n\treason=\"{}\"\r\n",
+                reason
+            )
+        );
+    }
+
+    #[test]
+    fn dmarc() {
+        let res = AuthenticationResults {
+            host: Some(HostVersion {
+                host: "mx.example.com",
+                version: None,
+            }),
+            dmarc_result: vec![
+                DmarcResult {
+                    code: DmarcResultCode::Fail,
+                    header_from: Some("example.org"),
+                    policy_dmarc: Some(DmarcPolicy::Reject),
+                    disposition: Some(DmarcPolicy::Quarantine),
+                    raw: None,
+                },
+                DmarcResult {
+                    code: DmarcResultCode::NoneDmarc,
+                    header_from: Some("example.net"),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            res.to_string(),
+            "mx.example.com; dmarc=fail header.from=example.org policy.dmarc=reject; dmarc=none header.from=example.net"
+        );
+    }
+
+    #[test]
+    fn none() {
+        let res = AuthenticationResults {
+            host: Some(HostVersion {
+                host: "mx.example.com",
+                version: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(res.to_string(), "mx.example.com; none");
+        assert_eq!(
+            res.to_header_field("\r\n").unwrap(),
+            "Authentication-Results: mx.example.com; none\r\n"
+        );
+    }
+
+    fn many_dkim() -> AuthenticationResults<'static> {
+        let dkim = DkimResult {
+            code: DkimResultCode::Pass,
+            reason: Some("a rather long reason explaining why this signature was fine"),
+            header_d: Some("a-long-signing-domain.example.com"),
+            header_i: Some("@a-long-signing-domain.example.com"),
+            header_s: Some("selector2024"),
+            header_a: Some(DkimAlgorithm::Ed25519_Sha256),
+            header_b: Some("AbCd1234"),
+            raw: None,
+        };
+        AuthenticationResults {
+            host: Some(HostVersion {
+                host: "mx.example.com",
+                version: None,
+            }),
+            spf_result: vec![SpfResult {
+                code: SpfResultCode::Pass,
+                smtp_mailfrom: Some("example.com"),
+                ..Default::default()
+            }],
+            dkim_result: vec![dkim.clone(), dkim],
+            iprev_result: vec![IpRevResult {
+                code: IpRevResultCode::Pass,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn folded_at_78() {
+        let res = many_dkim();
+        let field = res.to_header_field("\r\n").unwrap();
+        assert_eq!(
+            field,
+            "Authentication-Results: mx.example.com; spf=pass smtp.mailfrom=example.com;\r\n\tdkim=pass\r\n\treason=\"a rather long reason explaining why this signature was fine\"\r\n\theader.d=a-long-signing-domain.example.com\r\n\theader.i=@a-long-signing-domain.example.com header.s=selector2024\r\n\theader.a=ed25519-sha256 header.b=AbCd1234;\r\n\tdkim=pass\r\n\treason=\"a rather long reason explaining why this signature was fine\"\r\n\theader.d=a-long-signing-domain.example.com\r\n\theader.i=@a-long-signing-domain.example.com header.s=selector2024\r\n\theader.a=ed25519-sha256 header.b=AbCd1234; iprev=pass\r\n"
+        );
+        assert!(field.split("\r\n").all(|line| line.len() <= 78));
+
+        // Folding is undone by the parser
+        let value = field.strip_prefix("Authentication-Results:").unwrap();
+        let reparsed = AuthenticationResults::from(value);
+        assert_eq!(reparsed.errors, vec![]);
+        assert_eq!(reparsed.dkim_result.len(), 2);
+        assert_eq!(reparsed.dkim_result[1].header_b, Some("AbCd1234"));
+        assert_eq!(
+            reparsed.dkim_result[1].reason,
+            Some("a rather long reason explaining why this signature was fine")
+        );
+        assert_eq!(reparsed.iprev_result.len(), 1);
+    }
+
+    #[test]
+    fn insert_top_detects_lf() {
+        let raw = b"Received: from a\n\tby b\nSubject: hi\n\nBody\r\n";
+        let res = AuthenticationResults::from(" mx.example.com; spf=pass smtp.mailfrom=a.example");
+        let out = insert_auth_results(raw, &res, InsertPosition::Top).unwrap();
+        assert_eq!(
+            out,
+            b"Authentication-Results: mx.example.com; spf=pass smtp.mailfrom=a.example\nReceived: from a\n\tby b\nSubject: hi\n\nBody\r\n"
+        );
+    }
+
+    #[test]
+    fn insert_after_received() {
+        let raw = b"Received: from a\r\nReceived: from b\r\n\tby c\r\nSubject: hi\r\n\r\nBody\r\n";
+        let res = AuthenticationResults::from(" mx.example.com; none");
+        let out = insert_auth_results(raw, &res, InsertPosition::AfterReceived(1)).unwrap();
+        assert_eq!(
+            out,
+            b"Received: from a\r\nReceived: from b\r\n\tby c\r\nAuthentication-Results: mx.example.com; none\r\nSubject: hi\r\n\r\nBody\r\n"
+        );
+        assert_eq!(
+            insert_auth_results(raw, &res, InsertPosition::AfterReceived(2)),
+            Err(InsertAuthResultsError::ReceivedNotFound(2))
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! DNS resolving abstraction for the checks that need to query records
+//!
+//! This crate does not do any I/O - implement the trait(s) here on top of
+//! whatever resolver is used and pass it to the relevant check.
+
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::error::ResolverError;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{string::String, vec::Vec};
+
+/// Resolve TXT records
+pub trait TxtResolver {
+    /// Look up all the TXT records for the given fully qualified name
+    ///
+    /// Each record is returned with its character-strings concatenated.
+    /// A non-existent name or no TXT records must be reported as
+    /// [`ResolverError::NotFound`].
+    fn lookup_txt(&self, name: &str) -> Result<Vec<String>, ResolverError>;
+}
+
+/// Resolve the records SPF check_host() needs - RFC 7208 s. 5
+///
+/// Like with TXT records a non-existent name or no records of the type must
+/// be reported as [`ResolverError::NotFound`] - these count as void lookups.
+pub trait SpfResolver: TxtResolver {
+    /// Look up the A records of the given fully qualified name
+    fn lookup_a(&self, name: &str) -> Result<Vec<Ipv4Addr>, ResolverError>;
+    /// Look up the AAAA records of the given fully qualified name
+    fn lookup_aaaa(&self, name: &str) -> Result<Vec<Ipv6Addr>, ResolverError>;
+    /// Look up the MX exchange names of the given fully qualified name
+    fn lookup_mx(&self, name: &str) -> Result<Vec<String>, ResolverError>;
+    /// Look up the PTR names of the given address
+    fn lookup_ptr(&self, ip: IpAddr) -> Result<Vec<String>, ResolverError>;
+}
@@ -0,0 +1,260 @@
+//! Removing forged Authentication-Results at the border - RFC 8601 s. 5
+//!
+//! A border MTA must remove or rename any incoming Authentication-Results
+//! claiming its own authserv-id before adding its own. Everything else in the
+//! message including folding and the body is passed through byte for byte.
+
+use crate::alloc_yes::AuthenticationResults;
+use crate::error::RawMessageError;
+use crate::message::{RawHeader, RawMessage};
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{vec, vec::Vec};
+
+/// Authentication-Results header field name
+const AUTH_RESULTS: &str = "Authentication-Results";
+
+/// Default name forged Authentication-Results are renamed to
+pub const DEFAULT_RENAME_TO: &str = "X-Original-Authentication-Results";
+
+/// What to do with an Authentication-Results header field
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SanitizeAction {
+    /// Leave it as it is
+    Keep,
+    /// Remove it
+    Drop,
+    /// Rename it keeping the value
+    Rename,
+}
+
+/// Why the header field was acted on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SanitizeReason {
+    /// Claims one of the protected authserv-ids
+    Forged,
+    /// The authserv-id could not be parsed so whether it is forged is unknown
+    Unparseable,
+}
+
+/// Authentication-Results header field that was forged or could not be parsed
+#[derive(Clone, Debug, PartialEq)]
+pub struct SanitizedHeader<'msg> {
+    /// Byte offset of the header field in the original message
+    pub offset: usize,
+    /// The header field as it was in the original message
+    pub raw: &'msg [u8],
+    /// Why it was acted on
+    pub reason: SanitizeReason,
+    /// What was done
+    pub action: SanitizeAction,
+}
+
+/// Rewritten message with what was done to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct SanitizedMessage<'msg> {
+    /// The rewritten raw message
+    pub message: Vec<u8>,
+    /// Forged and unparseable header fields in the order they appeared
+    pub headers: Vec<SanitizedHeader<'msg>>,
+}
+
+/// Strips or renames Authentication-Results claiming the protected authserv-ids
+///
+/// The protected authserv-ids follow [`crate::auth_results::HostVersion::is_trusted_by`].
+/// Forged header fields are dropped and ones with an unparseable authserv-id
+/// kept by default.
+#[derive(Clone, Debug)]
+pub struct AuthResultsSanitizer<'a> {
+    protected: &'a [&'a str],
+    forged: SanitizeAction,
+    unparseable: SanitizeAction,
+    rename_to: &'a str,
+}
+
+impl<'a> AuthResultsSanitizer<'a> {
+    /// New sanitizer protecting the given authserv-ids
+    pub fn new(protected: &'a [&'a str]) -> Self {
+        Self {
+            protected,
+            forged: SanitizeAction::Drop,
+            unparseable: SanitizeAction::Keep,
+            rename_to: DEFAULT_RENAME_TO,
+        }
+    }
+    /// What to do with forged header fields
+    pub fn on_forged(mut self, action: SanitizeAction) -> Self {
+        self.forged = action;
+        self
+    }
+    /// What to do with header fields whose authserv-id could not be parsed
+    pub fn on_unparseable(mut self, action: SanitizeAction) -> Self {
+        self.unparseable = action;
+        self
+    }
+    /// Field name used by [`SanitizeAction::Rename`] instead of [`DEFAULT_RENAME_TO`]
+    pub fn rename_to(mut self, name: &'a str) -> Self {
+        self.rename_to = name;
+        self
+    }
+    // Reason and action for an Authentication-Results header field
+    fn classify(&self, header: &RawHeader<'_>) -> Option<(SanitizeReason, SanitizeAction)> {
+        let parsed = core::str::from_utf8(header.value)
+            .ok()
+            .map(AuthenticationResults::from);
+        match parsed {
+            Some(res) if res.host.is_some() => match res.is_trusted_by(self.protected) {
+                true => Some((SanitizeReason::Forged, self.forged)),
+                false => None,
+            },
+            _ => Some((SanitizeReason::Unparseable, self.unparseable)),
+        }
+    }
+    /// Rewrite the raw message - fails only when it can not be split into header fields
+    pub fn sanitize<'msg>(
+        &self,
+        raw: &'msg [u8],
+    ) -> Result<SanitizedMessage<'msg>, RawMessageError> {
+        let msg = RawMessage::try_from(raw)?;
+        let mut new_self = SanitizedMessage {
+            message: Vec::with_capacity(raw.len()),
+            headers: vec![],
+        };
+
+        let mut offset = 0;
+        for header in msg.headers.iter() {
+            let classified = match header.is(AUTH_RESULTS) {
+                true => self.classify(header),
+                false => None,
+            };
+            let action = match classified {
+                Some((reason, action)) => {
+                    new_self.headers.push(SanitizedHeader {
+                        offset,
+                        raw: header.raw,
+                        reason,
+                        action,
+                    });
+                    action
+                }
+                None => SanitizeAction::Keep,
+            };
+            match action {
+                SanitizeAction::Keep => new_self.message.extend_from_slice(header.raw),
+                SanitizeAction::Drop => {}
+                SanitizeAction::Rename => {
+                    new_self
+                        .message
+                        .extend_from_slice(self.rename_to.as_bytes());
+                    new_self
+                        .message
+                        .extend_from_slice(&header.raw[header.name.len()..]);
+                }
+            }
+            offset += header.raw.len();
+        }
+        // Separating empty line and the body
+        new_self.message.extend_from_slice(&raw[offset..]);
+
+        Ok(new_self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{fs::File, io::Read, path::PathBuf};
+
+    fn load_test_data(file: &str) -> Vec<u8> {
+        let mut file = File::open(PathBuf::from("test_data").join(file)).unwrap();
+        let mut data: Vec<u8> = vec![];
+        file.read_to_end(&mut data).unwrap();
+        data
+    }
+
+    const RAW: &[u8] = b"Authentication-Results: mx.example.com;\r\n\tspf=pass smtp.mailfrom=example.org\r\nReceived: from a\r\n  by b\r\nauthentication-results: MX.example.com; dkim=pass header.d=evil.example\r\nAuthentication-Results: mx.example.org; none\r\nAuthentication-Results: ;;;\r\nSubject: hi\r\n\r\nAuthentication-Results: mx.example.com; body\n";
+
+    #[test]
+    fn drop_forged() {
+        let sanitized = AuthResultsSanitizer::new(&["mx.example.com"])
+            .sanitize(RAW)
+            .unwrap();
+        assert_eq!(
+            sanitized.message,
+            b"Received: from a\r\n  by b\r\nAuthentication-Results: mx.example.org; none\r\nAuthentication-Results: ;;;\r\nSubject: hi\r\n\r\nAuthentication-Results: mx.example.com; body\n"
+        );
+        let found: Vec<(usize, SanitizeReason, SanitizeAction)> = sanitized
+            .headers
+            .iter()
+            .map(|h| (h.offset, h.reason, h.action))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (0, SanitizeReason::Forged, SanitizeAction::Drop),
+                (104, SanitizeReason::Forged, SanitizeAction::Drop),
+                (223, SanitizeReason::Unparseable, SanitizeAction::Keep),
+            ]
+        );
+        assert_eq!(&RAW[223..252], b"Authentication-Results: ;;;\r\n");
+    }
+
+    #[test]
+    fn rename_forged_and_unparseable() {
+        let sanitized = AuthResultsSanitizer::new(&["*.example.com"])
+            .on_forged(SanitizeAction::Rename)
+            .on_unparseable(SanitizeAction::Drop)
+            .sanitize(RAW)
+            .unwrap();
+        assert_eq!(
+            sanitized.message,
+            b"X-Original-Authentication-Results: mx.example.com;\r\n\tspf=pass smtp.mailfrom=example.org\r\nReceived: from a\r\n  by b\r\nX-Original-Authentication-Results: MX.example.com; dkim=pass header.d=evil.example\r\nAuthentication-Results: mx.example.org; none\r\nSubject: hi\r\n\r\nAuthentication-Results: mx.example.com; body\n"
+        );
+    }
+
+    #[test]
+    fn rename_to() {
+        let sanitized = AuthResultsSanitizer::new(&["mx.example.org"])
+            .on_forged(SanitizeAction::Rename)
+            .rename_to("X-Forged-AR")
+            .sanitize(b"Authentication-Results: mx.example.org; none\r\n\r\n")
+            .unwrap();
+        assert_eq!(
+            sanitized.message,
+            b"X-Forged-AR: mx.example.org; none\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn unchanged_without_forged() {
+        let raw = load_test_data("to_in_fastmail.eml");
+        let sanitized = AuthResultsSanitizer::new(&["mx.example.com"])
+            .sanitize(&raw)
+            .unwrap();
+        assert_eq!(sanitized.message, raw);
+        assert_eq!(sanitized.headers, vec![]);
+    }
+
+    #[test]
+    fn renamed_real_message() {
+        let raw = load_test_data("to_in_fastmail.eml");
+        let sanitized = AuthResultsSanitizer::new(&["*.messagingengine.com"])
+            .on_forged(SanitizeAction::Rename)
+            .sanitize(&raw)
+            .unwrap();
+        assert_eq!(sanitized.headers.len(), 4);
+        let expected = String::from_utf8(raw.clone()).unwrap().replace(
+            "\nAuthentication-Results:",
+            "\nX-Original-Authentication-Results:",
+        );
+        assert_eq!(sanitized.message, expected.as_bytes());
+    }
+
+    #[test]
+    fn invalid_message() {
+        assert_eq!(
+            AuthResultsSanitizer::new(&[]).sanitize(b"Subject: a\r\nno colon\r\n\r\n"),
+            Err(RawMessageError::InvalidHeader(12))
+        );
+    }
+}
@@ -1,4 +1,16 @@
-//! Method spf Result
+//! Method spf Result and SPF record - RFC 7208
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub(crate) mod macros;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub(crate) mod record;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use record::{SpfDirective, SpfMechanism, SpfQualifier, SpfRecord};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+mod check;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use check::{SpfEvaluation, SpfEvaluator};
 
 /// Parsed SPF Result
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -27,7 +39,7 @@ impl<'hdr> SpfResult<'hdr> {
 
 /// SPF Result Codes - s.2.7.2
 /// SPF defined in RFC 7208 s.2.6 - Results evaluation
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Ord)]
 pub enum SpfResultCode {
     /// Result code not seen
     #[default]
@@ -0,0 +1,876 @@
+//! SPF evaluation of the client IP against the published records - RFC 7208 s. 4
+
+use core::net::IpAddr;
+
+use crate::error::{ResolverError, SpfRecordError};
+use crate::resolver::SpfResolver;
+
+use super::macros::{expand, needs_validated, MacroContext};
+use super::{SpfMechanism, SpfRecord, SpfResult, SpfResultCode};
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// Terms causing DNS lookups allowed per check - s. 4.6.4
+const DNS_LOOKUP_LIMIT: usize = 10;
+
+/// DNS lookups returning no records allowed per check - s. 4.6.4
+const VOID_LOOKUP_LIMIT: usize = 2;
+
+/// MX names and PTR names looked up per mechanism - s. 4.6.4
+const NAME_LOOKUP_LIMIT: usize = 10;
+
+/// Domain names longer than this are truncated from the left - s. 7.3
+const DOMAIN_MAX: usize = 253;
+
+/// Evaluate SPF through the given resolver - check_host() of RFC 7208 s. 4
+#[derive(Debug)]
+pub struct SpfEvaluator<'res, R: SpfResolver + ?Sized> {
+    resolver: &'res R,
+    receiver: &'res str,
+    timestamp: u64,
+}
+
+/// SPF result with what it was decided on
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpfEvaluation {
+    /// Result of check_host()
+    pub code: SpfResultCode,
+    /// Explanation from exp= of a fail
+    pub explanation: Option<String>,
+    /// The directive that matched e.g. `-all` - none when nothing matched
+    pub mechanism: Option<String>,
+    /// smtp.mailfrom - the MAIL FROM identity checked
+    pub smtp_mailfrom: Option<String>,
+    /// smtp.helo - the HELO identity checked
+    pub smtp_helo: Option<String>,
+    /// DNS lookups counted against the limit
+    pub dns_lookups: usize,
+    /// DNS lookups that returned no records
+    pub void_lookups: usize,
+}
+
+impl SpfEvaluation {
+    /// Result for Authentication-Results with the explanation as the reason
+    pub fn spf_result(&self) -> SpfResult<'_> {
+        SpfResult {
+            code: self.code.clone(),
+            reason: self.explanation.as_deref(),
+            smtp_mailfrom: self.smtp_mailfrom.as_deref(),
+            smtp_helo: self.smtp_helo.as_deref(),
+            raw: None,
+        }
+    }
+}
+
+// Result of a record with the matched directive and the explanation
+struct Outcome {
+    code: SpfResultCode,
+    mechanism: Option<String>,
+    explanation: Option<String>,
+}
+
+// Whether the TXT record is an SPF record at all - s. 4.5
+fn is_spf1(txt: &str) -> bool {
+    match txt.get(..6) {
+        Some(version) if version.eq_ignore_ascii_case("v=spf1") => {
+            matches!(txt.as_bytes().get(6), None | Some(b' '))
+        }
+        _ => false,
+    }
+}
+
+// Fully qualified with no empty or overlong labels - s. 4.3
+fn is_valid_domain(domain: &str) -> bool {
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    domain.len() <= DOMAIN_MAX
+        && domain.contains('.')
+        && domain
+            .split('.')
+            .all(|label| !label.is_empty() && label.len() <= 63)
+}
+
+// Left labels removed until the name fits - s. 7.3
+fn truncate_domain(mut domain: &str) -> &str {
+    while domain.len() > DOMAIN_MAX {
+        domain = match domain.split_once('.') {
+            Some((_, rest)) => rest,
+            None => break,
+        };
+    }
+    domain
+}
+
+fn is_subdomain_of(name: &str, domain: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    name.eq_ignore_ascii_case(domain)
+        || (name.len() > domain.len()
+            && name.as_bytes()[name.len() - domain.len() - 1] == b'.'
+            && name[name.len() - domain.len()..].eq_ignore_ascii_case(domain))
+}
+
+// Whether the ip is within the network of the prefix length
+fn in_network(ip: IpAddr, network: IpAddr, cidr4: Option<u8>, cidr6: Option<u8>) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX
+                .checked_shl(32 - u32::from(cidr4.unwrap_or(32)))
+                .unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX
+                .checked_shl(128 - u32::from(cidr6.unwrap_or(128)))
+                .unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+// State over a single check_host() including the nested ones
+struct Check<'a, 'res, R: SpfResolver + ?Sized> {
+    evaluator: &'a SpfEvaluator<'res, R>,
+    ip: IpAddr,
+    sender: &'a str,
+    helo: &'a str,
+    dns_lookups: usize,
+    void_lookups: usize,
+}
+
+// Error carries the temperror / permerror that ends the whole check
+type Step<T> = Result<T, SpfResultCode>;
+
+impl<R: SpfResolver + ?Sized> Check<'_, '_, R> {
+    fn count_lookup(&mut self) -> Step<()> {
+        self.dns_lookups += 1;
+        match self.dns_lookups > DNS_LOOKUP_LIMIT {
+            true => Err(SpfResultCode::PermError),
+            false => Ok(()),
+        }
+    }
+    // Records of a lookup made by a term - none found counts as a void lookup
+    fn answer<T>(&mut self, answer: Result<Vec<T>, ResolverError>) -> Step<Vec<T>> {
+        match answer {
+            Ok(records) if !records.is_empty() => Ok(records),
+            Ok(_) | Err(ResolverError::NotFound) => {
+                self.void_lookups += 1;
+                match self.void_lookups > VOID_LOOKUP_LIMIT {
+                    true => Err(SpfResultCode::PermError),
+                    false => Ok(vec![]),
+                }
+            }
+            Err(_) => Err(SpfResultCode::TempError),
+        }
+    }
+    // Addresses of the same family as the client IP
+    fn addresses(&self, name: &str) -> Result<Vec<IpAddr>, ResolverError> {
+        let resolver = self.evaluator.resolver;
+        match self.ip {
+            IpAddr::V4(_) => resolver
+                .lookup_a(name)
+                .map(|addrs| addrs.into_iter().map(IpAddr::V4).collect()),
+            IpAddr::V6(_) => resolver
+                .lookup_aaaa(name)
+                .map(|addrs| addrs.into_iter().map(IpAddr::V6).collect()),
+        }
+    }
+    // Reverse names of the client IP that resolve back to it - s. 5.5
+    fn validated_names(&self, ptr_names: Vec<String>) -> Vec<String> {
+        ptr_names
+            .into_iter()
+            .take(NAME_LOOKUP_LIMIT)
+            .filter(|name| {
+                self.addresses(name)
+                    .is_ok_and(|addrs| addrs.contains(&self.ip))
+            })
+            .collect()
+    }
+    // %{p} prefers the domain itself or a subdomain of it - s. 7.3
+    fn validated_name(&self, domain: &str) -> String {
+        let names = match self.evaluator.resolver.lookup_ptr(self.ip) {
+            Ok(names) => self.validated_names(names),
+            Err(_) => vec![],
+        };
+        names
+            .iter()
+            .find(|name| is_subdomain_of(name, domain))
+            .or(names.first())
+            .map(|name| name.trim_end_matches('.').to_string())
+            .unwrap_or_else(|| "unknown".into())
+    }
+    fn expand(&self, val: &str, domain: &str, explain: bool) -> Step<String> {
+        let validated = match needs_validated(val) {
+            true => self.validated_name(domain),
+            false => String::new(),
+        };
+        let ctx = MacroContext {
+            sender: self.sender,
+            domain,
+            ip: self.ip,
+            helo: self.helo,
+            validated: &validated,
+            receiver: self.evaluator.receiver,
+            timestamp: self.evaluator.timestamp,
+        };
+        expand(val, &ctx, explain).map_err(|_| SpfResultCode::PermError)
+    }
+    // Expanded domain-spec or the current domain when not given
+    fn target(&self, domain_spec: Option<&str>, domain: &str) -> Step<String> {
+        match domain_spec {
+            Some(domain_spec) => {
+                let target = self.expand(domain_spec, domain, false)?;
+                Ok(truncate_domain(&target).to_string())
+            }
+            None => Ok(domain.to_string()),
+        }
+    }
+    fn matches(&mut self, mechanism: &SpfMechanism<'_>, domain: &str) -> Step<bool> {
+        if mechanism.is_dns_lookup() {
+            self.count_lookup()?;
+        }
+        let matched = match mechanism {
+            SpfMechanism::All => true,
+            SpfMechanism::Include(domain_spec) => {
+                let target = self.target(Some(domain_spec), domain)?;
+                match self.check(&target, true)?.code {
+                    SpfResultCode::Pass => true,
+                    SpfResultCode::Fail | SpfResultCode::SoftFail | SpfResultCode::Neutral => false,
+                    SpfResultCode::TempError => return Err(SpfResultCode::TempError),
+                    _ => return Err(SpfResultCode::PermError),
+                }
+            }
+            SpfMechanism::A {
+                domain: domain_spec,
+                cidr4,
+                cidr6,
+            } => {
+                let target = self.target(*domain_spec, domain)?;
+                let answer = self.addresses(&target);
+                self.answer(answer)?
+                    .into_iter()
+                    .any(|addr| in_network(self.ip, addr, *cidr4, *cidr6))
+            }
+            SpfMechanism::Mx {
+                domain: domain_spec,
+                cidr4,
+                cidr6,
+            } => {
+                let target = self.target(*domain_spec, domain)?;
+                let answer = self.evaluator.resolver.lookup_mx(&target);
+                let exchanges = self.answer(answer)?;
+                if exchanges.len() > NAME_LOOKUP_LIMIT {
+                    return Err(SpfResultCode::PermError);
+                }
+                let mut matched = false;
+                for exchange in exchanges.iter() {
+                    let addrs = match self.addresses(exchange) {
+                        Ok(addrs) => addrs,
+                        Err(ResolverError::NotFound) => continue,
+                        Err(_) => return Err(SpfResultCode::TempError),
+                    };
+                    if addrs
+                        .into_iter()
+                        .any(|addr| in_network(self.ip, addr, *cidr4, *cidr6))
+                    {
+                        matched = true;
+                        break;
+                    }
+                }
+                matched
+            }
+            SpfMechanism::Ptr(domain_spec) => {
+                let target = self.target(*domain_spec, domain)?;
+                // A failing PTR lookup is no match - s. 5.5
+                let names = match self.evaluator.resolver.lookup_ptr(self.ip) {
+                    Err(ResolverError::Temporary | ResolverError::Permanent) => vec![],
+                    answer => self.answer(answer)?,
+                };
+                self.validated_names(names)
+                    .iter()
+                    .any(|name| is_subdomain_of(name, &target))
+            }
+            SpfMechanism::Ip4 { addr, cidr } => in_network(self.ip, IpAddr::V4(*addr), *cidr, None),
+            SpfMechanism::Ip6 { addr, cidr } => in_network(self.ip, IpAddr::V6(*addr), None, *cidr),
+            SpfMechanism::Exists(domain_spec) => {
+                let target = self.target(Some(domain_spec), domain)?;
+                // Always an A lookup whatever the client IP - s. 5.7
+                let answer = self.evaluator.resolver.lookup_a(&target);
+                !self.answer(answer)?.is_empty()
+            }
+        };
+        Ok(matched)
+    }
+    // Explanation of a fail - any error just leaves it out - s. 6.2
+    fn explain(&self, exp: &str, domain: &str) -> Option<String> {
+        let target = self.target(Some(exp), domain).ok()?;
+        match self.evaluator.resolver.lookup_txt(&target).ok()?.as_slice() {
+            [explain] => self.expand(explain, domain, true).ok(),
+            _ => None,
+        }
+    }
+    // The single SPF record of the domain if any - s. 4.4 & 4.5
+    fn record(&self, domain: &str) -> Step<Option<String>> {
+        if !is_valid_domain(domain) {
+            return Ok(None);
+        }
+        let txts = match self.evaluator.resolver.lookup_txt(domain) {
+            Ok(txts) => txts,
+            Err(ResolverError::NotFound) => return Ok(None),
+            Err(_) => return Err(SpfResultCode::TempError),
+        };
+        let mut records = txts.into_iter().filter(|txt| is_spf1(txt));
+        match (records.next(), records.next()) {
+            (Some(record), None) => Ok(Some(record)),
+            (None, _) => Ok(None),
+            (Some(_), Some(_)) => Err(SpfResultCode::PermError),
+        }
+    }
+    // check_host() for the domain - explanations are not used from includes
+    fn check(&mut self, domain: &str, include: bool) -> Step<Outcome> {
+        let txt = match self.record(domain)? {
+            Some(txt) => txt,
+            None => {
+                return Ok(Outcome {
+                    code: SpfResultCode::NoneSpf,
+                    mechanism: None,
+                    explanation: None,
+                })
+            }
+        };
+        let record = SpfRecord::try_from(txt.as_str()).map_err(|e| match e {
+            SpfRecordError::NotSpf1 => SpfResultCode::NoneSpf,
+            _ => SpfResultCode::PermError,
+        })?;
+
+        for directive in record.directives.iter() {
+            if self.matches(&directive.mechanism, domain)? {
+                let code = directive.qualifier.result_code();
+                let explanation = match (&code, record.exp, include) {
+                    (SpfResultCode::Fail, Some(exp), false) => self.explain(exp, domain),
+                    _ => None,
+                };
+                return Ok(Outcome {
+                    code,
+                    mechanism: Some(directive.raw.to_string()),
+                    explanation,
+                });
+            }
+        }
+
+        // redirect= only when nothing matched - s. 6.1
+        if let Some(redirect) = record.redirect {
+            self.count_lookup()?;
+            let target = self.target(Some(redirect), domain)?;
+            let outcome = self.check(&target, include)?;
+            return match outcome.code {
+                SpfResultCode::NoneSpf => Err(SpfResultCode::PermError),
+                _ => Ok(outcome),
+            };
+        }
+
+        Ok(Outcome {
+            code: SpfResultCode::Neutral,
+            mechanism: None,
+            explanation: None,
+        })
+    }
+}
+
+impl<'res, R: SpfResolver + ?Sized> SpfEvaluator<'res, R> {
+    /// Evaluate through the resolver
+    pub fn new(resolver: &'res R) -> Self {
+        Self {
+            resolver,
+            receiver: "unknown",
+            timestamp: 0,
+        }
+    }
+    /// Receiving host for %{r} in explanations
+    pub fn with_receiver(mut self, receiver: &'res str) -> Self {
+        self.receiver = receiver;
+        self
+    }
+    /// Seconds since the epoch for %{t} in explanations
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+    /// check_host() - RFC 7208 s. 4
+    ///
+    /// The sender is the MAIL FROM address or postmaster@ the HELO domain
+    /// when checking that. A sender without a local-part gets postmaster.
+    pub fn check_host(&self, ip: IpAddr, domain: &str, sender: &str, helo: &str) -> SpfEvaluation {
+        // IPv4-mapped IPv6 addresses are evaluated as IPv4
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        };
+        let sender = match sender.rsplit_once('@') {
+            Some(("", sender_domain)) => format!("postmaster@{}", sender_domain),
+            Some(_) => sender.to_string(),
+            None => format!("postmaster@{}", sender),
+        };
+        let mut check = Check {
+            evaluator: self,
+            ip,
+            sender: &sender,
+            helo,
+            dns_lookups: 0,
+            void_lookups: 0,
+        };
+        let outcome = check.check(domain, false).unwrap_or_else(|code| Outcome {
+            code,
+            mechanism: None,
+            explanation: None,
+        });
+        SpfEvaluation {
+            code: outcome.code,
+            explanation: outcome.explanation,
+            mechanism: outcome.mechanism,
+            smtp_mailfrom: None,
+            smtp_helo: None,
+            dns_lookups: check.dns_lookups,
+            void_lookups: check.void_lookups,
+        }
+    }
+    /// Check the MAIL FROM identity - s. 2.4
+    ///
+    /// An empty reverse-path is checked as postmaster@ the HELO domain.
+    pub fn check_mailfrom(&self, ip: IpAddr, mailfrom: &str, helo: &str) -> SpfEvaluation {
+        let mailfrom = mailfrom.trim_start_matches('<').trim_end_matches('>');
+        let (domain, sender) = match mailfrom.rsplit_once('@') {
+            Some((_, domain)) => (domain, mailfrom),
+            None if mailfrom.is_empty() => (helo, helo),
+            None => (mailfrom, mailfrom),
+        };
+        SpfEvaluation {
+            smtp_mailfrom: Some(sender.to_string()),
+            ..self.check_host(ip, domain, sender, helo)
+        }
+    }
+    /// Check the HELO identity - s. 2.3
+    pub fn check_helo(&self, ip: IpAddr, helo: &str) -> SpfEvaluation {
+        SpfEvaluation {
+            smtp_helo: Some(helo.to_string()),
+            ..self.check_host(ip, helo, helo, helo)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::resolver::TxtResolver;
+    use core::net::{Ipv4Addr, Ipv6Addr};
+    use rstest::rstest;
+    use std::collections::HashMap;
+
+    // In-memory zone with the records by type
+    #[derive(Default)]
+    struct Zone {
+        txt: HashMap<&'static str, Vec<String>>,
+        a: HashMap<&'static str, Vec<Ipv4Addr>>,
+        aaaa: HashMap<&'static str, Vec<Ipv6Addr>>,
+        mx: HashMap<&'static str, Vec<String>>,
+        ptr: HashMap<IpAddr, Vec<String>>,
+        temporary: Vec<&'static str>,
+    }
+
+    impl Zone {
+        fn txt(mut self, name: &'static str, txt: &str) -> Self {
+            self.txt.entry(name).or_default().push(txt.to_string());
+            self
+        }
+        fn a(mut self, name: &'static str, addrs: &[&str]) -> Self {
+            let addrs = addrs.iter().map(|addr| addr.parse().unwrap()).collect();
+            self.a.insert(name, addrs);
+            self
+        }
+        fn aaaa(mut self, name: &'static str, addrs: &[&str]) -> Self {
+            let addrs = addrs.iter().map(|addr| addr.parse().unwrap()).collect();
+            self.aaaa.insert(name, addrs);
+            self
+        }
+        fn mx(mut self, name: &'static str, exchanges: &[&str]) -> Self {
+            let exchanges = exchanges.iter().map(|mx| mx.to_string()).collect();
+            self.mx.insert(name, exchanges);
+            self
+        }
+        fn ptr(mut self, ip: &str, names: &[&str]) -> Self {
+            let names = names.iter().map(|name| name.to_string()).collect();
+            self.ptr.insert(ip.parse().unwrap(), names);
+            self
+        }
+        fn temporary(mut self, name: &'static str) -> Self {
+            self.temporary.push(name);
+            self
+        }
+        fn get<T: Clone>(
+            &self,
+            records: &HashMap<&'static str, Vec<T>>,
+            name: &str,
+        ) -> Result<Vec<T>, ResolverError> {
+            if self.temporary.contains(&name) {
+                return Err(ResolverError::Temporary);
+            }
+            records.get(name).cloned().ok_or(ResolverError::NotFound)
+        }
+    }
+
+    impl TxtResolver for Zone {
+        fn lookup_txt(&self, name: &str) -> Result<Vec<String>, ResolverError> {
+            self.get(&self.txt, name)
+        }
+    }
+
+    impl SpfResolver for Zone {
+        fn lookup_a(&self, name: &str) -> Result<Vec<Ipv4Addr>, ResolverError> {
+            self.get(&self.a, name)
+        }
+        fn lookup_aaaa(&self, name: &str) -> Result<Vec<Ipv6Addr>, ResolverError> {
+            self.get(&self.aaaa, name)
+        }
+        fn lookup_mx(&self, name: &str) -> Result<Vec<String>, ResolverError> {
+            self.get(&self.mx, name)
+        }
+        fn lookup_ptr(&self, ip: IpAddr) -> Result<Vec<String>, ResolverError> {
+            self.ptr.get(&ip).cloned().ok_or(ResolverError::NotFound)
+        }
+    }
+
+    // RFC 7208 Appendix A
+    fn example_com() -> Zone {
+        Zone::default()
+            .txt("example.com", "v=spf1 +mx a:colo.example.com/28 -all")
+            .txt("example.com", "google-site-verification=abc")
+            .a("example.com", &["192.0.2.10", "192.0.2.11"])
+            .aaaa("example.com", &["2001:db8::10"])
+            .mx("example.com", &["mail-a.example.com", "mail-b.example.com"])
+            .a("mail-a.example.com", &["192.0.2.129"])
+            .a("mail-b.example.com", &["192.0.2.130"])
+            .aaaa("mail-b.example.com", &["2001:db8::130"])
+            .a("colo.example.com", &["192.0.2.50"])
+            .ptr("192.0.2.65", &["amy.example.com"])
+            .a("amy.example.com", &["192.0.2.65"])
+            .ptr("192.0.2.66", &["bob.example.com"])
+            .a("bob.example.com", &["192.0.2.99"])
+    }
+
+    fn check(zone: &Zone, ip: &str, domain: &str) -> SpfEvaluation {
+        SpfEvaluator::new(zone).check_host(
+            ip.parse().unwrap(),
+            domain,
+            "joe@example.com",
+            "mx.example.org",
+        )
+    }
+
+    fn outcome(evaluation: SpfEvaluation) -> (SpfResultCode, Option<String>) {
+        (evaluation.code, evaluation.mechanism)
+    }
+
+    #[rstest]
+    #[case("192.0.2.129", SpfResultCode::Pass, Some("+mx"))]
+    #[case("192.0.2.130", SpfResultCode::Pass, Some("+mx"))]
+    #[case("::ffff:192.0.2.130", SpfResultCode::Pass, Some("+mx"))]
+    #[case("2001:db8::130", SpfResultCode::Pass, Some("+mx"))]
+    #[case("192.0.2.48", SpfResultCode::Pass, Some("a:colo.example.com/28"))]
+    #[case("192.0.2.63", SpfResultCode::Pass, Some("a:colo.example.com/28"))]
+    #[case("192.0.2.64", SpfResultCode::Fail, Some("-all"))]
+    #[case("192.0.2.10", SpfResultCode::Fail, Some("-all"))]
+    #[case("2001:db8::10", SpfResultCode::Fail, Some("-all"))]
+    fn appendix_a(#[case] ip: &str, #[case] code: SpfResultCode, #[case] mechanism: Option<&str>) {
+        let evaluation = check(&example_com(), ip, "example.com");
+        assert_eq!(
+            outcome(evaluation),
+            (code, mechanism.map(|m| m.to_string()))
+        );
+    }
+
+    #[rstest]
+    #[case("v=spf1 a -all", "192.0.2.11", SpfResultCode::Pass)]
+    #[case("v=spf1 a//64 -all", "2001:db8::ffff", SpfResultCode::Pass)]
+    #[case("v=spf1 a:example.com/24 ~all", "192.0.2.200", SpfResultCode::Pass)]
+    #[case("v=spf1 mx/24 ~all", "192.0.2.200", SpfResultCode::Pass)]
+    #[case("v=spf1 ptr:example.com -all", "192.0.2.65", SpfResultCode::Pass)]
+    #[case("v=spf1 ptr:example.com -all", "192.0.2.66", SpfResultCode::Fail)]
+    #[case("v=spf1 ptr:example.org -all", "192.0.2.65", SpfResultCode::Fail)]
+    #[case("v=spf1 ip4:192.0.2.0/24 ?all", "192.0.2.1", SpfResultCode::Pass)]
+    #[case("v=spf1 ip4:192.0.2.0/24 ?all", "198.51.100.1", SpfResultCode::Neutral)]
+    #[case("v=spf1 ip4:0.0.0.0/0 -all", "198.51.100.1", SpfResultCode::Pass)]
+    #[case("v=spf1 ip4:192.0.2.1 -all", "2001:db8::1", SpfResultCode::Fail)]
+    #[case("v=spf1 ip6:2001:db8::/32 -all", "2001:db8:1::1", SpfResultCode::Pass)]
+    #[case(
+        "v=spf1 exists:%{ir}.list.example.com -all",
+        "192.0.2.77",
+        SpfResultCode::Pass
+    )]
+    #[case(
+        "v=spf1 exists:%{l}.users.example.com -all",
+        "192.0.2.1",
+        SpfResultCode::Pass
+    )]
+    #[case("v=spf1 ~all", "192.0.2.1", SpfResultCode::SoftFail)]
+    #[case("v=spf1", "192.0.2.1", SpfResultCode::Neutral)]
+    fn mechanisms(#[case] record: &str, #[case] ip: &str, #[case] code: SpfResultCode) {
+        let zone = Zone {
+            txt: HashMap::new(),
+            ..example_com()
+        }
+        .txt("test.example.com", record)
+        .a("test.example.com", &["192.0.2.11"])
+        .aaaa("test.example.com", &["2001:db8::1"])
+        .mx("test.example.com", &["mail-a.example.com"])
+        .a("77.2.0.192.list.example.com", &["127.0.0.2"])
+        .a("joe.users.example.com", &["127.0.0.2"]);
+        assert_eq!(check(&zone, ip, "test.example.com").code, code);
+    }
+
+    #[rstest]
+    #[case("v=spf1 include:pass.example.net -all", SpfResultCode::Pass)]
+    #[case("v=spf1 include:fail.example.net ~all", SpfResultCode::SoftFail)]
+    #[case("v=spf1 include:none.example.net -all", SpfResultCode::PermError)]
+    #[case("v=spf1 include:perm.example.net -all", SpfResultCode::PermError)]
+    #[case("v=spf1 include:temp.example.net -all", SpfResultCode::TempError)]
+    #[case("v=spf1 redirect=pass.example.net", SpfResultCode::Pass)]
+    #[case("v=spf1 redirect=fail.example.net", SpfResultCode::Fail)]
+    #[case("v=spf1 redirect=none.example.net", SpfResultCode::PermError)]
+    #[case("v=spf1 -all redirect=pass.example.net", SpfResultCode::Fail)]
+    #[case(
+        "v=spf1 include:pass.example.net redirect=fail.example.net",
+        SpfResultCode::Pass
+    )]
+    fn include_redirect(#[case] record: &str, #[case] code: SpfResultCode) {
+        let zone = Zone::default()
+            .txt("example.com", record)
+            .txt("pass.example.net", "v=spf1 ip4:192.0.2.1 -all")
+            .txt("fail.example.net", "v=spf1 -all")
+            .txt("perm.example.net", "v=spf1 foo")
+            .temporary("temp.example.net");
+        assert_eq!(check(&zone, "192.0.2.1", "example.com").code, code);
+    }
+
+    #[rstest]
+    #[case("nowhere.example.com", SpfResultCode::NoneSpf)]
+    #[case("localhost", SpfResultCode::NoneSpf)]
+    #[case("a..example.com", SpfResultCode::NoneSpf)]
+    #[case("other.example.com", SpfResultCode::NoneSpf)]
+    #[case("two.example.com", SpfResultCode::PermError)]
+    #[case("syntax.example.com", SpfResultCode::PermError)]
+    #[case("temp.example.com", SpfResultCode::TempError)]
+    #[case("a-temp.example.com", SpfResultCode::TempError)]
+    fn records(#[case] domain: &'static str, #[case] code: SpfResultCode) {
+        let zone = Zone::default()
+            .txt("other.example.com", "v=spf10 -all")
+            .txt("two.example.com", "v=spf1 -all")
+            .txt("two.example.com", "V=SPF1 +all")
+            .txt("syntax.example.com", "v=spf1 ip4:192.0.2.300 -all")
+            .temporary("temp.example.com")
+            .txt("a-temp.example.com", "v=spf1 a:temp.example.com -all");
+        assert_eq!(check(&zone, "192.0.2.1", domain).code, code);
+    }
+
+    #[test]
+    fn dns_lookup_limit() {
+        let terms: Vec<String> = (1..=11).map(|n| format!("a:h{}.example.com", n)).collect();
+        let at_limit = format!("v=spf1 {} -all", terms[..10].join(" "));
+        let over_limit = format!("v=spf1 {} -all", terms.join(" "));
+        let mut zone = Zone::default()
+            .txt("limit.example.com", &at_limit)
+            .txt("over.example.com", &over_limit);
+        for name in [
+            "h1.example.com",
+            "h2.example.com",
+            "h3.example.com",
+            "h4.example.com",
+            "h5.example.com",
+            "h6.example.com",
+            "h7.example.com",
+            "h8.example.com",
+            "h9.example.com",
+            "h10.example.com",
+            "h11.example.com",
+        ] {
+            zone = zone.a(name, &["198.51.100.1"]);
+        }
+        let evaluation = check(&zone, "192.0.2.1", "limit.example.com");
+        assert_eq!(evaluation.code, SpfResultCode::Fail);
+        assert_eq!(evaluation.dns_lookups, 10);
+        let evaluation = check(&zone, "192.0.2.1", "over.example.com");
+        assert_eq!(evaluation.code, SpfResultCode::PermError);
+        assert_eq!(evaluation.dns_lookups, 11);
+    }
+
+    #[test]
+    fn include_loop() {
+        let zone = Zone::default()
+            .txt("example.com", "v=spf1 include:example.net -all")
+            .txt("example.net", "v=spf1 include:example.com -all");
+        let evaluation = check(&zone, "192.0.2.1", "example.com");
+        assert_eq!(evaluation.code, SpfResultCode::PermError);
+        assert_eq!(evaluation.dns_lookups, 11);
+    }
+
+    #[rstest]
+    #[case(
+        "v=spf1 a:n1.example.com a:n2.example.com -all",
+        SpfResultCode::Fail,
+        2
+    )]
+    #[case(
+        "v=spf1 a:n1.example.com mx:n2.example.com exists:n3.example.com -all",
+        SpfResultCode::PermError,
+        3
+    )]
+    fn void_lookup_limit(
+        #[case] record: &str,
+        #[case] code: SpfResultCode,
+        #[case] void_lookups: usize,
+    ) {
+        let zone = Zone::default().txt("example.com", record);
+        let evaluation = check(&zone, "192.0.2.1", "example.com");
+        assert_eq!(evaluation.code, code);
+        assert_eq!(evaluation.void_lookups, void_lookups);
+    }
+
+    #[test]
+    fn mx_limit() {
+        let exchanges: Vec<String> = (1..=11).map(|n| format!("mx{}.example.com", n)).collect();
+        let exchanges: Vec<&str> = exchanges.iter().map(String::as_str).collect();
+        let zone = Zone::default()
+            .txt("example.com", "v=spf1 mx -all")
+            .mx("example.com", &exchanges);
+        assert_eq!(
+            check(&zone, "192.0.2.1", "example.com").code,
+            SpfResultCode::PermError
+        );
+    }
+
+    #[test]
+    fn explanation() {
+        let zone = Zone::default()
+            .txt(
+                "example.com",
+                "v=spf1 mx include:other.example.com -all exp=explain._spf.%{d}",
+            )
+            .txt(
+                "explain._spf.example.com",
+                "%{i} is not one of %{d}'s designated mail servers - see %{r} at %{t}",
+            )
+            .txt("other.example.com", "v=spf1 -all exp=explain._spf.%{d}")
+            .txt("explain._spf.other.example.com", "included");
+        let evaluation = SpfEvaluator::new(&zone)
+            .with_receiver("mx.example.net")
+            .with_timestamp(1709251200)
+            .check_host(
+                "192.0.2.1".parse().unwrap(),
+                "example.com",
+                "joe@example.com",
+                "mx.example.org",
+            );
+        assert_eq!(evaluation.code, SpfResultCode::Fail);
+        assert_eq!(evaluation.mechanism.as_deref(), Some("-all"));
+        assert_eq!(
+            evaluation.explanation.as_deref(),
+            Some("192.0.2.1 is not one of example.com's designated mail servers - see mx.example.net at 1709251200")
+        );
+    }
+
+    #[test]
+    fn validated_macro() {
+        let zone = example_com()
+            .txt(
+                "amy.example.com",
+                "v=spf1 exists:%{p}.allowed.example.com -all",
+            )
+            .a("amy.example.com.allowed.example.com", &["127.0.0.2"]);
+        assert_eq!(
+            check(&zone, "192.0.2.65", "amy.example.com").code,
+            SpfResultCode::Pass
+        );
+        // bob.example.com does not resolve back to the address
+        let zone = zone
+            .txt(
+                "bob.example.com",
+                "v=spf1 exists:%{p}.allowed.example.com -all",
+            )
+            .a("unknown.allowed.example.com", &["127.0.0.2"]);
+        assert_eq!(
+            check(&zone, "192.0.2.66", "bob.example.com").code,
+            SpfResultCode::Pass
+        );
+    }
+
+    #[test]
+    fn mailfrom_and_helo() {
+        let zone = Zone::default()
+            .txt(
+                "example.com",
+                "v=spf1 exists:%{l}.%{o}.senders.example.com -all",
+            )
+            .a("joe.example.com.senders.example.com", &["127.0.0.2"])
+            .a(
+                "postmaster.mx.example.com.senders.example.com",
+                &["127.0.0.2"],
+            )
+            .txt("mx.example.com", "v=spf1 redirect=example.com");
+        let evaluator = SpfEvaluator::new(&zone);
+        let ip = "192.0.2.1".parse().unwrap();
+
+        let evaluation = evaluator.check_mailfrom(ip, "<joe@example.com>", "mx.example.com");
+        assert_eq!(evaluation.code, SpfResultCode::Pass);
+        assert_eq!(
+            evaluation.spf_result(),
+            SpfResult {
+                code: SpfResultCode::Pass,
+                smtp_mailfrom: Some("joe@example.com"),
+                ..Default::default()
+            }
+        );
+
+        let evaluation = evaluator.check_mailfrom(ip, "<jane@example.com>", "mx.example.com");
+        assert_eq!(evaluation.code, SpfResultCode::Fail);
+
+        // Null reverse-path checks postmaster at the HELO domain
+        let evaluation = evaluator.check_mailfrom(ip, "<>", "mx.example.com");
+        assert_eq!(evaluation.code, SpfResultCode::Pass);
+
+        let evaluation = evaluator.check_helo(ip, "mx.example.com");
+        assert_eq!(evaluation.code, SpfResultCode::Pass);
+        assert_eq!(
+            evaluation.spf_result(),
+            SpfResult {
+                code: SpfResultCode::Pass,
+                smtp_helo: Some("mx.example.com"),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[rstest]
+    #[case("v=spf1", true)]
+    #[case("V=SPF1 -all", true)]
+    #[case("v=spf1\t-all", false)]
+    #[case("v=spf10", false)]
+    #[case("v=spf", false)]
+    fn spf1(#[case] txt: &str, #[case] expected: bool) {
+        assert_eq!(is_spf1(txt), expected);
+    }
+
+    #[test]
+    fn truncated() {
+        let long = format!("{}.example.com", vec!["a".repeat(60); 5].join("."));
+        assert!(long.len() > DOMAIN_MAX);
+        let truncated = truncate_domain(&long);
+        assert!(truncated.len() <= DOMAIN_MAX);
+        assert!(long.ends_with(truncated));
+        assert_eq!(truncated.split('.').count(), 5);
+    }
+}
@@ -0,0 +1,378 @@
+//! SPF macro-string syntax and expansion - RFC 7208 s. 7
+
+use core::fmt::Write;
+use core::net::IpAddr;
+
+use crate::error::SpfTermError;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Delimiters allowed in a macro-expand
+const DELIMITERS: &[u8] = b".-+,/_=";
+
+/// Values the macro letters expand to - s. 7.2
+#[derive(Debug)]
+pub(crate) struct MacroContext<'a> {
+    /// s - the sender with a local-part
+    pub sender: &'a str,
+    /// d - the current domain
+    pub domain: &'a str,
+    /// i / c / v - the client IP
+    pub ip: IpAddr,
+    /// h - HELO / EHLO domain
+    pub helo: &'a str,
+    /// p - validated domain name of the client IP
+    pub validated: &'a str,
+    /// r - the receiving host
+    pub receiver: &'a str,
+    /// t - seconds since the epoch
+    pub timestamp: u64,
+}
+
+impl MacroContext<'_> {
+    fn value(&self, letter: u8) -> String {
+        let (local, domain) = self
+            .sender
+            .rsplit_once('@')
+            .unwrap_or(("postmaster", self.sender));
+        match letter.to_ascii_lowercase() {
+            b's' => self.sender.into(),
+            b'l' => local.into(),
+            b'o' => domain.into(),
+            b'd' => self.domain.into(),
+            b'i' => match self.ip {
+                IpAddr::V4(ip) => ip.to_string(),
+                // Dot-format of the nibbles
+                IpAddr::V6(ip) => {
+                    let mut nibbles = String::with_capacity(63);
+                    for byte in ip.octets() {
+                        for nibble in [byte >> 4, byte & 0xf] {
+                            if !nibbles.is_empty() {
+                                nibbles.push('.');
+                            }
+                            let _ = write!(nibbles, "{:x}", nibble);
+                        }
+                    }
+                    nibbles
+                }
+            },
+            b'p' => self.validated.into(),
+            b'v' => match self.ip {
+                IpAddr::V4(_) => "in-addr".into(),
+                IpAddr::V6(_) => "ip6".into(),
+            },
+            b'h' => self.helo.into(),
+            b'c' => self.ip.to_string(),
+            b'r' => self.receiver.into(),
+            b't' => self.timestamp.to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+// Inside of %{ } - macro-letter transformers *delimiter
+struct MacroExpand<'a> {
+    letter: u8,
+    digits: Option<usize>,
+    reverse: bool,
+    delimiters: &'a [u8],
+}
+
+impl MacroExpand<'_> {
+    fn expand(&self, ctx: &MacroContext<'_>, out: &mut String) {
+        let value = ctx.value(self.letter);
+        let delimiters = match self.delimiters.is_empty() {
+            true => b".".as_slice(),
+            false => self.delimiters,
+        };
+        let mut parts: Vec<&str> = value
+            .split(|c: char| c.is_ascii() && delimiters.contains(&(c as u8)))
+            .collect();
+        if self.reverse {
+            parts.reverse();
+        }
+        let keep = self.digits.unwrap_or(parts.len()).min(parts.len());
+        let expanded = parts[parts.len() - keep..].join(".");
+        // Upper case letters are URL escaped
+        match self.letter.is_ascii_uppercase() {
+            true => {
+                for byte in expanded.bytes() {
+                    match byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                        true => out.push(byte as char),
+                        false => {
+                            let _ = write!(out, "%{:02X}", byte);
+                        }
+                    }
+                }
+            }
+            false => out.push_str(&expanded),
+        }
+    }
+}
+
+// Macro letters c, r and t are only allowed in explanations
+fn macro_letter(letter: u8, explain: bool) -> Result<u8, SpfTermError> {
+    match letter.to_ascii_lowercase() {
+        b's' | b'l' | b'o' | b'd' | b'i' | b'p' | b'h' | b'v' => Ok(letter),
+        b'c' | b'r' | b't' if explain => Ok(letter),
+        _ => Err(SpfTermError::InvalidMacro),
+    }
+}
+
+fn macro_expand(inner: &[u8], explain: bool) -> Result<MacroExpand<'_>, SpfTermError> {
+    let (letter, rest) = inner.split_first().ok_or(SpfTermError::InvalidMacro)?;
+    let letter = macro_letter(*letter, explain)?;
+    let digits_len = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+    let digits = match digits_len {
+        0 => None,
+        // DIGIT transformer must be nonzero - s. 7.3
+        _ if rest[..digits_len].iter().all(|b| *b == b'0') => {
+            return Err(SpfTermError::InvalidMacro)
+        }
+        // More parts than there are takes them all
+        _ => Some(
+            core::str::from_utf8(&rest[..digits_len])
+                .ok()
+                .and_then(|digits| digits.parse().ok())
+                .unwrap_or(usize::MAX),
+        ),
+    };
+    let rest = &rest[digits_len..];
+    let (reverse, delimiters) = match rest.first() {
+        Some(b'r' | b'R') => (true, &rest[1..]),
+        _ => (false, rest),
+    };
+    match delimiters.iter().all(|b| DELIMITERS.contains(b)) {
+        true => Ok(MacroExpand {
+            letter,
+            digits,
+            reverse,
+            delimiters,
+        }),
+        false => Err(SpfTermError::InvalidMacro),
+    }
+}
+
+// Walk the macro-string expanding into out when given the context
+fn walk(
+    val: &str,
+    explain: bool,
+    ctx: Option<&MacroContext<'_>>,
+    out: &mut String,
+) -> Result<bool, SpfTermError> {
+    let bytes = val.as_bytes();
+    let mut pos = 0;
+    let mut ends_with_expand = false;
+    while pos < bytes.len() {
+        ends_with_expand = bytes[pos] == b'%';
+        match (bytes[pos], bytes.get(pos + 1)) {
+            (b'%', Some(b'%')) => {
+                out.push('%');
+                pos += 2;
+            }
+            (b'%', Some(b'_')) => {
+                out.push(' ');
+                pos += 2;
+            }
+            (b'%', Some(b'-')) => {
+                out.push_str("%20");
+                pos += 2;
+            }
+            (b'%', Some(b'{')) => {
+                let end = bytes[pos..]
+                    .iter()
+                    .position(|b| *b == b'}')
+                    .ok_or(SpfTermError::InvalidMacro)?;
+                let expand = macro_expand(&bytes[pos + 2..pos + end], explain)?;
+                if let Some(ctx) = ctx {
+                    expand.expand(ctx, out);
+                }
+                pos += end + 1;
+            }
+            (b'%', _) => return Err(SpfTermError::InvalidMacro),
+            // explain-string = *( macro-string / SP )
+            (byte, _) if (0x21..=0x7e).contains(&byte) || (explain && byte == b' ') => {
+                out.push(byte as char);
+                pos += 1;
+            }
+            _ => return Err(SpfTermError::InvalidMacro),
+        }
+    }
+    Ok(ends_with_expand)
+}
+
+/// Check the macro-string returning whether it ends with a macro-expand
+pub(crate) fn check_macro_string(val: &str, explain: bool) -> Result<bool, SpfTermError> {
+    walk(val, explain, None, &mut String::new())
+}
+
+/// Expand the macro-string or with explain the explain-string - s. 7.3
+pub(crate) fn expand(
+    val: &str,
+    ctx: &MacroContext<'_>,
+    explain: bool,
+) -> Result<String, SpfTermError> {
+    let mut out = String::with_capacity(val.len());
+    walk(val, explain, Some(ctx), &mut out)?;
+    Ok(out)
+}
+
+/// Whether the macro-string needs the validated domain name of the client IP
+pub(crate) fn needs_validated(val: &str) -> bool {
+    val.as_bytes()
+        .windows(3)
+        .any(|w| w[0] == b'%' && w[1] == b'{' && w[2].eq_ignore_ascii_case(&b'p'))
+}
+
+// toplabel = ( *alphanum ALPHA *alphanum ) / ( 1*alphanum "-" *( alphanum / "-" ) alphanum )
+fn is_toplabel(label: &str) -> bool {
+    let bytes = label.as_bytes();
+    match (bytes.first(), bytes.last()) {
+        (Some(first), Some(last)) => {
+            first.is_ascii_alphanumeric()
+                && last.is_ascii_alphanumeric()
+                && bytes
+                    .iter()
+                    .all(|b| b.is_ascii_alphanumeric() || *b == b'-')
+                && (bytes.contains(&b'-') || bytes.iter().any(u8::is_ascii_alphabetic))
+        }
+        _ => false,
+    }
+}
+
+/// Check domain-spec = macro-string domain-end
+pub(crate) fn check_domain_spec(val: &str) -> Result<(), SpfTermError> {
+    if val.is_empty() {
+        return Err(SpfTermError::MissingArgument);
+    }
+    if check_macro_string(val, false)? {
+        return Ok(());
+    }
+    // domain-end = ( "." toplabel [ "." ] ) / macro-expand
+    let domain = val.strip_suffix('.').unwrap_or(val);
+    match domain.rsplit_once('.') {
+        Some((_, toplabel)) if is_toplabel(toplabel) => Ok(()),
+        _ => Err(SpfTermError::InvalidDomainSpec),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("example.com", Ok(false))]
+    #[case("%{d}", Ok(true))]
+    #[case("%{ir}.%{v}._spf.%{d2}", Ok(true))]
+    #[case("%{l1r-}.example.com", Ok(false))]
+    #[case("%{S}%%%_%-", Ok(true))]
+    #[case("%{d0}", Err(SpfTermError::InvalidMacro))]
+    #[case("%{c}", Err(SpfTermError::InvalidMacro))]
+    #[case("%{x}", Err(SpfTermError::InvalidMacro))]
+    #[case("%{d", Err(SpfTermError::InvalidMacro))]
+    #[case("%{dr!}", Err(SpfTermError::InvalidMacro))]
+    #[case("%d", Err(SpfTermError::InvalidMacro))]
+    #[case("100%", Err(SpfTermError::InvalidMacro))]
+    #[case("a b", Err(SpfTermError::InvalidMacro))]
+    fn macro_strings(#[case] val: &str, #[case] expected: Result<bool, SpfTermError>) {
+        assert_eq!(check_macro_string(val, false), expected);
+    }
+
+    #[test]
+    fn explain_letters() {
+        assert_eq!(check_macro_string("%{c} %{r} %{t}", true), Ok(true));
+    }
+
+    #[rstest]
+    #[case("example.com", Ok(()))]
+    #[case("example.com.", Ok(()))]
+    #[case("_spf.example-1.co", Ok(()))]
+    #[case("%{i}._ip.%{d}", Ok(()))]
+    #[case("host.1-2", Ok(()))]
+    #[case("", Err(SpfTermError::MissingArgument))]
+    #[case("localhost", Err(SpfTermError::InvalidDomainSpec))]
+    #[case("192.0.2.1", Err(SpfTermError::InvalidDomainSpec))]
+    #[case("example.-com", Err(SpfTermError::InvalidDomainSpec))]
+    #[case("example.", Err(SpfTermError::InvalidDomainSpec))]
+    fn domain_specs(#[case] val: &str, #[case] expected: Result<(), SpfTermError>) {
+        assert_eq!(check_domain_spec(val), expected);
+    }
+
+    fn context(ip: &str) -> MacroContext<'static> {
+        MacroContext {
+            sender: "strong-bad@email.example.com",
+            domain: "email.example.com",
+            ip: ip.parse().unwrap(),
+            helo: "mx.example.org",
+            validated: "mx.example.org",
+            receiver: "mx.example.net",
+            timestamp: 1709251200,
+        }
+    }
+
+    // RFC 7208 s. 7.4
+    #[rstest]
+    #[case("%{s}", "strong-bad@email.example.com")]
+    #[case("%{o}", "email.example.com")]
+    #[case("%{d}", "email.example.com")]
+    #[case("%{d4}", "email.example.com")]
+    #[case("%{d3}", "email.example.com")]
+    #[case("%{d2}", "example.com")]
+    #[case("%{d1}", "com")]
+    #[case("%{dr}", "com.example.email")]
+    #[case("%{d2r}", "example.email")]
+    #[case("%{l}", "strong-bad")]
+    #[case("%{l-}", "strong.bad")]
+    #[case("%{lr}", "strong-bad")]
+    #[case("%{lr-}", "bad.strong")]
+    #[case("%{l1r-}", "strong")]
+    #[case("%{d99999999999999999999}", "email.example.com")]
+    #[case("%{ir}.%{v}._spf.%{d2}", "3.2.0.192.in-addr._spf.example.com")]
+    #[case("%{lr-}.lp._spf.%{d2}", "bad.strong.lp._spf.example.com")]
+    #[case(
+        "%{lr-}.lp.%{ir}.%{v}._spf.%{d2}",
+        "bad.strong.lp.3.2.0.192.in-addr._spf.example.com"
+    )]
+    #[case(
+        "%{ir}.%{v}.%{l1r-}.lp._spf.%{d2}",
+        "3.2.0.192.in-addr.strong.lp._spf.example.com"
+    )]
+    #[case(
+        "%{d2}.trusted-domains.example.net",
+        "example.com.trusted-domains.example.net"
+    )]
+    #[case("%{S}", "strong-bad%40email.example.com")]
+    #[case("%{h}.%{p}", "mx.example.org.mx.example.org")]
+    #[case("%%%_%-", "% %20")]
+    fn expansion(#[case] val: &str, #[case] expected: &str) {
+        assert_eq!(expand(val, &context("192.0.2.3"), false).unwrap(), expected);
+    }
+
+    #[test]
+    fn expansion_ip6() {
+        assert_eq!(
+            expand("%{ir}.%{v}._spf.%{d2}", &context("2001:db8::cb01"), false).unwrap(),
+            "1.0.b.c.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6._spf.example.com"
+        );
+    }
+
+    #[test]
+    fn expansion_explain() {
+        assert_eq!(
+            expand(
+                "%{c} is not allowed for %{d} at %{r} %{t}",
+                &context("2001:db8::cb01"),
+                true
+            )
+            .unwrap(),
+            "2001:db8::cb01 is not allowed for email.example.com at mx.example.net 1709251200"
+        );
+        assert!(needs_validated("a.%{P}.b"));
+        assert!(!needs_validated("%{d}.p"));
+    }
+}
@@ -0,0 +1,117 @@
+//! SPF record published in DNS - RFC 7208 s. 4.5 - s. 6
+
+use core::net::{Ipv4Addr, Ipv6Addr};
+
+use super::SpfResultCode;
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+
+/// Qualifier in front of a mechanism - RFC 7208 s. 4.6.2
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SpfQualifier {
+    /// + or none given
+    #[default]
+    Pass,
+    /// -
+    Fail,
+    /// ~
+    SoftFail,
+    /// ?
+    Neutral,
+}
+
+impl SpfQualifier {
+    /// Result when the mechanism matches
+    pub fn result_code(&self) -> SpfResultCode {
+        match self {
+            Self::Pass => SpfResultCode::Pass,
+            Self::Fail => SpfResultCode::Fail,
+            Self::SoftFail => SpfResultCode::SoftFail,
+            Self::Neutral => SpfResultCode::Neutral,
+        }
+    }
+}
+
+/// Mechanism - RFC 7208 s. 5
+///
+/// Domains are domain-specs that may still contain macros - s. 7.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpfMechanism<'rec> {
+    /// all - always matches
+    All,
+    /// include:domain - matches when the domain gives a pass
+    Include(&'rec str),
+    /// a[:domain][/cidr4][//cidr6] - matches the A / AAAA records
+    A {
+        /// Domain instead of the current one
+        domain: Option<&'rec str>,
+        /// IPv4 prefix length
+        cidr4: Option<u8>,
+        /// IPv6 prefix length
+        cidr6: Option<u8>,
+    },
+    /// mx[:domain][/cidr4][//cidr6] - matches the addresses of the MX hosts
+    Mx {
+        /// Domain instead of the current one
+        domain: Option<&'rec str>,
+        /// IPv4 prefix length
+        cidr4: Option<u8>,
+        /// IPv6 prefix length
+        cidr6: Option<u8>,
+    },
+    /// ptr[:domain] - matches validated reverse names - use is discouraged
+    Ptr(Option<&'rec str>),
+    /// ip4:network[/cidr]
+    Ip4 {
+        /// Network address
+        addr: Ipv4Addr,
+        /// Prefix length - /32 when not given
+        cidr: Option<u8>,
+    },
+    /// ip6:network[/cidr]
+    Ip6 {
+        /// Network address
+        addr: Ipv6Addr,
+        /// Prefix length - /128 when not given
+        cidr: Option<u8>,
+    },
+    /// exists:domain - matches when the domain has an A record
+    Exists(&'rec str),
+}
+
+impl SpfMechanism<'_> {
+    /// Whether evaluating the mechanism takes a DNS lookup counted against the limit - s. 4.6.4
+    pub fn is_dns_lookup(&self) -> bool {
+        matches!(
+            self,
+            Self::Include(_) | Self::A { .. } | Self::Mx { .. } | Self::Ptr(_) | Self::Exists(_)
+        )
+    }
+}
+
+/// Qualified mechanism
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpfDirective<'rec> {
+    /// Qualifier
+    pub qualifier: SpfQualifier,
+    /// Mechanism
+    pub mechanism: SpfMechanism<'rec>,
+    /// Raw term
+    pub raw: &'rec str,
+}
+
+/// RFC 7208 s. 4.5 - v=spf1 followed by directives and modifiers
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpfRecord<'rec> {
+    /// Directives in the order they are evaluated
+    pub directives: Vec<SpfDirective<'rec>>,
+    /// redirect=domain - s. 6.1
+    pub redirect: Option<&'rec str>,
+    /// exp=domain with the explanation - s. 6.2
+    pub exp: Option<&'rec str>,
+    /// Unrecognised modifiers - RFC 7208 s. 6 says to ignore these
+    pub unknown_modifiers: Vec<(&'rec str, &'rec str)>,
+    /// Raw unparsed
+    pub raw: &'rec str,
+}
@@ -5,9 +5,9 @@
 //! the implementation between allocating and non-allocating
 
 /// Implement this to denote it's a DKIM Verifier containing result set
-#[allow(unused_variables, dead_code)]
-pub(crate) trait ResultsVerifier {
-    fn return_path_atleast_one_dkim_pass(&self, selector: &str) -> bool;
+pub trait ResultsVerifier {
+    /// Whether at least one DKIM result for a d= of the given domain is a pass
+    fn return_path_atleast_one_dkim_pass(&self, domain: &str) -> bool;
 }
 
 #[allow(unused_variables, dead_code)]